@@ -947,6 +947,7 @@ pub struct RecipeInfo {
     pub session_settings: Option<SessionSettings>,
     pub sub_recipes: Option<Vec<goose::recipe::SubRecipe>>,
     pub final_output_response: Option<goose::recipe::Response>,
+    pub goose_mode: Option<goose::config::GooseMode>,
     pub retry_config: Option<goose::agents::types::RetryConfig>,
 }
 
@@ -1115,6 +1116,7 @@ pub async fn cli() -> anyhow::Result<()> {
                         quiet: false,
                         sub_recipes: None,
                         final_output_response: None,
+                        goose_mode: None,
                         retry_config: None,
                         output_format: "text".to_string(),
                     })
@@ -1330,6 +1332,7 @@ pub async fn cli() -> anyhow::Result<()> {
                 final_output_response: recipe_info
                     .as_ref()
                     .and_then(|r| r.final_output_response.clone()),
+                goose_mode: recipe_info.as_ref().and_then(|r| r.goose_mode),
                 retry_config: recipe_info.as_ref().and_then(|r| r.retry_config.clone()),
                 output_format,
             })
@@ -1541,6 +1544,7 @@ pub async fn cli() -> anyhow::Result<()> {
                     quiet: false,
                     sub_recipes: None,
                     final_output_response: None,
+                    goose_mode: None,
                     retry_config: None,
                     output_format: "text".to_string(),
                 })
@@ -227,6 +227,9 @@ impl GooseAcpAgent {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let provider = create(&provider_name, model_config).await?;
 
@@ -761,6 +764,9 @@ impl acp::Agent for GooseAcpAgent {
             schedule_id: None,
             max_turns: None,
             retry_config: None,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
         };
 
         let mut stream = self
@@ -54,6 +54,7 @@ pub async fn agent_generator(
         quiet: false,
         sub_recipes: None,
         final_output_response: None,
+        goose_mode: None,
         retry_config: None,
         output_format: "text".to_string(),
     })
@@ -764,6 +764,7 @@ pub fn configure_extensions_dialog() -> anyhow::Result<()> {
                     bundled: Some(true),
                     description,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             });
 
@@ -865,6 +866,7 @@ pub fn configure_extensions_dialog() -> anyhow::Result<()> {
                     timeout: Some(timeout),
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             });
 
@@ -960,6 +962,7 @@ pub fn configure_extensions_dialog() -> anyhow::Result<()> {
                     timeout: Some(timeout),
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             });
 
@@ -1081,6 +1084,7 @@ pub fn configure_extensions_dialog() -> anyhow::Result<()> {
                     timeout: Some(timeout),
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             });
 
@@ -1701,6 +1705,7 @@ pub async fn handle_openrouter_auth() -> anyhow::Result<()> {
                                 bundled: Some(true),
                                 description: "Developer extension".to_string(),
                                 available_tools: Vec::new(),
+                                required: false,
                             },
                         });
                         println!("✓ Developer extension enabled");
@@ -1777,6 +1782,7 @@ pub async fn handle_tetrate_auth() -> anyhow::Result<()> {
                                 bundled: Some(true),
                                 description: "Developer extension".to_string(),
                                 available_tools: Vec::new(),
+                                required: false,
                             },
                         });
                         println!("✓ Developer extension enabled");
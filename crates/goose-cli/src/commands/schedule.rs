@@ -86,6 +86,9 @@ pub async fn handle_schedule_add(
         paused: false,
         current_session_id: None,
         process_start_time: None,
+        user_id: None,
+        valid_from: None,
+        valid_until: None,
     };
 
     let scheduler_storage_path =
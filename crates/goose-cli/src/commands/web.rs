@@ -514,6 +514,9 @@ async fn process_message_streaming(
         schedule_id: None,
         max_turns: None,
         retry_config: None,
+        user_id: None,
+        thinking_budget: None,
+        reply_deadline: None,
     };
 
     match agent.reply(user_message, session_config, None).await {
@@ -613,6 +616,13 @@ async fn process_message_streaming(
                     Ok(AgentEvent::ModelChange { model, mode }) => {
                         tracing::info!("Model changed to {} in {} mode", model, mode);
                     }
+                    Ok(AgentEvent::ApprovalRequired { .. }) => {
+                        // Rendered from the confirmation request in the preceding message
+                    }
+                    Ok(AgentEvent::Reasoning(_)) => {
+                        // The web interface only shows the answer
+                    }
+                    Ok(AgentEvent::Usage(_)) => {}
                     Err(e) => {
                         error!("Error in message stream: {}", e);
                         let mut sender = sender.lock().await;
@@ -64,6 +64,7 @@ pub fn extract_recipe_info_from_cli(
         }),
         sub_recipes: Some(all_sub_recipes),
         final_output_response: recipe.response,
+        goose_mode: recipe.mode,
         retry_config: recipe.retry,
     };
 
@@ -145,6 +145,7 @@ mod tests {
                     timeout: None,
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
                 ExtensionConfig::Stdio {
                     name: "slack-mcp".to_string(),
@@ -156,6 +157,7 @@ mod tests {
                     description: "slack-mcp".to_string(),
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
                 ExtensionConfig::Builtin {
                     name: "builtin-ext".to_string(),
@@ -164,6 +166,7 @@ mod tests {
                     timeout: None,
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             ]),
             settings: None,
@@ -173,6 +176,7 @@ mod tests {
             response: None,
             sub_recipes: None,
             retry: None,
+            mode: None,
         }
     }
 
@@ -216,6 +220,7 @@ mod tests {
             response: None,
             sub_recipes: None,
             retry: None,
+            mode: None,
         };
 
         let secrets = discover_recipe_secrets(&recipe);
@@ -240,6 +245,7 @@ mod tests {
                     timeout: None,
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
                 ExtensionConfig::Stdio {
                     name: "service-b".to_string(),
@@ -251,6 +257,7 @@ mod tests {
                     description: "service-b".to_string(),
                     bundled: None,
                     available_tools: Vec::new(),
+                    required: false,
                 },
             ]),
             settings: None,
@@ -260,6 +267,7 @@ mod tests {
             response: None,
             sub_recipes: None,
             retry: None,
+            mode: None,
         };
 
         let secrets = discover_recipe_secrets(&recipe);
@@ -298,6 +306,7 @@ mod tests {
                 timeout: None,
                 bundled: None,
                 available_tools: Vec::new(),
+                required: false,
             }]),
             sub_recipes: Some(vec![SubRecipe {
                 name: "child-recipe".to_string(),
@@ -312,6 +321,7 @@ mod tests {
             parameters: None,
             response: None,
             retry: None,
+            mode: None,
         };
 
         let secrets = discover_recipe_secrets(&recipe);
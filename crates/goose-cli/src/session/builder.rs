@@ -5,7 +5,7 @@ use goose::agents::types::{RetryConfig, SessionConfig};
 use goose::agents::Agent;
 use goose::config::{
     extensions::{get_extension_by_name, set_extension, ExtensionEntry},
-    get_all_extensions, get_enabled_extensions, Config, ExtensionConfig,
+    get_all_extensions, get_enabled_extensions, Config, ExtensionConfig, GooseMode,
 };
 use goose::providers::create;
 use goose::recipe::{Response, SubRecipe};
@@ -66,6 +66,8 @@ pub struct SessionBuilderConfig {
     pub sub_recipes: Option<Vec<SubRecipe>>,
     /// Final output expected response
     pub final_output_response: Option<Response>,
+    /// Goose mode required by the recipe, overriding GOOSE_MODE for this session
+    pub goose_mode: Option<GooseMode>,
     /// Retry configuration for automated validation and recovery
     pub retry_config: Option<RetryConfig>,
     /// Output format (text, json)
@@ -97,6 +99,7 @@ impl Default for SessionBuilderConfig {
             quiet: false,
             sub_recipes: None,
             final_output_response: None,
+            goose_mode: None,
             retry_config: None,
             output_format: "text".to_string(),
         }
@@ -315,6 +318,7 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> CliSession {
         .apply_recipe_components(
             session_config.sub_recipes,
             session_config.final_output_response,
+            session_config.goose_mode,
             true,
         )
         .await;
@@ -433,6 +437,9 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> CliSession {
     // If we get extensions_override, only run those extensions and none other
     let extensions_to_run: Vec<_> = if let Some(extensions) = session_config.extensions_override {
         agent.disable_router_for_recipe().await;
+        agent
+            .limit_enabled_extensions(Some(extensions.iter().map(|e| e.name()).collect()))
+            .await;
         extensions.into_iter().collect()
     } else if session_config.resume {
         match SessionManager::get_session(&session_id, false).await {
@@ -456,8 +463,12 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> CliSession {
     let agent_ptr = Arc::new(agent);
 
     let mut waiting_on = HashSet::new();
+    let mut required = HashSet::new();
     for extension in extensions_to_run {
         waiting_on.insert(extension.name());
+        if extension.is_required() {
+            required.insert(extension.name());
+        }
         let agent_ptr = agent_ptr.clone();
         set.spawn(async move {
             (
@@ -490,6 +501,18 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> CliSession {
 
     spinner.clear();
 
+    if let Some((name, err)) = offer_debug.iter().find(|(name, _)| required.contains(name)) {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Required extension '{}' failed to start: {}",
+                name, err
+            ))
+            .red()
+        );
+        process::exit(1);
+    }
+
     for (name, err) in offer_debug {
         if let Err(debug_err) = offer_extension_debugging_help(
             &name,
@@ -643,6 +666,9 @@ pub async fn build_session(session_config: SessionBuilderConfig) -> CliSession {
         schedule_id: None,
         max_turns: None,
         retry_config: None,
+        user_id: None,
+        thinking_budget: None,
+        reply_deadline: None,
     };
 
     if let Err(e) = session
@@ -711,6 +737,7 @@ mod tests {
             quiet: false,
             sub_recipes: None,
             final_output_response: None,
+            goose_mode: None,
             retry_config: None,
             output_format: "text".to_string(),
         };
@@ -234,6 +234,7 @@ impl CliSession {
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         };
 
         self.agent
@@ -264,6 +265,7 @@ impl CliSession {
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         };
 
         self.agent
@@ -295,6 +297,7 @@ impl CliSession {
             timeout: Some(goose::config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         };
 
         self.agent
@@ -322,6 +325,7 @@ impl CliSession {
                     bundled: None,
                     description: name.to_string(),
                     available_tools: Vec::new(),
+                    required: false,
                 }
             } else {
                 ExtensionConfig::Builtin {
@@ -331,6 +335,7 @@ impl CliSession {
                     bundled: None,
                     description: name.to_string(),
                     available_tools: Vec::new(),
+                    required: false,
                 }
             };
             self.agent
@@ -658,7 +663,7 @@ impl CliSession {
                     println!("{}", console::style("Generating Recipe").green());
 
                     output::show_thinking();
-                    let recipe = self.agent.create_recipe(self.messages.clone()).await;
+                    let recipe = self.agent.create_recipe(self.messages.clone(), None).await;
                     output::hide_thinking();
 
                     match recipe {
@@ -821,6 +826,9 @@ impl CliSession {
             schedule_id: self.scheduled_job_id.clone(),
             max_turns: self.max_turns,
             retry_config: self.retry_config.clone(),
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
         };
         let user_message = self
             .messages
@@ -895,6 +903,7 @@ impl CliSession {
                                     cliclack::select(prompt)
                                         .item(Permission::AllowOnce, "Allow", "Allow the tool call once")
                                         .item(Permission::AlwaysAllow, "Always Allow", "Always allow the tool call")
+                                        .item(Permission::AlwaysAllowForSession, "Allow for Session", "Allow the tool call for the rest of this session")
                                         .item(Permission::DenyOnce, "Deny", "Deny the tool call")
                                         .item(Permission::Cancel, "Cancel", "Cancel the AI response and tool call")
                                         .interact()
@@ -1153,6 +1162,15 @@ impl CliSession {
                                 eprintln!("Model changed to {} in {} mode", model, mode);
                             }
                         }
+                        Some(Ok(AgentEvent::ApprovalRequired { .. })) => {
+                            // The prompt is shown for the confirmation request in the preceding message
+                        }
+                        Some(Ok(AgentEvent::Reasoning(reasoning))) => {
+                            output::render_reasoning(&reasoning);
+                        }
+                        Some(Ok(AgentEvent::Usage(_))) => {
+                            // Totals are read from the session when the reply finishes
+                        }
 
                         Some(Err(e)) => {
                             // TODO(Douwe): Delete this
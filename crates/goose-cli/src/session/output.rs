@@ -185,14 +185,7 @@ pub fn render_message(message: &Message, debug: bool) {
             MessageContent::Image(image) => {
                 println!("Image: [data: {}, type: {}]", image.data, image.mime_type);
             }
-            MessageContent::Thinking(thinking) => {
-                if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok()
-                    && std::io::stdout().is_terminal()
-                {
-                    println!("\n{}", style("Thinking:").dim().italic());
-                    print_markdown(&thinking.thinking, theme);
-                }
-            }
+            MessageContent::Thinking(thinking) => render_reasoning(&thinking.thinking),
             MessageContent::RedactedThinking(_) => {
                 // For redacted thinking, print thinking was redacted
                 println!("\n{}", style("Thinking:").dim().italic());
@@ -220,6 +213,14 @@ pub fn render_message(message: &Message, debug: bool) {
     let _ = std::io::stdout().flush();
 }
 
+/// Show the model's reasoning when GOOSE_CLI_SHOW_THINKING is set
+pub fn render_reasoning(reasoning: &str) {
+    if std::env::var("GOOSE_CLI_SHOW_THINKING").is_ok() && std::io::stdout().is_terminal() {
+        println!("\n{}", style("Thinking:").dim().italic());
+        print_markdown(reasoning, get_theme());
+    }
+}
+
 pub fn render_text(text: &str, color: Option<Color>, dim: bool) {
     render_text_no_newlines(format!("\n{}\n\n", text).as_str(), color, dim);
 }
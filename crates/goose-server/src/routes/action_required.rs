@@ -38,6 +38,7 @@ pub async fn confirm_tool_action(
     let agent = state.get_agent_for_route(request.session_id).await?;
     let permission = match request.action.as_str() {
         "always_allow" => Permission::AlwaysAllow,
+        "always_allow_for_session" => Permission::AlwaysAllowForSession,
         "allow_once" => Permission::AllowOnce,
         "deny" => Permission::DenyOnce,
         _ => Permission::DenyOnce,
@@ -180,7 +180,7 @@ async fn create_recipe(
 
     let agent = state.get_agent_for_route(request.session_id).await?;
 
-    let recipe_result = agent.create_recipe(conversation).await;
+    let recipe_result = agent.create_recipe(conversation, None).await;
 
     match recipe_result {
         Ok(mut recipe) => {
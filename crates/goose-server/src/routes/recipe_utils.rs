@@ -166,9 +166,15 @@ pub async fn apply_recipe_to_agent(
         .apply_recipe_components(
             recipe.sub_recipes.clone(),
             recipe.response.clone(),
+            recipe.mode,
             include_final_output_tool,
         )
         .await;
+    if let Some(extensions) = &recipe.extensions {
+        agent
+            .limit_enabled_extensions(Some(extensions.iter().map(|e| e.name()).collect()))
+            .await;
+    }
 
     recipe.instructions.as_ref().map(|instructions| {
         let mut context: HashMap<&str, Value> = HashMap::new();
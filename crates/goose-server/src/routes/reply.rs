@@ -12,7 +12,7 @@ use goose::agents::{AgentEvent, SessionConfig};
 use goose::conversation::message::{Message, MessageContent, TokenState};
 use goose::conversation::Conversation;
 use goose::session::SessionManager;
-use rmcp::model::ServerNotification;
+use rmcp::model::{JsonObject, ServerNotification};
 use serde::{Deserialize, Serialize};
 use std::{
     convert::Infallible,
@@ -141,6 +141,21 @@ pub enum MessageEvent {
     UpdateConversation {
         conversation: Conversation,
     },
+    ApprovalRequired {
+        request_id: String,
+        tool_name: String,
+        #[schema(value_type = Object)]
+        arguments: JsonObject,
+    },
+    Reasoning {
+        reasoning: String,
+    },
+    Usage {
+        model: String,
+        input_tokens: Option<i32>,
+        output_tokens: Option<i32>,
+        total_tokens: Option<i32>,
+    },
     Ping,
 }
 
@@ -276,6 +291,9 @@ pub async fn reply(
             schedule_id: session.schedule_id.clone(),
             max_turns: None,
             retry_config: None,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
         };
 
         let user_message = match messages.last() {
@@ -349,6 +367,20 @@ pub async fn reply(
                         Ok(Some(Ok(AgentEvent::ModelChange { model, mode }))) => {
                             stream_event(MessageEvent::ModelChange { model, mode }, &tx, &cancel_token).await;
                         }
+                        Ok(Some(Ok(AgentEvent::ApprovalRequired { request_id, tool_name, arguments }))) => {
+                            stream_event(MessageEvent::ApprovalRequired { request_id, tool_name, arguments }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::Reasoning(reasoning)))) => {
+                            stream_event(MessageEvent::Reasoning { reasoning }, &tx, &cancel_token).await;
+                        }
+                        Ok(Some(Ok(AgentEvent::Usage(usage)))) => {
+                            stream_event(MessageEvent::Usage {
+                                model: usage.model,
+                                input_tokens: usage.usage.input_tokens,
+                                output_tokens: usage.usage.output_tokens,
+                                total_tokens: usage.usage.total_tokens,
+                            }, &tx, &cancel_token).await;
+                        }
                         Ok(Some(Ok(AgentEvent::McpNotification((request_id, n))))) => {
                             stream_event(MessageEvent::Notification{
                                 request_id: request_id.clone(),
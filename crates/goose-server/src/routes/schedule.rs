@@ -103,6 +103,9 @@ async fn create_schedule(
         paused: false,
         current_session_id: None,
         process_start_time: None,
+        user_id: None,
+        valid_from: None,
+        valid_until: None,
     };
     scheduler
         .add_scheduled_job(job.clone(), true)
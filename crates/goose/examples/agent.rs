@@ -45,6 +45,9 @@ async fn main() -> anyhow::Result<()> {
         schedule_id: None,
         max_turns: None,
         retry_config: None,
+        user_id: None,
+        thinking_budget: None,
+        reply_deadline: None,
     };
 
     let user_message = Message::user()
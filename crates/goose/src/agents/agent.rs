@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::future::Future;
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
 use futures::stream::BoxStream;
@@ -9,11 +12,19 @@ use futures::{stream, FutureExt, Stream, StreamExt, TryStreamExt};
 use uuid::Uuid;
 
 use super::final_output_tool::FinalOutputTool;
+use super::notification_sink::NotificationFileSink;
 use super::platform_tools;
-use super::tool_execution::{ToolCallResult, CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE};
+use super::settings::AgentSettings;
+use super::tool_execution::{
+    deadline_token, dedup_tool_response, prioritized_tool_streams, retry_tool_call,
+    with_tool_timeout, InFlightTools, ToolCallResult, CANCELLED_RESPONSE,
+    CHAT_MODE_TOOL_SKIPPED_RESPONSE, DECLINED_RESPONSE, EMPTY_TOOL_RESULT_PLACEHOLDER,
+};
 use crate::action_required_manager::ActionRequiredManager;
-use crate::agents::extension::{ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo};
-use crate::agents::extension_manager::{get_parameter_names, ExtensionManager};
+use crate::agents::extension::{
+    DuplicateExtensionPolicy, ExtensionConfig, ExtensionError, ExtensionResult, ToolInfo, ToolParam,
+};
+use crate::agents::extension_manager::{get_parameter_names, get_parameters, ExtensionManager};
 use crate::agents::extension_manager_extension::MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE;
 use crate::agents::final_output_tool::{FINAL_OUTPUT_CONTINUATION_MESSAGE, FINAL_OUTPUT_TOOL_NAME};
 use crate::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME;
@@ -22,6 +33,7 @@ use crate::agents::recipe_tools::dynamic_task_tools::{
     create_dynamic_task, create_dynamic_task_tool, DYNAMIC_TASK_TOOL_NAME_PREFIX,
 };
 use crate::agents::retry::{RetryManager, RetryResult};
+use crate::agents::router_tool_selector::RouterToolSelector;
 use crate::agents::router_tools::ROUTER_LLM_SEARCH_TOOL_NAME;
 use crate::agents::sub_recipe_manager::SubRecipeManager;
 use crate::agents::subagent_execution_tool::lib::ExecutionMode;
@@ -30,43 +42,54 @@ use crate::agents::subagent_execution_tool::subagent_execute_task_tool::{
 };
 use crate::agents::subagent_execution_tool::tasks_manager::TasksManager;
 use crate::agents::subagent_task_config::TaskConfig;
+use crate::agents::tool_history::{ToolHistory, ToolHistoryEntry, ToolHistoryFilter};
 use crate::agents::tool_route_manager::ToolRouteManager;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
 use crate::agents::types::SessionConfig;
-use crate::agents::types::{FrontendTool, SharedProvider, ToolResultReceiver};
-use crate::config::{get_enabled_extensions, Config, GooseMode};
+use crate::agents::types::{
+    ConfigSnapshot, ErrorRecoveryAction, FrontendTool, LargeResponseStrategy, SessionMetricsJson,
+    SharedProvider, ToolPolicy, ToolResultReceiver, ToolSchemaFormat, ToolSetDiff,
+    TruncationPolicy,
+};
+use crate::config::{Config, GooseMode};
 use crate::context_mgmt::{
     check_if_compaction_needed, compact_messages, DEFAULT_COMPACTION_THRESHOLD,
 };
 use crate::conversation::message::{
     ActionRequiredData, Message, MessageContent, SystemNotificationType, ToolRequest,
 };
-use crate::conversation::{debug_conversation_fix, fix_conversation, Conversation};
+use crate::conversation::{
+    debug_conversation_fix, fix_conversation, markdown, merge_conversations, Conversation,
+};
 use crate::mcp_utils::ToolResult;
 use crate::permission::permission_inspector::PermissionInspector;
 use crate::permission::permission_judge::PermissionCheckResult;
 use crate::permission::PermissionConfirmation;
-use crate::providers::base::Provider;
+use crate::providers::base::{Provider, ProviderUsage};
 use crate::providers::errors::ProviderError;
-use crate::recipe::{Author, Recipe, Response, Settings, SubRecipe};
+use crate::providers::toolshim::modify_system_prompt_for_tool_json;
+use crate::recipe::{Recipe, Response, SubRecipe};
 use crate::scheduler_trait::SchedulerTrait;
 use crate::security::security_inspector::SecurityInspector;
 use crate::session::extension_data::{EnabledExtensionsState, ExtensionState};
 use crate::session::{Session, SessionManager};
 use crate::tool_inspection::ToolInspectionManager;
-use crate::tool_monitor::RepetitionInspector;
-use crate::utils::is_token_cancelled;
-use regex::Regex;
+use crate::tool_monitor::{RepetitionInspector, RepetitionState};
+use crate::utils::{is_token_cancelled, token_cancelled};
+use chrono::Utc;
 use rmcp::model::{
-    CallToolRequestParam, Content, ErrorCode, ErrorData, GetPromptResult, Prompt,
+    CallToolRequestParam, Content, ErrorCode, ErrorData, GetPromptResult, Prompt, Role,
     ServerNotification, Tool,
 };
 use serde_json::Value;
-use tokio::sync::{mpsc, Mutex};
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Mutex, Notify};
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, instrument, warn};
 
 const DEFAULT_MAX_TURNS: u32 = 1000;
+const REPLY_EVENT_DELAY_MS_KEY: &str = "GOOSE_REPLY_EVENT_DELAY_MS";
 const COMPACTION_THINKING_TEXT: &str = "goose is compacting the conversation...";
 pub const MANUAL_COMPACT_TRIGGERS: &[&str] =
     &["Please compact this conversation", "/compact", "/summarize"];
@@ -81,6 +104,17 @@ pub struct ReplyContext {
     pub initial_messages: Vec<Message>,
 }
 
+/// Agent state `run_recipe_once` changes, put back once the run finishes
+struct RecipeRunState {
+    enable_limit: Option<Vec<String>>,
+    mode_override: Option<GooseMode>,
+    extensions: Vec<String>,
+    frontend_tools: HashMap<String, FrontendTool>,
+    frontend_instructions: Option<String>,
+    sub_recipes: SubRecipeManager,
+    final_output_tool: Option<FinalOutputTool>,
+}
+
 pub struct ToolCategorizeResult {
     pub frontend_requests: Vec<ToolRequest>,
     pub remaining_requests: Vec<ToolRequest>,
@@ -107,14 +141,109 @@ pub struct Agent {
     pub(super) scheduler_service: Mutex<Option<Arc<dyn SchedulerTrait>>>,
     pub(super) retry_manager: RetryManager,
     pub(super) tool_inspection_manager: ToolInspectionManager,
+    pub(super) settings: Mutex<AgentSettings>,
+    pub(super) in_flight_tools: Arc<InFlightTools>,
+    pub(super) tool_history: Arc<ToolHistory>,
+    pub(super) error_recovery_tx: mpsc::Sender<ErrorRecoveryAction>,
+    pub(super) error_recovery_rx: Mutex<mpsc::Receiver<ErrorRecoveryAction>>,
+    pub(super) notification_sink: Mutex<Option<NotificationFileSink>>,
+    /// Tool names offered by the latest tool refresh, keyed by session id
+    pub(super) last_tool_names: Mutex<HashMap<String, BTreeSet<String>>>,
+    /// Change made by the latest tool refresh, keyed by session id
+    pub(super) tool_set_diffs: Mutex<HashMap<String, ToolSetDiff>>,
+    /// User id from each session's SessionConfig, keyed by session id
+    pub(super) session_user_ids: Mutex<HashMap<String, String>>,
+    /// Mode set by the loaded recipe, taking precedence over GOOSE_MODE
+    pub(super) goose_mode_override: Mutex<Option<GooseMode>>,
+    /// Signalled on every reply and tool dispatch to restart the idle timeout
+    pub(super) activity: Arc<Notify>,
+    pub(super) idle_watcher: Mutex<Option<JoinHandle<()>>>,
+    /// Reply streams still alive; the idle timeout does not fire while any are
+    pub(super) active_replies: Arc<AtomicUsize>,
+    /// Set when the idle timeout stopped the extensions
+    pub(super) stopped_when_idle: Arc<AtomicBool>,
 }
 
 #[derive(Clone, Debug)]
 pub enum AgentEvent {
     Message(Message),
     McpNotification((String, ServerNotification)),
-    ModelChange { model: String, mode: String },
+    ModelChange {
+        model: String,
+        mode: String,
+    },
     HistoryReplaced(Conversation),
+    /// A tool call waits for the user's decision, sent through `handle_confirmation`.
+    /// Follows the message carrying the same confirmation request.
+    ApprovalRequired {
+        request_id: String,
+        tool_name: String,
+        arguments: rmcp::model::JsonObject,
+    },
+    /// Reasoning the provider emitted separately from its answer. The `Message` that
+    /// follows leaves this reasoning out, though it is kept in the conversation.
+    Reasoning(String),
+    /// Tokens used by one provider call, sent as soon as its response is complete
+    Usage(ProviderUsage),
+}
+
+/// An event from one branch of `Agent::reply_fanout`
+#[derive(Debug)]
+pub struct FanoutEvent {
+    /// Position of the provider in the list given to `reply_fanout`
+    pub provider_index: usize,
+    pub provider_name: String,
+    /// An error ends this provider's branch only
+    pub event: Result<AgentEvent>,
+}
+
+impl AgentEvent {
+    /// Events for a provider response: its reasoning, if any, then the response without it.
+    /// A response carrying nothing but reasoning produces no `Message` event.
+    fn for_response(mut message: Message) -> Vec<AgentEvent> {
+        let mut reasoning = Vec::new();
+        message.content.retain(|content| match content {
+            MessageContent::Thinking(thinking) => {
+                reasoning.push(thinking.thinking.clone());
+                false
+            }
+            _ => true,
+        });
+
+        let mut events = Vec::new();
+        let had_reasoning = !reasoning.is_empty();
+        if reasoning.iter().any(|text| !text.is_empty()) {
+            events.push(AgentEvent::Reasoning(reasoning.concat()));
+        }
+        if !had_reasoning || !message.content.is_empty() {
+            events.push(AgentEvent::Message(message));
+        }
+        events
+    }
+
+    /// An `ApprovalRequired` event for each tool confirmation requested in `message`
+    fn approvals_requested_in(message: &Message) -> Vec<AgentEvent> {
+        message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ActionRequired(action_required) => match &action_required.data {
+                    ActionRequiredData::ToolConfirmation {
+                        id,
+                        tool_name,
+                        arguments,
+                        ..
+                    } => Some(AgentEvent::ApprovalRequired {
+                        request_id: id.clone(),
+                        tool_name: tool_name.clone(),
+                        arguments: arguments.clone(),
+                    }),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl Default for Agent {
@@ -157,11 +286,47 @@ where
     })
 }
 
+/// Reject frontend tools whose input schema is not a valid JSON schema for an object
+fn validate_frontend_tool_schema(tool: &Tool) -> ExtensionResult<()> {
+    let invalid = |reason: String| {
+        ExtensionError::ConfigError(format!(
+            "Frontend tool '{}' has an invalid input schema: {}",
+            tool.name, reason
+        ))
+    };
+
+    let schema = Value::Object(tool.input_schema.as_ref().clone());
+    jsonschema::meta::validate(&schema).map_err(|e| invalid(e.to_string()))?;
+
+    match schema.get("type") {
+        None => Ok(()),
+        Some(Value::String(t)) if t == "object" => Ok(()),
+        Some(other) => Err(invalid(format!("expected type \"object\", got {}", other))),
+    }
+}
+
+/// Space out events so that consecutive yields are at least `delay` apart
+fn with_event_delay(
+    events: BoxStream<'_, Result<AgentEvent>>,
+    delay: Duration,
+) -> BoxStream<'_, Result<AgentEvent>> {
+    if delay.is_zero() {
+        return events;
+    }
+    Box::pin(events.enumerate().then(move |(index, event)| async move {
+        if index > 0 {
+            tokio::time::sleep(delay).await;
+        }
+        event
+    }))
+}
+
 impl Agent {
     pub fn new() -> Self {
         // Create channels with buffer size 32 (adjust if needed)
         let (confirm_tx, confirm_rx) = mpsc::channel(32);
         let (tool_tx, tool_rx) = mpsc::channel(32);
+        let (recovery_tx, recovery_rx) = mpsc::channel(1);
         let provider = Arc::new(Mutex::new(None));
 
         Self {
@@ -181,6 +346,20 @@ impl Agent {
             scheduler_service: Mutex::new(None),
             retry_manager: RetryManager::new(),
             tool_inspection_manager: Self::create_default_tool_inspection_manager(),
+            settings: Mutex::new(AgentSettings::default()),
+            in_flight_tools: Arc::new(InFlightTools::default()),
+            tool_history: Arc::new(ToolHistory::default()),
+            error_recovery_tx: recovery_tx,
+            error_recovery_rx: Mutex::new(recovery_rx),
+            notification_sink: Mutex::new(None),
+            last_tool_names: Mutex::new(HashMap::new()),
+            tool_set_diffs: Mutex::new(HashMap::new()),
+            session_user_ids: Mutex::new(HashMap::new()),
+            goose_mode_override: Mutex::new(None),
+            activity: Arc::new(Notify::new()),
+            idle_watcher: Mutex::new(None),
+            active_replies: Arc::new(AtomicUsize::new(0)),
+            stopped_when_idle: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -205,6 +384,27 @@ impl Agent {
         tool_inspection_manager
     }
 
+    /// The repetition counts of the tool monitor, for a scheduler to persist across restarts
+    pub fn export_tool_monitor_state(&self) -> Option<RepetitionState> {
+        self.tool_inspection_manager
+            .repetition_inspector()
+            .map(RepetitionInspector::export_state)
+    }
+
+    /// Add persisted repetition counts to the tool monitor's own
+    pub fn import_tool_monitor_state(&self, state: RepetitionState) {
+        if let Some(inspector) = self.tool_inspection_manager.repetition_inspector() {
+            inspector.import_state(state);
+        }
+    }
+
+    /// Deny a tool call once it has been made this many times in a row with the same arguments
+    pub fn set_max_tool_repetitions(&self, max_repetitions: Option<u32>) {
+        if let Some(inspector) = self.tool_inspection_manager.repetition_inspector() {
+            inspector.set_max_repetitions(max_repetitions);
+        }
+    }
+
     /// Reset the retry attempts counter to 0
     pub async fn reset_retry_attempts(&self) {
         self.retry_manager.reset_attempts().await;
@@ -258,6 +458,7 @@ impl Agent {
     async fn prepare_reply_context(
         &self,
         unfixed_conversation: Conversation,
+        session_id: &str,
         working_dir: &std::path::Path,
     ) -> Result<ReplyContext> {
         let unfixed_messages = unfixed_conversation.messages().clone();
@@ -273,11 +474,11 @@ impl Agent {
             );
         }
         let initial_messages = conversation.messages().clone();
-        let config = Config::global();
 
-        let (tools, toolshim_tools, system_prompt) =
-            self.prepare_tools_and_prompt(working_dir).await?;
-        let goose_mode = config.get_goose_mode().unwrap_or(GooseMode::Auto);
+        let (tools, toolshim_tools, system_prompt) = self
+            .prepare_tools_and_prompt(session_id, working_dir)
+            .await?;
+        let goose_mode = self.goose_mode().await;
 
         self.tool_inspection_manager
             .update_permission_inspector_mode(goose_mode)
@@ -366,6 +567,29 @@ impl Agent {
         }
     }
 
+    /// Answer every tool request that has no response yet with a cancellation error, so the
+    /// turn stays well-formed when a reply is cancelled while tools are pending
+    async fn cancel_unanswered_tools(
+        request_to_response_map: &HashMap<String, Arc<Mutex<Message>>>,
+    ) {
+        for (request_id, response_msg) in request_to_response_map {
+            let mut response = response_msg.lock().await;
+            let answered = response.content.iter().any(
+                |content| matches!(content, MessageContent::ToolResponse(r) if &r.id == request_id),
+            );
+            if !answered {
+                *response = response.clone().with_tool_response(
+                    request_id.clone(),
+                    Err(ErrorData::new(
+                        ErrorCode::INTERNAL_ERROR,
+                        CANCELLED_RESPONSE.to_string(),
+                        None,
+                    )),
+                );
+            }
+        }
+    }
+
     pub async fn set_scheduler(&self, scheduler: Arc<dyn SchedulerTrait>) {
         let mut scheduler_service = self.scheduler_service.lock().await;
         *scheduler_service = Some(scheduler);
@@ -406,12 +630,66 @@ impl Agent {
         sub_recipe_manager.add_sub_recipe_tools(sub_recipes);
     }
 
+    /// Stop every extension and the idle timeout
+    pub async fn shutdown(&self) {
+        if let Some(watcher) = self.idle_watcher.lock().await.take() {
+            watcher.abort();
+        }
+        self.extension_manager.shutdown().await;
+    }
+
+    /// Override the configured goose mode for this agent, or fall back to it again with None
+    pub async fn set_goose_mode_override(&self, mode: Option<GooseMode>) {
+        *self.goose_mode_override.lock().await = mode;
+    }
+
+    /// The mode in effect: the recipe's override if set, otherwise GOOSE_MODE
+    pub async fn goose_mode(&self) -> GooseMode {
+        match *self.goose_mode_override.lock().await {
+            Some(mode) => mode,
+            None => Config::global().get_goose_mode().unwrap_or(GooseMode::Auto),
+        }
+    }
+
+    /// Capture provider, mode, extensions, routing, tool monitoring and permission rules
+    /// in a serializable form, leaving out credentials and extension environments
+    pub async fn config_snapshot(&self) -> ConfigSnapshot {
+        let provider = self.provider().await.ok();
+        let mut extensions = self
+            .extension_manager
+            .list_extensions()
+            .await
+            .unwrap_or_default();
+        extensions.sort();
+
+        ConfigSnapshot {
+            provider: provider.as_ref().map(|p| p.get_name().to_string()),
+            model: provider.map(|p| p.get_model_config().model_name),
+            mode: self.goose_mode().await,
+            extensions,
+            router_strategy: self.tool_route_manager.router_strategy().await,
+            tool_inspectors: self
+                .tool_inspection_manager
+                .inspector_names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            max_tool_repetitions: self.tool_inspection_manager.max_tool_repetitions(),
+            permission_rules: self.tool_inspection_manager.user_permissions().await,
+        }
+    }
+
     pub async fn apply_recipe_components(
         &self,
         sub_recipes: Option<Vec<SubRecipe>>,
         response: Option<Response>,
+        mode: Option<GooseMode>,
         include_final_output: bool,
     ) {
+        if mode.is_some() {
+            self.set_goose_mode_override(mode).await;
+        }
+
         if let Some(sub_recipes) = sub_recipes {
             self.add_sub_recipes(sub_recipes).await;
         }
@@ -423,15 +701,198 @@ impl Agent {
         }
     }
 
+    /// Run a single reply turn of `recipe` in a fresh hidden session, without scheduling it.
+    /// The recipe's instructions extend that session's system prompt and its extensions are
+    /// started first; returns the messages the agent produced for `input`. The extensions,
+    /// mode, sub-recipes and final output tool the recipe sets up only last for the run, even
+    /// if it fails.
+    pub async fn run_recipe_once(&self, recipe: &Recipe, input: &str) -> Result<Vec<Message>> {
+        let working_dir = std::env::current_dir().unwrap_or_default();
+        let session = SessionManager::create_session(
+            working_dir,
+            recipe.title.clone(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let state = self.take_recipe_run_state().await;
+        let result = self.run_recipe_in_session(recipe, input, &session.id).await;
+        self.restore_recipe_run_state(state).await;
+        result
+    }
+
+    async fn take_recipe_run_state(&self) -> RecipeRunState {
+        RecipeRunState {
+            enable_limit: self.extension_manager.enabled_extensions_limit().await,
+            mode_override: *self.goose_mode_override.lock().await,
+            extensions: self
+                .extension_manager
+                .list_extensions()
+                .await
+                .unwrap_or_default(),
+            frontend_tools: self.frontend_tools.lock().await.clone(),
+            frontend_instructions: self.frontend_instructions.lock().await.clone(),
+            sub_recipes: self.sub_recipe_manager.lock().await.clone(),
+            final_output_tool: self.final_output_tool.lock().await.take(),
+        }
+    }
+
+    async fn restore_recipe_run_state(&self, state: RecipeRunState) {
+        for name in self
+            .extension_manager
+            .list_extensions()
+            .await
+            .unwrap_or_default()
+        {
+            if !state.extensions.contains(&name) {
+                if let Err(e) = self.remove_extension(&name).await {
+                    warn!("Failed to remove recipe extension '{}': {}", name, e);
+                }
+            }
+        }
+        self.limit_enabled_extensions(state.enable_limit).await;
+        self.set_goose_mode_override(state.mode_override).await;
+        *self.frontend_tools.lock().await = state.frontend_tools;
+        *self.frontend_instructions.lock().await = state.frontend_instructions;
+        *self.sub_recipe_manager.lock().await = state.sub_recipes;
+        *self.final_output_tool.lock().await = state.final_output_tool;
+    }
+
+    async fn run_recipe_in_session(
+        &self,
+        recipe: &Recipe,
+        input: &str,
+        session_id: &str,
+    ) -> Result<Vec<Message>> {
+        if let Some(instructions) = &recipe.instructions {
+            self.extend_system_prompt_for_session(session_id, instructions.clone())
+                .await;
+        }
+        if let Some(extensions) = &recipe.extensions {
+            self.limit_enabled_extensions(Some(extensions.iter().map(|e| e.name()).collect()))
+                .await;
+        }
+        for extension in recipe.extensions.iter().flatten() {
+            // Leave extensions the agent already runs alone, they outlive the run
+            if self
+                .extension_manager
+                .has_extension(&extension.name())
+                .await
+            {
+                continue;
+            }
+            if let Err(e) = self.add_extension(extension.clone()).await {
+                if extension.is_required() {
+                    return Err(anyhow!(
+                        "Required extension '{}' failed to start: {}",
+                        extension.name(),
+                        e
+                    ));
+                }
+                warn!(
+                    "Failed to add recipe extension '{}': {}",
+                    extension.name(),
+                    e
+                );
+            }
+        }
+        self.apply_recipe_components(recipe.sub_recipes.clone(), None, recipe.mode, false)
+            .await;
+        if let Some(response) = recipe.response.clone() {
+            // Its instructions go to this session only, the tool itself is put back afterwards
+            let final_output_tool = FinalOutputTool::new(response);
+            self.extend_system_prompt_for_session(session_id, final_output_tool.system_prompt())
+                .await;
+            *self.final_output_tool.lock().await = Some(final_output_tool);
+        }
+
+        let session_config = SessionConfig {
+            id: session_id.to_string(),
+            schedule_id: None,
+            max_turns: None,
+            retry_config: recipe.retry.clone(),
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
+        };
+        let mut stream = self
+            .reply(Message::user().with_text(input), session_config, None)
+            .await?;
+
+        let mut messages = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                messages.push(message);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Combine the histories of two sessions into one valid message sequence, `primary`
+    /// first. The sessions themselves are left unchanged.
+    pub async fn merge_sessions(&self, primary: &str, secondary: &str) -> Result<Vec<Message>> {
+        let primary = SessionManager::get_session(primary, true).await?;
+        let secondary = SessionManager::get_session(secondary, true).await?;
+
+        let (merged, issues) = merge_conversations(
+            &primary.conversation.unwrap_or_default(),
+            &secondary.conversation.unwrap_or_default(),
+        );
+        if !issues.is_empty() {
+            debug!(
+                "Merged sessions '{}' and '{}': {}",
+                primary.id,
+                secondary.id,
+                issues.join(", ")
+            );
+        }
+        Ok(merged.messages().clone())
+    }
+
+    /// Replace the message at `index` in the session's persisted history. The replacement
+    /// keeps the original message's id and is rejected if the history would become invalid.
+    pub async fn amend_message(
+        &self,
+        session_id: &str,
+        index: usize,
+        mut message: Message,
+    ) -> Result<()> {
+        let session = SessionManager::get_session(session_id, true).await?;
+        let mut messages = session.conversation.unwrap_or_default().messages().clone();
+        let Some(original) = messages.get_mut(index) else {
+            return Err(anyhow!(
+                "Message index {} is out of range for session '{}' with {} messages",
+                index,
+                session_id,
+                messages.len()
+            ));
+        };
+        if message.id.is_none() {
+            message.id = original.id.clone();
+        }
+        *original = message;
+
+        let conversation = Conversation::new(messages).map_err(|e| {
+            anyhow!(
+                "Amending message {} would leave an invalid history: {}",
+                index,
+                e
+            )
+        })?;
+        SessionManager::replace_conversation(session_id, &conversation).await
+    }
+
     /// Dispatch a single tool call to the appropriate client
     #[instrument(skip(self, tool_call, request_id), fields(input, output))]
     pub async fn dispatch_tool_call(
         &self,
-        tool_call: CallToolRequestParam,
+        mut tool_call: CallToolRequestParam,
         request_id: String,
         cancellation_token: Option<CancellationToken>,
         session: &Session,
     ) -> (String, Result<ToolCallResult, ErrorData>) {
+        self.activity.notify_one();
+        let started_at = Utc::now();
         if session.session_type == crate::session::SessionType::SubAgent
             && (tool_call.name == DYNAMIC_TASK_TOOL_NAME_PREFIX
                 || tool_call.name == SUBAGENT_EXECUTE_TASK_TOOL_NAME)
@@ -446,14 +907,72 @@ impl Agent {
             );
         }
 
+        if !self
+            .extension_manager
+            .tool_policy()
+            .await
+            .permits(&tool_call.name)
+        {
+            return (
+                request_id,
+                Err(ErrorData::new(
+                    ErrorCode::INVALID_REQUEST,
+                    format!("Tool '{}' is blocked by the tool policy", tool_call.name),
+                    None,
+                )),
+            );
+        }
+
+        if let Some(reason) = self.sandbox_rejection(&tool_call.name).await {
+            return (
+                request_id,
+                Err(ErrorData::new(ErrorCode::INVALID_REQUEST, reason, None)),
+            );
+        }
+
+        if let Some(inspector) = self.tool_inspection_manager.repetition_inspector() {
+            inspector.record_tool_call(&tool_call);
+        }
+
+        let (argument_transform, validator, executor) = {
+            let settings = self.settings.lock().await;
+            (
+                settings.argument_transforms.get(&*tool_call.name).cloned(),
+                settings.reference_validator.clone(),
+                settings.tool_executor.clone(),
+            )
+        };
+        if let Some(transform) = argument_transform {
+            let arguments = tool_call.arguments.take().unwrap_or_default();
+            tool_call.arguments = Some(transform(arguments, &session.working_dir));
+        }
+
+        let unknown_references = match (&validator, &tool_call.arguments) {
+            (Some(validator), Some(arguments)) => validator.unknown_references(arguments),
+            _ => vec![],
+        };
+
         if tool_call.name == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME {
             let arguments = tool_call
                 .arguments
                 .map(Value::Object)
                 .unwrap_or(Value::Object(serde_json::Map::new()));
+            let user_id = self.session_user_ids.lock().await.get(&session.id).cloned();
             let result = self
-                .handle_schedule_management(arguments, request_id.clone())
+                .handle_schedule_management(arguments, request_id.clone(), user_id)
                 .await;
+            let result = match &validator {
+                Some(validator) => validator.annotate(&unknown_references, result),
+                None => result,
+            };
+            self.tool_history.record(ToolHistoryEntry {
+                request_id: request_id.clone(),
+                session_id: session.id.clone(),
+                tool_name: tool_call.name.to_string(),
+                started_at,
+                finished_at: Utc::now(),
+                error: result.as_ref().err().map(|e| e.message.to_string()),
+            });
             return (request_id, Ok(ToolCallResult::from(result)));
         }
 
@@ -597,42 +1116,224 @@ impl Agent {
         } else if tool_call.name == ROUTER_LLM_SEARCH_TOOL_NAME {
             match self
                 .tool_route_manager
-                .dispatch_route_search_tool(tool_call.arguments.unwrap_or_default())
+                .dispatch_route_search_tool(
+                    tool_call.arguments.unwrap_or_default(),
+                    &self.extension_manager,
+                )
                 .await
             {
                 Ok(tool_result) => tool_result,
                 Err(e) => return (request_id, Err(e)),
             }
+        } else if let Some(executor) = executor.filter(|executor| executor.handles(&tool_call)) {
+            let call = tool_call.clone();
+            let session_id = session.id.clone();
+            ToolCallResult {
+                result: Box::new(async move { executor.execute(call, &session_id).await }.boxed()),
+                notification_stream: None,
+            }
         } else {
+            let cancellation_token = cancellation_token.unwrap_or_default();
             // Clone the result to ensure no references to extension_manager are returned
             let result = self
                 .extension_manager
-                .dispatch_tool_call(tool_call.clone(), cancellation_token.unwrap_or_default())
+                .dispatch_tool_call(tool_call.clone(), cancellation_token.clone())
                 .await;
-            result.unwrap_or_else(|e| {
+            let result = result.unwrap_or_else(|e| {
                 ToolCallResult::from(Err(ErrorData::new(
                     ErrorCode::INTERNAL_ERROR,
                     e.to_string(),
                     None,
                 )))
-            })
+            });
+            let (retry_policy, timeout) = {
+                let settings = self.settings.lock().await;
+                (
+                    settings
+                        .tool_retry_policies
+                        .get(tool_call.name.as_ref())
+                        .cloned(),
+                    settings
+                        .tool_timeouts
+                        .get(tool_call.name.as_ref())
+                        .copied()
+                        .or(settings.default_tool_timeout),
+                )
+            };
+            let result = match retry_policy {
+                Some(policy) => retry_tool_call(
+                    result,
+                    policy,
+                    self.extension_manager.clone(),
+                    tool_call.clone(),
+                    cancellation_token,
+                ),
+                None => result,
+            };
+            match timeout {
+                Some(limit) => with_tool_timeout(result, limit, tool_call.name.to_string()),
+                None => result,
+            }
         };
 
         debug!("WAITING_TOOL_END: {}", tool_call.name);
 
+        let notification_stream = match (
+            result.notification_stream,
+            self.notification_sink.lock().await.clone(),
+        ) {
+            (Some(stream), Some(sink)) => {
+                let session_id = session.id.clone();
+                let sink_request_id = request_id.clone();
+                let stream: Box<dyn Stream<Item = ServerNotification> + Send + Unpin> =
+                    Box::new(Box::pin(stream.then(move |notification| {
+                        let sink = sink.clone();
+                        let session_id = session_id.clone();
+                        let request_id = sink_request_id.clone();
+                        async move {
+                            if let Err(e) =
+                                sink.write(&session_id, &request_id, &notification).await
+                            {
+                                warn!("Failed to write notification to sink: {}", e);
+                            }
+                            notification
+                        }
+                    })));
+                Some(stream)
+            }
+            (stream, _) => stream,
+        };
+
+        let tool_name = tool_call.name.to_string();
+        let history = self.tool_history.clone();
+        let history_request_id = request_id.clone();
+        let session_id = session.id.clone();
+        let settings = self.settings.lock().await;
+        let transform = settings.result_transform.clone();
+        let compact_serializer = settings.compact_serializer.clone();
+        let truncation = settings.truncation_policies.get(&tool_name).copied();
+        // A truncation policy alone still means its part is kept inline
+        let strategy = settings
+            .large_response_strategies
+            .get(&tool_name)
+            .copied()
+            .unwrap_or(match truncation {
+                Some(_) => LargeResponseStrategy::Truncate,
+                None => LargeResponseStrategy::default(),
+            });
+        drop(settings);
+
         (
             request_id,
             Ok(ToolCallResult {
-                notification_stream: result.notification_stream,
-                result: Box::new(
-                    result
-                        .result
-                        .map(super::large_response_handler::process_tool_response),
-                ),
+                notification_stream,
+                result: Box::new(result.result.map(move |output| {
+                    let output = match &compact_serializer {
+                        Some(serializer) => output.map(|content| serializer.compact(content)),
+                        None => output,
+                    };
+                    let output = super::large_response_handler::process_tool_response(
+                        output,
+                        strategy,
+                        truncation.unwrap_or(TruncationPolicy::Head),
+                    )
+                    .map(|content| {
+                        if content.is_empty() {
+                            vec![Content::text(EMPTY_TOOL_RESULT_PLACEHOLDER)]
+                        } else {
+                            content
+                        }
+                    });
+                    let output = match transform {
+                        Some(transform) => output.map(|content| transform(&tool_name, content)),
+                        None => output,
+                    };
+                    let output = match validator {
+                        Some(validator) => validator.annotate(&unknown_references, output),
+                        None => output,
+                    };
+                    history.record(ToolHistoryEntry {
+                        request_id: history_request_id,
+                        session_id,
+                        tool_name,
+                        started_at,
+                        finished_at: Utc::now(),
+                        error: output.as_ref().err().map(|e| e.message.to_string()),
+                    });
+                    output
+                })),
             }),
         )
     }
 
+    /// Also write tool notifications to a per-session log file as they arrive
+    pub async fn set_notification_sink(&self, sink: Option<NotificationFileSink>) {
+        *self.notification_sink.lock().await = sink;
+    }
+
+    /// Recent stderr output of an extension's process, useful when it fails or crashes
+    pub async fn extension_logs(&self, name: &str) -> Vec<String> {
+        self.extension_manager.extension_logs(name).await
+    }
+
+    /// Tools added and removed by the latest tool refresh for `session_id`, compared with
+    /// the session's refresh before it
+    pub async fn tool_set_diff(&self, session_id: &str) -> ToolSetDiff {
+        self.tool_set_diffs
+            .lock()
+            .await
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Request ids of the tool calls currently running in a reply
+    pub fn in_flight_tools(&self) -> Vec<String> {
+        self.in_flight_tools.request_ids()
+    }
+
+    /// Cancel every running tool call; each one responds with a cancellation error.
+    /// Returns how many calls were cancelled.
+    pub fn cancel_all_tools(&self) -> usize {
+        self.in_flight_tools.cancel_all()
+    }
+
+    /// Finished tool calls matching `filter`, oldest first. Only the most recent calls are kept.
+    pub fn tool_history(&self, filter: ToolHistoryFilter) -> Vec<ToolHistoryEntry> {
+        self.tool_history.query(&filter)
+    }
+
+    /// Hide tools the policy does not permit from the model and refuse calls to them. Entries
+    /// match full tool names, so platform tools are named with their extension prefix, such as
+    /// `extensionmanager__manage_extensions`
+    pub async fn set_tool_policy(&self, policy: ToolPolicy) {
+        self.extension_manager.set_tool_policy(policy).await;
+    }
+
+    /// Why the sandbox, if one is set, refuses to run `tool_name`
+    async fn sandbox_rejection(&self, tool_name: &str) -> Option<String> {
+        let sandbox = self.settings.lock().await.sandbox.clone()?;
+        let extension = self.extension_manager.extension_for_tool(tool_name).await;
+        if !sandbox.allows(tool_name, extension.as_deref()) {
+            return Some(format!(
+                "Tool '{}' is not on the sandbox allowlist",
+                tool_name
+            ));
+        }
+        let read_only = self
+            .get_tool(tool_name)
+            .await
+            .and_then(|tool| tool.annotations)
+            .and_then(|annotations| annotations.read_only_hint)
+            .unwrap_or(false);
+        (!read_only).then(|| {
+            format!(
+                "Tool '{}' is not annotated read-only and cannot run in sandbox mode",
+                tool_name
+            )
+        })
+    }
+
     /// Save current extension state to session metadata
     /// Should be called after any extension add/remove operation
     pub async fn save_extension_state(&self, session: &SessionConfig) -> Result<()> {
@@ -656,12 +1357,17 @@ impl Agent {
     }
 
     pub async fn add_extension(&self, extension: ExtensionConfig) -> ExtensionResult<()> {
+        let mut previous_tools = None;
         match &extension {
             ExtensionConfig::Frontend {
                 tools,
                 instructions,
                 ..
             } => {
+                for tool in tools {
+                    validate_frontend_tool_schema(tool)?;
+                }
+
                 // For frontend tools, just store them in the frontend_tools map
                 let mut frontend_tools = self.frontend_tools.lock().await;
                 for tool in tools {
@@ -683,6 +1389,19 @@ impl Agent {
                 }
             }
             _ => {
+                // Tools of an extension this replaces, so the router index can be corrected
+                if self
+                    .extension_manager
+                    .has_extension(&extension.name())
+                    .await
+                {
+                    previous_tools = Some(
+                        self.extension_manager
+                            .get_prefixed_tools(Some(extension.name()))
+                            .await
+                            .unwrap_or_default(),
+                    );
+                }
                 self.extension_manager
                     .add_extension(extension.clone())
                     .await?;
@@ -691,23 +1410,34 @@ impl Agent {
 
         // If LLM tool selection is functional, index the tools
         if self.tool_route_manager.is_router_functional().await {
-            let selector = self.tool_route_manager.get_router_tool_selector().await;
-            if let Some(selector) = selector {
-                let selector = Arc::new(selector);
-                if let Err(e) = ToolRouterIndexManager::update_extension_tools(
-                    &selector,
-                    &self.extension_manager,
-                    &extension.name(),
-                    "add",
-                )
-                .await
-                {
-                    return Err(ExtensionError::SetupError(format!(
-                        "Failed to index tools for extension {}: {}",
-                        extension.name(),
-                        e
-                    )));
+            let indexed = match previous_tools {
+                Some(previous) => {
+                    self.tool_route_manager
+                        .reindex_reloaded_extension(
+                            &self.extension_manager,
+                            &extension.name(),
+                            &previous,
+                        )
+                        .await
                 }
+                None => match self.tool_route_manager.get_router_tool_selector().await {
+                    Some(selector) => {
+                        ToolRouterIndexManager::update_extension_tools(
+                            &selector,
+                            &self.extension_manager,
+                            &extension.name(),
+                            "add",
+                        )
+                        .await
+                    }
+                    None => Ok(()),
+                },
+            };
+            if let Err(e) = indexed {
+                return Err(ExtensionError::IndexingFailed {
+                    extension: extension.name(),
+                    message: e.to_string(),
+                });
             }
         }
 
@@ -739,33 +1469,180 @@ impl Agent {
             prefixed_tools.push(subagent_execute_task_tool::create_subagent_execute_task_tool());
         }
 
+        let policy = self.extension_manager.tool_policy().await;
+        prefixed_tools.retain(|tool| policy.permits(&tool.name));
         prefixed_tools
     }
 
-    pub async fn list_tools_for_router(&self) -> Vec<Tool> {
-        self.tool_route_manager
-            .list_tools_for_router(&self.extension_manager)
-            .await
-    }
-
-    pub async fn remove_extension(&self, name: &str) -> Result<()> {
-        self.extension_manager.remove_extension(name).await?;
+    /// Look up a tool by name among frontend, platform and extension tools, without listing
+    /// every extension's tools
+    pub async fn get_tool(&self, name: &str) -> Option<Tool> {
+        if let Some(frontend_tool) = self.get_frontend_tool(name).await {
+            return Some(frontend_tool.tool);
+        }
 
-        // If LLM tool selection is functional, remove tools from the index
-        if self.tool_route_manager.is_router_functional().await {
-            let selector = self.tool_route_manager.get_router_tool_selector().await;
-            if let Some(selector) = selector {
-                ToolRouterIndexManager::update_extension_tools(
-                    &selector,
-                    &self.extension_manager,
-                    name,
-                    "remove",
-                )
-                .await?;
+        let platform = [
+            platform_tools::manage_schedule_tool(),
+            create_dynamic_task_tool(),
+            subagent_execute_task_tool::create_subagent_execute_task_tool(),
+        ];
+        if let Some(tool) = platform.into_iter().find(|tool| tool.name == name) {
+            return Some(tool);
+        }
+        if let Some(tool) = self
+            .sub_recipe_manager
+            .lock()
+            .await
+            .sub_recipe_tools
+            .get(name)
+        {
+            return Some(tool.clone());
+        }
+        if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
+            let tool = final_output_tool.tool();
+            if tool.name == name {
+                return Some(tool);
             }
         }
 
-        Ok(())
+        self.extension_manager.get_tool(name).await
+    }
+
+    pub async fn list_tools_for_router(&self, session_id: &str) -> Vec<Tool> {
+        if self.tool_route_manager.is_router_bypassed().await {
+            return self.list_tools(None).await;
+        }
+        self.tool_route_manager
+            .list_tools_for_router(&self.extension_manager, session_id)
+            .await
+    }
+
+    /// Names of every tool `dispatch_tool_call` can handle, sorted and deduplicated
+    pub async fn dispatchable_tool_names(&self) -> Vec<String> {
+        let mut names: BTreeSet<String> = self
+            .list_tools(None)
+            .await
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        names.extend(self.frontend_tools.lock().await.keys().cloned());
+        if self.tool_route_manager.is_router_functional().await {
+            names.insert(ROUTER_LLM_SEARCH_TOOL_NAME.to_string());
+        }
+        names.into_iter().collect()
+    }
+
+    /// Stable hash of the names and input schemas of every available tool, for cache keys and
+    /// spotting changes to the tool set
+    pub async fn tools_fingerprint(&self) -> String {
+        let mut tools: Vec<(String, String)> = self
+            .list_tools(None)
+            .await
+            .into_iter()
+            .chain(
+                self.frontend_tools
+                    .lock()
+                    .await
+                    .values()
+                    .map(|frontend_tool| frontend_tool.tool.clone()),
+            )
+            .map(|tool| {
+                let schema = serde_json::to_string(&tool.input_schema).unwrap_or_default();
+                (tool.name.to_string(), schema)
+            })
+            .collect();
+        tools.sort();
+
+        let mut hasher = Sha256::new();
+        for (name, schema) in tools {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update(schema.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Route tools through `selector` instead of the built-in strategy, e.g. to use a custom
+    /// retrieval backend. It takes effect when the router is enabled; tools are indexed into it
+    /// as extensions are added and when the provider is next set.
+    pub async fn set_router_selector(&self, selector: Arc<Box<dyn RouterToolSelector>>) {
+        self.tool_route_manager
+            .set_custom_router_tool_selector(selector)
+            .await;
+    }
+
+    /// Choose whether adding an extension whose name is already loaded fails (the default) or
+    /// replaces the running extension
+    pub async fn set_duplicate_extension_policy(&self, policy: DuplicateExtensionPolicy) {
+        self.extension_manager
+            .set_duplicate_extension_policy(policy)
+            .await;
+    }
+
+    /// Offer every tool regardless of the router, e.g. to debug why a tool isn't selected
+    pub async fn set_router_bypass(&self, bypass: bool) {
+        self.tool_route_manager.set_router_bypass(bypass).await;
+    }
+
+    pub async fn remove_extension(&self, name: &str) -> Result<()> {
+        self.extension_manager.remove_extension(name).await?;
+
+        // If LLM tool selection is functional, remove tools from the index
+        if self.tool_route_manager.is_router_functional().await {
+            let selector = self.tool_route_manager.get_router_tool_selector().await;
+            if let Some(selector) = selector {
+                ToolRouterIndexManager::update_extension_tools(
+                    &selector,
+                    &self.extension_manager,
+                    name,
+                    "remove",
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restart extension `name` with an updated `config`, re-indexing its tools for the router
+    /// only if they changed. Renaming an extension this way is an error.
+    pub async fn reload_extension(
+        &self,
+        name: &str,
+        config: ExtensionConfig,
+    ) -> ExtensionResult<()> {
+        let previous = self
+            .extension_manager
+            .get_prefixed_tools(Some(name.to_string()))
+            .await
+            .unwrap_or_default();
+        self.extension_manager
+            .reload_extension(name, config)
+            .await?;
+
+        if self.tool_route_manager.is_router_functional().await {
+            self.tool_route_manager
+                .reindex_reloaded_extension(&self.extension_manager, name, &previous)
+                .await
+                .map_err(|e| ExtensionError::IndexingFailed {
+                    extension: name.to_string(),
+                    message: e.to_string(),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep `name` loaded when the model asks `manage_extensions` to disable it
+    pub async fn pin_extension(&self, name: &str) {
+        self.extension_manager.pin_extension(name).await;
+    }
+
+    /// Refuse requests from the model to enable extensions other than `names`, e.g. those a
+    /// recipe declares; `None` lifts the restriction
+    pub async fn limit_enabled_extensions(&self, names: Option<Vec<String>>) {
+        self.extension_manager.limit_enabled_extensions(names).await;
     }
 
     pub async fn list_extensions(&self) -> Vec<String> {
@@ -790,6 +1667,35 @@ impl Agent {
         }
     }
 
+    /// Answer the provider error the reply is waiting on. An action sent while no error is
+    /// pending is discarded when the next error is reported.
+    pub async fn handle_error_recovery(&self, action: ErrorRecoveryAction) {
+        if let Err(e) = self.error_recovery_tx.try_send(action) {
+            error!("Failed to send error recovery action: {}", e);
+        }
+    }
+
+    /// Drop recovery actions left over from before the error about to be reported
+    async fn discard_stale_error_recovery(&self) {
+        let mut rx = self.error_recovery_rx.lock().await;
+        while rx.try_recv().is_ok() {}
+    }
+
+    async fn wait_for_error_recovery(
+        &self,
+        cancel_token: &Option<CancellationToken>,
+    ) -> ErrorRecoveryAction {
+        let mut rx = self.error_recovery_rx.lock().await;
+        let action = match cancel_token {
+            Some(token) => tokio::select! {
+                _ = token.cancelled() => None,
+                action = rx.recv() => action,
+            },
+            None => rx.recv().await,
+        };
+        action.unwrap_or(ErrorRecoveryAction::Abandon)
+    }
+
     #[instrument(skip(self, user_message, session_config), fields(user_message))]
     pub async fn reply(
         &self,
@@ -820,6 +1726,20 @@ impl Agent {
             }
         }
 
+        let active_reply = self.track_active_reply();
+        if self.stopped_when_idle.swap(false, Ordering::SeqCst) {
+            warn!(
+                "Extensions were stopped after the agent sat idle; session {} continues without them",
+                session_config.id
+            );
+        }
+        if let Some(user_id) = &session_config.user_id {
+            self.session_user_ids
+                .lock()
+                .await
+                .insert(session_config.id.clone(), user_id.clone());
+        }
+
         let message_text = user_message.as_concat_text();
         let is_manual_compact = MANUAL_COMPACT_TRIGGERS.contains(&message_text.trim());
 
@@ -866,6 +1786,7 @@ impl Agent {
         let conversation_to_compact = conversation.clone();
 
         Ok(Box::pin(async_stream::try_stream! {
+            let _active_reply = active_reply;
             let final_conversation = if !needs_auto_compact && !is_manual_compact {
                 conversation
             } else {
@@ -924,7 +1845,13 @@ impl Agent {
             };
 
             if !is_manual_compact {
-                let mut reply_stream = self.reply_internal(final_conversation, session_config, session, cancel_token).await?;
+                let event_delay = Duration::from_millis(
+                    Config::global()
+                        .get_param::<u64>(REPLY_EVENT_DELAY_MS_KEY)
+                        .unwrap_or(0),
+                );
+                let reply_stream = self.reply_internal(final_conversation, session_config, session, cancel_token).await?;
+                let mut reply_stream = with_event_delay(reply_stream, event_delay);
                 while let Some(event) = reply_stream.next().await {
                     yield event?;
                 }
@@ -932,6 +1859,84 @@ impl Agent {
         }))
     }
 
+    /// Send `messages` to each of `providers` at once and merge their responses into one
+    /// stream, tagging every event with the provider it came from, e.g. to compare models.
+    ///
+    /// Each branch is a single provider call. Tool requests in a response are reported but
+    /// never executed, so the shared extension manager is only read to build the system prompt
+    /// and tool list, and the tool inspectors, including the repetition inspector, see none of
+    /// these calls. Nothing is written to a session. The messages are cloned for each branch.
+    pub async fn reply_fanout(
+        &self,
+        messages: Vec<Message>,
+        providers: Vec<Arc<dyn Provider>>,
+    ) -> Result<BoxStream<'static, FanoutEvent>> {
+        let mut tools = self.list_tools(None).await;
+        if let Some(cap) = self.settings.lock().await.max_offered_tools {
+            tools.truncate(cap);
+        }
+
+        let mut branches = Vec::with_capacity(providers.len());
+        for (provider_index, provider) in providers.into_iter().enumerate() {
+            let model_config = provider.get_model_config();
+            let mut system_prompt = self
+                .build_system_prompt(&model_config.model_name, false, None)
+                .await;
+            let (tools, toolshim_tools) = if model_config.toolshim {
+                system_prompt = modify_system_prompt_for_tool_json(&system_prompt, &tools);
+                (vec![], tools.clone())
+            } else {
+                (tools.clone(), vec![])
+            };
+            let messages = messages.clone();
+            let provider_name = provider.get_name().to_string();
+            let tag = move |event: Result<AgentEvent>| FanoutEvent {
+                provider_index,
+                provider_name: provider_name.clone(),
+                event,
+            };
+
+            let branch = async_stream::stream! {
+                let stream = Self::stream_response_from_provider(
+                    provider,
+                    &system_prompt,
+                    &messages,
+                    &tools,
+                    &toolshim_tools,
+                    None,
+                    None,
+                )
+                .await;
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        yield tag(Err(e.into()));
+                        return;
+                    }
+                };
+                while let Some(next) = stream.next().await {
+                    match next {
+                        Ok((message, usage)) => {
+                            if let Some(usage) = usage {
+                                yield tag(Ok(AgentEvent::Usage(usage)));
+                            }
+                            for event in message.into_iter().flat_map(AgentEvent::for_response) {
+                                yield tag(Ok(event));
+                            }
+                        }
+                        Err(e) => {
+                            yield tag(Err(e.into()));
+                            return;
+                        }
+                    }
+                }
+            };
+            branches.push(Box::pin(branch));
+        }
+
+        Ok(Box::pin(stream::select_all(branches)))
+    }
+
     async fn reply_internal(
         &self,
         conversation: Conversation,
@@ -940,7 +1945,7 @@ impl Agent {
         cancel_token: Option<CancellationToken>,
     ) -> Result<BoxStream<'_, Result<AgentEvent>>> {
         let context = self
-            .prepare_reply_context(conversation, &session.working_dir)
+            .prepare_reply_context(conversation, &session.id, &session.working_dir)
             .await?;
         let ReplyContext {
             mut conversation,
@@ -962,10 +1967,23 @@ impl Agent {
             }
         });
 
+        let user_cancel_token = cancel_token.clone();
+        let cancel_token = match session_config.reply_deadline {
+            Some(limit) => Some(deadline_token(cancel_token, limit)),
+            None => cancel_token,
+        };
+
         Ok(Box::pin(async_stream::try_stream! {
             let _ = reply_span.enter();
+            // Stops the deadline timer once the reply ends
+            let _deadline_guard = session_config
+                .reply_deadline
+                .and(cancel_token.clone())
+                .map(CancellationToken::drop_guard);
             let mut turns_taken = 0u32;
             let max_turns = session_config.max_turns.unwrap_or(DEFAULT_MAX_TURNS);
+            let retry_policy = self.settings.lock().await.provider_retry_policy.clone();
+            let mut provider_retries = 0;
 
             loop {
                 if is_token_cancelled(&cancel_token) {
@@ -997,26 +2015,46 @@ impl Agent {
                     &self.extension_manager,
                 ).await;
 
-                let mut stream = Self::stream_response_from_provider(
+                let (pre_send_hook, prompt_cache) = {
+                    let settings = self.settings.lock().await;
+                    (settings.pre_send_hook.clone(), settings.prompt_cache)
+                };
+                let outgoing_messages = match pre_send_hook {
+                    Some(hook) => hook(conversation_with_moim.messages().clone()),
+                    None => conversation_with_moim.messages().clone(),
+                };
+
+                let offered_tools = self.apply_tool_gates(&tools, &conversation).await;
+                let offered_toolshim_tools = self.apply_tool_gates(&toolshim_tools, &conversation).await;
+                let request = Self::stream_response_from_provider(
                     self.provider().await?,
                     &system_prompt,
-                    conversation_with_moim.messages(),
-                    &tools,
-                    &toolshim_tools,
-                ).await?;
+                    &outgoing_messages,
+                    &offered_tools,
+                    &offered_toolshim_tools,
+                    session_config.thinking_budget,
+                    prompt_cache,
+                );
+                // The agent's policy replaces the provider's own retries rather than stacking on them
+                let stream = if retry_policy.is_some() {
+                    crate::providers::retry::without_provider_retry(request).await?
+                } else {
+                    request.await?
+                };
+                let mut stream = Box::pin(stream.take_until(token_cancelled(cancel_token.clone())));
 
                 let mut no_tools_called = true;
                 let mut messages_to_add = Conversation::default();
                 let mut tools_updated = false;
                 let mut did_recovery_compact_this_iteration = false;
+                let mut retry_after_error = false;
+                let mut response_started = false;
+                let mut provider_failed = false;
 
                 while let Some(next) = stream.next().await {
-                    if is_token_cancelled(&cancel_token) {
-                        break;
-                    }
-
                     match next {
                         Ok((response, usage)) => {
+                            response_started = true;
                             // Emit model change event if provider is lead-worker
                             let provider = self.provider().await?;
                             if let Some(lead_worker) = provider.as_lead_worker() {
@@ -1040,6 +2078,17 @@ impl Agent {
 
                             if let Some(ref usage) = usage {
                                 Self::update_session_metrics(&session_config, usage, false).await?;
+                                yield AgentEvent::Usage(usage.clone());
+
+                                if let Some(explanation) = usage.finish_reason.as_ref().and_then(|r| r.explanation()) {
+                                    warn!("Provider stopped early: {:?}", usage.finish_reason);
+                                    yield AgentEvent::Message(
+                                        Message::assistant().with_system_notification(
+                                            SystemNotificationType::InlineMessage,
+                                            explanation,
+                                        )
+                                    );
+                                }
                             }
 
                             if let Some(response) = response {
@@ -1050,10 +2099,12 @@ impl Agent {
                                 } = self.categorize_tools(&response, &tools).await;
                                 let requests_to_record: Vec<ToolRequest> = frontend_requests.iter().chain(remaining_requests.iter()).cloned().collect();
                                 self.tool_route_manager
-                                    .record_tool_requests(&requests_to_record)
+                                    .record_tool_requests(&session_config.id, &requests_to_record)
                                     .await;
 
-                                yield AgentEvent::Message(filtered_response.clone());
+                                for event in AgentEvent::for_response(filtered_response.clone()) {
+                                    yield event;
+                                }
                                 tokio::task::yield_now().await;
 
                                 let num_tool_requests = frontend_requests.len() + remaining_requests.len();
@@ -1103,7 +2154,7 @@ impl Agent {
                                         )
                                         .await?;
 
-                                    let permission_check_result = self.tool_inspection_manager
+                                    let mut permission_check_result = self.tool_inspection_manager
                                         .process_inspection_results_with_permission_inspector(
                                             &remaining_requests,
                                             &inspection_results,
@@ -1117,6 +2168,13 @@ impl Agent {
                                             result.needs_approval.extend(remaining_requests.iter().cloned());
                                             result
                                         });
+                                    self.tool_inspection_manager
+                                        .apply_session_approvals(
+                                            &session_config.id,
+                                            &mut permission_check_result,
+                                            &inspection_results,
+                                        )
+                                        .await;
 
                                     // Track extension requests
                                     let mut enable_extension_request_ids = vec![];
@@ -1147,7 +2205,11 @@ impl Agent {
                                     );
 
                                     while let Some(msg) = tool_approval_stream.try_next().await? {
+                                        let approvals = AgentEvent::approvals_requested_in(&msg);
                                         yield AgentEvent::Message(msg);
+                                        for approval in approvals {
+                                            yield approval;
+                                        }
                                     }
 
                                     tool_futures = {
@@ -1155,20 +2217,35 @@ impl Agent {
                                         futures_lock.drain(..).collect::<Vec<_>>()
                                     };
 
+                                    let tool_priorities = self.settings.lock().await.tool_priorities.clone();
+                                    let priority_of = |request_id: &str| {
+                                        remaining_requests
+                                            .iter()
+                                            .find(|request| request.id == request_id)
+                                            .and_then(|request| request.tool_call.as_ref().ok())
+                                            .and_then(|tool_call| tool_priorities.get(tool_call.name.as_ref()))
+                                            .copied()
+                                            .unwrap_or(0)
+                                    };
                                     let with_id = tool_futures
                                         .into_iter()
                                         .map(|(request_id, stream)| {
-                                            stream.map(move |item| (request_id.clone(), item))
+                                            let priority = priority_of(&request_id);
+                                            let stream = self.in_flight_tools
+                                                .track(request_id.clone(), stream)
+                                                .map(move |item| (request_id.clone(), item));
+                                            (priority, stream)
                                         })
                                         .collect::<Vec<_>>();
 
-                                    let mut combined = stream::select_all(with_id);
+                                    // Cancelling drops the running tool streams, which stops their calls
+                                    let mut combined = Box::pin(
+                                        prioritized_tool_streams(with_id)
+                                            .take_until(token_cancelled(cancel_token.clone())),
+                                    );
                                     let mut all_install_successful = true;
 
                                     while let Some((request_id, item)) = combined.next().await {
-                                        if is_token_cancelled(&cancel_token) {
-                                            break;
-                                        }
 
                                         for msg in Self::drain_elicitation_messages(&session_config.id).await {
                                             yield AgentEvent::Message(msg);
@@ -1192,6 +2269,11 @@ impl Agent {
                                         }
                                     }
 
+                                    drop(combined);
+                                    if is_token_cancelled(&cancel_token) {
+                                        Self::cancel_unanswered_tools(&request_to_response_map).await;
+                                    }
+
                                     // check for remaining elicitation messages after all tools complete
                                     for msg in Self::drain_elicitation_messages(&session_config.id).await {
                                         yield AgentEvent::Message(msg);
@@ -1211,8 +2293,14 @@ impl Agent {
                                             .with_id(format!("msg_{}", Uuid::new_v4()))
                                             .with_tool_request(request.id.clone(), request.tool_call.clone());
                                         messages_to_add.push(request_msg);
-                                        let final_response = tool_response_messages[idx]
+                                        let mut final_response = tool_response_messages[idx]
                                                                 .lock().await.clone();
+                                        if self.settings.lock().await.dedup_tool_results {
+                                            final_response = dedup_tool_response(
+                                                conversation.iter().chain(messages_to_add.iter()),
+                                                final_response,
+                                            );
+                                        }
                                         yield AgentEvent::Message(final_response.clone());
                                         messages_to_add.push(final_response);
                                     }
@@ -1257,23 +2345,58 @@ impl Agent {
                             }
                         }
                         Err(ref provider_err) => {
+                            provider_failed = true;
+                            // Part of the response has already gone out, so a retry would repeat it
+                            let retry_delay = retry_policy
+                                .as_ref()
+                                .filter(|_| !response_started)
+                                .and_then(|policy| policy.delay_before_retry(provider_err, provider_retries + 1));
+                            if let Some(delay) = retry_delay {
+                                provider_retries += 1;
+                                debug!(
+                                    "Provider request failed with a transient error, retry {} in {:?}: {}",
+                                    provider_retries, delay, provider_err
+                                );
+                                retry_after_error = tokio::select! {
+                                    _ = tokio::time::sleep(delay) => true,
+                                    _ = token_cancelled(cancel_token.clone()) => false,
+                                };
+                                break;
+                            }
                             crate::posthog::emit_error(provider_err.telemetry_type());
                             error!("Error: {}", provider_err);
+                            let resume_on_error = self.settings.lock().await.resume_on_error;
+                            if resume_on_error {
+                                self.discard_stale_error_recovery().await;
+                            }
                             yield AgentEvent::Message(
                                 Message::assistant().with_text(
                                     format!("Ran into this error: {provider_err}.\n\nPlease retry if you think this is a transient or recoverable error.")
                                 )
                             );
+                            if resume_on_error {
+                                retry_after_error = self.wait_for_error_recovery(&cancel_token).await
+                                    == ErrorRecoveryAction::Retry;
+                            }
                             break;
                         }
                     }
                 }
+                if retry_after_error {
+                    // Retries go back through the top of the loop, but repeat this turn
+                    info!("Retrying provider request after error");
+                    turns_taken -= 1;
+                    continue;
+                }
+                if !provider_failed {
+                    provider_retries = 0;
+                }
                 if tools_updated {
                     (tools, toolshim_tools, system_prompt) =
-                        self.prepare_tools_and_prompt(&working_dir).await?;
+                        self.prepare_tools_and_prompt(&session_config.id, &working_dir).await?;
                 }
-                let mut exit_chat = false;
-                if no_tools_called {
+                let mut exit_chat = is_token_cancelled(&cancel_token);
+                if no_tools_called && !exit_chat {
                     if let Some(final_output_tool) = self.final_output_tool.lock().await.as_ref() {
                         if final_output_tool.final_output.is_none() {
                             warn!("Final output tool has not been called yet. Continuing agent loop.");
@@ -1320,6 +2443,19 @@ impl Agent {
 
                 tokio::task::yield_now().await;
             }
+
+            if is_token_cancelled(&user_cancel_token) {
+                yield AgentEvent::Message(
+                    Message::assistant().with_text("The reply was cancelled before it finished.")
+                );
+            } else if is_token_cancelled(&cancel_token) {
+                if let Some(limit) = session_config.reply_deadline {
+                    yield AgentEvent::Message(Message::assistant().with_text(format!(
+                        "The reply was stopped after reaching its deadline of {:?}.",
+                        limit
+                    )));
+                }
+            }
         }))
     }
 
@@ -1328,6 +2464,106 @@ impl Agent {
         prompt_manager.add_system_prompt_extra(instruction);
     }
 
+    /// Add an instruction to the system prompt of a single session only
+    pub async fn extend_system_prompt_for_session(&self, session_id: &str, instruction: String) {
+        let mut prompt_manager = self.prompt_manager.lock().await;
+        prompt_manager.add_session_system_prompt_extra(session_id, instruction);
+    }
+
+    /// Write the tools the model is offered to `path` as a JSON array, in the shape `format`'s
+    /// provider would send them. These are the tools a reply sends without the router:
+    /// extension, platform, frontend, sub-recipe and final output tools.
+    pub async fn export_tool_schemas(&self, path: &Path, format: ToolSchemaFormat) -> Result<()> {
+        let mut tools = self.unrouted_tools().await?;
+        if let Some(cap) = self.settings.lock().await.max_offered_tools {
+            tools.truncate(cap);
+        }
+        let schemas = match format {
+            ToolSchemaFormat::OpenAi => crate::providers::formats::openai::format_tools(&tools)?,
+            ToolSchemaFormat::Anthropic => {
+                crate::providers::formats::anthropic::format_tools(&tools)
+            }
+        };
+        tokio::fs::write(path, serde_json::to_string_pretty(&schemas)?).await?;
+        Ok(())
+    }
+
+    /// Export token usage, estimated cost and tool activity for a session
+    pub async fn export_session_metrics(
+        &self,
+        session_config: &SessionConfig,
+    ) -> Result<SessionMetricsJson> {
+        let session = SessionManager::get_session(&session_config.id, true).await?;
+
+        let mut tool_call_counts = std::collections::BTreeMap::new();
+        if let Some(conversation) = &session.conversation {
+            for message in conversation.messages() {
+                for request in message.content.iter().filter_map(|c| c.as_tool_request()) {
+                    if let Ok(tool_call) = &request.tool_call {
+                        *tool_call_counts
+                            .entry(tool_call.name.to_string())
+                            .or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        let estimated_cost = match (&session.provider_name, &session.model_config) {
+            (Some(provider_name), Some(model_config)) => {
+                crate::providers::pricing::get_model_pricing(
+                    provider_name,
+                    &model_config.model_name,
+                )
+                .await
+                .map(|pricing| {
+                    session.accumulated_input_tokens.unwrap_or(0) as f64 * pricing.input_cost
+                        + session.accumulated_output_tokens.unwrap_or(0) as f64
+                            * pricing.output_cost
+                })
+            }
+            _ => None,
+        };
+
+        Ok(SessionMetricsJson {
+            session_id: session.id,
+            input_tokens: session.accumulated_input_tokens,
+            output_tokens: session.accumulated_output_tokens,
+            total_tokens: session.accumulated_total_tokens,
+            estimated_cost,
+            message_count: session.message_count,
+            tool_call_counts,
+        })
+    }
+
+    /// Replay the most recent user message against another provider and return its response.
+    /// Nothing is written to the session and the agent's own provider is left untouched.
+    pub async fn rerun_last_turn(
+        &self,
+        session_config: &SessionConfig,
+        provider_override: Arc<dyn Provider>,
+    ) -> Result<Message> {
+        let session = SessionManager::get_session(&session_config.id, true).await?;
+        let messages = session
+            .conversation
+            .as_ref()
+            .ok_or_else(|| anyhow!("Session {} has no conversation", session_config.id))?
+            .agent_visible_messages();
+
+        let last_user_index = messages
+            .iter()
+            .rposition(|m| m.role == Role::User && !m.is_tool_response())
+            .ok_or_else(|| anyhow!("Session {} has no user message to re-run", session.id))?;
+
+        let (tools, _toolshim_tools, system_prompt) = self
+            .prepare_tools_and_prompt(&session.id, &session.working_dir)
+            .await?;
+
+        let (response, _usage) = provider_override
+            .complete(&system_prompt, &messages[..=last_user_index], &tools)
+            .await?;
+        Ok(response)
+    }
+
     pub async fn update_provider(
         &self,
         provider: Arc<dyn Provider>,
@@ -1369,6 +2605,12 @@ impl Agent {
         prompt_manager.set_system_prompt_override(template);
     }
 
+    /// Set the values of custom placeholders in the system prompt override
+    pub async fn set_prompt_template_variables(&self, variables: HashMap<String, String>) {
+        let mut prompt_manager = self.prompt_manager.lock().await;
+        prompt_manager.set_template_variables(variables);
+    }
+
     pub async fn list_extension_prompts(&self) -> HashMap<String, Vec<Prompt>> {
         self.extension_manager
             .list_prompts(CancellationToken::default())
@@ -1399,6 +2641,16 @@ impl Agent {
         Err(anyhow!("Prompt '{}' not found", name))
     }
 
+    /// Simplified argument descriptors for the tool called `name`, or None if no such tool
+    pub async fn tool_parameters(&self, name: &str) -> Option<Vec<ToolParam>> {
+        self.get_tool(name).await.map(|tool| get_parameters(&tool))
+    }
+
+    /// Render `messages` as readable Markdown for sharing, see [`markdown::to_markdown`]
+    pub fn export_markdown(&self, messages: &[Message]) -> String {
+        markdown::to_markdown(messages)
+    }
+
     pub async fn get_plan_prompt(&self) -> Result<String> {
         let tools = self.extension_manager.get_prefixed_tools(None).await?;
         let tools_info = tools
@@ -1426,218 +2678,14 @@ impl Agent {
             error!("Failed to send tool result: {}", e);
         }
     }
-
-    pub async fn create_recipe(&self, mut messages: Conversation) -> Result<Recipe> {
-        tracing::info!("Starting recipe creation with {} messages", messages.len());
-
-        let extensions_info = self.extension_manager.get_extensions_info().await;
-        tracing::debug!("Retrieved {} extensions info", extensions_info.len());
-        let (extension_count, tool_count) =
-            self.extension_manager.get_extension_and_tool_counts().await;
-
-        // Get model name from provider
-        let provider = self.provider().await.map_err(|e| {
-            tracing::error!("Failed to get provider for recipe creation: {}", e);
-            e
-        })?;
-        let model_config = provider.get_model_config();
-        let model_name = &model_config.model_name;
-        tracing::debug!("Using model: {}", model_name);
-
-        let prompt_manager = self.prompt_manager.lock().await;
-        let system_prompt = prompt_manager
-            .builder(model_name)
-            .with_extensions(extensions_info.into_iter())
-            .with_frontend_instructions(self.frontend_instructions.lock().await.clone())
-            .with_extension_and_tool_counts(extension_count, tool_count)
-            .build();
-
-        let recipe_prompt = prompt_manager.get_recipe_prompt().await;
-        let tools = self
-            .extension_manager
-            .get_prefixed_tools(None)
-            .await
-            .map_err(|e| {
-                tracing::error!("Failed to get tools for recipe creation: {}", e);
-                e
-            })?;
-
-        messages.push(Message::user().with_text(recipe_prompt));
-
-        let (messages, issues) = fix_conversation(messages);
-        if !issues.is_empty() {
-            issues
-                .iter()
-                .for_each(|issue| tracing::warn!(recipe.conversation.issue = issue));
-        }
-
-        tracing::debug!(
-            "Added recipe prompt to messages, total messages: {}",
-            messages.len()
-        );
-
-        tracing::info!("Calling provider to generate recipe content");
-        let (result, _usage) = self
-            .provider
-            .lock()
-            .await
-            .as_ref()
-            .ok_or_else(|| {
-                let error = anyhow!("Provider not available during recipe creation");
-                tracing::error!("{}", error);
-                error
-            })?
-            .complete(&system_prompt, messages.messages(), &tools)
-            .await
-            .map_err(|e| {
-                tracing::error!("Provider completion failed during recipe creation: {}", e);
-                e
-            })?;
-
-        let content = result.as_concat_text();
-        tracing::debug!(
-            "Provider returned content with {} characters",
-            content.len()
-        );
-
-        // the response may be contained in ```json ```, strip that before parsing json
-        let re = Regex::new(r"(?s)```[^\n]*\n(.*?)\n```").unwrap();
-        let clean_content = re
-            .captures(&content)
-            .and_then(|caps| caps.get(1).map(|m| m.as_str()))
-            .unwrap_or(&content)
-            .trim()
-            .to_string();
-
-        let (instructions, activities) =
-            if let Ok(json_content) = serde_json::from_str::<Value>(&clean_content) {
-                let instructions = json_content
-                    .get("instructions")
-                    .ok_or_else(|| anyhow!("Missing 'instructions' in json response"))?
-                    .as_str()
-                    .ok_or_else(|| anyhow!("instructions' is not a string"))?
-                    .to_string();
-
-                let activities = json_content
-                    .get("activities")
-                    .ok_or_else(|| anyhow!("Missing 'activities' in json response"))?
-                    .as_array()
-                    .ok_or_else(|| anyhow!("'activities' is not an array'"))?
-                    .iter()
-                    .map(|act| {
-                        act.as_str()
-                            .map(|s| s.to_string())
-                            .ok_or(anyhow!("'activities' array element is not a string"))
-                    })
-                    .collect::<Result<_, _>>()?;
-
-                (instructions, activities)
-            } else {
-                tracing::warn!("Failed to parse JSON, falling back to string parsing");
-                // If we can't get valid JSON, try string parsing
-                // Use split_once to get the content after "Instructions:".
-                let after_instructions = content
-                    .split_once("instructions:")
-                    .map(|(_, rest)| rest)
-                    .unwrap_or(&content);
-
-                // Split once more to separate instructions from activities.
-                let (instructions_part, activities_text) = after_instructions
-                    .split_once("activities:")
-                    .unwrap_or((after_instructions, ""));
-
-                let instructions = instructions_part
-                    .trim_end_matches(|c: char| c.is_whitespace() || c == '#')
-                    .trim()
-                    .to_string();
-                let activities_text = activities_text.trim();
-
-                // Regex to remove bullet markers or numbers with an optional dot.
-                let bullet_re = Regex::new(r"^[•\-*\d]+\.?\s*").expect("Invalid regex");
-
-                // Process each line in the activities section.
-                let activities: Vec<String> = activities_text
-                    .lines()
-                    .map(|line| bullet_re.replace(line, "").to_string())
-                    .map(|s| s.trim().to_string())
-                    .filter(|line| !line.is_empty())
-                    .collect();
-
-                (instructions, activities)
-            };
-
-        let extension_configs = get_enabled_extensions();
-
-        let author = Author {
-            contact: std::env::var("USER")
-                .or_else(|_| std::env::var("USERNAME"))
-                .ok(),
-            metadata: None,
-        };
-
-        // Ideally we'd get the name of the provider we are using from the provider itself,
-        // but it doesn't know and the plumbing looks complicated.
-        let config = Config::global();
-        let provider_name: String = config
-            .get_goose_provider()
-            .expect("No provider configured. Run 'goose configure' first");
-
-        let settings = Settings {
-            goose_provider: Some(provider_name.clone()),
-            goose_model: Some(model_name.clone()),
-            temperature: Some(model_config.temperature.unwrap_or(0.0)),
-        };
-
-        tracing::debug!(
-            "Building recipe with {} activities and {} extensions",
-            activities.len(),
-            extension_configs.len()
-        );
-
-        let (title, description) =
-            if let Ok(json_content) = serde_json::from_str::<Value>(&clean_content) {
-                let title = json_content
-                    .get("title")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("Custom recipe from chat")
-                    .to_string();
-
-                let description = json_content
-                    .get("description")
-                    .and_then(|d| d.as_str())
-                    .unwrap_or("a custom recipe instance from this chat session")
-                    .to_string();
-
-                (title, description)
-            } else {
-                (
-                    "Custom recipe from chat".to_string(),
-                    "a custom recipe instance from this chat session".to_string(),
-                )
-            };
-
-        let recipe = Recipe::builder()
-            .title(title)
-            .description(description)
-            .instructions(instructions)
-            .activities(activities)
-            .extensions(extension_configs)
-            .settings(settings)
-            .author(author)
-            .build()
-            .map_err(|e| {
-                tracing::error!("Failed to build recipe: {}", e);
-                anyhow!("Recipe build failed: {}", e)
-            })?;
-
-        tracing::info!("Recipe creation completed successfully");
-        Ok(recipe)
-    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::test_extension::{add_notifying_extension, TwoLongTasksProvider};
+    use crate::model::ModelConfig;
+    use crate::providers::scriptedprovider::ScriptedProvider;
     use crate::recipe::Response;
 
     #[tokio::test]
@@ -1697,4 +2745,916 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_tool_monitor_state_carries_repetitions_to_a_new_agent() -> Result<()> {
+        let call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let mut state = None;
+        let mut calls_received = Vec::new();
+        for run in ["before-restart", "after-restart"] {
+            let agent = Agent::new();
+            let received_arguments = add_notifying_extension(&agent, vec![]).await;
+            agent.set_goose_mode_override(Some(GooseMode::Auto)).await;
+            agent.set_max_tool_repetitions(Some(1));
+            if let Some(state) = state.take() {
+                agent.import_tool_monitor_state(state);
+            }
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                format!("tool-monitor-{run}"),
+                crate::session::SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(
+                    Arc::new(ScriptedProvider::calling_tools(vec![call.clone()])),
+                    &session.id,
+                )
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let mut stream = agent
+                .reply(Message::user().with_text("notify"), session_config, None)
+                .await?;
+            while let Some(event) = stream.next().await {
+                event?;
+            }
+
+            calls_received.push(received_arguments.lock().unwrap().len());
+            state = agent.export_tool_monitor_state();
+            assert_ne!(state, Some(RepetitionState::default()));
+        }
+
+        // The second agent already counts the first agent's call, so the repeat is denied
+        assert_eq!(calls_received, vec![1, 0]);
+        Ok(())
+    }
+
+    /// A selector whose index is unreachable, so indexing and searching both fail
+    struct FailingIndexSelector;
+
+    #[async_trait::async_trait]
+    impl RouterToolSelector for FailingIndexSelector {
+        async fn select_tools(&self, _params: rmcp::model::JsonObject) -> ToolResult<Vec<Content>> {
+            Err(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                "index unavailable".to_string(),
+                None,
+            ))
+        }
+
+        async fn index_tools(&self, _tools: &[Tool], _extension_name: &str) -> ToolResult<()> {
+            Err(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                "index unavailable".to_string(),
+                None,
+            ))
+        }
+
+        async fn remove_tool(&self, _tool_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn record_tool_call(&self, _tool_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn get_recent_tool_calls(&self, _limit: usize) -> ToolResult<Vec<String>> {
+            Ok(vec![])
+        }
+    }
+
+    /// Reports a fixed list of recently called tools, standing in for a custom retrieval backend
+    struct RecentCallsSelector(Vec<String>);
+
+    #[async_trait::async_trait]
+    impl RouterToolSelector for RecentCallsSelector {
+        async fn select_tools(&self, _params: rmcp::model::JsonObject) -> ToolResult<Vec<Content>> {
+            Ok(vec![])
+        }
+
+        async fn index_tools(&self, _tools: &[Tool], _extension_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn remove_tool(&self, _tool_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn record_tool_call(&self, _tool_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn get_recent_tool_calls(&self, _limit: usize) -> ToolResult<Vec<String>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_custom_router_selector_used_for_router_tools() {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![]).await;
+        agent
+            .set_router_selector(Arc::new(Box::new(RecentCallsSelector(vec![
+                "notify__long_task".to_string(),
+            ]))))
+            .await;
+        let tools = agent
+            .tool_route_manager
+            .list_tools_for_router(&agent.extension_manager, "session")
+            .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+
+        let names: Vec<&str> = tools.iter().map(|t| &*t.name).collect();
+        assert_eq!(
+            names,
+            vec![ROUTER_LLM_SEARCH_TOOL_NAME, "notify__long_task"]
+        );
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_platform_tool_description_override() {
+        let key = crate::agents::platform_tools::PLATFORM_TOOL_DESCRIPTIONS_CONFIG_KEY;
+        let original = std::env::var(key).ok();
+        std::env::set_var(
+            key,
+            serde_json::json!({
+                PLATFORM_MANAGE_SCHEDULE_TOOL_NAME: "Planifier des recettes. {{ tool_name }}"
+            })
+            .to_string(),
+        );
+
+        let tools = Agent::new().list_tools(Some("platform".to_string())).await;
+
+        match original {
+            Some(val) => std::env::set_var(key, val),
+            None => std::env::remove_var(key),
+        }
+
+        let schedule = tools
+            .iter()
+            .find(|tool| tool.name == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME)
+            .unwrap();
+        assert_eq!(
+            schedule.description.as_deref(),
+            Some("Planifier des recettes. platform__manage_schedule")
+        );
+    }
+
+    /// Records the tools removed from the index
+    struct RemovalRecordingSelector(Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[async_trait::async_trait]
+    impl RouterToolSelector for RemovalRecordingSelector {
+        async fn select_tools(&self, _params: rmcp::model::JsonObject) -> ToolResult<Vec<Content>> {
+            Ok(vec![])
+        }
+
+        async fn index_tools(&self, _tools: &[Tool], _extension_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn remove_tool(&self, tool_name: &str) -> ToolResult<()> {
+            self.0.lock().unwrap().push(tool_name.to_string());
+            Ok(())
+        }
+
+        async fn record_tool_call(&self, _tool_name: &str) -> ToolResult<()> {
+            Ok(())
+        }
+
+        async fn get_recent_tool_calls(&self, _limit: usize) -> ToolResult<Vec<String>> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_replacing_extension_updates_router_index() -> Result<()> {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+        let todo = |available_tools: Vec<String>| ExtensionConfig::Platform {
+            name: "todo".to_string(),
+            description: "todo".to_string(),
+            bundled: None,
+            available_tools,
+            required: false,
+        };
+
+        let agent = Agent::new();
+        let removed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        agent
+            .set_router_selector(Arc::new(Box::new(RemovalRecordingSelector(
+                removed.clone(),
+            ))))
+            .await;
+        let result = async {
+            agent.add_extension(todo(vec![])).await?;
+            let rejected = agent.add_extension(todo(vec!["missing".to_string()])).await;
+            agent
+                .set_duplicate_extension_policy(DuplicateExtensionPolicy::Replace)
+                .await;
+            agent
+                .add_extension(todo(vec!["missing".to_string()]))
+                .await?;
+            Ok::<_, ExtensionError>(rejected)
+        }
+        .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+
+        assert!(matches!(result?, Err(ExtensionError::AlreadyLoaded(_))));
+        assert_eq!(*removed.lock().unwrap(), vec!["todo__todo_write"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_config_snapshot_reflects_settings() {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let agent = Agent::new();
+        *agent.provider.lock().await = Some(Arc::new(TwoLongTasksProvider));
+        agent
+            .set_goose_mode_override(Some(GooseMode::Approve))
+            .await;
+        add_notifying_extension(&agent, vec![]).await;
+        agent
+            .set_router_selector(Arc::new(Box::new(RecentCallsSelector(vec![]))))
+            .await;
+        let permissions_file = tempfile::NamedTempFile::new().unwrap();
+        *agent
+            .tool_inspection_manager
+            .permission_inspector()
+            .unwrap()
+            .permission_manager
+            .lock()
+            .await = crate::config::PermissionManager::new(permissions_file.path());
+        agent
+            .tool_inspection_manager
+            .update_permission_manager(
+                "notify__long_task",
+                crate::config::permission::PermissionLevel::NeverAllow,
+            )
+            .await;
+
+        let snapshot = agent.config_snapshot().await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+
+        assert_eq!(snapshot.provider.as_deref(), Some("two-long-tasks"));
+        assert_eq!(snapshot.model.as_deref(), Some("mock-model"));
+        assert_eq!(snapshot.mode, GooseMode::Approve);
+        assert_eq!(snapshot.extensions, vec!["notify"]);
+        assert_eq!(
+            snapshot.router_strategy,
+            crate::agents::RouterStrategy::Custom
+        );
+        assert_eq!(
+            snapshot.tool_inspectors,
+            vec!["security", "permission", "repetition"]
+        );
+        assert_eq!(snapshot.max_tool_repetitions, None);
+        assert_eq!(
+            snapshot.permission_rules,
+            std::collections::BTreeMap::from([(
+                "notify__long_task".to_string(),
+                crate::config::permission::PermissionLevel::NeverAllow
+            )])
+        );
+        assert!(serde_json::to_value(&snapshot).is_ok());
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_router_recent_tools_are_scoped_to_session() -> Result<()> {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let result = async {
+            let agent = Agent::new();
+            add_notifying_extension(&agent, vec![]).await;
+            agent
+                .add_extension(ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    bundled: None,
+                    available_tools: vec![],
+                    required: false,
+                })
+                .await?;
+            let selector = crate::agents::router_tool_selector::LLMToolSelector::new(Arc::new(
+                TwoLongTasksProvider,
+            ))
+            .await?;
+            agent
+                .set_router_selector(Arc::new(Box::new(selector)))
+                .await;
+
+            let request = |name: &str| ToolRequest {
+                id: format!("call_{name}"),
+                tool_call: Ok(CallToolRequestParam {
+                    name: name.to_string().into(),
+                    arguments: None,
+                }),
+                thought_signature: None,
+            };
+            agent
+                .tool_route_manager
+                .record_tool_requests("session_a", &[request("notify__long_task")])
+                .await;
+            agent
+                .tool_route_manager
+                .record_tool_requests("session_b", &[request("todo__todo_write")])
+                .await;
+
+            let mut surfaced = Vec::new();
+            for session_id in ["session_a", "session_b", "session_c"] {
+                let names: Vec<String> = agent
+                    .list_tools_for_router(session_id)
+                    .await
+                    .into_iter()
+                    .map(|t| t.name.to_string())
+                    .filter(|name| name != ROUTER_LLM_SEARCH_TOOL_NAME)
+                    .collect();
+                surfaced.push(names);
+            }
+            anyhow::Ok(surfaced)
+        }
+        .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+
+        assert_eq!(
+            result?,
+            vec![
+                vec!["notify__long_task".to_string()],
+                vec!["todo__todo_write".to_string()],
+                vec![],
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_extension_reports_spawn_failure() {
+        let agent = Agent::new();
+        let result = agent
+            .add_extension(ExtensionConfig::Stdio {
+                name: "missing".to_string(),
+                description: String::new(),
+                cmd: "/nonexistent/goose-test-extension".to_string(),
+                args: vec![],
+                envs: Default::default(),
+                env_keys: vec![],
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await;
+
+        assert!(matches!(result, Err(ExtensionError::SpawnFailed(_))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extension_stderr_is_captured_when_it_dies() {
+        let agent = Agent::new();
+        let result = agent
+            .add_extension(ExtensionConfig::Stdio {
+                name: "crashy".to_string(),
+                description: String::new(),
+                cmd: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    "echo 'loading config' >&2; echo 'fatal: API_TOKEN is not set' >&2; exit 1"
+                        .to_string(),
+                ],
+                envs: Default::default(),
+                env_keys: vec![],
+                timeout: Some(10),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await;
+
+        let err = result.expect_err("extension exits before initializing");
+        assert!(err.to_string().contains("fatal: API_TOKEN is not set"));
+        assert_eq!(
+            agent.extension_logs("crashy").await,
+            vec!["loading config", "fatal: API_TOKEN is not set"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extension_startup_error_masks_env_secrets() {
+        let secret = "sk-test-4f9a1c2e7b";
+        let agent = Agent::new();
+        let result = agent
+            .add_extension(ExtensionConfig::Stdio {
+                name: "leaky".to_string(),
+                description: String::new(),
+                cmd: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    "echo \"auth rejected for token $SERVICE_TOKEN\" >&2; exit 1".to_string(),
+                ],
+                envs: crate::agents::extension::Envs::new(HashMap::from([(
+                    "SERVICE_TOKEN".to_string(),
+                    secret.to_string(),
+                )])),
+                env_keys: vec![],
+                timeout: Some(10),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await;
+
+        let message = result
+            .expect_err("extension exits before initializing")
+            .to_string();
+        assert!(
+            message.contains("auth rejected for token ****"),
+            "{message}"
+        );
+        assert!(!message.contains(secret));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_add_extension_reports_indexing_failure() {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let agent = Agent::new();
+        agent
+            .tool_route_manager
+            .set_router_tool_selector(Some(Arc::new(Box::new(FailingIndexSelector))))
+            .await;
+        let result = agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+
+        match result {
+            Err(ExtensionError::IndexingFailed { extension, message }) => {
+                assert_eq!(extension, "todo");
+                assert!(message.contains("index unavailable"));
+            }
+            other => panic!("Expected IndexingFailed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_router_falls_back_to_all_tools_when_unavailable() -> Result<()> {
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let result = async {
+            let agent = Agent::new();
+            add_notifying_extension(&agent, vec![]).await;
+            *agent.provider.lock().await = Some(Arc::new(TwoLongTasksProvider));
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "router-fallback-test".to_string(),
+                crate::session::SessionType::Hidden,
+            )
+            .await?;
+
+            // No selector was initialized, so every tool is offered
+            let (tools, _, _) = agent
+                .prepare_tools_and_prompt(&session.id, &session.working_dir)
+                .await?;
+            let names: Vec<&str> = tools.iter().map(|t| &*t.name).collect();
+            assert!(names.contains(&"notify__long_task"), "{names:?}");
+            assert!(!names.contains(&ROUTER_LLM_SEARCH_TOOL_NAME));
+
+            // A failing search lists every tool instead
+            agent
+                .tool_route_manager
+                .set_router_tool_selector(Some(Arc::new(Box::new(FailingIndexSelector))))
+                .await;
+            let search = CallToolRequestParam {
+                name: ROUTER_LLM_SEARCH_TOOL_NAME.into(),
+                arguments: Some(rmcp::object!({"query": "long running work"})),
+            };
+            let (_, result) = agent
+                .dispatch_tool_call(search, "call_1".to_string(), None, &session)
+                .await;
+            let content = result
+                .map_err(|e| anyhow!(e.message))?
+                .result
+                .await
+                .map_err(|e| anyhow!(e.message))?;
+            assert!(content.iter().any(|c| c
+                .as_text()
+                .unwrap()
+                .text
+                .contains("Tool: notify__long_task")));
+            Ok(())
+        }
+        .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+        result
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_event_delay_spaces_reply_events() {
+        let events = stream::iter((0..3).map(|i| {
+            Ok(AgentEvent::Message(
+                Message::assistant().with_text(i.to_string()),
+            ))
+        }))
+        .boxed();
+
+        let start = tokio::time::Instant::now();
+        let mut paced = with_event_delay(events, Duration::from_millis(250));
+        let mut arrivals = Vec::new();
+        while let Some(event) = paced.next().await {
+            event.unwrap();
+            arrivals.push(start.elapsed());
+        }
+
+        assert_eq!(
+            arrivals,
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(250),
+                Duration::from_millis(500)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frontend_tool_with_invalid_schema_is_rejected() {
+        let agent = Agent::new();
+        let valid = Tool::new(
+            "show_chart",
+            "Render a chart",
+            rmcp::object!({"type": "object", "properties": {"title": {"type": "string"}}}),
+        );
+        let invalid = Tool::new(
+            "pick_color",
+            "Pick a color",
+            rmcp::object!({"type": "object", "properties": {"color": {"type": "colour"}}}),
+        );
+
+        let result = agent
+            .add_extension(ExtensionConfig::Frontend {
+                name: "frontend".to_string(),
+                description: "frontend".to_string(),
+                tools: vec![valid, invalid],
+                instructions: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await;
+
+        match result {
+            Err(ExtensionError::ConfigError(message)) => {
+                assert!(message.contains("pick_color"), "{}", message);
+            }
+            other => panic!("Expected ConfigError, got {:?}", other),
+        }
+        assert!(!agent.is_frontend_tool("show_chart").await);
+        assert!(!agent.is_frontend_tool("pick_color").await);
+    }
+
+    #[tokio::test]
+    async fn test_tools_fingerprint_tracks_tool_set() -> Result<()> {
+        let agent = Agent::new();
+        let initial = agent.tools_fingerprint().await;
+        assert_eq!(initial, agent.tools_fingerprint().await);
+
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        let with_todo = agent.tools_fingerprint().await;
+        assert_ne!(initial, with_todo);
+        assert_eq!(with_todo, agent.tools_fingerprint().await);
+
+        agent.remove_extension("todo").await?;
+        assert_eq!(initial, agent.tools_fingerprint().await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dispatchable_tool_names_covers_every_tool_source() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent
+            .add_extension(ExtensionConfig::Frontend {
+                name: "frontend".to_string(),
+                description: "frontend".to_string(),
+                tools: vec![Tool::new(
+                    "show_chart",
+                    "Render a chart",
+                    rmcp::object!({"type": "object", "properties": {}}),
+                )],
+                instructions: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent
+            .add_final_output_tool(Response {
+                json_schema: Some(serde_json::json!({"type": "object", "properties": {}})),
+            })
+            .await;
+
+        let dir = tempfile::tempdir()?;
+        let recipe_path = dir.path().join("summarize.yaml");
+        std::fs::write(
+            &recipe_path,
+            "version: 1.0.0\ntitle: Summarize\ndescription: Summarize\ninstructions: Summarize\n",
+        )?;
+        agent
+            .add_sub_recipes(vec![SubRecipe {
+                name: "summarize".to_string(),
+                path: recipe_path.to_string_lossy().to_string(),
+                values: None,
+                sequential_when_repeated: false,
+                description: None,
+            }])
+            .await;
+
+        let names = agent.dispatchable_tool_names().await;
+        for expected in [
+            "show_chart",
+            "todo__todo_write",
+            FINAL_OUTPUT_TOOL_NAME,
+            PLATFORM_MANAGE_SCHEDULE_TOOL_NAME,
+            DYNAMIC_TASK_TOOL_NAME_PREFIX,
+            SUBAGENT_EXECUTE_TASK_TOOL_NAME,
+            "subrecipe__create_task_summarize",
+        ] {
+            assert!(
+                names.iter().any(|name| name == expected),
+                "{expected} missing from {names:?}"
+            );
+        }
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_tool_schemas_in_each_format() -> Result<()> {
+        let agent = Agent::new();
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "export-tool-schemas".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        agent
+            .update_provider(Arc::new(ScriptedProvider::replying("done")), &session.id)
+            .await?;
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent
+            .add_extension(ExtensionConfig::Frontend {
+                name: "frontend".to_string(),
+                description: "frontend".to_string(),
+                tools: vec![Tool::new(
+                    "show_chart",
+                    "Render a chart",
+                    rmcp::object!({"type": "object", "properties": {}}),
+                )],
+                instructions: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent
+            .add_final_output_tool(Response {
+                json_schema: Some(serde_json::json!({"type": "object"})),
+            })
+            .await;
+
+        let dir = tempfile::tempdir()?;
+        for (format, name_pointer) in [
+            (ToolSchemaFormat::OpenAi, "/function/name"),
+            (ToolSchemaFormat::Anthropic, "/name"),
+        ] {
+            let path = dir.path().join(format!("{:?}.json", format));
+            agent.export_tool_schemas(&path, format).await?;
+
+            let exported: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let names: Vec<&str> = exported
+                .iter()
+                .filter_map(|tool| tool.pointer(name_pointer).and_then(Value::as_str))
+                .collect();
+            assert_eq!(names.len(), exported.len());
+            // The same tools a reply offers: extension, frontend, platform and final output
+            for expected in [
+                "todo__todo_write",
+                "show_chart",
+                PLATFORM_MANAGE_SCHEDULE_TOOL_NAME,
+                FINAL_OUTPUT_TOOL_NAME,
+            ] {
+                assert!(names.contains(&expected), "{format:?}: {names:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_parameters_describes_schema_properties() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .add_extension(ExtensionConfig::Frontend {
+                name: "frontend".to_string(),
+                description: "frontend".to_string(),
+                tools: vec![Tool::new(
+                    "show_chart",
+                    "Render a chart",
+                    rmcp::object!({
+                        "type": "object",
+                        "properties": {
+                            "title": {"type": "string", "description": "Chart heading"},
+                            "points": {"type": "integer"}
+                        },
+                        "required": ["title"]
+                    }),
+                )],
+                instructions: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+
+        let mut params = agent.tool_parameters("show_chart").await.unwrap();
+        params.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(
+            params,
+            vec![
+                ToolParam {
+                    name: "points".to_string(),
+                    param_type: "integer".to_string(),
+                    required: false,
+                    description: None,
+                },
+                ToolParam {
+                    name: "title".to_string(),
+                    param_type: "string".to_string(),
+                    required: true,
+                    description: Some("Chart heading".to_string()),
+                },
+            ]
+        );
+        assert!(agent.tool_parameters("missing_tool").await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_checks_platform_and_extension_tools() {
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![]).await;
+
+        let schedule_tool = agent
+            .get_tool(PLATFORM_MANAGE_SCHEDULE_TOOL_NAME)
+            .await
+            .expect("platform tool should be found");
+        assert!(schedule_tool.input_schema.contains_key("properties"));
+        assert!(agent.get_tool("notify__long_task").await.is_some());
+        assert!(agent.get_tool("notify__missing").await.is_none());
+    }
+
+    struct UnavailableProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for UnavailableProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "unavailable"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock-model").unwrap()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            Err(ProviderError::ExecutionError("unavailable".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reply_fanout_isolates_failing_provider() -> Result<()> {
+        let agent = Agent::new();
+        let received_arguments = add_notifying_extension(&agent, vec![]).await;
+
+        let events: Vec<FanoutEvent> = agent
+            .reply_fanout(
+                vec![Message::user().with_text("start both tasks")],
+                vec![
+                    Arc::new(TwoLongTasksProvider),
+                    Arc::new(UnavailableProvider),
+                ],
+            )
+            .await?
+            .collect()
+            .await;
+
+        let tool_requests = events
+            .iter()
+            .filter(|e| e.provider_index == 0)
+            .filter_map(|e| match &e.event {
+                Ok(AgentEvent::Message(message)) => Some(message),
+                _ => None,
+            })
+            .flat_map(|message| message.content.iter())
+            .filter(|content| matches!(content, MessageContent::ToolRequest(_)))
+            .count();
+        assert_eq!(tool_requests, 2);
+        assert!(received_arguments.lock().unwrap().is_empty());
+
+        let failed: Vec<_> = events.iter().filter(|e| e.event.is_err()).collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].provider_index, 1);
+        assert_eq!(failed[0].provider_name, "unavailable");
+        Ok(())
+    }
 }
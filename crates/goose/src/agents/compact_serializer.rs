@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rmcp::model::Content;
+use serde_json::Value;
+
+/// Rewrites JSON tool results without whitespace and with long keys abbreviated, so
+/// structured output takes fewer tokens in the conversation
+#[derive(Debug, Clone, Default)]
+pub struct CompactSerializer {
+    abbreviations: BTreeMap<String, String>,
+}
+
+impl CompactSerializer {
+    /// Only strip whitespace; add abbreviations with `with_abbreviation`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `key` as `abbreviation` in compacted results
+    pub fn with_abbreviation(
+        mut self,
+        key: impl Into<String>,
+        abbreviation: impl Into<String>,
+    ) -> Self {
+        self.abbreviations.insert(key.into(), abbreviation.into());
+        self
+    }
+
+    /// Compact every text item that holds a JSON object or array, leaving other content as
+    /// is. When keys were abbreviated, a note with the mapping is appended.
+    pub fn compact(&self, content: Vec<Content>) -> Vec<Content> {
+        let mut used = BTreeSet::new();
+        let mut compacted: Vec<Content> = content
+            .into_iter()
+            .map(|item| {
+                let Some(value) = item.as_text().and_then(|t| parse_structured(&t.text)) else {
+                    return item;
+                };
+                let value = self.abbreviate(value, &mut used);
+                match serde_json::to_string(&value) {
+                    Ok(text) => Content::text(text),
+                    Err(_) => item,
+                }
+            })
+            .collect();
+
+        if !used.is_empty() {
+            compacted.push(Content::text(self.mapping_note(&used)));
+        }
+        compacted
+    }
+
+    fn abbreviate(&self, value: Value, used: &mut BTreeSet<String>) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let value = self.abbreviate(value, used);
+                        match self.abbreviations.get(&key) {
+                            Some(short) => {
+                                used.insert(key);
+                                (short.clone(), value)
+                            }
+                            None => (key, value),
+                        }
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.abbreviate(item, used))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn mapping_note(&self, used: &BTreeSet<String>) -> String {
+        let mapping = used
+            .iter()
+            .map(|key| format!("{}={}", self.abbreviations[key], key))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("JSON above is compacted with abbreviated keys: {}", mapping)
+    }
+}
+
+fn parse_structured(text: &str) -> Option<Value> {
+    match serde_json::from_str(text.trim()).ok()? {
+        value @ (Value::Object(_) | Value::Array(_)) => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compacts_structured_result_and_notes_mapping() {
+        let serializer = CompactSerializer::new()
+            .with_abbreviation("description", "d")
+            .with_abbreviation("identifier", "id");
+        let content = vec![
+            Content::text("{\n  \"identifier\": [\n    {\"description\": \"first\"}\n  ]\n}"),
+            Content::text("plain text stays as is"),
+        ];
+
+        let compacted = serializer.compact(content);
+        let texts: Vec<&str> = compacted
+            .iter()
+            .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+            .collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                r#"{"id":[{"d":"first"}]}"#,
+                "plain text stays as is",
+                "JSON above is compacted with abbreviated keys: d=description, id=identifier",
+            ]
+        );
+    }
+}
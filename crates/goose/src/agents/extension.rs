@@ -121,10 +121,68 @@ pub enum ExtensionError {
     TaskJoinError(#[from] tokio::task::JoinError),
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-    #[error("failed to initialize MCP client: {0}")]
-    InitializeError(#[from] ClientInitializeError),
+    #[error("failed to spawn extension process: {0}")]
+    SpawnFailed(std::io::Error),
+    #[error("MCP handshake with extension failed: {0}")]
+    HandshakeFailed(#[from] ClientInitializeError),
     #[error("{0}")]
     ProcessExit(#[from] ProcessExit),
+    #[error("failed to index tools for extension {extension}: {message}")]
+    IndexingFailed { extension: String, message: String },
+    #[error("extension '{0}' is already loaded")]
+    AlreadyLoaded(String),
+}
+
+/// What adding an extension does when one with the same name is already loaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateExtensionPolicy {
+    /// Fail with `ExtensionError::AlreadyLoaded`, leaving the running extension untouched
+    #[default]
+    Reject,
+    /// Stop the running extension and start the new config in its place
+    Replace,
+}
+
+/// Values shorter than this are too common to mask without mangling the message
+const MIN_MASKED_SECRET_LEN: usize = 4;
+
+fn mask_secret_values(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| secret.len() >= MIN_MASKED_SECRET_LEN)
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), "****")
+        })
+}
+
+impl ExtensionError {
+    /// Replace any of `secrets` appearing in the error message with `****`
+    pub fn mask_secrets(self, secrets: &[String]) -> Self {
+        let message = self.to_string();
+        if mask_secret_values(&message, secrets) == message {
+            return self;
+        }
+        match self {
+            ExtensionError::ConfigError(m) => {
+                ExtensionError::ConfigError(mask_secret_values(&m, secrets))
+            }
+            ExtensionError::SetupError(m) => {
+                ExtensionError::SetupError(mask_secret_values(&m, secrets))
+            }
+            ExtensionError::ProcessExit(exit) => ExtensionError::ProcessExit(ProcessExit {
+                stderr: mask_secret_values(&exit.stderr, secrets),
+                source: exit.source,
+            }),
+            ExtensionError::IndexingFailed { extension, message } => {
+                ExtensionError::IndexingFailed {
+                    extension,
+                    message: mask_secret_values(&message, secrets),
+                }
+            }
+            other => ExtensionError::SetupError(mask_secret_values(&other.to_string(), secrets)),
+        }
+    }
 }
 
 pub type ExtensionResult<T> = Result<T, ExtensionError>;
@@ -243,6 +301,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Standard I/O client with command and arguments
     #[serde(rename = "stdio")]
@@ -264,6 +324,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Built-in extension that is part of the bundled goose MCP server
     #[serde(rename = "builtin")]
@@ -280,6 +342,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Platform extensions that have direct access to the agent etc and run in the agent process
     #[serde(rename = "platform")]
@@ -293,6 +357,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Streamable HTTP client with a URI endpoint using MCP Streamable HTTP specification
     #[serde(rename = "streamable_http")]
@@ -316,6 +382,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Frontend-provided tools that will be called through the frontend
     #[serde(rename = "frontend")]
@@ -333,6 +401,8 @@ pub enum ExtensionConfig {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     /// Inline Python code that will be executed using uvx
     #[serde(rename = "inline_python")]
@@ -351,6 +421,8 @@ pub enum ExtensionConfig {
         dependencies: Option<Vec<String>>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
 }
 
@@ -363,6 +435,7 @@ impl Default for ExtensionConfig {
             timeout: Some(config::DEFAULT_EXTENSION_TIMEOUT),
             bundled: Some(true),
             available_tools: Vec::new(),
+            required: false,
         }
     }
 }
@@ -378,6 +451,7 @@ impl ExtensionConfig {
             timeout: Some(timeout.into()),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         }
     }
 
@@ -397,6 +471,7 @@ impl ExtensionConfig {
             timeout: Some(timeout.into()),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         }
     }
 
@@ -416,6 +491,7 @@ impl ExtensionConfig {
             timeout: Some(timeout.into()),
             bundled: None,
             available_tools: Vec::new(),
+            required: false,
         }
     }
 
@@ -432,6 +508,7 @@ impl ExtensionConfig {
             timeout: Some(timeout.into()),
             dependencies: None,
             available_tools: Vec::new(),
+            required: false,
         }
     }
 
@@ -450,6 +527,7 @@ impl ExtensionConfig {
                 description,
                 bundled,
                 available_tools,
+                required,
                 ..
             } => Self::Stdio {
                 name,
@@ -461,6 +539,7 @@ impl ExtensionConfig {
                 timeout,
                 bundled,
                 available_tools,
+                required,
             },
             other => other,
         }
@@ -485,6 +564,19 @@ impl ExtensionConfig {
         .to_string()
     }
 
+    /// Whether a failure to start this extension should abort startup
+    pub fn is_required(&self) -> bool {
+        match self {
+            Self::Sse { required, .. }
+            | Self::StreamableHttp { required, .. }
+            | Self::Stdio { required, .. }
+            | Self::Builtin { required, .. }
+            | Self::Platform { required, .. }
+            | Self::Frontend { required, .. }
+            | Self::InlinePython { required, .. } => *required,
+        }
+    }
+
     /// Check if a tool should be available to the LLM
     pub fn is_tool_available(&self, tool_name: &str) -> bool {
         let available_tools = match self {
@@ -568,6 +660,17 @@ where
     Ok(opt.unwrap_or_default())
 }
 
+/// Simplified description of one tool argument, e.g. for UI tooltips
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ToolParam {
+    pub name: String,
+    /// JSON schema type, alternatives joined with " | ", or "any" when unspecified
+    #[serde(rename = "type")]
+    pub param_type: String,
+    pub required: bool,
+    pub description: Option<String>,
+}
+
 /// Information about the tool used for building prompts
 #[derive(Clone, Debug, Serialize, ToSchema)]
 pub struct ToolInfo {
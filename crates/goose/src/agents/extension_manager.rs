@@ -11,14 +11,14 @@ use rmcp::transport::{
     ConfigureCommandExt, DynamicTransportError, SseClientTransport, StreamableHttpClientTransport,
     TokioChildProcess,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::option::Option;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tempfile::{tempdir, TempDir};
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::Mutex;
 use tokio::task;
@@ -27,11 +27,11 @@ use tokio_util::sync::CancellationToken;
 use tracing::{error, warn};
 
 use super::extension::{
-    ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult, PlatformExtensionContext,
-    ToolInfo, PLATFORM_EXTENSIONS,
+    DuplicateExtensionPolicy, ExtensionConfig, ExtensionError, ExtensionInfo, ExtensionResult,
+    PlatformExtensionContext, ToolInfo, ToolParam, PLATFORM_EXTENSIONS,
 };
 use super::tool_execution::ToolCallResult;
-use super::types::SharedProvider;
+use super::types::{SharedProvider, ToolPolicy};
 use crate::agents::extension::{Envs, ProcessExit};
 use crate::agents::extension_malware_check;
 use crate::agents::mcp_client::{McpClient, McpClientTrait};
@@ -42,7 +42,7 @@ use crate::prompt_template;
 use crate::subprocess::configure_command_no_window;
 use rmcp::model::{
     CallToolRequestParam, Content, ErrorCode, ErrorData, GetPromptResult, Prompt, ResourceContents,
-    ServerInfo, Tool,
+    ServerInfo, ServerNotification, Tool,
 };
 use rmcp::transport::auth::AuthClient;
 use schemars::_private::NoSerialize;
@@ -94,8 +94,78 @@ impl Extension {
 /// Manages goose extensions / MCP clients and their interactions
 pub struct ExtensionManager {
     extensions: Mutex<HashMap<String, Extension>>,
+    /// Short prefixes used instead of the extension name when naming its tools
+    tool_prefix_aliases: Mutex<HashMap<String, String>>,
     context: Mutex<PlatformExtensionContext>,
     provider: SharedProvider,
+    tool_result_cache: Arc<Mutex<Option<ToolResultCache>>>,
+    /// Tools each extension offered when last listed, before prefixing, so single tools can be
+    /// looked up without asking the extension again
+    listed_tools: Arc<Mutex<HashMap<String, Vec<Tool>>>>,
+    /// Stderr captured from each extension process, keyed by extension name
+    extension_logs: Mutex<HashMap<String, ExtensionLogBuffer>>,
+    /// Extensions the model may not disable through `manage_extensions`
+    pinned: Mutex<HashSet<String>>,
+    /// The only extensions the model may enable through `manage_extensions`, if limited
+    enable_allowlist: Mutex<Option<HashSet<String>>>,
+    /// Tools filtered out of every listing
+    tool_policy: Mutex<ToolPolicy>,
+    duplicate_policy: Mutex<DuplicateExtensionPolicy>,
+}
+
+/// Successful results of opted-in tools, keyed by tool name and arguments
+struct ToolResultCache {
+    tools: HashSet<String>,
+    ttl: Duration,
+    entries: HashMap<String, CachedToolResult>,
+}
+
+struct CachedToolResult {
+    extension: String,
+    stored_at: Instant,
+    content: Vec<Content>,
+}
+
+impl ToolResultCache {
+    fn key(tool_call: &CallToolRequestParam) -> String {
+        let arguments = tool_call
+            .arguments
+            .as_ref()
+            .map(|args| Value::Object(args.clone()).to_string())
+            .unwrap_or_default();
+        format!("{}:{}", tool_call.name, arguments)
+    }
+
+    fn get(&mut self, key: &str) -> Option<Vec<Content>> {
+        match self.entries.get(key) {
+            Some(entry) if entry.stored_at.elapsed() < self.ttl => Some(entry.content.clone()),
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store `content`, dropping expired entries so results of one-off calls don't pile up
+    fn insert(&mut self, key: String, extension: String, content: Vec<Content>) {
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        self.entries.insert(
+            key,
+            CachedToolResult {
+                extension,
+                stored_at: Instant::now(),
+                content,
+            },
+        );
+    }
+
+    /// Drop results from `extension`, which may answer differently once restarted
+    fn forget_extension(&mut self, extension: &str) {
+        self.entries.retain(|_, entry| entry.extension != extension);
+    }
 }
 
 /// A flattened representation of a resource used by the agent to prepare inference
@@ -181,16 +251,70 @@ pub fn get_parameter_names(tool: &Tool) -> Vec<String> {
         .unwrap_or_default()
 }
 
+/// Name, type, description and whether it is required for each property in the tool's schema
+pub fn get_parameters(tool: &Tool) -> Vec<ToolParam> {
+    let required: HashSet<&str> = tool
+        .input_schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|name| name.as_str()).collect())
+        .unwrap_or_default();
+    let properties = tool.input_schema.get("properties");
+
+    get_parameter_names(tool)
+        .into_iter()
+        .map(|name| {
+            let schema = properties.and_then(|props| props.get(&name));
+            let param_type = match schema.and_then(|s| s.get("type")) {
+                Some(Value::String(t)) => t.clone(),
+                Some(Value::Array(types)) => types
+                    .iter()
+                    .filter_map(|t| t.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+                _ => "any".to_string(),
+            };
+            ToolParam {
+                required: required.contains(name.as_str()),
+                description: schema
+                    .and_then(|s| s.get("description"))
+                    .and_then(|d| d.as_str())
+                    .map(str::to_string),
+                param_type,
+                name,
+            }
+        })
+        .collect()
+}
+
 impl Default for ExtensionManager {
     fn default() -> Self {
         Self::new(Arc::new(Mutex::new(None)))
     }
 }
 
+/// Most recent stderr lines kept for each extension process
+const MAX_EXTENSION_LOG_LINES: usize = 500;
+/// Stderr lines quoted in errors when an extension process dies
+const EXTENSION_LOG_TAIL_LINES: usize = 20;
+
+type ExtensionLogBuffer = Arc<std::sync::Mutex<VecDeque<String>>>;
+
+fn log_tail(logs: &ExtensionLogBuffer, lines: usize) -> String {
+    let logs = logs.lock().unwrap();
+    let skip = logs.len().saturating_sub(lines);
+    logs.iter()
+        .skip(skip)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 async fn child_process_client(
     mut command: Command,
     timeout: &Option<u64>,
     provider: SharedProvider,
+    logs: ExtensionLogBuffer,
 ) -> ExtensionResult<McpClient> {
     #[cfg(unix)]
     command.process_group(0);
@@ -202,15 +326,24 @@ async fn child_process_client(
 
     let (transport, mut stderr) = TokioChildProcess::builder(command)
         .stderr(Stdio::piped())
-        .spawn()?;
-    let mut stderr = stderr.take().ok_or_else(|| {
+        .spawn()
+        .map_err(ExtensionError::SpawnFailed)?;
+    let stderr = stderr.take().ok_or_else(|| {
         ExtensionError::SetupError("failed to attach child process stderr".to_owned())
     })?;
 
+    // Keeps collecting for the lifetime of the process so later crashes can be diagnosed too
+    let stderr_logs = logs.clone();
     let stderr_task = tokio::spawn(async move {
-        let mut all_stderr = Vec::new();
-        stderr.read_to_end(&mut all_stderr).await?;
-        Ok::<String, std::io::Error>(String::from_utf8_lossy(&all_stderr).into())
+        let mut lines = BufReader::new(stderr).lines();
+        while let Some(line) = lines.next_line().await? {
+            let mut logs = stderr_logs.lock().unwrap();
+            if logs.len() == MAX_EXTENSION_LOG_LINES {
+                logs.pop_front();
+            }
+            logs.push_back(line);
+        }
+        Ok::<(), std::io::Error>(())
     });
 
     let client_result = McpClient::connect(
@@ -225,7 +358,7 @@ async fn child_process_client(
         Err(error) => {
             let error_task_out = stderr_task.await?;
             Err::<McpClient, ExtensionError>(match error_task_out {
-                Ok(stderr_content) => ProcessExit::new(stderr_content, error).into(),
+                Ok(()) => ProcessExit::new(log_tail(&logs, EXTENSION_LOG_TAIL_LINES), error).into(),
                 Err(e) => e.into(),
             })
         }
@@ -254,16 +387,38 @@ fn extract_auth_error(
     }
 }
 
+/// `tool` as offered to the model, named with its extension's prefix
+fn prefixed_tool(prefix: &str, tool: &Tool) -> Tool {
+    Tool {
+        name: format!("{}__{}", prefix, tool.name).into(),
+        description: tool.description.clone(),
+        input_schema: tool.input_schema.clone(),
+        annotations: tool.annotations.clone(),
+        output_schema: tool.output_schema.clone(),
+        icons: None,
+        title: None,
+        meta: None,
+    }
+}
+
 impl ExtensionManager {
     pub fn new(provider: SharedProvider) -> Self {
         Self {
             extensions: Mutex::new(HashMap::new()),
+            tool_prefix_aliases: Mutex::new(HashMap::new()),
             context: Mutex::new(PlatformExtensionContext {
                 session_id: None,
                 extension_manager: None,
                 tool_route_manager: None,
             }),
             provider,
+            tool_result_cache: Arc::new(Mutex::new(None)),
+            listed_tools: Arc::new(Mutex::new(HashMap::new())),
+            extension_logs: Mutex::new(HashMap::new()),
+            pinned: Mutex::new(HashSet::new()),
+            enable_allowlist: Mutex::new(None),
+            tool_policy: Mutex::new(ToolPolicy::default()),
+            duplicate_policy: Mutex::new(DuplicateExtensionPolicy::default()),
         }
     }
 
@@ -288,10 +443,52 @@ impl ExtensionManager {
             .any(|ext| ext.supports_resources())
     }
 
+    /// Choose what `add_extension` does with a name that is already loaded
+    pub async fn set_duplicate_extension_policy(&self, policy: DuplicateExtensionPolicy) {
+        *self.duplicate_policy.lock().await = policy;
+    }
+
+    /// Whether an extension called `name` is loaded
+    pub async fn has_extension(&self, name: &str) -> bool {
+        self.extensions
+            .lock()
+            .await
+            .contains_key(&normalize(name.to_string()))
+    }
+
+    /// Start and register an extension. A name that is already loaded is rejected or
+    /// replaced according to the duplicate extension policy.
     pub async fn add_extension(&self, config: ExtensionConfig) -> ExtensionResult<()> {
+        let policy = *self.duplicate_policy.lock().await;
+        if policy == DuplicateExtensionPolicy::Reject && self.has_extension(&config.key()).await {
+            return Err(ExtensionError::AlreadyLoaded(config.name()));
+        }
+        self.replace_extension(config).await
+    }
+
+    /// Start and register an extension, replacing any loaded extension with the same name
+    async fn replace_extension(&self, config: ExtensionConfig) -> ExtensionResult<()> {
+        self.forget_cached_results(&normalize(config.key())).await;
+        let mut secrets = Vec::new();
+        self.start_extension(config, &mut secrets)
+            .await
+            .map_err(|e| e.mask_secrets(&secrets))
+    }
+
+    /// Start and register an extension, recording the env values it receives in `secrets`
+    async fn start_extension(
+        &self,
+        config: ExtensionConfig,
+        secrets: &mut Vec<String>,
+    ) -> ExtensionResult<()> {
         let config_name = config.key().to_string();
         let sanitized_name = normalize(config_name.clone());
         let mut temp_dir = None;
+        let logs = ExtensionLogBuffer::default();
+        self.extension_logs
+            .lock()
+            .await
+            .insert(sanitized_name.clone(), logs.clone());
 
         /// Helper function to merge environment variables from direct envs and keychain-stored env_keys
         async fn merge_environments(
@@ -382,6 +579,7 @@ impl ExtensionManager {
             } => {
                 // Merge environment variables from direct envs and keychain-stored env_keys
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
+                secrets.extend(all_envs.values().cloned());
 
                 // Helper function to substitute environment variables in a string
                 // Supports both ${VAR} and $VAR syntax
@@ -485,6 +683,7 @@ impl ExtensionManager {
                 ..
             } => {
                 let all_envs = merge_environments(envs, env_keys, &sanitized_name).await?;
+                secrets.extend(all_envs.values().cloned());
 
                 // Check for malicious packages before launching the process
                 extension_malware_check::deny_if_malicious_cmd_args(cmd, args).await?;
@@ -495,7 +694,9 @@ impl ExtensionManager {
                     command.args(args).envs(all_envs);
                 });
 
-                let client = child_process_client(command, timeout, self.provider.clone()).await?;
+                let client =
+                    child_process_client(command, timeout, self.provider.clone(), logs.clone())
+                        .await?;
                 Box::new(client)
             }
             ExtensionConfig::Builtin {
@@ -505,6 +706,7 @@ impl ExtensionManager {
                 timeout,
                 bundled: _,
                 available_tools: _,
+                required: _,
             } => {
                 let cmd = std::env::current_exe()
                     .and_then(|path| {
@@ -524,7 +726,9 @@ impl ExtensionManager {
                 let command = Command::new(cmd).configure(|command| {
                     command.arg("mcp").arg(name);
                 });
-                let client = child_process_client(command, timeout, self.provider.clone()).await?;
+                let client =
+                    child_process_client(command, timeout, self.provider.clone(), logs.clone())
+                        .await?;
                 Box::new(client)
             }
             ExtensionConfig::Platform { name, .. } => {
@@ -560,7 +764,9 @@ impl ExtensionManager {
                     command.arg("python").arg(file_path.to_str().unwrap());
                 });
 
-                let client = child_process_client(command, timeout, self.provider.clone()).await?;
+                let client =
+                    child_process_client(command, timeout, self.provider.clone(), logs.clone())
+                        .await?;
 
                 Box::new(client)
             }
@@ -584,6 +790,99 @@ impl ExtensionManager {
         Ok(())
     }
 
+    /// Start several extensions concurrently, running at most `concurrency` startups at once.
+    /// Each extension's outcome is reported individually so one failure does not stop the rest.
+    pub async fn add_extensions_parallel(
+        &self,
+        configs: Vec<ExtensionConfig>,
+        concurrency: usize,
+    ) -> Vec<(String, ExtensionResult<()>)> {
+        futures::stream::iter(configs)
+            .map(|config| async move {
+                let name = config.name();
+                let result = self.add_extension(config).await;
+                if let Err(e) = &result {
+                    warn!(extension = %name, error = %e, "Failed to start extension");
+                }
+                (name, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Start extensions one after another. A failed required extension, or any failure with
+    /// `fail_fast`, stops the batch and removes every extension started by this call; failed
+    /// optional extensions are logged and skipped. Returns the outcome of every attempted start.
+    pub async fn add_extensions(
+        &self,
+        configs: Vec<ExtensionConfig>,
+        fail_fast: bool,
+    ) -> Vec<(String, ExtensionResult<()>)> {
+        let mut results = Vec::with_capacity(configs.len());
+        let mut started = Vec::new();
+
+        for config in configs {
+            let name = config.name();
+            let required = config.is_required();
+            let key = normalize(config.key().to_string());
+            let already_running = self.extensions.lock().await.contains_key(&key);
+
+            let result = self.add_extension(config).await;
+            let failed = result.is_err();
+            if let Err(e) = &result {
+                warn!(extension = %name, error = %e, "Failed to start extension");
+            } else if !already_running {
+                started.push(key);
+            }
+            results.push((name, result));
+
+            if failed && (fail_fast || required) {
+                for key in started.iter().rev() {
+                    let _ = self.remove_extension(key).await;
+                }
+                break;
+            }
+        }
+
+        results
+    }
+
+    /// Cache successful results of `tools` (full prefixed names) for `ttl`, so repeated calls
+    /// with identical arguments skip the extension. Only use this for idempotent, read-only tools.
+    pub async fn enable_tool_result_cache(
+        &self,
+        tools: impl IntoIterator<Item = String>,
+        ttl: Duration,
+    ) {
+        *self.tool_result_cache.lock().await = Some(ToolResultCache {
+            tools: tools.into_iter().collect(),
+            ttl,
+            entries: HashMap::new(),
+        });
+    }
+
+    pub async fn disable_tool_result_cache(&self) {
+        *self.tool_result_cache.lock().await = None;
+    }
+
+    async fn forget_cached_results(&self, sanitized_name: &str) {
+        if let Some(cache) = self.tool_result_cache.lock().await.as_mut() {
+            cache.forget_extension(sanitized_name);
+        }
+        self.listed_tools.lock().await.remove(sanitized_name);
+    }
+
+    /// Stderr lines captured from the extension's process, oldest first
+    pub async fn extension_logs(&self, name: &str) -> Vec<String> {
+        self.extension_logs
+            .lock()
+            .await
+            .get(&normalize(name.to_string()))
+            .map(|logs| logs.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
     pub async fn add_client(
         &self,
         name: String,
@@ -592,12 +891,32 @@ impl ExtensionManager {
         info: Option<ServerInfo>,
         temp_dir: Option<TempDir>,
     ) {
+        self.listed_tools.lock().await.remove(&name);
+        self.forget_listing_on_tool_list_change(name.clone(), &client)
+            .await;
         self.extensions
             .lock()
             .await
             .insert(name, Extension::new(config, client, info, temp_dir));
     }
 
+    /// Drop `name`'s cached tool listing whenever its server reports that its tools changed.
+    /// The watch ends once the client is dropped.
+    async fn forget_listing_on_tool_list_change(&self, name: String, client: &McpClientBox) {
+        let mut notifications = client.lock().await.subscribe().await;
+        let listed_tools = self.listed_tools.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                if matches!(
+                    notification,
+                    ServerNotification::ToolListChangedNotification(_)
+                ) {
+                    listed_tools.lock().await.remove(&name);
+                }
+            }
+        });
+    }
+
     /// Get extensions info
     pub async fn get_extensions_info(&self) -> Vec<ExtensionInfo> {
         self.extensions
@@ -618,6 +937,130 @@ impl ExtensionManager {
     pub async fn remove_extension(&self, name: &str) -> ExtensionResult<()> {
         let sanitized_name = normalize(name.to_string());
         self.extensions.lock().await.remove(&sanitized_name);
+        self.extension_logs.lock().await.remove(&sanitized_name);
+        self.tool_prefix_aliases
+            .lock()
+            .await
+            .remove(&sanitized_name);
+        self.forget_cached_results(&sanitized_name).await;
+        Ok(())
+    }
+
+    /// Restart a loaded extension with an updated `config`, e.g. new env vars or arguments,
+    /// keeping its tool prefix alias. The config must keep the extension's name.
+    pub async fn reload_extension(
+        &self,
+        name: &str,
+        config: ExtensionConfig,
+    ) -> ExtensionResult<()> {
+        let sanitized_name = normalize(name.to_string());
+        if normalize(config.key()) != sanitized_name {
+            return Err(ExtensionError::ConfigError(format!(
+                "cannot reload extension '{}' with a config named '{}'; remove it and add the renamed extension instead",
+                name,
+                config.name()
+            )));
+        }
+        if !self.extensions.lock().await.contains_key(&sanitized_name) {
+            return Err(ExtensionError::ConfigError(format!(
+                "extension '{}' is not loaded",
+                name
+            )));
+        }
+
+        // The new client replaces the old entry, which shuts down the old transport
+        self.replace_extension(config).await
+    }
+
+    /// Stop and remove every extension
+    pub async fn shutdown(&self) {
+        self.extensions.lock().await.clear();
+        self.extension_logs.lock().await.clear();
+        self.tool_prefix_aliases.lock().await.clear();
+        self.listed_tools.lock().await.clear();
+    }
+
+    /// Refuse requests from the model to disable `name`
+    pub async fn pin_extension(&self, name: &str) {
+        self.pinned.lock().await.insert(normalize(name.to_string()));
+    }
+
+    /// Whether `name` was pinned with `pin_extension`
+    pub async fn is_pinned(&self, name: &str) -> bool {
+        self.pinned
+            .lock()
+            .await
+            .contains(&normalize(name.to_string()))
+    }
+
+    /// Refuse requests from the model to enable extensions other than `names`; `None` lifts
+    /// the restriction
+    pub async fn limit_enabled_extensions(&self, names: Option<Vec<String>>) {
+        *self.enable_allowlist.lock().await =
+            names.map(|names| names.into_iter().map(normalize).collect());
+    }
+
+    /// The extensions the model may enable, as set by `limit_enabled_extensions`
+    pub async fn enabled_extensions_limit(&self) -> Option<Vec<String>> {
+        self.enable_allowlist
+            .lock()
+            .await
+            .as_ref()
+            .map(|allowed| allowed.iter().cloned().collect())
+    }
+
+    /// Whether the model may enable `name`, as limited by `limit_enabled_extensions`
+    pub async fn may_enable(&self, name: &str) -> bool {
+        self.enable_allowlist
+            .lock()
+            .await
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&normalize(name.to_string())))
+    }
+
+    /// Leave tools the policy does not permit out of `get_prefixed_tools`
+    pub async fn set_tool_policy(&self, policy: ToolPolicy) {
+        *self.tool_policy.lock().await = policy;
+    }
+
+    pub async fn tool_policy(&self) -> ToolPolicy {
+        self.tool_policy.lock().await.clone()
+    }
+
+    /// Use `alias` instead of the extension name when prefixing its tools, or restore the
+    /// default prefix when `alias` is None
+    pub async fn set_tool_prefix_alias(
+        &self,
+        extension_name: &str,
+        alias: Option<&str>,
+    ) -> ExtensionResult<()> {
+        let sanitized_name = normalize(extension_name.to_string());
+        let mut aliases = self.tool_prefix_aliases.lock().await;
+
+        let Some(alias) = alias else {
+            aliases.remove(&sanitized_name);
+            return Ok(());
+        };
+
+        let alias = normalize(alias.to_string());
+        if alias.is_empty() {
+            return Err(ExtensionError::ConfigError(
+                "Tool prefix alias cannot be empty".to_string(),
+            ));
+        }
+        let taken_by_alias = aliases
+            .iter()
+            .any(|(name, existing)| *name != sanitized_name && *existing == alias);
+        let taken_by_name =
+            alias != sanitized_name && self.extensions.lock().await.contains_key(&alias);
+        if taken_by_alias || taken_by_name {
+            return Err(ExtensionError::ConfigError(format!(
+                "Tool prefix '{}' is already used by another extension",
+                alias
+            )));
+        }
+
+        aliases.insert(sanitized_name, alias);
         Ok(())
     }
 
@@ -651,6 +1094,9 @@ impl ExtensionManager {
         &self,
         extension_name: Option<String>,
     ) -> ExtensionResult<Vec<Tool>> {
+        let aliases = self.tool_prefix_aliases.lock().await.clone();
+        let policy = self.tool_policy().await;
+
         // Filter clients based on the provided extension_name or include all if None
         let filtered_clients: Vec<_> = self
             .extensions
@@ -664,11 +1110,18 @@ impl ExtensionManager {
                     true
                 }
             })
-            .map(|(name, ext)| (name.clone(), ext.config.clone(), ext.get_client()))
+            .map(|(name, ext)| {
+                let prefix = aliases.get(name).unwrap_or(name).clone();
+                (name.clone(), prefix, ext.config.clone(), ext.get_client())
+            })
             .collect();
 
         let cancel_token = CancellationToken::default();
-        let client_futures = filtered_clients.into_iter().map(|(name, config, client)| {
+        let listed: Vec<_> = filtered_clients
+            .iter()
+            .map(|(name, prefix, _, _)| (name.clone(), prefix.clone()))
+            .collect();
+        let client_futures = filtered_clients.into_iter().map(|(_, _, config, client)| {
             let cancel_token = cancel_token.clone();
             task::spawn(async move {
                 let mut tools = Vec::new();
@@ -676,22 +1129,12 @@ impl ExtensionManager {
                 let mut client_tools = client_guard.list_tools(None, cancel_token).await?;
 
                 loop {
-                    for tool in client_tools.tools {
-                        let is_available = config.is_tool_available(&tool.name);
-
-                        if is_available {
-                            tools.push(Tool {
-                                name: format!("{}__{}", name, tool.name).into(),
-                                description: tool.description,
-                                input_schema: tool.input_schema,
-                                annotations: tool.annotations,
-                                output_schema: tool.output_schema,
-                                icons: None,
-                                title: None,
-                                meta: None,
-                            });
-                        }
-                    }
+                    tools.extend(
+                        client_tools
+                            .tools
+                            .into_iter()
+                            .filter(|tool| config.is_tool_available(&tool.name)),
+                    );
 
                     // Exit loop when there are no more pages
                     if client_tools.next_cursor.is_none() {
@@ -712,17 +1155,57 @@ impl ExtensionManager {
 
         // Aggregate tools and handle errors
         let mut tools = Vec::new();
-        for result in results {
+        let mut listed_tools = Vec::new();
+        for ((name, prefix), result) in listed.into_iter().zip(results) {
             match result {
-                Ok(Ok(client_tools)) => tools.extend(client_tools),
+                Ok(Ok(client_tools)) => {
+                    tools.extend(
+                        client_tools
+                            .iter()
+                            .map(|tool| prefixed_tool(&prefix, tool))
+                            .filter(|tool| policy.permits(&tool.name)),
+                    );
+                    listed_tools.push((name, client_tools));
+                }
                 Ok(Err(err)) => return Err(err),
                 Err(join_err) => return Err(ExtensionError::from(join_err)),
             }
         }
+        self.listed_tools.lock().await.extend(listed_tools);
 
         Ok(tools)
     }
 
+    /// Look up a single tool by its prefixed name among the tools its extension offered when
+    /// last listed, listing only that extension if it hasn't been yet
+    pub async fn get_tool(&self, prefixed_name: &str) -> Option<Tool> {
+        let (extension_name, prefix, _) = self.get_client_for_tool(prefixed_name).await?;
+        let cached = self.listed_tools.lock().await.get(&extension_name).cloned();
+        let Some(tools) = cached else {
+            return self
+                .get_prefixed_tools(Some(extension_name))
+                .await
+                .ok()?
+                .into_iter()
+                .find(|tool| tool.name == prefixed_name);
+        };
+        let tool_name = prefixed_name.strip_prefix(&format!("{}__", prefix))?;
+        let policy = self.tool_policy().await;
+        tools
+            .iter()
+            .find(|tool| tool.name == tool_name)
+            .map(|tool| prefixed_tool(&prefix, tool))
+            .filter(|tool| policy.permits(&tool.name))
+    }
+
+    /// The extension that owns a prefixed tool name, going by the prefix each extension's
+    /// tools are listed under
+    pub async fn extension_for_tool(&self, prefixed_name: &str) -> Option<String> {
+        self.get_client_for_tool(prefixed_name)
+            .await
+            .map(|(extension_name, _, _)| extension_name)
+    }
+
     /// Get the extension prompt including client instructions
     pub async fn get_planning_prompt(&self, tools_info: Vec<ToolInfo>) -> String {
         let mut context: HashMap<&str, Value> = HashMap::new();
@@ -731,14 +1214,21 @@ impl ExtensionManager {
         prompt_template::render_global_file("plan.md", &context).expect("Prompt should render")
     }
 
-    /// Find and return a reference to the appropriate client for a tool call
-    async fn get_client_for_tool(&self, prefixed_name: &str) -> Option<(String, McpClientBox)> {
+    /// Find the client for a tool call, returning the extension name and the tool prefix it uses
+    async fn get_client_for_tool(
+        &self,
+        prefixed_name: &str,
+    ) -> Option<(String, String, McpClientBox)> {
+        let aliases = self.tool_prefix_aliases.lock().await.clone();
         self.extensions
             .lock()
             .await
             .iter()
-            .find(|(key, _)| prefixed_name.starts_with(*key))
-            .map(|(name, extension)| (name.clone(), extension.get_client()))
+            .map(|(name, extension)| (name, aliases.get(name).unwrap_or(name), extension))
+            .filter(|(_, prefix, _)| prefixed_name.starts_with(&format!("{}__", prefix)))
+            // A prefix may itself start with a shorter one, e.g. `t` and `t__x`
+            .max_by_key(|(_, prefix, _)| prefix.len())
+            .map(|(name, prefix, extension)| (name.clone(), prefix.clone(), extension.get_client()))
     }
 
     // Function that gets executed for read_resource tool
@@ -958,17 +1448,17 @@ impl ExtensionManager {
         cancellation_token: CancellationToken,
     ) -> Result<ToolCallResult> {
         // Dispatch tool call based on the prefix naming convention
-        let (client_name, client) =
-            self.get_client_for_tool(&tool_call.name)
-                .await
-                .ok_or_else(|| {
-                    ErrorData::new(ErrorCode::RESOURCE_NOT_FOUND, tool_call.name.clone(), None)
-                })?;
+        let (client_name, tool_prefix, client) = self
+            .get_client_for_tool(&tool_call.name)
+            .await
+            .ok_or_else(|| {
+                ErrorData::new(ErrorCode::RESOURCE_NOT_FOUND, tool_call.name.clone(), None)
+            })?;
 
         // rsplit returns the iterator in reverse, tool_name is then at 0
         let tool_name = tool_call
             .name
-            .strip_prefix(client_name.as_str())
+            .strip_prefix(tool_prefix.as_str())
             .and_then(|s| s.strip_prefix("__"))
             .ok_or_else(|| {
                 ErrorData::new(ErrorCode::RESOURCE_NOT_FOUND, tool_call.name.clone(), None)
@@ -989,22 +1479,51 @@ impl ExtensionManager {
             }
         }
 
+        let cache_key = match self.tool_result_cache.lock().await.as_mut() {
+            Some(cache) if cache.tools.contains(&*tool_call.name) => {
+                let key = ToolResultCache::key(&tool_call);
+                if let Some(content) = cache.get(&key) {
+                    return Ok(ToolCallResult::from(Ok(content)));
+                }
+                Some(key)
+            }
+            _ => None,
+        };
+
         let arguments = tool_call.arguments.clone();
         let client = client.clone();
         let notifications_receiver = client.lock().await.subscribe().await;
+        let cache = self.tool_result_cache.clone();
+        let logs = self.extension_logs.lock().await.get(&client_name).cloned();
 
         let fut = async move {
             let client_guard = client.lock().await;
-            client_guard
+            let result = client_guard
                 .call_tool(&tool_name, arguments, cancellation_token)
-                .await
-                .map(|call| call.content)
-                .map_err(|e| match e {
-                    ServiceError::McpError(error_data) => error_data,
-                    _ => {
-                        ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), e.maybe_to_value())
-                    }
-                })
+                .await;
+            // Errors the tool reports in its result may be transient, so they are not cached
+            let cacheable = matches!(&result, Ok(call) if call.is_error != Some(true));
+            let result = result.map(|call| call.content).map_err(|e| match e {
+                ServiceError::McpError(error_data) => error_data,
+                _ => {
+                    let tail = logs
+                        .as_ref()
+                        .map(|logs| log_tail(logs, EXTENSION_LOG_TAIL_LINES))
+                        .unwrap_or_default();
+                    let message = if tail.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{}\nRecent extension stderr:\n{}", e, tail)
+                    };
+                    ErrorData::new(ErrorCode::INTERNAL_ERROR, message, e.maybe_to_value())
+                }
+            });
+            if let (Some(key), Ok(content), true) = (cache_key, &result, cacheable) {
+                if let Some(cache) = cache.lock().await.as_mut() {
+                    cache.insert(key, client_name, content.clone());
+                }
+            }
+            result
         };
 
         Ok(ToolCallResult {
@@ -1179,6 +1698,25 @@ impl ExtensionManager {
             .map(|ext| ext.get_client())
     }
 
+    /// System prompt fragments offered by extensions right now, keyed by extension name
+    pub async fn collect_system_prompt_fragments(&self) -> HashMap<String, String> {
+        let clients: Vec<(String, McpClientBox)> = self
+            .extensions
+            .lock()
+            .await
+            .iter()
+            .map(|(name, ext)| (name.clone(), ext.get_client()))
+            .collect();
+
+        let mut fragments = HashMap::new();
+        for (name, client) in clients {
+            if let Some(fragment) = client.lock().await.get_system_prompt_fragment().await {
+                fragments.insert(name, fragment);
+            }
+        }
+        fragments
+    }
+
     pub async fn collect_moim(&self) -> Option<String> {
         let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
         let mut content = format!("<info-msg>\nDatetime: {}\n", timestamp);
@@ -1237,7 +1775,11 @@ mod tests {
                 timeout: None,
                 bundled: None,
                 available_tools,
+                required: false,
             };
+            self.listed_tools.lock().await.remove(&sanitized_name);
+            self.forget_listing_on_tool_list_change(sanitized_name.clone(), &client)
+                .await;
             let extension = Extension::new(config, client, None, None);
             self.extensions
                 .lock()
@@ -1338,6 +1880,166 @@ mod tests {
         }
     }
 
+    struct CountingClient {
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+        listings: Arc<std::sync::atomic::AtomicUsize>,
+        /// Notifications handed to the first subscriber
+        notifications: std::sync::Mutex<Option<mpsc::Receiver<ServerNotification>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl McpClientTrait for CountingClient {
+        fn get_info(&self) -> Option<&InitializeResult> {
+            None
+        }
+
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListResourcesResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn read_resource(
+            &self,
+            _uri: &str,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ReadResourceResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn list_tools(
+            &self,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListToolsResult, Error> {
+            self.listings
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(ListToolsResult {
+                tools: vec![Tool::new(
+                    "read".to_string(),
+                    "Counts its calls".to_string(),
+                    object!({"type": "object"}),
+                )],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _name: &str,
+            arguments: Option<JsonObject>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<CallToolResult, Error> {
+            let n = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            let missing = arguments
+                .and_then(|args| args.get("path").cloned())
+                .is_some_and(|path| path == "missing.txt");
+            Ok(CallToolResult {
+                content: vec![Content::text(format!("call {}", n))],
+                is_error: missing.then_some(true),
+                structured_content: None,
+                meta: None,
+            })
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+            _cancellation_token: CancellationToken,
+        ) -> Result<ListPromptsResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn get_prompt(
+            &self,
+            _name: &str,
+            _arguments: Value,
+            _cancellation_token: CancellationToken,
+        ) -> Result<GetPromptResult, Error> {
+            Err(Error::TransportClosed)
+        }
+
+        async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+            self.notifications
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap_or_else(|| mpsc::channel(1).1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_result_cache_serves_identical_calls() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        extension_manager
+            .add_mock_extension(
+                "counter".to_string(),
+                Arc::new(Mutex::new(Box::new(CountingClient {
+                    calls: calls.clone(),
+                    listings: Default::default(),
+                    notifications: Default::default(),
+                }))),
+            )
+            .await;
+        extension_manager
+            .enable_tool_result_cache(["counter__read".to_string()], Duration::from_secs(60))
+            .await;
+
+        let call = |path: &str| CallToolRequestParam {
+            name: "counter__read".into(),
+            arguments: Some(object!({"path": path})),
+        };
+        let manager = &extension_manager;
+        let run = move |tool_call| async move {
+            manager
+                .dispatch_tool_call(tool_call, CancellationToken::default())
+                .await
+                .unwrap()
+                .result
+                .await
+                .unwrap()
+        };
+
+        let first = run(call("a.txt")).await;
+        let second = run(call("a.txt")).await;
+        assert_eq!(first[0].as_text().unwrap().text, "call 1");
+        assert_eq!(second[0].as_text().unwrap().text, "call 1");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        run(call("b.txt")).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // Results the tool flags as errors are not served again
+        run(call("missing.txt")).await;
+        run(call("missing.txt")).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        // Removing the extension drops its results
+        extension_manager.remove_extension("counter").await.unwrap();
+        extension_manager
+            .add_mock_extension(
+                "counter".to_string(),
+                Arc::new(Mutex::new(Box::new(CountingClient {
+                    calls: calls.clone(),
+                    listings: Default::default(),
+                    notifications: Default::default(),
+                }))),
+            )
+            .await;
+        let fresh = run(call("a.txt")).await;
+        assert_eq!(fresh[0].as_text().unwrap().text, "call 5");
+
+        extension_manager
+            .enable_tool_result_cache(["counter__read".to_string()], Duration::ZERO)
+            .await;
+        run(call("a.txt")).await;
+        run(call("a.txt")).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 7);
+    }
+
     #[tokio::test]
     async fn test_get_client_for_tool() {
         let extension_manager = ExtensionManager::new_without_provider();
@@ -1396,6 +2098,32 @@ mod tests {
             .is_some());
     }
 
+    #[tokio::test]
+    async fn test_get_client_for_tool_with_colliding_prefixes() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        for name in ["t", "tavily", "t__x"] {
+            extension_manager
+                .add_mock_extension(
+                    name.to_string(),
+                    Arc::new(Mutex::new(Box::new(MockClient {}))),
+                )
+                .await;
+        }
+
+        for (tool, extension) in [
+            ("t__todo_write", "t"),
+            ("tavily__search", "tavily"),
+            ("t__x__search", "t__x"),
+        ] {
+            let (name, prefix, _) = extension_manager.get_client_for_tool(tool).await.unwrap();
+            assert_eq!((name.as_str(), prefix.as_str()), (extension, extension));
+        }
+        assert!(extension_manager
+            .get_client_for_tool("tx__search")
+            .await
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_dispatch_tool_call() {
         // test that dispatch_tool_call parses out the sanitized name correctly, and extracts
@@ -1681,4 +2409,382 @@ mod tests {
         );
         assert_eq!(result, "Authorization: Bearer secret123 and API key456");
     }
+
+    #[tokio::test]
+    async fn test_add_extensions_parallel_reports_every_result() {
+        let extension_manager = ExtensionManager::new_without_provider();
+
+        let platform = |name: &str| ExtensionConfig::Platform {
+            name: name.to_string(),
+            description: name.to_string(),
+            bundled: None,
+            available_tools: vec![],
+            required: false,
+        };
+        let configs = vec![
+            platform("todo"),
+            platform("does_not_exist"),
+            platform("extensionmanager"),
+            platform("chatrecall"),
+        ];
+
+        let results: HashMap<String, ExtensionResult<()>> = extension_manager
+            .add_extensions_parallel(configs, 2)
+            .await
+            .into_iter()
+            .collect();
+
+        assert_eq!(results.len(), 4);
+        assert!(matches!(
+            results["does_not_exist"],
+            Err(ExtensionError::ConfigError(_))
+        ));
+        for name in ["todo", "extensionmanager", "chatrecall"] {
+            assert!(results[name].is_ok(), "{} failed to start", name);
+        }
+
+        let mut started = extension_manager.list_extensions().await.unwrap();
+        started.sort();
+        assert_eq!(started, vec!["chatrecall", "extensionmanager", "todo"]);
+    }
+
+    /// todo and chatrecall start; does_not_exist fails and is required if `failing_required`
+    fn batch_configs(failing_required: bool) -> Vec<ExtensionConfig> {
+        ["todo", "does_not_exist", "chatrecall"]
+            .into_iter()
+            .map(|name| ExtensionConfig::Platform {
+                name: name.to_string(),
+                description: name.to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: failing_required && name == "does_not_exist",
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_add_extensions_best_effort_continues_past_failures() {
+        let extension_manager = ExtensionManager::new_without_provider();
+
+        let results = extension_manager
+            .add_extensions(batch_configs(false), false)
+            .await;
+
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["todo", "does_not_exist", "chatrecall"]);
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_err());
+        assert!(results[2].1.is_ok());
+
+        let mut running = extension_manager.list_extensions().await.unwrap();
+        running.sort();
+        assert_eq!(running, vec!["chatrecall", "todo"]);
+    }
+
+    #[tokio::test]
+    async fn test_add_extensions_aborts_on_failed_required_extension() {
+        let extension_manager = ExtensionManager::new_without_provider();
+
+        let results = extension_manager
+            .add_extensions(batch_configs(true), false)
+            .await;
+
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["todo", "does_not_exist"]);
+        assert!(results[1].1.is_err());
+        assert!(extension_manager
+            .list_extensions()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_extensions_fail_fast_rolls_back_started_extensions() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        extension_manager
+            .add_mock_extension(
+                "existing".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+
+        let results = extension_manager
+            .add_extensions(batch_configs(false), true)
+            .await;
+
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["todo", "does_not_exist"]);
+        assert!(matches!(results[1].1, Err(ExtensionError::ConfigError(_))));
+
+        assert_eq!(
+            extension_manager.list_extensions().await.unwrap(),
+            vec!["existing"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_prefix_alias_round_trip() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        let long_name = "acme_corporate_knowledge_base_search_connector";
+
+        extension_manager
+            .add_mock_extension(
+                long_name.to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        extension_manager
+            .set_tool_prefix_alias(long_name, Some("kb"))
+            .await
+            .unwrap();
+
+        let tools = extension_manager.get_prefixed_tools(None).await.unwrap();
+        assert!(tools.iter().any(|t| t.name == "kb__tool"));
+        assert!(!tools.iter().any(|t| t.name.starts_with(long_name)));
+
+        let tool_call = CallToolRequestParam {
+            name: "kb__tool".into(),
+            arguments: Some(object!({})),
+        };
+        let result = extension_manager
+            .dispatch_tool_call(tool_call, CancellationToken::default())
+            .await
+            .expect("aliased tool should dispatch");
+        assert!(result.result.await.is_ok());
+
+        extension_manager
+            .set_tool_prefix_alias(long_name, None)
+            .await
+            .unwrap();
+        let tools = extension_manager.get_prefixed_tools(None).await.unwrap();
+        assert!(tools
+            .iter()
+            .any(|t| t.name == format!("{}__tool", long_name).as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_resolves_prefix() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        extension_manager
+            .add_mock_extension(
+                "test_extension".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        extension_manager
+            .add_mock_extension(
+                "knowledge_base".to_string(),
+                Arc::new(Mutex::new(Box::new(MockClient {}))),
+            )
+            .await;
+        extension_manager
+            .set_tool_prefix_alias("knowledge_base", Some("kb"))
+            .await
+            .unwrap();
+
+        let tool = extension_manager
+            .get_tool("test_extension__available_tool")
+            .await
+            .expect("tool should be found");
+        assert_eq!(tool.name, "test_extension__available_tool");
+        assert_eq!(tool.description.as_deref(), Some("An available tool"));
+
+        assert!(extension_manager.get_tool("kb__tool").await.is_some());
+        assert!(extension_manager
+            .get_tool("knowledge_base__tool")
+            .await
+            .is_none());
+        assert!(extension_manager
+            .get_tool("test_extension__missing")
+            .await
+            .is_none());
+        assert!(extension_manager.get_tool("unknown__tool").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_tool_uses_last_listing() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        let listings = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let client = |notifications| -> McpClientBox {
+            Arc::new(Mutex::new(Box::new(CountingClient {
+                calls: Default::default(),
+                listings: listings.clone(),
+                notifications: std::sync::Mutex::new(notifications),
+            })))
+        };
+        extension_manager
+            .add_mock_extension("counter".to_string(), client(None))
+            .await;
+        let listed = || listings.load(std::sync::atomic::Ordering::SeqCst);
+
+        extension_manager.get_prefixed_tools(None).await.unwrap();
+        assert_eq!(listed(), 1);
+        for _ in 0..3 {
+            let tool = extension_manager.get_tool("counter__read").await.unwrap();
+            assert_eq!(tool.name, "counter__read");
+        }
+        assert!(extension_manager.get_tool("counter__write").await.is_none());
+        assert_eq!(listed(), 1);
+
+        // The policy in effect now applies to the cached tools
+        extension_manager
+            .set_tool_policy(ToolPolicy::default().with_denied(["counter__read"]))
+            .await;
+        assert!(extension_manager.get_tool("counter__read").await.is_none());
+        extension_manager
+            .set_tool_policy(ToolPolicy::default())
+            .await;
+
+        // A replaced extension is listed again on the next lookup
+        let (tool_list_changes, notifications) = mpsc::channel(1);
+        extension_manager
+            .add_mock_extension("counter".to_string(), client(Some(notifications)))
+            .await;
+        assert!(extension_manager.get_tool("counter__read").await.is_some());
+        assert!(extension_manager.get_tool("counter__read").await.is_some());
+        assert_eq!(listed(), 2);
+
+        // So is one whose server reports that its tools changed
+        tool_list_changes
+            .send(ServerNotification::ToolListChangedNotification(
+                rmcp::model::ToolListChangedNotification {
+                    method: rmcp::model::ToolListChangedNotificationMethod,
+                    extensions: Default::default(),
+                },
+            ))
+            .await
+            .unwrap();
+        while extension_manager
+            .listed_tools
+            .lock()
+            .await
+            .contains_key("counter")
+        {
+            tokio::task::yield_now().await;
+        }
+        assert!(extension_manager.get_tool("counter__read").await.is_some());
+        assert_eq!(listed(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reload_extension_keeps_alias_and_rejects_rename() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        let todo = |name: &str| ExtensionConfig::Platform {
+            name: name.to_string(),
+            description: "todo".to_string(),
+            bundled: None,
+            available_tools: vec![],
+            required: false,
+        };
+        extension_manager.add_extension(todo("todo")).await.unwrap();
+        extension_manager
+            .set_tool_prefix_alias("todo", Some("t"))
+            .await
+            .unwrap();
+
+        extension_manager
+            .reload_extension("todo", todo("todo"))
+            .await
+            .unwrap();
+        let tools = extension_manager.get_prefixed_tools(None).await.unwrap();
+        assert!(tools.iter().any(|t| t.name == "t__todo_write"));
+
+        let err = extension_manager
+            .reload_extension("todo", todo("chatrecall"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtensionError::ConfigError(_)));
+        assert!(err.to_string().contains("chatrecall"));
+        assert_eq!(
+            extension_manager.list_extensions().await.unwrap(),
+            vec!["todo"]
+        );
+    }
+
+    fn todo_with_tools(available_tools: Vec<String>) -> ExtensionConfig {
+        ExtensionConfig::Platform {
+            name: "todo".to_string(),
+            description: "todo".to_string(),
+            bundled: None,
+            available_tools,
+            required: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_extension_rejects_duplicate_name_by_default() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        extension_manager
+            .add_extension(todo_with_tools(vec![]))
+            .await
+            .unwrap();
+
+        let err = extension_manager
+            .add_extension(todo_with_tools(vec!["todo_write".to_string()]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ExtensionError::AlreadyLoaded(ref name) if name == "todo"));
+
+        let configs = extension_manager.get_extension_configs().await;
+        assert_eq!(configs.len(), 1);
+        assert!(matches!(
+            &configs[0],
+            ExtensionConfig::Platform { available_tools, .. } if available_tools.is_empty()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_extension_replaces_duplicate_name_when_allowed() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        extension_manager
+            .set_duplicate_extension_policy(DuplicateExtensionPolicy::Replace)
+            .await;
+        extension_manager
+            .add_extension(todo_with_tools(vec![]))
+            .await
+            .unwrap();
+
+        extension_manager
+            .add_extension(todo_with_tools(vec!["missing".to_string()]))
+            .await
+            .expect("duplicate should replace the running extension");
+
+        assert_eq!(
+            extension_manager.list_extensions().await.unwrap(),
+            vec!["todo"]
+        );
+        assert!(extension_manager
+            .get_prefixed_tools(None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_prefix_alias_rejects_collisions() {
+        let extension_manager = ExtensionManager::new_without_provider();
+        for name in ["first", "second"] {
+            extension_manager
+                .add_mock_extension(
+                    name.to_string(),
+                    Arc::new(Mutex::new(Box::new(MockClient {}))),
+                )
+                .await;
+        }
+
+        let result = extension_manager
+            .set_tool_prefix_alias("first", Some("second"))
+            .await;
+        assert!(matches!(result, Err(ExtensionError::ConfigError(_))));
+
+        extension_manager
+            .set_tool_prefix_alias("first", Some("f"))
+            .await
+            .unwrap();
+        let result = extension_manager
+            .set_tool_prefix_alias("second", Some("f"))
+            .await;
+        assert!(matches!(result, Err(ExtensionError::ConfigError(_))));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::agents::extension::PlatformExtensionContext;
 use crate::agents::mcp_client::{Error, McpClientTrait};
+use crate::agents::platform_tools::platform_tool_description;
 use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
 use crate::config::get_extension_by_name;
 use anyhow::Result;
@@ -149,6 +150,16 @@ impl ExtensionManagerClient {
             param_name: "arguments".to_string(),
         })?;
 
+        let has_name = arguments
+            .get("extension_name")
+            .and_then(|v| v.as_str())
+            .is_some_and(|name| !name.trim().is_empty());
+        if !has_name {
+            return Err(ExtensionManagerToolError::MissingParameter {
+                param_name: "extension_name".to_string(),
+            });
+        }
+
         let params: ManageExtensionsParams =
             serde_json::from_value(serde_json::Value::Object(arguments))?;
 
@@ -182,6 +193,32 @@ impl ExtensionManagerClient {
                 )
             })?;
 
+        if action == ManageExtensionAction::Disable
+            && extension_manager.is_pinned(&extension_name).await
+        {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!(
+                    "The extension '{}' is pinned and cannot be disabled",
+                    extension_name
+                ),
+                None,
+            ));
+        }
+
+        if action == ManageExtensionAction::Enable
+            && !extension_manager.may_enable(&extension_name).await
+        {
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!(
+                    "The extension '{}' is not declared by the current recipe and cannot be enabled",
+                    extension_name
+                ),
+                None,
+            ));
+        }
+
         let tool_route_manager = self
             .context
             .tool_route_manager
@@ -372,10 +409,13 @@ impl ExtensionManagerClient {
             }),
             Tool::new(
                 MANAGE_EXTENSIONS_TOOL_NAME.to_string(),
-                "Tool to manage extensions and tools in goose context.
+                platform_tool_description(
+                    MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE,
+                    "Tool to manage extensions and tools in goose context.
             Enable or disable extensions to help complete tasks.
             Enable or disable an extension by providing the extension name.
-            ".to_string(),
+            ",
+                ),
                 Arc::new(
                     serde_json::to_value(schema_for!(ManageExtensionsParams))
                         .expect("Failed to serialize schema")
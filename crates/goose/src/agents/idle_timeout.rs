@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::agents::Agent;
+
+/// Keeps the idle timeout suspended while a reply stream is alive, restarting it once dropped
+pub(super) struct ActiveReplyGuard {
+    active_replies: Arc<AtomicUsize>,
+    activity: Arc<Notify>,
+}
+
+impl Drop for ActiveReplyGuard {
+    fn drop(&mut self) {
+        self.active_replies.fetch_sub(1, Ordering::SeqCst);
+        self.activity.notify_one();
+    }
+}
+
+impl Agent {
+    /// Shut the agent down once `timeout` passes without a reply or tool dispatch,
+    /// replacing any idle timeout set before. The timeout does not run while a reply is
+    /// still streaming.
+    pub async fn set_idle_timeout(&self, timeout: Duration) {
+        let activity = self.activity.clone();
+        let active_replies = self.active_replies.clone();
+        let stopped_when_idle = self.stopped_when_idle.clone();
+        let extension_manager = Arc::downgrade(&self.extension_manager);
+        let watcher = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = activity.notified() => {}
+                    _ = tokio::time::sleep(timeout) => {
+                        if active_replies.load(Ordering::SeqCst) > 0 {
+                            continue;
+                        }
+                        if let Some(extension_manager) = extension_manager.upgrade() {
+                            info!("Agent idle for {:?}, stopping extensions", timeout);
+                            extension_manager.shutdown().await;
+                            stopped_when_idle.store(true, Ordering::SeqCst);
+                        }
+                        break;
+                    }
+                }
+            }
+        });
+        if let Some(previous) = self.idle_watcher.lock().await.replace(watcher) {
+            previous.abort();
+        }
+    }
+
+    pub(super) fn track_active_reply(&self) -> ActiveReplyGuard {
+        self.active_replies.fetch_add(1, Ordering::SeqCst);
+        self.activity.notify_one();
+        ActiveReplyGuard {
+            active_replies: self.active_replies.clone(),
+            activity: self.activity.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{ExtensionConfig, SessionConfig};
+    use crate::conversation::message::Message;
+    use crate::providers::scriptedprovider::ScriptedProvider;
+    use crate::session::SessionManager;
+    use anyhow::Result;
+    use futures::StreamExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeout_stops_extensions() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent.set_idle_timeout(Duration::from_secs(60)).await;
+
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert_eq!(agent.list_extensions().await, vec!["todo".to_string()]);
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(agent.list_extensions().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeout_waits_for_streaming_reply() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "idle-reply-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let provider = ScriptedProvider::replying("done").with_delay(Duration::from_secs(90));
+        agent
+            .update_provider(Arc::new(provider), &session.id)
+            .await?;
+        agent.set_idle_timeout(Duration::from_secs(60)).await;
+
+        let session_config = SessionConfig {
+            id: session.id,
+            schedule_id: None,
+            max_turns: None,
+            retry_config: None,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
+        };
+        let mut stream = agent
+            .reply(Message::user().with_text("slow"), session_config, None)
+            .await?;
+        let consume = async {
+            while let Some(event) = stream.next().await {
+                event?;
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        let check = async {
+            tokio::time::sleep(Duration::from_secs(75)).await;
+            agent.list_extensions().await
+        };
+        let (consumed, during_reply) = tokio::join!(consume, check);
+        consumed?;
+        drop(stream);
+        assert_eq!(during_reply, vec!["todo".to_string()]);
+
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        assert_eq!(agent.list_extensions().await, vec!["todo".to_string()]);
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(agent.list_extensions().await.is_empty());
+        Ok(())
+    }
+}
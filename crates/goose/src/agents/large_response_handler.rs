@@ -1,23 +1,44 @@
+use super::types::{LargeResponseStrategy, TruncationPolicy};
 use chrono::Utc;
-use rmcp::model::{Content, ErrorData};
+use rmcp::model::{Content, ErrorData, RawContent};
 use std::fs::File;
 use std::io::Write;
 
 const LARGE_TEXT_THRESHOLD: usize = 200_000;
 
-/// Process tool response and handle large text content
+/// Process tool response and handle large text content as `strategy` says; `truncation`
+/// chooses the part kept by `LargeResponseStrategy::Truncate`.
 pub fn process_tool_response(
     response: Result<Vec<Content>, ErrorData>,
+    strategy: LargeResponseStrategy,
+    truncation: TruncationPolicy,
 ) -> Result<Vec<Content>, ErrorData> {
+    if strategy == LargeResponseStrategy::Passthrough {
+        return response;
+    }
     match response {
         Ok(contents) => {
             let mut processed_contents = Vec::new();
 
             for content in contents {
+                // The UI fetches referenced files itself, so the reference is kept as is
+                if is_file_reference(&content) {
+                    processed_contents.push(content);
+                    continue;
+                }
+
                 match content.as_text() {
                     Some(text_content) => {
                         // Check if text exceeds threshold
                         if text_content.text.chars().count() > LARGE_TEXT_THRESHOLD {
+                            if strategy == LargeResponseStrategy::Truncate {
+                                processed_contents.push(Content::text(truncate_text(
+                                    &text_content.text,
+                                    LARGE_TEXT_THRESHOLD,
+                                    truncation,
+                                )));
+                                continue;
+                            }
                             // Write to temp file
                             match write_large_text_to_file(&text_content.text) {
                                 Ok(file_path) => {
@@ -57,6 +78,39 @@ pub fn process_tool_response(
     }
 }
 
+/// A resource link to a local file, returned by tools instead of inlining large output
+fn is_file_reference(content: &Content) -> bool {
+    matches!(&content.raw, RawContent::ResourceLink(link) if link.uri.starts_with("file://"))
+}
+
+/// Keep `limit` characters of `text` as chosen by `policy`, marking where the rest was cut.
+/// The full text is still written to a file so it can be examined with other tools.
+fn truncate_text(text: &str, limit: usize, policy: TruncationPolicy) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let omitted = chars.len().saturating_sub(limit);
+    if omitted == 0 {
+        return text.to_string();
+    }
+
+    let marker = match write_large_text_to_file(text) {
+        Ok(file_path) => format!(
+            "\n[... {} characters omitted, full output stored in {} ...]\n",
+            omitted, file_path
+        ),
+        Err(_) => format!("\n[... {} characters omitted ...]\n", omitted),
+    };
+    let head = |n: usize| chars[..n].iter().collect::<String>();
+    let tail = |n: usize| chars[chars.len() - n..].iter().collect::<String>();
+
+    match policy {
+        TruncationPolicy::Head => format!("{}{}", head(limit), marker),
+        TruncationPolicy::Tail => format!("{}{}", marker, tail(limit)),
+        TruncationPolicy::Middle => {
+            format!("{}{}{}", head(limit / 2), marker, tail(limit - limit / 2))
+        }
+    }
+}
+
 /// Write large text content to a temporary file
 fn write_large_text_to_file(content: &str) -> Result<String, std::io::Error> {
     // Create temp directory if it doesn't exist
@@ -78,7 +132,7 @@ fn write_large_text_to_file(content: &str) -> Result<String, std::io::Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rmcp::model::{Content, ErrorCode, ErrorData};
+    use rmcp::model::{AnnotateAble, Content, ErrorCode, ErrorData, RawResource};
     use std::borrow::Cow;
     use std::fs;
     use std::path::Path;
@@ -92,7 +146,12 @@ mod tests {
         let response = Ok(vec![content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(
+            response,
+            LargeResponseStrategy::Summarize,
+            TruncationPolicy::Head,
+        )
+        .unwrap();
 
         // Verify the response is unchanged
         assert_eq!(processed.len(), 1);
@@ -112,7 +171,12 @@ mod tests {
         let response = Ok(vec![content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(
+            response,
+            LargeResponseStrategy::Summarize,
+            TruncationPolicy::Head,
+        )
+        .unwrap();
 
         // Verify the response contains a message about the file
         assert_eq!(processed.len(), 1);
@@ -149,7 +213,12 @@ mod tests {
         let response = Ok(vec![image_content]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(
+            response,
+            LargeResponseStrategy::Summarize,
+            TruncationPolicy::Head,
+        )
+        .unwrap();
 
         // Verify the response is unchanged
         assert_eq!(processed.len(), 1);
@@ -161,6 +230,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_file_reference_passes_through() {
+        let mut resource = RawResource::new("file:///tmp/build.log", "build.log");
+        resource.size = Some((LARGE_TEXT_THRESHOLD * 10) as u32);
+        let reference = RawContent::ResourceLink(resource).no_annotation();
+
+        let processed = process_tool_response(
+            Ok(vec![reference.clone()]),
+            LargeResponseStrategy::Truncate,
+            TruncationPolicy::Head,
+        )
+        .unwrap();
+
+        assert_eq!(processed, vec![reference]);
+    }
+
     #[test]
     fn test_mixed_content_handled_correctly() {
         // Create a response with mixed content types
@@ -171,7 +256,12 @@ mod tests {
         let response = Ok(vec![small_text, large_text, image]);
 
         // Process the response
-        let processed = process_tool_response(response).unwrap();
+        let processed = process_tool_response(
+            response,
+            LargeResponseStrategy::Summarize,
+            TruncationPolicy::Head,
+        )
+        .unwrap();
 
         // Verify each item is handled correctly
         assert_eq!(processed.len(), 3);
@@ -209,6 +299,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_truncation_policies_keep_the_chosen_part() {
+        let text = format!("{}{}{}", "h".repeat(50), "m".repeat(100), "t".repeat(50));
+
+        let head = truncate_text(&text, 40, TruncationPolicy::Head);
+        assert!(head.starts_with(&"h".repeat(40)));
+        assert!(head.contains("160 characters omitted"));
+
+        let tail = truncate_text(&text, 40, TruncationPolicy::Tail);
+        assert!(tail.ends_with(&"t".repeat(40)));
+
+        let middle = truncate_text(&text, 40, TruncationPolicy::Middle);
+        assert!(middle.starts_with(&"h".repeat(20)));
+        assert!(middle.ends_with(&"t".repeat(20)));
+        assert!(!middle.contains("mm"));
+
+        assert_eq!(truncate_text("short", 40, TruncationPolicy::Tail), "short");
+    }
+
+    #[test]
+    fn test_one_megabyte_text_in_each_strategy() {
+        let text = "0123456789abcdef".repeat(65_536);
+
+        let process = |strategy| {
+            let processed = process_tool_response(
+                Ok(vec![Content::text(text.clone())]),
+                strategy,
+                TruncationPolicy::Tail,
+            )
+            .unwrap();
+            assert_eq!(processed.len(), 1);
+            processed[0].as_text().unwrap().text.clone()
+        };
+
+        let truncated = process(LargeResponseStrategy::Truncate);
+        assert!(truncated.ends_with(&text[text.len() - LARGE_TEXT_THRESHOLD..]));
+        assert!(truncated.contains("characters omitted"));
+        assert!(truncated.len() < text.len());
+
+        let summarized = process(LargeResponseStrategy::Summarize);
+        assert!(summarized.contains("(1048576 characters)"));
+        if let Some(file_path) = summarized.split("stored in the file: ").nth(1) {
+            let _ = fs::remove_file(Path::new(file_path.trim()));
+        }
+
+        assert_eq!(process(LargeResponseStrategy::Passthrough), text);
+    }
+
     #[test]
     fn test_error_response_passes_through() {
         // Create an error response
@@ -220,7 +358,11 @@ mod tests {
         let response: Result<Vec<Content>, ErrorData> = Err(error);
 
         // Process the response
-        let processed = process_tool_response(response);
+        let processed = process_tool_response(
+            response,
+            LargeResponseStrategy::Summarize,
+            TruncationPolicy::Head,
+        );
 
         // Verify the error is passed through unchanged
         assert!(processed.is_err());
@@ -3,7 +3,7 @@ use crate::agents::types::SharedProvider;
 use crate::session_context::SESSION_ID_HEADER;
 use rmcp::model::{
     Content, CreateElicitationRequestParam, CreateElicitationResult, ElicitationAction, ErrorCode,
-    JsonObject,
+    JsonObject, PromptMessageContent,
 };
 /// MCP client implementation for Goose
 use rmcp::{
@@ -17,6 +17,7 @@ use rmcp::{
         LoggingMessageNotificationMethod, PaginatedRequestParam, ProgressNotification,
         ProgressNotificationMethod, ProtocolVersion, ReadResourceRequest, ReadResourceRequestParam,
         ReadResourceResult, RequestId, Role, SamplingMessage, ServerNotification, ServerResult,
+        ToolListChangedNotification, ToolListChangedNotificationMethod,
     },
     service::{
         ClientInitializeError, PeerRequestOptions, RequestContext, RequestHandle, RunningService,
@@ -37,6 +38,9 @@ pub type BoxError = Box<dyn std::error::Error + Sync + Send>;
 
 pub type Error = rmcp::ServiceError;
 
+/// Prompt an extension can serve to add text to the system prompt on every turn
+pub const SYSTEM_PROMPT_FRAGMENT_PROMPT: &str = "goose_system_prompt";
+
 #[async_trait::async_trait]
 pub trait McpClientTrait: Send + Sync {
     async fn list_resources(
@@ -84,6 +88,30 @@ pub trait McpClientTrait: Send + Sync {
     async fn get_moim(&self) -> Option<String> {
         None
     }
+
+    /// Text to merge into the system prompt, served by servers with prompt support as the
+    /// `goose_system_prompt` prompt and fetched again each time the prompt is built
+    async fn get_system_prompt_fragment(&self) -> Option<String> {
+        self.get_info()?.capabilities.prompts.as_ref()?;
+        let result = self
+            .get_prompt(
+                SYSTEM_PROMPT_FRAGMENT_PROMPT,
+                Value::Object(JsonObject::new()),
+                CancellationToken::default(),
+            )
+            .await
+            .ok()?;
+        let fragment = result
+            .messages
+            .into_iter()
+            .filter_map(|message| match message.content {
+                PromptMessageContent::Text { text } => Some(text),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        (!fragment.is_empty()).then_some(fragment)
+    }
 }
 
 pub struct GooseClient {
@@ -144,6 +172,24 @@ impl ClientHandler for GooseClient {
             });
     }
 
+    async fn on_tool_list_changed(
+        &self,
+        context: rmcp::service::NotificationContext<rmcp::RoleClient>,
+    ) {
+        self.notification_handlers
+            .lock()
+            .await
+            .iter()
+            .for_each(|handler| {
+                let _ = handler.try_send(ServerNotification::ToolListChangedNotification(
+                    ToolListChangedNotification {
+                        method: ToolListChangedNotificationMethod,
+                        extensions: context.extensions.clone(),
+                    },
+                ));
+            });
+    }
+
     async fn create_message(
         &self,
         params: CreateMessageRequestParam,
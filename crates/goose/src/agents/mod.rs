@@ -1,35 +1,58 @@
 mod agent;
 pub(crate) mod chatrecall_extension;
+pub mod compact_serializer;
 pub mod extension;
 pub mod extension_malware_check;
 pub mod extension_manager;
 pub mod extension_manager_extension;
 pub mod final_output_tool;
+mod idle_timeout;
 mod large_response_handler;
 pub mod mcp_client;
 pub mod moim;
+pub mod notification_sink;
 pub mod platform_tools;
 pub mod prompt_manager;
+mod recipe_generation;
 pub mod recipe_tools;
+pub mod reference_validator;
 mod reply_parts;
 pub mod retry;
 mod router_tool_selector;
 mod router_tools;
 mod schedule_tool;
+mod settings;
 pub(crate) mod skills_extension;
 pub mod sub_recipe_manager;
 pub mod subagent_execution_tool;
 pub mod subagent_handler;
 mod subagent_task_config;
+#[cfg(test)]
+mod test_extension;
 pub(crate) mod todo_extension;
+pub mod todo_store;
 mod tool_execution;
+pub mod tool_executor;
+pub mod tool_history;
 mod tool_route_manager;
 mod tool_router_index_manager;
 pub mod types;
 
-pub use agent::{Agent, AgentEvent, MANUAL_COMPACT_TRIGGERS};
-pub use extension::ExtensionConfig;
+pub use agent::{Agent, AgentEvent, FanoutEvent, MANUAL_COMPACT_TRIGGERS};
+pub use compact_serializer::CompactSerializer;
+pub use extension::{DuplicateExtensionPolicy, ExtensionConfig};
 pub use extension_manager::ExtensionManager;
+pub use notification_sink::NotificationFileSink;
 pub use prompt_manager::PromptManager;
+pub use recipe_generation::{RecipeCreationCancelled, RecipeParseError, RecipeTooLarge};
+pub use router_tool_selector::RouterToolSelector;
 pub use subagent_task_config::TaskConfig;
-pub use types::{FrontendTool, RetryConfig, SessionConfig, SuccessCheck};
+pub use tool_execution::TOOL_TIMEOUT_ERROR_CODE;
+pub use tool_executor::ToolExecutor;
+pub use tool_history::{ToolHistoryEntry, ToolHistoryFilter, ToolOutcome};
+pub use types::{
+    ArgumentTransform, ConfigSnapshot, ErrorRecoveryAction, FrontendTool, LargeResponseStrategy,
+    PreSendHook, RecipeProgressCallback, ResultTransform, RetryConfig, RetryPolicy, RouterStrategy,
+    SessionConfig, SessionMetricsJson, SuccessCheck, ToolGate, ToolOfferingDecision, ToolPolicy,
+    ToolRetryPolicy, ToolSandbox, ToolSchemaFormat, ToolSetDiff, TruncationPolicy,
+};
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use rmcp::model::ServerNotification;
+use serde_json::json;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+/// Appends MCP notifications emitted by tool calls to a per-session log file
+#[derive(Debug, Clone)]
+pub struct NotificationFileSink {
+    dir: PathBuf,
+}
+
+impl NotificationFileSink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the log file used for the given session
+    pub fn path_for(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.notifications.jsonl", session_id))
+    }
+
+    /// Write a single notification as one JSON line
+    pub async fn write(
+        &self,
+        session_id: &str,
+        request_id: &str,
+        notification: &ServerNotification,
+    ) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path_for(session_id))
+            .await?;
+
+        let line = json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "request_id": request_id,
+            "notification": notification,
+        });
+        file.write_all(format!("{}\n", line).as_bytes()).await?;
+        file.flush().await
+    }
+}
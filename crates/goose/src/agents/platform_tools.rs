@@ -1,40 +1,77 @@
+use std::collections::HashMap;
+
 use indoc::indoc;
 use rmcp::model::{Tool, ToolAnnotations};
 use rmcp::object;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::prompt_template;
+
 pub const PLATFORM_MANAGE_SCHEDULE_TOOL_NAME: &str = "platform__manage_schedule";
 
+/// Config key mapping platform tool names to the descriptions shown to the model in their place
+pub const PLATFORM_TOOL_DESCRIPTIONS_CONFIG_KEY: &str = "GOOSE_PLATFORM_TOOL_DESCRIPTIONS";
+
+/// The configured description of `tool_name`, or `default` when none is set. Overrides are
+/// templates that can include the built-in text as `{{ default }}`.
+pub fn platform_tool_description(tool_name: &str, default: &str) -> String {
+    let overrides: HashMap<String, String> = Config::global()
+        .get_param(PLATFORM_TOOL_DESCRIPTIONS_CONFIG_KEY)
+        .unwrap_or_default();
+    let Some(template) = overrides.get(tool_name) else {
+        return default.to_string();
+    };
+    let context = HashMap::from([("default", default), ("tool_name", tool_name)]);
+    prompt_template::render_inline_once(template, &context).unwrap_or_else(|e| {
+        warn!("Invalid description override for '{}': {}", tool_name, e);
+        default.to_string()
+    })
+}
+
 pub fn manage_schedule_tool() -> Tool {
-    Tool::new(
-        PLATFORM_MANAGE_SCHEDULE_TOOL_NAME.to_string(),
+    let description = platform_tool_description(
+        PLATFORM_MANAGE_SCHEDULE_TOOL_NAME,
         indoc! {r#"
             Manage scheduled recipe execution for this goose instance.
             
             Actions:
-            - "list": List all scheduled jobs
-            - "create": Create a new scheduled job from a recipe file
+            - "list": List all scheduled jobs with a readable description of each schedule
+            - "create": Create a new scheduled job from a recipe file or inline recipe content
             - "run_now": Execute a scheduled job immediately  
             - "pause": Pause a scheduled job
             - "unpause": Resume a paused job
             - "delete": Remove a scheduled job
             - "kill": Terminate a currently running job
-            - "inspect": Get details about a running job
+            - "inspect": Get details about a job's schedule and whether it is running
+            - "running": List all currently running jobs with their sessions and start times
             - "sessions": List execution history for a job
             - "session_content": Get the full content (messages) of a specific session
-        "#}
-        .to_string(),
+            - "preview": Show the next fire times of a cron expression without creating a job
+            - "validate": Check the arguments of a create action and report the job it would create, without creating it
+        "#},
+    );
+    Tool::new(
+        PLATFORM_MANAGE_SCHEDULE_TOOL_NAME.to_string(),
+        description,
         object!({
             "type": "object",
             "required": ["action"],
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["list", "create", "run_now", "pause", "unpause", "delete", "kill", "inspect", "sessions", "session_content"]
+                    "enum": ["list", "create", "run_now", "pause", "unpause", "delete", "kill", "inspect", "running", "sessions", "session_content", "preview", "validate"]
                 },
                 "job_id": {"type": "string", "description": "Job identifier for operations on existing jobs"},
-                "recipe_path": {"type": "string", "description": "Path to recipe file for create action"},
-                "cron_expression": {"type": "string", "description": "A cron expression for create action. Supports both 5-field (minute hour day month weekday) and 6-field (second minute hour day month weekday) formats. 5-field expressions are automatically converted to 6-field by prepending '0' for seconds."},
+                "recipe_path": {"type": "string", "description": "Path to recipe file for create and validate actions"},
+                "recipe_content": {"type": "string", "description": "Inline JSON or YAML recipe for create and validate actions, used instead of recipe_path"},
+                "cron_expression": {"type": "string", "description": "A cron expression for create, validate and preview actions. Supports both 5-field (minute hour day month weekday) and 6-field (second minute hour day month weekday) formats. 5-field expressions are automatically converted to 6-field by prepending '0' for seconds."},
+                "valid_from": {"type": "string", "description": "Optional RFC3339 time before which the created job does not run"},
+                "valid_until": {"type": "string", "description": "Optional RFC3339 time after which the created job stops running and is deleted"},
                 "limit": {"type": "integer", "description": "Limit for sessions list", "default": 50},
-                "session_id": {"type": "string", "description": "Session identifier for session_content action"}
+                "session_id": {"type": "string", "description": "Session identifier for session_content action"},
+                "count": {"type": "integer", "description": "Number of fire times for preview action", "default": 5},
+                "timezone": {"type": "string", "description": "Timezone for preview action: 'local' (default), 'UTC' or an offset like '+02:00'"}
             }
         }),
     ).annotate(ToolAnnotations {
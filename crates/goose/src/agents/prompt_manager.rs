@@ -1,9 +1,12 @@
 #[cfg(test)]
 use chrono::DateTime;
 use chrono::Utc;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
 
 use crate::agents::extension::ExtensionInfo;
 use crate::agents::recipe_tools::dynamic_task_tools::should_enabled_subagents;
@@ -19,9 +22,27 @@ use std::path::Path;
 const MAX_EXTENSIONS: usize = 5;
 const MAX_TOOLS: usize = 50;
 
+/// A bare `{{ name }}` placeholder in an override prompt
+static TEMPLATE_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid regex"));
+
+/// Named parts of the system prompt, composed after the base prompt in declaration order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PromptSection {
+    Intro,
+    Tools,
+    Guidelines,
+    Recipe,
+}
+
 pub struct PromptManager {
     system_prompt_override: Option<String>,
+    sections: BTreeMap<PromptSection, String>,
     system_prompt_extras: Vec<String>,
+    session_system_prompt_extras: HashMap<String, Vec<String>>,
+    template_variables: HashMap<String, String>,
+    /// Unknown placeholders already logged, so each is only reported once
+    reported_placeholders: Mutex<HashSet<String>>,
     current_date_timestamp: String,
 }
 
@@ -44,6 +65,7 @@ struct SystemPromptContext {
     enable_subagents: bool,
     max_extensions: usize,
     max_tools: usize,
+    template_variables: BTreeMap<String, String>,
 }
 
 pub struct SystemPromptBuilder<'a, M> {
@@ -55,6 +77,8 @@ pub struct SystemPromptBuilder<'a, M> {
     extension_tool_count: Option<(usize, usize)>,
     router_enabled: bool,
     hints: Option<String>,
+    session_id: Option<String>,
+    goose_mode: Option<GooseMode>,
 }
 
 impl<'a> SystemPromptBuilder<'a, PromptManager> {
@@ -89,6 +113,18 @@ impl<'a> SystemPromptBuilder<'a, PromptManager> {
         self
     }
 
+    /// Describe `mode` instead of the configured GOOSE_MODE, when set
+    pub fn with_goose_mode(mut self, mode: Option<GooseMode>) -> Self {
+        self.goose_mode = mode;
+        self
+    }
+
+    /// Include the extras registered for this session
+    pub fn with_session_id(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
     pub fn with_hints(mut self, working_dir: &Path) -> Self {
         let config = Config::global();
         let hints_filenames = config
@@ -138,13 +174,40 @@ impl<'a> SystemPromptBuilder<'a, PromptManager> {
             })
             .collect();
 
-        let config = Config::global();
-        let goose_mode = config.get_goose_mode().unwrap_or(GooseMode::Auto);
+        let goose_mode = self
+            .goose_mode
+            .unwrap_or_else(|| Config::global().get_goose_mode().unwrap_or(GooseMode::Auto));
 
         let extension_tool_limits = self
             .extension_tool_count
             .filter(|(extensions, tools)| *extensions > MAX_EXTENSIONS || *tools > MAX_TOOLS);
 
+        let mut template_variables = BTreeMap::from([
+            ("model_name".to_string(), self.model_name.clone()),
+            (
+                "extensions".to_string(),
+                sanitized_extensions_info
+                    .iter()
+                    .map(|ext| ext.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            (
+                "date".to_string(),
+                self.manager
+                    .current_date_timestamp
+                    .chars()
+                    .take(10)
+                    .collect(),
+            ),
+        ]);
+        template_variables.extend(
+            self.manager
+                .template_variables
+                .iter()
+                .map(|(name, value)| (name.clone(), sanitize_unicode_tags(value))),
+        );
+
         let context = SystemPromptContext {
             extensions: sanitized_extensions_info,
             tool_selection_strategy: self.router_enabled.then(llm_search_tool_prompt),
@@ -155,19 +218,39 @@ impl<'a> SystemPromptBuilder<'a, PromptManager> {
             enable_subagents: should_enabled_subagents(self.model_name.as_str()),
             max_extensions: MAX_EXTENSIONS,
             max_tools: MAX_TOOLS,
+            template_variables,
         };
 
         let base_prompt = if let Some(override_prompt) = &self.manager.system_prompt_override {
             let sanitized_override_prompt = sanitize_unicode_tags(override_prompt);
-            prompt_template::render_inline_once(&sanitized_override_prompt, &context)
+            let resolved_override_prompt = self
+                .manager
+                .resolve_placeholders(&sanitized_override_prompt, &context);
+            prompt_template::render_inline_once(&resolved_override_prompt, &context)
         } else {
             prompt_template::render_global_file("system.md", &context)
         }
         .unwrap_or_else(|_| {
             "You are a general-purpose AI agent called goose, created by Block".to_string()
         });
+        let base_prompt = std::iter::once(base_prompt)
+            .chain(
+                self.manager
+                    .sections
+                    .values()
+                    .map(|text| sanitize_unicode_tags(text)),
+            )
+            .collect::<Vec<_>>()
+            .join("\n\n");
 
         let mut system_prompt_extras = self.manager.system_prompt_extras.clone();
+        if let Some(session_extras) = self
+            .session_id
+            .as_ref()
+            .and_then(|id| self.manager.session_system_prompt_extras.get(id))
+        {
+            system_prompt_extras.extend(session_extras.iter().cloned());
+        }
 
         // Add hints if provided
         if let Some(hints) = self.hints {
@@ -202,7 +285,11 @@ impl PromptManager {
     pub fn new() -> Self {
         PromptManager {
             system_prompt_override: None,
+            sections: BTreeMap::new(),
             system_prompt_extras: Vec::new(),
+            session_system_prompt_extras: HashMap::new(),
+            template_variables: HashMap::new(),
+            reported_placeholders: Mutex::new(HashSet::new()),
             // Use the fixed current date time so that prompt cache can be used.
             // Filtering to an hour to balance user time accuracy and multi session prompt cache hits.
             current_date_timestamp: Utc::now().format("%Y-%m-%d %H:00").to_string(),
@@ -213,7 +300,11 @@ impl PromptManager {
     pub fn with_timestamp(dt: DateTime<Utc>) -> Self {
         PromptManager {
             system_prompt_override: None,
+            sections: BTreeMap::new(),
             system_prompt_extras: Vec::new(),
+            session_system_prompt_extras: HashMap::new(),
+            template_variables: HashMap::new(),
+            reported_placeholders: Mutex::new(HashSet::new()),
             current_date_timestamp: dt.format("%Y-%m-%d %H:%M:%S").to_string(),
         }
     }
@@ -223,11 +314,60 @@ impl PromptManager {
         self.system_prompt_extras.push(instruction);
     }
 
+    /// Add an additional instruction that only applies to prompts built for `session_id`
+    pub fn add_session_system_prompt_extra(&mut self, session_id: &str, instruction: String) {
+        self.session_system_prompt_extras
+            .entry(session_id.to_string())
+            .or_default()
+            .push(instruction);
+    }
+
     /// Override the system prompt with custom text
     pub fn set_system_prompt_override(&mut self, template: String) {
         self.system_prompt_override = Some(template);
     }
 
+    /// Values for `{{ name }}` placeholders in the override prompt, alongside the built-in
+    /// `model_name`, `extensions` and `date`
+    pub fn set_template_variables(&mut self, variables: HashMap<String, String>) {
+        self.template_variables = variables;
+    }
+
+    /// Point bare placeholders at the template variables. Placeholders that name neither a
+    /// variable nor a context field are escaped so they render as written.
+    fn resolve_placeholders(&self, template: &str, context: &SystemPromptContext) -> String {
+        let context_fields = serde_json::to_value(context).unwrap_or_default();
+        TEMPLATE_PLACEHOLDER
+            .replace_all(template, |caps: &regex::Captures| {
+                let name = &caps[1];
+                if context.template_variables.contains_key(name) {
+                    format!("{{{{ template_variables.{} }}}}", name)
+                } else if context_fields.get(name).is_some() {
+                    caps[0].to_string()
+                } else {
+                    if self
+                        .reported_placeholders
+                        .lock()
+                        .unwrap()
+                        .insert(name.to_string())
+                    {
+                        tracing::warn!("Unknown system prompt variable '{}' left as is", name);
+                    }
+                    format!("{{% raw %}}{}{{% endraw %}}", &caps[0])
+                }
+            })
+            .into_owned()
+    }
+
+    /// Set the text of `section`, replacing what it held before
+    pub fn set_section(&mut self, section: PromptSection, text: String) {
+        self.sections.insert(section, text);
+    }
+
+    pub fn remove_section(&mut self, section: PromptSection) {
+        self.sections.remove(&section);
+    }
+
     pub fn builder<'a>(&'a self, model_name: &str) -> SystemPromptBuilder<'a, Self> {
         SystemPromptBuilder {
             model_name: model_name.to_string(),
@@ -238,6 +378,8 @@ impl PromptManager {
             extension_tool_count: None,
             router_enabled: false,
             hints: None,
+            session_id: None,
+            goose_mode: None,
         }
     }
 
@@ -315,6 +457,74 @@ mod tests {
         assert!(result.contains("emojis"));
     }
 
+    #[test]
+    fn test_session_extras_do_not_leak_between_sessions() {
+        let mut manager = PromptManager::new();
+        manager.add_system_prompt_extra("Shared instruction".to_string());
+        manager.add_session_system_prompt_extra("session-a", "Only for A".to_string());
+
+        let prompt_a = manager
+            .builder("gpt-4o")
+            .with_session_id("session-a")
+            .build();
+        let prompt_b = manager
+            .builder("gpt-4o")
+            .with_session_id("session-b")
+            .build();
+
+        assert!(prompt_a.contains("Shared instruction"));
+        assert!(prompt_a.contains("Only for A"));
+        assert!(prompt_b.contains("Shared instruction"));
+        assert!(!prompt_b.contains("Only for A"));
+    }
+
+    #[test]
+    fn test_override_template_variables() {
+        let mut manager =
+            PromptManager::with_timestamp(DateTime::<Utc>::from_timestamp(0, 0).unwrap());
+        manager.set_system_prompt_override(
+            "{{ model_name }} on {{date}} with {{ extensions }} for {{team}}, {{ unknown }} {{ is_autonomous }}"
+                .to_string(),
+        );
+        manager.set_template_variables(HashMap::from([(
+            "team".to_string(),
+            "{{ model_name }} {% raw %}".to_string(),
+        )]));
+
+        let prompt = manager
+            .builder("gpt-4o")
+            .with_extension(ExtensionInfo::new("memory", "", false))
+            .with_extension(ExtensionInfo::new("developer", "", false))
+            .with_goose_mode(Some(GooseMode::Auto))
+            .build();
+
+        assert_eq!(
+            prompt,
+            "gpt-4o on 1970-01-01 with developer, memory for {{ model_name }} {% raw %}, {{ unknown }} true"
+        );
+    }
+
+    #[test]
+    fn test_sections_compose_in_order() {
+        let mut manager = PromptManager::new();
+        manager.set_system_prompt_override("Base prompt".to_string());
+        manager.set_section(PromptSection::Recipe, "Follow the recipe".to_string());
+        manager.set_section(PromptSection::Intro, "You help with releases".to_string());
+        manager.set_section(PromptSection::Guidelines, "Be brief".to_string());
+        assert_eq!(
+            manager.builder("gpt-4o").build(),
+            "Base prompt\n\nYou help with releases\n\nBe brief\n\nFollow the recipe"
+        );
+
+        manager.set_section(PromptSection::Guidelines, "Explain each step".to_string());
+        manager.remove_section(PromptSection::Recipe);
+        manager.add_system_prompt_extra("Extra".to_string());
+        assert_eq!(
+            manager.builder("gpt-4o").build(),
+            "Base prompt\n\nYou help with releases\n\nExplain each step\n\n# Additional Instructions:\n\nExtra"
+        );
+    }
+
     #[test]
     fn test_build_system_prompt_sanitizes_extension_instructions() {
         let manager = PromptManager::new();
@@ -0,0 +1,491 @@
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use regex::Regex;
+use rmcp::model::Tool;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::types::RecipeProgressCallback;
+use crate::agents::Agent;
+use crate::config::{get_enabled_extensions, Config};
+use crate::conversation::message::Message;
+use crate::conversation::{fix_conversation, Conversation};
+use crate::model::{ModelConfig, ResponseFormat};
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::errors::ProviderError;
+use crate::recipe::{Author, Recipe, Settings};
+use crate::utils::token_cancelled;
+
+/// Complete a recipe request, with `json_config` when the provider can enforce JSON output
+async fn complete_recipe_request(
+    provider: &dyn Provider,
+    json_config: Option<&ModelConfig>,
+    system: &str,
+    messages: &[Message],
+    tools: &[Tool],
+) -> Result<(Message, ProviderUsage), ProviderError> {
+    match json_config {
+        Some(config) => {
+            provider
+                .complete_with_model(config, system, messages, tools)
+                .await
+        }
+        None => provider.complete(system, messages, tools).await,
+    }
+}
+
+/// Returned by [`Agent::create_recipe`] when its cancellation token fires before the provider responds
+#[derive(Debug, thiserror::Error)]
+#[error("Recipe creation was cancelled")]
+pub struct RecipeCreationCancelled;
+
+/// Returned by [`Agent::create_recipe`] when the generated instructions and activities
+/// are longer than the configured maximum
+#[derive(Debug, thiserror::Error)]
+#[error("Generated recipe is {size} characters, over the {limit} character limit")]
+pub struct RecipeTooLarge {
+    pub size: usize,
+    pub limit: usize,
+}
+
+/// Asked once when a generated recipe can't be parsed and strict JSON is enabled
+const RECIPE_JSON_CORRECTION_PROMPT: &str = "Your previous response could not be parsed. \
+Reply with only a JSON object with string fields \"title\", \"description\" and \"instructions\" \
+and an \"activities\" array of strings, with no other text.";
+
+/// Returned by [`Agent::create_recipe`] when no recipe can be extracted from the provider's
+/// response. Carries the response so callers can show what the model said.
+#[derive(Debug, thiserror::Error)]
+#[error("Could not parse the generated recipe: {reason}")]
+pub struct RecipeParseError {
+    pub reason: String,
+    pub raw: String,
+}
+
+/// A recipe as the provider describes it, before it is turned into a [`Recipe`]
+#[derive(Debug, PartialEq, Deserialize)]
+struct RecipeDraft {
+    #[serde(default = "RecipeDraft::default_title")]
+    title: String,
+    #[serde(default = "RecipeDraft::default_description")]
+    description: String,
+    instructions: String,
+    #[serde(default)]
+    activities: Vec<String>,
+}
+
+impl RecipeDraft {
+    fn default_title() -> String {
+        "Custom recipe from chat".to_string()
+    }
+
+    fn default_description() -> String {
+        "a custom recipe instance from this chat session".to_string()
+    }
+
+    /// Read the first JSON object in `text` that describes a recipe, ignoring the case of
+    /// its keys
+    fn from_json(text: &str) -> Option<Self> {
+        json_objects(text).find_map(|object| {
+            let object: serde_json::Map<String, Value> = object
+                .into_iter()
+                .map(|(key, value)| (key.to_lowercase(), value))
+                .collect();
+            serde_json::from_value(Value::Object(object)).ok()
+        })
+    }
+
+    /// Read "Instructions:" and "Activities:" sections from a prose response
+    fn from_sections(text: &str) -> Option<Self> {
+        let instructions_re = Regex::new(r"(?i)instructions\**:\**").unwrap();
+        let activities_re = Regex::new(r"(?i)activities\**:\**").unwrap();
+        let after_instructions = &text[instructions_re.find(text)?.end()..];
+        let (instructions_part, activities_text) = match activities_re.find(after_instructions) {
+            Some(m) => (
+                &after_instructions[..m.start()],
+                &after_instructions[m.end()..],
+            ),
+            None => (after_instructions, ""),
+        };
+        Some(Self {
+            instructions: instructions_part
+                .trim_end_matches(|c: char| c.is_whitespace() || c == '#' || c == '*')
+                .trim()
+                .to_string(),
+            activities: bullet_lines(activities_text),
+            ..Self::verbatim("")
+        })
+    }
+
+    /// Use the whole response as the instructions
+    fn verbatim(text: &str) -> Self {
+        Self {
+            title: Self::default_title(),
+            description: Self::default_description(),
+            instructions: text.trim().to_string(),
+            activities: Vec::new(),
+        }
+    }
+}
+
+/// Every balanced `{...}` block in `text` that parses as a JSON object, in order, so JSON
+/// wrapped in code fences or prose is still found
+fn json_objects(text: &str) -> impl Iterator<Item = serde_json::Map<String, Value>> + '_ {
+    text.char_indices()
+        .filter(|(_, c)| *c == '{')
+        .filter_map(|(start, _)| {
+            let end = start + balanced_object_len(&text[start..])?;
+            match serde_json::from_str(&text[start..end]) {
+                Ok(Value::Object(object)) => Some(object),
+                _ => None,
+            }
+        })
+}
+
+/// Length of the `{...}` block at the start of `text`, skipping braces inside strings
+fn balanced_object_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Non-empty lines of a list, without bullet markers or numbering
+fn bullet_lines(text: &str) -> Vec<String> {
+    let bullet_re = Regex::new(r"^[•\-*\d]+\.?\s*").expect("Invalid regex");
+    text.lines()
+        .map(|line| bullet_re.replace(line.trim(), "").trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Parse a recipe generation response, preferring the first JSON object in it and falling
+/// back to "Instructions:"/"Activities:" sections
+fn parse_generated_recipe(content: &str) -> Result<RecipeDraft, RecipeParseError> {
+    if let Some(draft) = RecipeDraft::from_json(content) {
+        return Ok(draft);
+    }
+    tracing::warn!("No recipe JSON in the response, falling back to section parsing");
+    RecipeDraft::from_sections(content).ok_or_else(|| RecipeParseError {
+        reason: "the response has neither a recipe JSON object nor an instructions section"
+            .to_string(),
+        raw: content.to_string(),
+    })
+}
+
+impl Agent {
+    pub async fn create_recipe(
+        &self,
+        messages: Conversation,
+        cancel_token: Option<CancellationToken>,
+    ) -> Result<Recipe> {
+        self.generate_recipe(messages, cancel_token, None).await
+    }
+
+    /// Like [`Agent::create_recipe`], but reports generated text to `on_progress` as it arrives.
+    /// Providers without streaming support deliver the whole response in a single callback.
+    pub async fn create_recipe_with_progress(
+        &self,
+        messages: Conversation,
+        cancel_token: Option<CancellationToken>,
+        on_progress: RecipeProgressCallback,
+    ) -> Result<Recipe> {
+        self.generate_recipe(messages, cancel_token, Some(on_progress))
+            .await
+    }
+
+    async fn generate_recipe(
+        &self,
+        mut messages: Conversation,
+        cancel_token: Option<CancellationToken>,
+        on_progress: Option<RecipeProgressCallback>,
+    ) -> Result<Recipe> {
+        tracing::info!("Starting recipe creation with {} messages", messages.len());
+        let (strict_recipe_json, verbatim_recipe_fallback) = {
+            let settings = self.settings.lock().await;
+            (
+                settings.strict_recipe_json,
+                settings.verbatim_recipe_fallback,
+            )
+        };
+
+        let extensions_info = self.extension_manager.get_extensions_info().await;
+        tracing::debug!("Retrieved {} extensions info", extensions_info.len());
+        let (extension_count, tool_count) =
+            self.extension_manager.get_extension_and_tool_counts().await;
+
+        // Get model name from provider
+        let provider = self.provider().await.map_err(|e| {
+            tracing::error!("Failed to get provider for recipe creation: {}", e);
+            e
+        })?;
+        let model_config = provider.get_model_config();
+        let model_name = &model_config.model_name;
+        tracing::debug!("Using model: {}", model_name);
+        // Providers that can enforce JSON output are told to, so the recipe parses first time
+        let json_config = provider.supports_response_format().then(|| {
+            model_config
+                .clone()
+                .with_response_format(Some(ResponseFormat::Json))
+        });
+
+        let prompt_manager = self.prompt_manager.lock().await;
+        let system_prompt = prompt_manager
+            .builder(model_name)
+            .with_extensions(extensions_info.into_iter())
+            .with_frontend_instructions(self.frontend_instructions.lock().await.clone())
+            .with_extension_and_tool_counts(extension_count, tool_count)
+            .build();
+
+        let recipe_prompt = prompt_manager.get_recipe_prompt().await;
+        let tools = self
+            .extension_manager
+            .get_prefixed_tools(None)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to get tools for recipe creation: {}", e);
+                e
+            })?;
+
+        messages.push(Message::user().with_text(recipe_prompt));
+
+        let (messages, issues) = fix_conversation(messages);
+        if !issues.is_empty() {
+            issues
+                .iter()
+                .for_each(|issue| tracing::warn!(recipe.conversation.issue = issue));
+        }
+
+        tracing::debug!(
+            "Added recipe prompt to messages, total messages: {}",
+            messages.len()
+        );
+
+        tracing::info!("Calling provider to generate recipe content");
+        let completion = async {
+            let Some(on_progress) = &on_progress else {
+                return complete_recipe_request(
+                    provider.as_ref(),
+                    json_config.as_ref(),
+                    &system_prompt,
+                    messages.messages(),
+                    &tools,
+                )
+                .await
+                .map(|(message, _usage)| message);
+            };
+
+            if !provider.supports_streaming() {
+                let (message, _usage) = complete_recipe_request(
+                    provider.as_ref(),
+                    json_config.as_ref(),
+                    &system_prompt,
+                    messages.messages(),
+                    &tools,
+                )
+                .await?;
+                on_progress(&message.as_concat_text());
+                return Ok(message);
+            }
+
+            let mut stream = match json_config.as_ref() {
+                Some(config) => {
+                    provider
+                        .stream_with_model(config, &system_prompt, messages.messages(), &tools)
+                        .await?
+                }
+                None => {
+                    provider
+                        .stream(&system_prompt, messages.messages(), &tools)
+                        .await?
+                }
+            };
+            let mut text = String::new();
+            while let Some(chunk) = stream.next().await {
+                if let (Some(message), _usage) = chunk? {
+                    let delta = message.as_concat_text();
+                    if !delta.is_empty() {
+                        on_progress(&delta);
+                        text.push_str(&delta);
+                    }
+                }
+            }
+            Ok(Message::assistant().with_text(text))
+        };
+        let completion_result = tokio::select! {
+            _ = token_cancelled(cancel_token.clone()) => {
+                tracing::info!("Recipe creation cancelled");
+                return Err(RecipeCreationCancelled.into());
+            }
+            result = completion => result,
+        };
+        let result = completion_result.map_err(|e| {
+            tracing::error!("Provider completion failed during recipe creation: {}", e);
+            e
+        })?;
+
+        let content = result.as_concat_text();
+        tracing::debug!(
+            "Provider returned content with {} characters",
+            content.len()
+        );
+
+        let parsed = match parse_generated_recipe(&content) {
+            Ok(fields) => Ok(fields),
+            Err(e) if strict_recipe_json => {
+                tracing::warn!(
+                    "Could not parse recipe response ({}), asking for strict JSON",
+                    e
+                );
+                let mut messages = messages;
+                messages.push(result);
+                messages.push(Message::user().with_text(RECIPE_JSON_CORRECTION_PROMPT));
+                let correction = complete_recipe_request(
+                    provider.as_ref(),
+                    json_config.as_ref(),
+                    &system_prompt,
+                    messages.messages(),
+                    &tools,
+                );
+                let (corrected, _usage) = tokio::select! {
+                    _ = token_cancelled(cancel_token.clone()) => {
+                        tracing::info!("Recipe creation cancelled");
+                        return Err(RecipeCreationCancelled.into());
+                    }
+                    result = correction => result?,
+                };
+                parse_generated_recipe(&corrected.as_concat_text())
+            }
+            Err(e) => Err(e),
+        };
+        let RecipeDraft {
+            title,
+            description,
+            instructions,
+            activities,
+        } = match parsed {
+            Ok(fields) => fields,
+            Err(e) if verbatim_recipe_fallback => {
+                tracing::warn!("{}, using the response as the instructions", e);
+                RecipeDraft::verbatim(&content)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let limit = self.settings.lock().await.max_recipe_size;
+        let size = instructions.chars().count()
+            + activities
+                .iter()
+                .map(|activity| activity.chars().count())
+                .sum::<usize>();
+        if size > limit {
+            tracing::warn!("Generated recipe is {} characters, rejecting", size);
+            return Err(RecipeTooLarge { size, limit }.into());
+        }
+
+        let extension_configs = get_enabled_extensions();
+
+        let author = Author {
+            contact: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok(),
+            metadata: None,
+        };
+
+        // Ideally we'd get the name of the provider we are using from the provider itself,
+        // but it doesn't know and the plumbing looks complicated.
+        let config = Config::global();
+        let provider_name: String = config
+            .get_goose_provider()
+            .expect("No provider configured. Run 'goose configure' first");
+
+        let settings = Settings {
+            goose_provider: Some(provider_name.clone()),
+            goose_model: Some(model_name.clone()),
+            temperature: Some(model_config.temperature.unwrap_or(0.0)),
+        };
+
+        tracing::debug!(
+            "Building recipe with {} activities and {} extensions",
+            activities.len(),
+            extension_configs.len()
+        );
+
+        let recipe = Recipe::builder()
+            .title(title)
+            .description(description)
+            .instructions(instructions)
+            .activities(activities)
+            .extensions(extension_configs)
+            .settings(settings)
+            .author(author)
+            .build()
+            .map_err(|e| {
+                tracing::error!("Failed to build recipe: {}", e);
+                anyhow!("Recipe build failed: {}", e)
+            })?;
+
+        tracing::info!("Recipe creation completed successfully");
+        Ok(recipe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_generated_recipe_reads_fenced_json() {
+        let content = "Here is the recipe:\n```json\n{\"Title\": \"Triage\", \
+            \"instructions\": \"Label new {bugs}\", \"activities\": [\"Label\"]}\n```";
+
+        let draft = parse_generated_recipe(content).unwrap();
+        assert_eq!(draft.title, "Triage");
+        assert_eq!(draft.instructions, "Label new {bugs}");
+        assert_eq!(draft.activities, vec!["Label"]);
+    }
+
+    #[test]
+    fn test_parse_generated_recipe_reads_bare_json() {
+        let content = r#"Sure! {"instructions": "Summarize the day", "activities": []} Done."#;
+
+        let draft = parse_generated_recipe(content).unwrap();
+        assert_eq!(draft.instructions, "Summarize the day");
+        assert_eq!(draft.title, RecipeDraft::default_title());
+    }
+
+    #[test]
+    fn test_parse_generated_recipe_handles_prose() {
+        let sections = "**Instructions:** Review open PRs\n\n**Activities:**\n- Review\n2. Merge";
+        let draft = parse_generated_recipe(sections).unwrap();
+        assert_eq!(draft.instructions, "Review open PRs");
+        assert_eq!(draft.activities, vec!["Review", "Merge"]);
+
+        let prose = "I think you should review PRs every morning.";
+        let err = parse_generated_recipe(prose).unwrap_err();
+        assert_eq!(err.raw, prose);
+    }
+}
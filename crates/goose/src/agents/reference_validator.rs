@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use rmcp::model::{Content, JsonObject};
+
+use crate::mcp_utils::ToolResult;
+
+/// Flags tool arguments that reference identifiers the agent has never seen, such as a
+/// job id the model made up instead of taking from an earlier `list` result
+pub struct ReferenceValidator {
+    keys: HashSet<String>,
+    known: Mutex<HashSet<String>>,
+}
+
+impl Default for ReferenceValidator {
+    /// Checks `job_id` and `session_id` arguments
+    fn default() -> Self {
+        Self::new(["job_id", "session_id"])
+    }
+}
+
+impl ReferenceValidator {
+    /// Check the string arguments named in `keys`
+    pub fn new<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            known: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Treat `ids` as seen, e.g. ids shown to the user outside of tool results
+    pub fn add_known<I, S>(&self, ids: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.known
+            .lock()
+            .unwrap()
+            .extend(ids.into_iter().map(Into::into));
+    }
+
+    /// Remember every identifier-like word in a tool's text output
+    pub fn learn_from(&self, content: &[Content]) {
+        let mut known = self.known.lock().unwrap();
+        for text in content.iter().filter_map(|c| c.as_text()) {
+            known.extend(
+                text.text
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    /// Checked arguments whose value has not been seen, as `(argument, value)` pairs
+    pub fn unknown_references(&self, arguments: &JsonObject) -> Vec<(String, String)> {
+        let known = self.known.lock().unwrap();
+        let mut unknown: Vec<(String, String)> = arguments
+            .iter()
+            .filter(|(key, _)| self.keys.contains(key.as_str()))
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .filter(|(_, value)| !known.contains(value))
+            .collect();
+        unknown.sort();
+        unknown
+    }
+
+    /// Learn from a successful tool result and append a note about `unknown` references
+    pub fn annotate(
+        &self,
+        unknown: &[(String, String)],
+        output: ToolResult<Vec<Content>>,
+    ) -> ToolResult<Vec<Content>> {
+        output.map(|mut content| {
+            self.learn_from(&content);
+            if !unknown.is_empty() {
+                content.push(Content::text(hallucination_note(unknown)));
+            }
+            content
+        })
+    }
+}
+
+/// Note appended to a tool result whose arguments referenced unseen identifiers
+fn hallucination_note(unknown: &[(String, String)]) -> String {
+    let references = unknown
+        .iter()
+        .map(|(key, value)| format!("{} '{}'", key, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "Note: {} did not appear in any earlier result and may not exist. \
+         Look up the correct value before relying on this result.",
+        references
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::object;
+
+    #[test]
+    fn test_flags_only_unseen_checked_arguments() {
+        let validator = ReferenceValidator::default();
+        validator.learn_from(&[Content::text("Jobs:\n- daily_report (0 9 * * *)")]);
+
+        let arguments = object!({
+            "job_id": "weekly_digest",
+            "session_id": "daily_report",
+            "action": "run_now"
+        });
+        assert_eq!(
+            validator.unknown_references(&arguments),
+            vec![("job_id".to_string(), "weekly_digest".to_string())]
+        );
+
+        validator.add_known(["weekly_digest"]);
+        assert!(validator.unknown_references(&arguments).is_empty());
+    }
+}
@@ -1,15 +1,20 @@
 use anyhow::Result;
+use std::collections::{BTreeSet, HashSet};
 use std::sync::Arc;
 
 use async_stream::try_stream;
 use futures::stream::StreamExt;
 use serde_json::{json, Value};
-use tracing::debug;
+use tracing::{debug, warn};
 
 use super::super::agents::Agent;
+use crate::agents::types::{ToolGate, ToolOfferingDecision, ToolSetDiff};
 use crate::conversation::message::{Message, MessageContent, ToolRequest};
-use crate::conversation::Conversation;
-use crate::providers::base::{stream_from_single_message, MessageStream, Provider, ProviderUsage};
+use crate::conversation::{remove_orphaned_tool_responses, Conversation};
+use crate::model::PromptCacheHints;
+use crate::providers::base::{
+    stream_from_single_message, MessageStream, Provider, ProviderUsage, SystemPromptPlacement,
+};
 use crate::providers::errors::ProviderError;
 use crate::providers::toolshim::{
     augment_message_with_tool_calls, convert_tool_messages_to_text,
@@ -20,7 +25,7 @@ use crate::agents::recipe_tools::dynamic_task_tools::should_enabled_subagents;
 use crate::session::SessionManager;
 #[cfg(test)]
 use crate::session::SessionType;
-use rmcp::model::Tool;
+use rmcp::model::{Role, Tool};
 
 fn coerce_value(s: &str, schema: &Value) -> Value {
     let type_str = schema.get("type");
@@ -111,71 +116,214 @@ async fn toolshim_postprocess(
 impl Agent {
     pub async fn prepare_tools_and_prompt(
         &self,
+        session_id: &str,
         working_dir: &std::path::Path,
     ) -> Result<(Vec<Tool>, Vec<Tool>, String)> {
-        // Get router enabled status
-        let router_enabled = self.tool_route_manager.is_router_enabled().await;
+        let (mut tools, router_enabled) = self.routed_tools(session_id).await?;
+        if let Some(cap) = self.settings.lock().await.max_offered_tools {
+            tools.truncate(cap);
+        }
 
-        // Get tools from extension manager
-        let mut tools = self.list_tools_for_router().await;
+        // Get model name from provider
+        let provider = self.provider().await?;
+        let model_config = provider.get_model_config();
+        let mut system_prompt = self
+            .build_system_prompt(
+                &model_config.model_name,
+                router_enabled,
+                Some((session_id, working_dir)),
+            )
+            .await;
 
-        // If router is disabled and no tools were returned, fall back to regular tools
-        if !router_enabled && tools.is_empty() {
-            tools = self.list_tools(None).await;
-            let provider = self.provider().await?;
-            let model_name = provider.get_model_config().model_name;
-
-            if !should_enabled_subagents(&model_name) {
-                tools.retain(|tool| {
-                    tool.name != crate::agents::subagent_execution_tool::subagent_execute_task_tool::SUBAGENT_EXECUTE_TASK_TOOL_NAME
-                        && tool.name != crate::agents::recipe_tools::dynamic_task_tools::DYNAMIC_TASK_TOOL_NAME_PREFIX
-                });
-            }
+        // Handle toolshim if enabled
+        let mut toolshim_tools = vec![];
+        if model_config.toolshim {
+            // If tool interpretation is enabled, modify the system prompt
+            system_prompt = modify_system_prompt_for_tool_json(&system_prompt, &tools);
+            // Make a copy of tools before emptying
+            toolshim_tools = tools.clone();
+            // Empty the tools vector for provider completion
+            tools = vec![];
         }
 
-        // Add frontend tools
-        let frontend_tools = self.frontend_tools.lock().await;
-        for frontend_tool in frontend_tools.values() {
-            tools.push(frontend_tool.tool.clone());
+        let current_names: BTreeSet<String> = tools
+            .iter()
+            .chain(toolshim_tools.iter())
+            .map(|tool| tool.name.to_string())
+            .collect();
+        let mut last_tool_names = self.last_tool_names.lock().await;
+        if let Some(previous) = last_tool_names.get(session_id) {
+            self.tool_set_diffs.lock().await.insert(
+                session_id.to_string(),
+                ToolSetDiff::between(previous, &current_names),
+            );
         }
+        last_tool_names.insert(session_id.to_string(), current_names);
+        drop(last_tool_names);
 
-        if !router_enabled {
-            // Stable tool ordering is important for multi session prompt caching.
-            tools.sort_by(|a, b| a.name.cmp(&b.name));
-        }
+        Ok((tools, toolshim_tools, system_prompt))
+    }
 
-        // Prepare system prompt
-        let extensions_info = self.extension_manager.get_extensions_info().await;
+    /// The system prompt for `model_name`, with session details and working directory hints
+    /// when `session` is given
+    pub(crate) async fn build_system_prompt(
+        &self,
+        model_name: &str,
+        router_enabled: bool,
+        session: Option<(&str, &std::path::Path)>,
+    ) -> String {
+        let mut extensions_info = self.extension_manager.get_extensions_info().await;
+        let mut fragments = self
+            .extension_manager
+            .collect_system_prompt_fragments()
+            .await;
+        for info in &mut extensions_info {
+            if let Some(fragment) = fragments.remove(&info.name) {
+                if !info.instructions.is_empty() {
+                    info.instructions.push_str("\n\n");
+                }
+                info.instructions.push_str(&fragment);
+            }
+        }
         let (extension_count, tool_count) =
             self.extension_manager.get_extension_and_tool_counts().await;
 
-        // Get model name from provider
-        let provider = self.provider().await?;
-        let model_config = provider.get_model_config();
-        let model_name = &model_config.model_name;
-
         let prompt_manager = self.prompt_manager.lock().await;
-        let mut system_prompt = prompt_manager
+        let mut builder = prompt_manager
             .builder(model_name)
             .with_extensions(extensions_info.into_iter())
             .with_frontend_instructions(self.frontend_instructions.lock().await.clone())
             .with_extension_and_tool_counts(extension_count, tool_count)
             .with_router_enabled(router_enabled)
-            .with_hints(working_dir)
-            .build();
+            .with_goose_mode(*self.goose_mode_override.lock().await);
+        if let Some((session_id, working_dir)) = session {
+            builder = builder.with_session_id(session_id).with_hints(working_dir);
+        }
+        builder.build()
+    }
 
-        // Handle toolshim if enabled
-        let mut toolshim_tools = vec![];
-        if model_config.toolshim {
-            // If tool interpretation is enabled, modify the system prompt
-            system_prompt = modify_system_prompt_for_tool_json(&system_prompt, &tools);
-            // Make a copy of tools before emptying
-            toolshim_tools = tools.clone();
-            // Empty the tools vector for provider completion
-            tools = vec![];
+    /// Tools the router offers for this session, before the tool cap and gates apply,
+    /// and whether the router is enabled this turn
+    async fn routed_tools(&self, session_id: &str) -> Result<(Vec<Tool>, bool)> {
+        // Get router enabled status; a bypassed router behaves as disabled for this turn
+        let router_bypassed = self.tool_route_manager.is_router_bypassed().await;
+        let mut router_enabled =
+            !router_bypassed && self.tool_route_manager.is_router_enabled().await;
+        // Without a working selector the model would only see the search tool, so offer
+        // every tool as if the router were off
+        if router_enabled && !self.tool_route_manager.is_router_functional().await {
+            warn!("Tool router is enabled but has no selector; offering all tools");
+            router_enabled = false;
         }
 
-        Ok((tools, toolshim_tools, system_prompt))
+        // Get tools from extension manager
+        let mut tools = if router_bypassed {
+            vec![]
+        } else {
+            self.list_tools_for_router(session_id).await
+        };
+
+        // If router is disabled and no tools were returned, fall back to regular tools
+        if !router_enabled && tools.is_empty() {
+            return Ok((self.unrouted_tools().await?, router_enabled));
+        }
+
+        self.add_frontend_tools(&mut tools).await;
+        if !router_enabled {
+            // Stable tool ordering is important for multi session prompt caching.
+            tools.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        Ok((tools, router_enabled))
+    }
+
+    /// The tools offered to the model when the router is off: extension, platform, sub-recipe,
+    /// final output and frontend tools, in a stable order
+    pub(crate) async fn unrouted_tools(&self) -> Result<Vec<Tool>> {
+        let mut tools = self.list_tools(None).await;
+        let provider = self.provider().await?;
+        let model_name = provider.get_model_config().model_name;
+
+        if !should_enabled_subagents(&model_name) {
+            tools.retain(|tool| {
+                tool.name != crate::agents::subagent_execution_tool::subagent_execute_task_tool::SUBAGENT_EXECUTE_TASK_TOOL_NAME
+                    && tool.name != crate::agents::recipe_tools::dynamic_task_tools::DYNAMIC_TASK_TOOL_NAME_PREFIX
+            });
+        }
+
+        self.add_frontend_tools(&mut tools).await;
+        // Stable tool ordering is important for multi session prompt caching.
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tools)
+    }
+
+    /// Add the frontend tools, then drop whatever the tool policy does not permit
+    async fn add_frontend_tools(&self, tools: &mut Vec<Tool>) {
+        let frontend_tools = self.frontend_tools.lock().await;
+        for frontend_tool in frontend_tools.values() {
+            tools.push(frontend_tool.tool.clone());
+        }
+        let policy = self.extension_manager.tool_policy().await;
+        tools.retain(|tool| policy.permits(&tool.name));
+    }
+
+    /// Explain whether `tool_name` is offered to the model on the session's next turn,
+    /// checking the router, the tool cap and tool gates in the order the reply applies them
+    pub async fn explain_tool_offering(
+        &self,
+        tool_name: &str,
+        session_id: &str,
+    ) -> Result<ToolOfferingDecision> {
+        let (tools, router_enabled) = self.routed_tools(session_id).await?;
+        let Some(position) = tools.iter().position(|tool| tool.name == tool_name) else {
+            let available = self.dispatchable_tool_names().await;
+            return Ok(
+                if router_enabled && available.iter().any(|n| n == tool_name) {
+                    ToolOfferingDecision::FilteredByRouter
+                } else {
+                    ToolOfferingDecision::Disabled
+                },
+            );
+        };
+
+        if let Some(cap) = self.settings.lock().await.max_offered_tools {
+            if position >= cap {
+                return Ok(ToolOfferingDecision::OverCap { cap });
+            }
+        }
+
+        let conversation = SessionManager::get_session(session_id, true)
+            .await?
+            .conversation
+            .unwrap_or_default();
+        let tool = &tools[position..=position];
+        let unmet_gate = self
+            .settings
+            .lock()
+            .await
+            .tool_gates
+            .iter()
+            .filter(|gate| gate.tool == tool_name)
+            .find(|gate| {
+                filter_gated_tools(tool, std::slice::from_ref(*gate), &conversation).is_empty()
+            })
+            .cloned();
+        if let Some(gate) = unmet_gate {
+            return Ok(ToolOfferingDecision::Gated {
+                requires: gate.requires,
+            });
+        }
+
+        Ok(ToolOfferingDecision::Offered)
+    }
+
+    /// Drop gated tools whose prerequisite has not yet succeeded in the conversation
+    pub(crate) async fn apply_tool_gates(
+        &self,
+        tools: &[Tool],
+        conversation: &Conversation,
+    ) -> Vec<Tool> {
+        filter_gated_tools(tools, &self.settings.lock().await.tool_gates, conversation)
     }
 
     /// Stream a response from the LLM provider.
@@ -186,18 +334,46 @@ impl Agent {
         messages: &[Message],
         tools: &[Tool],
         toolshim_tools: &[Tool],
+        thinking_budget: Option<u32>,
+        prompt_cache: Option<PromptCacheHints>,
     ) -> Result<MessageStream, ProviderError> {
         let config = provider.get_model_config();
+        let budget = thinking_budget.filter(|_| provider.supports_thinking_budget());
+        let cache = prompt_cache.filter(|_| provider.supports_prompt_cache_hints());
+        // Budgets and cache hints travel in a per-request copy of the model config
+        let request_config = config
+            .clone()
+            .with_thinking_budget(budget.or(config.thinking_budget))
+            .with_prompt_cache(cache.or(config.prompt_cache));
+
+        // Providers reject tool responses without a matching request, so drop them here
+        let (messages, removed) = remove_orphaned_tool_responses(messages.to_vec());
+        for issue in &removed {
+            warn!("Sanitized history before provider call: {}", issue);
+        }
 
         // Convert tool messages to text if toolshim is enabled
         let messages_for_provider = if config.toolshim {
-            convert_tool_messages_to_text(messages)
+            convert_tool_messages_to_text(&messages)
         } else {
-            Conversation::new_unvalidated(messages.to_vec())
+            Conversation::new_unvalidated(messages)
+        };
+
+        let (system_prompt, messages_for_provider) = match provider.system_prompt_placement() {
+            SystemPromptPlacement::Separate => (system_prompt.to_owned(), messages_for_provider),
+            SystemPromptPlacement::FirstMessage => {
+                let mut messages = messages_for_provider.messages().clone();
+                match messages.first_mut() {
+                    Some(first) if first.role == Role::User => {
+                        first.content.insert(0, MessageContent::text(system_prompt));
+                    }
+                    _ => messages.insert(0, Message::user().with_text(system_prompt)),
+                }
+                (String::new(), Conversation::new_unvalidated(messages))
+            }
         };
 
         // Clone owned data to move into the async stream
-        let system_prompt = system_prompt.to_owned();
         let tools = tools.to_owned();
         let toolshim_tools = toolshim_tools.to_owned();
         let provider = provider.clone();
@@ -207,7 +383,8 @@ impl Agent {
         let stream_result = if provider.supports_streaming() {
             debug!("WAITING_LLM_STREAM_START");
             let result = provider
-                .stream(
+                .stream_with_model(
+                    &request_config,
                     system_prompt.as_str(),
                     messages_for_provider.messages(),
                     &tools,
@@ -218,7 +395,8 @@ impl Agent {
         } else {
             debug!("WAITING_LLM_START");
             let complete_result = provider
-                .complete(
+                .complete_with_model(
+                    &request_config,
                     system_prompt.as_str(),
                     messages_for_provider.messages(),
                     &tools,
@@ -350,21 +528,6 @@ impl Agent {
         is_compaction_usage: bool,
     ) -> Result<()> {
         let session_id = session_config.id.as_str();
-        let session = SessionManager::get_session(session_id, false).await?;
-
-        let accumulate = |a: Option<i32>, b: Option<i32>| -> Option<i32> {
-            match (a, b) {
-                (Some(x), Some(y)) => Some(x + y),
-                _ => a.or(b),
-            }
-        };
-
-        let accumulated_total =
-            accumulate(session.accumulated_total_tokens, usage.usage.total_tokens);
-        let accumulated_input =
-            accumulate(session.accumulated_input_tokens, usage.usage.input_tokens);
-        let accumulated_output =
-            accumulate(session.accumulated_output_tokens, usage.usage.output_tokens);
 
         let (current_total, current_input, current_output) = if is_compaction_usage {
             // After compaction: summary output becomes new input context
@@ -383,9 +546,11 @@ impl Agent {
             .total_tokens(current_total)
             .input_tokens(current_input)
             .output_tokens(current_output)
-            .accumulated_total_tokens(accumulated_total)
-            .accumulated_input_tokens(accumulated_input)
-            .accumulated_output_tokens(accumulated_output)
+            .record_usage(
+                usage.usage.total_tokens,
+                usage.usage.input_tokens,
+                usage.usage.output_tokens,
+            )
             .apply()
             .await?;
 
@@ -393,6 +558,45 @@ impl Agent {
     }
 }
 
+fn filter_gated_tools(
+    tools: &[Tool],
+    gates: &[ToolGate],
+    conversation: &Conversation,
+) -> Vec<Tool> {
+    if gates.is_empty() {
+        return tools.to_vec();
+    }
+
+    let succeeded_ids: HashSet<&str> = conversation
+        .messages()
+        .iter()
+        .flat_map(|m| m.content.iter())
+        .filter_map(|c| c.as_tool_response())
+        .filter(|response| response.tool_result.is_ok())
+        .map(|response| response.id.as_str())
+        .collect();
+    let succeeded_tools: HashSet<&str> = conversation
+        .messages()
+        .iter()
+        .flat_map(|m| m.content.iter())
+        .filter_map(|c| c.as_tool_request())
+        .filter(|request| succeeded_ids.contains(request.id.as_str()))
+        .filter_map(|request| request.tool_call.as_ref().ok())
+        .map(|call| &*call.name)
+        .collect();
+
+    tools
+        .iter()
+        .filter(|tool| {
+            gates
+                .iter()
+                .filter(|gate| gate.tool == tool.name)
+                .all(|gate| succeeded_tools.contains(gate.requires.as_str()))
+        })
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,13 +681,15 @@ mod tests {
                 instructions: None,
                 bundled: None,
                 available_tools: vec![],
+                required: false,
             })
             .await
             .unwrap();
 
         let working_dir = std::env::current_dir()?;
-        let (tools, _toolshim_tools, _system_prompt) =
-            agent.prepare_tools_and_prompt(&working_dir).await?;
+        let (tools, _toolshim_tools, _system_prompt) = agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
 
         // Ensure both platform and frontend tools are present
         let names: Vec<String> = tools.iter().map(|t| t.name.clone().into_owned()).collect();
@@ -498,4 +704,649 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn prepare_tools_offers_all_tools_when_router_bypassed() -> anyhow::Result<()> {
+        // GOOSE_ENABLE_ROUTER turns on LLM routing, the only strategy this tree has; there is
+        // no vector strategy to configure
+        let original = std::env::var("GOOSE_ENABLE_ROUTER").ok();
+        std::env::set_var("GOOSE_ENABLE_ROUTER", "true");
+
+        let result = async {
+            let agent = crate::agents::Agent::new();
+
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "test-router-bypass".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+
+            let model_config = ModelConfig::new("test-model").unwrap();
+            let provider = std::sync::Arc::new(MockProvider { model_config });
+            agent.update_provider(provider, &session.id).await?;
+
+            let working_dir = std::env::current_dir()?;
+            let (routed, _, _) = agent
+                .prepare_tools_and_prompt(&session.id, &working_dir)
+                .await?;
+            assert!(routed
+                .iter()
+                .any(|t| t.name == crate::agents::router_tools::ROUTER_LLM_SEARCH_TOOL_NAME));
+
+            agent.set_router_bypass(true).await;
+            let (tools, _, _) = agent
+                .prepare_tools_and_prompt(&session.id, &working_dir)
+                .await?;
+            let names: Vec<String> = tools.iter().map(|t| t.name.clone().into_owned()).collect();
+
+            assert!(names
+                .iter()
+                .any(|n| n == crate::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME));
+            assert!(!names
+                .iter()
+                .any(|n| n == crate::agents::router_tools::ROUTER_LLM_SEARCH_TOOL_NAME));
+            assert_eq!(
+                agent.list_tools_for_router(&session.id).await.len(),
+                agent.list_tools(None).await.len()
+            );
+            Ok(())
+        }
+        .await;
+
+        match original {
+            Some(val) => std::env::set_var("GOOSE_ENABLE_ROUTER", val),
+            None => std::env::remove_var("GOOSE_ENABLE_ROUTER"),
+        }
+        result
+    }
+
+    #[tokio::test]
+    async fn prepare_tools_reports_tools_added_since_previous_refresh() -> anyhow::Result<()> {
+        let agent = crate::agents::Agent::new();
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "test-tool-set-diff".to_string(),
+            SessionType::Hidden,
+        )
+        .await?;
+        let model_config = ModelConfig::new("test-model").unwrap();
+        let provider = std::sync::Arc::new(MockProvider { model_config });
+        agent.update_provider(provider, &session.id).await?;
+        agent.disable_router_for_recipe().await;
+
+        let working_dir = std::env::current_dir()?;
+        agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
+        assert!(agent.tool_set_diff(&session.id).await.is_empty());
+
+        agent
+            .add_extension(crate::agents::extension::ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+        agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
+
+        let diff = agent.tool_set_diff(&session.id).await;
+        assert_eq!(diff.added, vec!["todo__todo_write".to_string()]);
+        assert!(diff.removed.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tool_gate_hides_tool_until_prerequisite_succeeds() {
+        use rmcp::model::{CallToolRequestParam, Content, ErrorCode, ErrorData};
+
+        let agent = crate::agents::Agent::new();
+        agent
+            .add_tool_gate(ToolGate {
+                tool: "deploy__release".to_string(),
+                requires: "build__run".to_string(),
+            })
+            .await;
+
+        let tools = vec![
+            Tool::new(
+                "build__run".to_string(),
+                "Build".to_string(),
+                object!({"type": "object"}),
+            ),
+            Tool::new(
+                "deploy__release".to_string(),
+                "Release".to_string(),
+                object!({"type": "object"}),
+            ),
+        ];
+        let offered_names = |offered: Vec<Tool>| -> Vec<String> {
+            offered.into_iter().map(|t| t.name.to_string()).collect()
+        };
+        let build_call = || {
+            Ok(CallToolRequestParam {
+                name: "build__run".into(),
+                arguments: None,
+            })
+        };
+
+        let empty = Conversation::new_unvalidated(vec![]);
+        assert_eq!(
+            offered_names(agent.apply_tool_gates(&tools, &empty).await),
+            vec!["build__run"]
+        );
+
+        let failed = Conversation::new_unvalidated(vec![
+            Message::assistant().with_tool_request("call_1", build_call()),
+            Message::user().with_tool_response(
+                "call_1",
+                Err(ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    "build failed",
+                    None,
+                )),
+            ),
+        ]);
+        assert_eq!(
+            offered_names(agent.apply_tool_gates(&tools, &failed).await),
+            vec!["build__run"]
+        );
+
+        let succeeded = Conversation::new_unvalidated(vec![
+            Message::assistant().with_tool_request("call_2", build_call()),
+            Message::user().with_tool_response("call_2", Ok(vec![Content::text("built")])),
+        ]);
+        assert_eq!(
+            offered_names(agent.apply_tool_gates(&tools, &succeeded).await),
+            vec!["build__run", "deploy__release"]
+        );
+    }
+
+    #[tokio::test]
+    async fn explain_tool_offering_cites_cap() -> anyhow::Result<()> {
+        let agent = crate::agents::Agent::new();
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "test-explain-offering".to_string(),
+            SessionType::Hidden,
+        )
+        .await?;
+        let model_config = ModelConfig::new("test-model").unwrap();
+        let provider = std::sync::Arc::new(MockProvider { model_config });
+        agent.update_provider(provider, &session.id).await?;
+        agent.disable_router_for_recipe().await;
+
+        let working_dir = std::env::current_dir()?;
+        let (tools, _, _) = agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
+        let last = tools.last().unwrap().name.to_string();
+        assert!(agent
+            .explain_tool_offering(&last, &session.id)
+            .await?
+            .is_offered());
+
+        agent.set_max_offered_tools(Some(tools.len() - 1)).await;
+        let decision = agent.explain_tool_offering(&last, &session.id).await?;
+        assert_eq!(
+            decision,
+            ToolOfferingDecision::OverCap {
+                cap: tools.len() - 1
+            }
+        );
+        assert!(decision.reason().unwrap().contains("cap"));
+
+        let (capped, _, _) = agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
+        assert!(!capped.iter().any(|t| t.name == last));
+        assert_eq!(
+            agent
+                .explain_tool_offering("missing__tool", &session.id)
+                .await?,
+            ToolOfferingDecision::Disabled
+        );
+        Ok(())
+    }
+
+    #[derive(Clone)]
+    struct RecordingProvider {
+        placement: SystemPromptPlacement,
+        received: Arc<std::sync::Mutex<Option<(String, Vec<Message>)>>>,
+    }
+
+    #[async_trait]
+    impl Provider for RecordingProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "recording"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model").unwrap()
+        }
+
+        fn system_prompt_placement(&self) -> SystemPromptPlacement {
+            self.placement
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            *self.received.lock().unwrap() = Some((system.to_string(), messages.to_vec()));
+            Ok((
+                Message::assistant().with_text("ok"),
+                ProviderUsage::new("recording".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn system_prompt_is_placed_where_each_provider_expects_it() -> anyhow::Result<()> {
+        let messages = vec![Message::user().with_text("hello")];
+
+        for placement in [
+            SystemPromptPlacement::Separate,
+            SystemPromptPlacement::FirstMessage,
+        ] {
+            let received = Arc::new(std::sync::Mutex::new(None));
+            let provider = Arc::new(RecordingProvider {
+                placement,
+                received: received.clone(),
+            });
+
+            crate::agents::Agent::stream_response_from_provider(
+                provider,
+                "be helpful",
+                &messages,
+                &[],
+                &[],
+                None,
+                None,
+            )
+            .await?;
+
+            let (system, sent) = received.lock().unwrap().take().unwrap();
+            let texts: Vec<String> = sent.iter().map(|m| m.as_concat_text()).collect();
+            match placement {
+                SystemPromptPlacement::Separate => {
+                    assert_eq!(system, "be helpful");
+                    assert_eq!(texts, vec!["hello"]);
+                }
+                SystemPromptPlacement::FirstMessage => {
+                    assert!(system.is_empty());
+                    assert_eq!(texts, vec!["be helpful\nhello"]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn orphaned_tool_responses_are_dropped_before_the_provider_call() -> anyhow::Result<()> {
+        let messages = vec![
+            Message::user().with_tool_response("trimmed_call", Ok(vec![])),
+            Message::user().with_text("what next?"),
+            Message::assistant().with_tool_request(
+                "kept_call",
+                Ok(rmcp::model::CallToolRequestParam {
+                    name: "todo__todo_read".into(),
+                    arguments: None,
+                }),
+            ),
+            Message::user().with_tool_response("kept_call", Ok(vec![])),
+        ];
+        let received = Arc::new(std::sync::Mutex::new(None));
+        let provider = Arc::new(RecordingProvider {
+            placement: SystemPromptPlacement::Separate,
+            received: received.clone(),
+        });
+
+        crate::agents::Agent::stream_response_from_provider(
+            provider,
+            "",
+            &messages,
+            &[],
+            &[],
+            None,
+            None,
+        )
+        .await?;
+
+        let (_, sent) = received.lock().unwrap().take().unwrap();
+        let response_ids: Vec<String> = sent
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|c| match c {
+                MessageContent::ToolResponse(resp) => Some(resp.id.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(response_ids, vec!["kept_call"]);
+        assert_eq!(sent.len(), 3);
+        Ok(())
+    }
+
+    /// Records the thinking budget of the model config each request is made with
+    struct ReasoningProvider {
+        supports_budget: bool,
+        streaming: bool,
+        seen_budgets: Arc<std::sync::Mutex<Vec<Option<u32>>>>,
+    }
+
+    #[async_trait]
+    impl Provider for ReasoningProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "reasoning"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model").unwrap()
+        }
+
+        fn supports_thinking_budget(&self) -> bool {
+            self.supports_budget
+        }
+
+        fn supports_streaming(&self) -> bool {
+            self.streaming
+        }
+
+        async fn complete_with_model(
+            &self,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            assert!(
+                !self.streaming,
+                "a thinking budget must not force a blocking request"
+            );
+            self.seen_budgets
+                .lock()
+                .unwrap()
+                .push(model_config.thinking_budget);
+            Ok((
+                Message::assistant().with_text("ok"),
+                ProviderUsage::new("reasoning".to_string(), Usage::default()),
+            ))
+        }
+
+        async fn stream_with_model(
+            &self,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<MessageStream, ProviderError> {
+            self.seen_budgets
+                .lock()
+                .unwrap()
+                .push(model_config.thinking_budget);
+            Ok(stream_from_single_message(
+                Message::assistant().with_text("ok"),
+                ProviderUsage::new("reasoning".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn thinking_budget_reaches_providers_that_support_it() -> anyhow::Result<()> {
+        let messages = vec![Message::user().with_text("think hard")];
+        for (supports_budget, streaming, expected) in [
+            (true, false, Some(4096)),
+            (true, true, Some(4096)),
+            (false, false, None),
+        ] {
+            let seen_budgets = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let provider = Arc::new(ReasoningProvider {
+                supports_budget,
+                streaming,
+                seen_budgets: seen_budgets.clone(),
+            });
+
+            crate::agents::Agent::stream_response_from_provider(
+                provider,
+                "",
+                &messages,
+                &[],
+                &[],
+                Some(4096),
+                None,
+            )
+            .await?;
+
+            assert_eq!(*seen_budgets.lock().unwrap(), vec![expected]);
+        }
+        Ok(())
+    }
+
+    /// Streams, supports prompt cache hints and records the ones each request is made with
+    struct CachingProvider {
+        seen_hints: Arc<std::sync::Mutex<Vec<Option<PromptCacheHints>>>>,
+    }
+
+    #[async_trait]
+    impl Provider for CachingProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "caching"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("test-model").unwrap()
+        }
+
+        fn supports_prompt_cache_hints(&self) -> bool {
+            true
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<(Message, ProviderUsage), ProviderError> {
+            Err(ProviderError::ExecutionError(
+                "cache hints must not force a blocking request".to_string(),
+            ))
+        }
+
+        async fn stream_with_model(
+            &self,
+            model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> anyhow::Result<MessageStream, ProviderError> {
+            self.seen_hints
+                .lock()
+                .unwrap()
+                .push(model_config.prompt_cache);
+            Ok(stream_from_single_message(
+                Message::assistant().with_text("ok"),
+                ProviderUsage::new("caching".to_string(), Usage::default()),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn prompt_cache_hints_mark_the_system_prompt() -> anyhow::Result<()> {
+        let seen_hints = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let provider = Arc::new(CachingProvider {
+            seen_hints: seen_hints.clone(),
+        });
+        let hints = PromptCacheHints {
+            system_prompt: true,
+            leading_messages: 1,
+        };
+
+        crate::agents::Agent::stream_response_from_provider(
+            provider,
+            "be helpful",
+            &[Message::user().with_text("hello")],
+            &[],
+            &[],
+            None,
+            Some(hints),
+        )
+        .await?;
+
+        let seen = seen_hints.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].is_some_and(|hints| hints.system_prompt));
+        Ok(())
+    }
+
+    /// Extension that contributes a fragment through the system prompt fragment prompt
+    struct FragmentClient {
+        info: rmcp::model::InitializeResult,
+    }
+
+    #[async_trait]
+    impl crate::agents::mcp_client::McpClientTrait for FragmentClient {
+        async fn list_resources(
+            &self,
+            _next_cursor: Option<String>,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::ListResourcesResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+
+        async fn read_resource(
+            &self,
+            _uri: &str,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::ReadResourceResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+
+        async fn list_tools(
+            &self,
+            _next_cursor: Option<String>,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::ListToolsResult, rmcp::ServiceError> {
+            Ok(rmcp::model::ListToolsResult {
+                tools: vec![],
+                next_cursor: None,
+            })
+        }
+
+        async fn call_tool(
+            &self,
+            _name: &str,
+            _arguments: Option<rmcp::model::JsonObject>,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::CallToolResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+
+        async fn list_prompts(
+            &self,
+            _next_cursor: Option<String>,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::ListPromptsResult, rmcp::ServiceError> {
+            Err(rmcp::ServiceError::TransportClosed)
+        }
+
+        async fn get_prompt(
+            &self,
+            name: &str,
+            _arguments: Value,
+            _cancel_token: tokio_util::sync::CancellationToken,
+        ) -> Result<rmcp::model::GetPromptResult, rmcp::ServiceError> {
+            assert_eq!(
+                name,
+                crate::agents::mcp_client::SYSTEM_PROMPT_FRAGMENT_PROMPT
+            );
+            Ok(rmcp::model::GetPromptResult {
+                description: None,
+                messages: vec![rmcp::model::PromptMessage::new_text(
+                    rmcp::model::PromptMessageRole::User,
+                    "The deploy freeze is active until Friday.",
+                )],
+            })
+        }
+
+        async fn subscribe(&self) -> tokio::sync::mpsc::Receiver<rmcp::model::ServerNotification> {
+            tokio::sync::mpsc::channel(1).1
+        }
+
+        fn get_info(&self) -> Option<&rmcp::model::InitializeResult> {
+            Some(&self.info)
+        }
+    }
+
+    #[tokio::test]
+    async fn prepare_tools_merges_extension_prompt_fragments() -> anyhow::Result<()> {
+        let agent = crate::agents::Agent::new();
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "test-prompt-fragments".to_string(),
+            SessionType::Hidden,
+        )
+        .await?;
+        let model_config = ModelConfig::new("test-model").unwrap();
+        let provider = std::sync::Arc::new(MockProvider { model_config });
+        agent.update_provider(provider, &session.id).await?;
+
+        let info = rmcp::model::InitializeResult {
+            capabilities: rmcp::model::ServerCapabilities::builder()
+                .enable_prompts()
+                .build(),
+            ..Default::default()
+        };
+        agent
+            .extension_manager
+            .add_client(
+                "deploys".to_string(),
+                crate::agents::extension::ExtensionConfig::Builtin {
+                    name: "deploys".to_string(),
+                    display_name: None,
+                    description: "deploy status".to_string(),
+                    timeout: None,
+                    bundled: None,
+                    available_tools: vec![],
+                    required: false,
+                },
+                Arc::new(tokio::sync::Mutex::new(Box::new(FragmentClient {
+                    info: info.clone(),
+                }))),
+                Some(info),
+                None,
+            )
+            .await;
+
+        let working_dir = std::env::current_dir()?;
+        let (_, _, system_prompt) = agent
+            .prepare_tools_and_prompt(&session.id, &working_dir)
+            .await?;
+        assert!(system_prompt.contains("The deploy freeze is active until Friday."));
+        Ok(())
+    }
 }
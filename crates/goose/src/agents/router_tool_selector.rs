@@ -9,6 +9,7 @@ use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tracing::warn;
 
 use crate::conversation::message::Message;
 use crate::prompt_template::render_global_file;
@@ -27,12 +28,120 @@ pub trait RouterToolSelector: Send + Sync {
     async fn remove_tool(&self, tool_name: &str) -> Result<(), ErrorData>;
     async fn record_tool_call(&self, tool_name: &str) -> Result<(), ErrorData>;
     async fn get_recent_tool_calls(&self, limit: usize) -> Result<Vec<String>, ErrorData>;
+
+    /// Record a call made in `session_id`. Selectors that don't track sessions share one history.
+    async fn record_session_tool_call(
+        &self,
+        _session_id: &str,
+        tool_name: &str,
+    ) -> Result<(), ErrorData> {
+        self.record_tool_call(tool_name).await
+    }
+
+    /// Most recent calls made in `session_id`, newest first
+    async fn get_session_recent_tool_calls(
+        &self,
+        _session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, ErrorData> {
+        self.get_recent_tool_calls(limit).await
+    }
+
+    /// Index several extensions' tools, given as extension name and tools. Selectors that can
+    /// index them in one request, e.g. to a remote embedding service, should override this.
+    /// Returns the extensions that failed to index with their errors.
+    async fn index_tools_batch(&self, batch: &[(String, Vec<Tool>)]) -> Vec<(String, ErrorData)> {
+        let mut failures = Vec::new();
+        for (extension_name, tools) in batch {
+            if let Err(e) = self.index_tools(tools, extension_name).await {
+                failures.push((extension_name.clone(), e));
+            }
+        }
+        failures
+    }
+
+    /// Like `select_tools`, but when the search fails, e.g. while the provider behind it is
+    /// unavailable, the tools in `fallback` are listed instead, limited to the requested
+    /// extension if there is one. Invalid parameters are still reported.
+    async fn select_tools_or_fallback(
+        &self,
+        params: JsonObject,
+        fallback: &[Tool],
+    ) -> Result<Vec<Content>, ErrorData> {
+        let extension_name = params
+            .get("extension_name")
+            .and_then(|v| v.as_str())
+            .map(|name| format!("{}__", name));
+        match self.select_tools(params).await {
+            Err(e) if e.code != ErrorCode::INVALID_PARAMS => {
+                warn!(
+                    "Tool search failed, listing all tools instead: {}",
+                    e.message
+                );
+                Ok(fallback
+                    .iter()
+                    .filter(|tool| {
+                        extension_name
+                            .as_ref()
+                            .is_none_or(|prefix| tool.name.starts_with(prefix.as_str()))
+                    })
+                    .map(|tool| Content::text(describe_tool(tool)))
+                    .collect())
+            }
+            result => result,
+        }
+    }
+}
+
+/// A tool as the selectors list it: its name, description and input schema
+fn describe_tool(tool: &Tool) -> String {
+    format!(
+        "Tool: {}\nDescription: {}\nSchema: {}",
+        tool.name,
+        tool.description
+            .as_ref()
+            .map(|d| d.as_ref())
+            .unwrap_or_default(),
+        serde_json::to_string_pretty(&tool.input_schema).unwrap_or_else(|_| "{}".to_string())
+    )
+}
+
+/// Add `tools` under `extension_name`. A tool indexed again replaces its earlier definition.
+fn index_into(
+    tool_strings: &mut HashMap<String, Vec<(String, String)>>,
+    tools: &[Tool],
+    extension_name: &str,
+) {
+    if tools.is_empty() {
+        return;
+    }
+    let entries = tool_strings.entry(extension_name.to_string()).or_default();
+    for tool in tools {
+        let tool_string = describe_tool(tool);
+        match entries
+            .iter_mut()
+            .find(|(name, _)| name == tool.name.as_ref())
+        {
+            Some(entry) => entry.1 = tool_string,
+            None => entries.push((tool.name.to_string(), tool_string)),
+        }
+    }
+}
+
+const RECENT_TOOL_CALLS_CAPACITY: usize = 100;
+
+fn push_recent_call(recent_calls: &mut VecDeque<String>, tool_name: &str) {
+    if recent_calls.len() >= RECENT_TOOL_CALLS_CAPACITY {
+        recent_calls.pop_front();
+    }
+    recent_calls.push_back(tool_name.to_string());
 }
 
 pub struct LLMToolSelector {
     llm_provider: Arc<dyn Provider>,
-    tool_strings: Arc<RwLock<HashMap<String, String>>>, // extension_name -> tool_string
+    tool_strings: Arc<RwLock<HashMap<String, Vec<(String, String)>>>>, // extension_name -> (tool name, tool string)
     recent_tool_calls: Arc<RwLock<VecDeque<String>>>,
+    session_tool_calls: Arc<RwLock<HashMap<String, VecDeque<String>>>>, // session_id -> recent calls
 }
 
 impl LLMToolSelector {
@@ -40,7 +149,10 @@ impl LLMToolSelector {
         Ok(Self {
             llm_provider: provider.clone(),
             tool_strings: Arc::new(RwLock::new(HashMap::new())),
-            recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(100))),
+            recent_tool_calls: Arc::new(RwLock::new(VecDeque::with_capacity(
+                RECENT_TOOL_CALLS_CAPACITY,
+            ))),
+            session_tool_calls: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 }
@@ -64,14 +176,21 @@ impl RouterToolSelector for LLMToolSelector {
 
         // Get relevant tool strings based on extension_name
         let tool_strings = self.tool_strings.read().await;
+        let describe_extension = |entries: &Vec<(String, String)>| {
+            entries
+                .iter()
+                .map(|(_, tool_string)| tool_string.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
         let relevant_tools = if let Some(ext) = &extension_name {
-            tool_strings.get(ext).cloned()
+            tool_strings.get(ext).map(describe_extension)
         } else {
             // If no extension specified, use all tools
             Some(
                 tool_strings
                     .values()
-                    .cloned()
+                    .map(describe_extension)
                     .collect::<Vec<String>>()
                     .join("\n"),
             )
@@ -104,7 +223,11 @@ impl RouterToolSelector for LLMToolSelector {
 
             // Extract just the message content from the response
             let (message, _usage) = response;
-            let text = message.content[0].as_text().unwrap_or_default();
+            let text = message
+                .content
+                .first()
+                .and_then(|content| content.as_text())
+                .unwrap_or_default();
 
             // Split the response into individual tool entries
             let tool_entries: Vec<Content> = text
@@ -121,47 +244,31 @@ impl RouterToolSelector for LLMToolSelector {
 
     async fn index_tools(&self, tools: &[Tool], extension_name: &str) -> Result<(), ErrorData> {
         let mut tool_strings = self.tool_strings.write().await;
+        index_into(&mut tool_strings, tools, extension_name);
+        Ok(())
+    }
 
-        for tool in tools {
-            let tool_string = format!(
-                "Tool: {}\nDescription: {}\nSchema: {}",
-                tool.name,
-                tool.description
-                    .as_ref()
-                    .map(|d| d.as_ref())
-                    .unwrap_or_default(),
-                serde_json::to_string_pretty(&tool.input_schema)
-                    .unwrap_or_else(|_| "{}".to_string())
-            );
-
-            // Use the provided extension_name instead of parsing from tool name
-            let entry = tool_strings.entry(extension_name.to_string()).or_default();
-
-            // Check if this tool already exists in the entry
-            if !entry.contains(&format!("Tool: {}", tool.name)) {
-                if !entry.is_empty() {
-                    entry.push_str("\n\n");
-                }
-                entry.push_str(&tool_string);
-            }
+    /// Indexes every extension under one write lock, so a search never sees part of the batch
+    async fn index_tools_batch(&self, batch: &[(String, Vec<Tool>)]) -> Vec<(String, ErrorData)> {
+        let mut tool_strings = self.tool_strings.write().await;
+        for (extension_name, tools) in batch {
+            index_into(&mut tool_strings, tools, extension_name);
         }
-
-        Ok(())
+        Vec::new()
     }
+
+    /// Remove one tool, leaving the rest of its extension indexed
     async fn remove_tool(&self, tool_name: &str) -> Result<(), ErrorData> {
         let mut tool_strings = self.tool_strings.write().await;
-        if let Some(extension_name) = tool_name.split("__").next() {
-            tool_strings.remove(extension_name);
-        }
+        tool_strings.retain(|_, entries| {
+            entries.retain(|(name, _)| name != tool_name);
+            !entries.is_empty()
+        });
         Ok(())
     }
 
     async fn record_tool_call(&self, tool_name: &str) -> Result<(), ErrorData> {
-        let mut recent_calls = self.recent_tool_calls.write().await;
-        if recent_calls.len() >= 100 {
-            recent_calls.pop_front();
-        }
-        recent_calls.push_back(tool_name.to_string());
+        push_recent_call(&mut *self.recent_tool_calls.write().await, tool_name);
         Ok(())
     }
 
@@ -169,6 +276,32 @@ impl RouterToolSelector for LLMToolSelector {
         let recent_calls = self.recent_tool_calls.read().await;
         Ok(recent_calls.iter().rev().take(limit).cloned().collect())
     }
+
+    async fn record_session_tool_call(
+        &self,
+        session_id: &str,
+        tool_name: &str,
+    ) -> Result<(), ErrorData> {
+        self.record_tool_call(tool_name).await?;
+        let mut session_calls = self.session_tool_calls.write().await;
+        push_recent_call(
+            session_calls.entry(session_id.to_string()).or_default(),
+            tool_name,
+        );
+        Ok(())
+    }
+
+    async fn get_session_recent_tool_calls(
+        &self,
+        session_id: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, ErrorData> {
+        let session_calls = self.session_tool_calls.read().await;
+        Ok(session_calls
+            .get(session_id)
+            .map(|calls| calls.iter().rev().take(limit).cloned().collect())
+            .unwrap_or_default())
+    }
 }
 
 // Helper function to create a boxed tool selector
@@ -178,3 +311,52 @@ pub async fn create_tool_selector(
     let selector = LLMToolSelector::new(provider).await?;
     Ok(Box::new(selector))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::scriptedprovider::ScriptedProvider;
+    use rmcp::object;
+
+    #[tokio::test]
+    async fn test_removing_a_tool_keeps_the_rest_of_its_extension() {
+        let provider = Arc::new(ScriptedProvider::replying(""));
+        let selector = LLMToolSelector::new(provider.clone()).await.unwrap();
+        let tools = ["dev__shell", "dev__edit"]
+            .map(|name| Tool::new(name.to_string(), name.to_string(), object!({})));
+        selector.index_tools(&tools, "dev").await.unwrap();
+
+        selector.remove_tool("dev__shell").await.unwrap();
+        selector
+            .select_tools(object!({"query": "edit a file", "extension_name": "dev"}))
+            .await
+            .unwrap();
+
+        let prompt = provider.requests()[0].messages[0].as_concat_text();
+        assert!(prompt.contains("Tool: dev__edit"));
+        assert!(!prompt.contains("Tool: dev__shell"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_indexes_every_extension() {
+        let provider = Arc::new(ScriptedProvider::replying(""));
+        let selector = LLMToolSelector::new(provider.clone()).await.unwrap();
+        let batch = ["dev", "todo"].map(|extension| {
+            let name = format!("{extension}__run");
+            (
+                extension.to_string(),
+                vec![Tool::new(name.clone(), name, object!({}))],
+            )
+        });
+
+        assert!(selector.index_tools_batch(&batch).await.is_empty());
+        selector
+            .select_tools(object!({"query": "run"}))
+            .await
+            .unwrap();
+
+        let prompt = provider.requests()[0].messages[0].as_concat_text();
+        assert!(prompt.contains("Tool: dev__run"));
+        assert!(prompt.contains("Tool: todo__run"));
+    }
+}
@@ -3,22 +3,225 @@
 //! This module contains all the handlers for the schedule management platform tool,
 //! including job creation, execution, monitoring, and session management.
 
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::mcp_utils::ToolResult;
-use chrono::Utc;
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use croner::Cron;
+use futures::stream::BoxStream;
 use rmcp::model::{Content, ErrorCode, ErrorData};
+use serde::Deserialize;
 
-use super::Agent;
+use super::{Agent, AgentEvent};
+use crate::agents::extension::ExtensionConfig;
+use crate::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME;
+use crate::config::Config;
 use crate::recipe::Recipe;
 use crate::scheduler_trait::SchedulerTrait;
 
+/// Actions accepted by the schedule management tool
+const SCHEDULE_ACTIONS: &[&str] = &[
+    "list",
+    "create",
+    "run_now",
+    "pause",
+    "unpause",
+    "delete",
+    "kill",
+    "inspect",
+    "running",
+    "sessions",
+    "session_content",
+    "preview",
+    "validate",
+];
+
+/// Fire times returned by the preview action when no `count` is given
+const DEFAULT_PREVIEW_COUNT: usize = 5;
+const MAX_PREVIEW_COUNT: usize = 50;
+
+/// How often `stream_job_output` checks a running job's session for new messages
+const JOB_OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Largest edit distance at which an unknown action is still treated as a typo
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// Closest valid action to `action`, if it looks like a typo of one
+fn suggest_action(action: &str) -> Option<&'static str> {
+    let action = action.to_lowercase();
+    SCHEDULE_ACTIONS
+        .iter()
+        .map(|candidate| (edit_distance(&action, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn unknown_action_message(action: &str) -> String {
+    let valid = SCHEDULE_ACTIONS.join(", ");
+    match suggest_action(action) {
+        Some(suggestion) => format!(
+            "Unknown action: {}. Did you mean '{}'? Valid actions: {}",
+            action, suggestion, valid
+        ),
+        None => format!("Unknown action: {}. Valid actions: {}", action, valid),
+    }
+}
+
+/// What to do when a recipe being scheduled can itself use the schedule management tool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SelfSchedulingPolicy {
+    #[default]
+    Warn,
+    Refuse,
+}
+
+impl SelfSchedulingPolicy {
+    fn from_config() -> Self {
+        Config::global()
+            .get_param::<Self>("GOOSE_SELF_SCHEDULING_POLICY")
+            .unwrap_or_default()
+    }
+}
+
+/// The schedule tool's name without the `platform__` prefix
+const MANAGE_SCHEDULE_TOOL_NAME: &str = "manage_schedule";
+
+/// Whether running `recipe` can reach the schedule tool: its instructions or prompt name the
+/// tool, it declares the platform extension with the tool available, or one of its sub-recipes
+/// does. Sub-recipes are loaded from their paths as they are when the recipe runs; ones that
+/// can't be loaded are skipped.
+fn recipe_uses_schedule_tool(recipe: &Recipe, visited: &mut HashSet<String>) -> bool {
+    let mentioned = [&recipe.instructions, &recipe.prompt]
+        .into_iter()
+        .flatten()
+        .any(|text| names_schedule_tool(text));
+    let declared = recipe
+        .extensions
+        .iter()
+        .flatten()
+        .any(declares_schedule_tool);
+    mentioned
+        || declared
+        || recipe.sub_recipes.iter().flatten().any(|sub_recipe| {
+            visited.insert(sub_recipe.path.clone())
+                && load_recipe_file(&sub_recipe.path)
+                    .is_ok_and(|sub_recipe| recipe_uses_schedule_tool(&sub_recipe, visited))
+        })
+}
+
+/// Whether `text` names the schedule tool as a whole word, by its full or bare name
+fn names_schedule_tool(text: &str) -> bool {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .any(|word| word == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME || word == MANAGE_SCHEDULE_TOOL_NAME)
+}
+
+fn declares_schedule_tool(extension: &ExtensionConfig) -> bool {
+    extension.name() == "platform" && extension.is_tool_available(MANAGE_SCHEDULE_TOOL_NAME)
+}
+
+/// Parse the recipe file at `recipe_path`, as JSON for `.json` files and YAML otherwise
+fn load_recipe_file(recipe_path: &str) -> ToolResult<Recipe> {
+    if !std::path::Path::new(recipe_path).exists() {
+        return Err(ErrorData::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Recipe file not found: {}", recipe_path),
+            None,
+        ));
+    }
+
+    let content = std::fs::read_to_string(recipe_path).map_err(|e| {
+        ErrorData::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Cannot read recipe file: {}", e),
+            None,
+        )
+    })?;
+    if recipe_path.ends_with(".json") {
+        serde_json::from_str::<Recipe>(&content).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Invalid JSON recipe: {}", e),
+                None,
+            )
+        })
+    } else {
+        serde_yaml::from_str::<Recipe>(&content).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Invalid YAML recipe: {}", e),
+                None,
+            )
+        })
+    }
+}
+
+fn parse_inline_recipe(content: &str) -> ToolResult<Recipe> {
+    Recipe::from_content(content).map_err(|e| {
+        ErrorData::new(
+            ErrorCode::INVALID_PARAMS,
+            format!("Invalid inline recipe: {}", e),
+            None,
+        )
+    })
+}
+
+/// Save an inline recipe as the recipe file of `job_id`, returning its path
+fn save_inline_recipe(job_id: &str, recipe: &Recipe) -> ToolResult<String> {
+    let internal_error = |message: String| ErrorData::new(ErrorCode::INTERNAL_ERROR, message, None);
+    let yaml = serde_yaml::to_string(recipe).map_err(|e| internal_error(e.to_string()))?;
+    let path = crate::scheduler::get_default_scheduled_recipes_dir()
+        .map_err(|e| internal_error(e.to_string()))?
+        .join(format!("{}.yaml", job_id));
+    std::fs::write(&path, yaml)
+        .map_err(|e| internal_error(format!("Cannot save inline recipe: {}", e)))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Parse an optional RFC3339 timestamp argument such as `valid_from`
+fn parse_validity_bound(
+    arguments: &serde_json::Value,
+    key: &str,
+) -> Result<Option<DateTime<Utc>>, ErrorData> {
+    let Some(value) = arguments.get(key).and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("Invalid '{}' timestamp '{}': {}", key, value, e),
+                None,
+            )
+        })
+}
+
 impl Agent {
-    /// Handle schedule management tool calls
+    /// Handle schedule management tool calls. `user_id` is recorded on jobs created by the call.
     pub async fn handle_schedule_management(
         &self,
         arguments: serde_json::Value,
         _request_id: String,
+        user_id: Option<String>,
     ) -> ToolResult<Vec<Content>> {
         let scheduler = match self.scheduler_service.lock().await.as_ref() {
             Some(s) => s.clone(),
@@ -44,18 +247,27 @@ impl Agent {
 
         match action {
             "list" => self.handle_list_jobs(scheduler).await,
-            "create" => self.handle_create_job(scheduler, arguments).await,
+            "create" => {
+                self.handle_create_job(scheduler, arguments, user_id, false)
+                    .await
+            }
             "run_now" => self.handle_run_now(scheduler, arguments).await,
             "pause" => self.handle_pause_job(scheduler, arguments).await,
             "unpause" => self.handle_unpause_job(scheduler, arguments).await,
             "delete" => self.handle_delete_job(scheduler, arguments).await,
             "kill" => self.handle_kill_job(scheduler, arguments).await,
             "inspect" => self.handle_inspect_job(scheduler, arguments).await,
+            "running" => self.handle_list_running_jobs(scheduler).await,
             "sessions" => self.handle_list_sessions(scheduler, arguments).await,
             "session_content" => self.handle_session_content(arguments).await,
+            "preview" => self.handle_preview_schedule(arguments),
+            "validate" => {
+                self.handle_create_job(scheduler, arguments, user_id, true)
+                    .await
+            }
             _ => Err(ErrorData::new(
-                ErrorCode::INTERNAL_ERROR,
-                format!("Unknown action: {}", action),
+                ErrorCode::INVALID_PARAMS,
+                unknown_action_message(action),
                 None,
             )),
         }
@@ -65,35 +277,45 @@ impl Agent {
         &self,
         scheduler: Arc<dyn SchedulerTrait>,
     ) -> ToolResult<Vec<Content>> {
-        let jobs = scheduler.list_scheduled_jobs().await;
-        let jobs_json = serde_json::to_string_pretty(&jobs).map_err(|e| {
-            ErrorData::new(
-                ErrorCode::INTERNAL_ERROR,
-                format!("Failed to serialize jobs: {}", e),
-                None,
-            )
-        })?;
+        let jobs = scheduler
+            .list_scheduled_jobs()
+            .await
+            .into_iter()
+            .map(|job| {
+                let mut value = serde_json::to_value(&job)?;
+                if let (Some(fields), Some(description)) =
+                    (value.as_object_mut(), describe_cron(&job.cron))
+                {
+                    fields.insert("cron_description".to_string(), description.into());
+                }
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>, serde_json::Error>>();
+        let jobs_json = jobs
+            .and_then(|jobs| serde_json::to_string_pretty(&jobs))
+            .map_err(|e| {
+                ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    format!("Failed to serialize jobs: {}", e),
+                    None,
+                )
+            })?;
         Ok(vec![Content::text(format!(
             "Scheduled Jobs:\n{}",
             jobs_json
         ))])
     }
 
+    /// Create a job, or with `dry_run` only validate it and report the job that would be created
     async fn handle_create_job(
         &self,
         scheduler: Arc<dyn SchedulerTrait>,
         arguments: serde_json::Value,
+        user_id: Option<String>,
+        dry_run: bool,
     ) -> ToolResult<Vec<Content>> {
-        let recipe_path = arguments
-            .get("recipe_path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| {
-                ErrorData::new(
-                    ErrorCode::INVALID_PARAMS,
-                    "Missing 'recipe_path' parameter".to_string(),
-                    None,
-                )
-            })?;
+        let recipe_content = arguments.get("recipe_content").and_then(|v| v.as_str());
+        let recipe_path = arguments.get("recipe_path").and_then(|v| v.as_str());
 
         let cron_expression = arguments
             .get("cron_expression")
@@ -105,6 +327,7 @@ impl Agent {
                     None,
                 )
             })?;
+        let (normalized_cron, _) = parse_cron(cron_expression)?;
 
         // Get the execution_mode parameter, defaulting to "background" if not provided
         let execution_mode = arguments
@@ -112,63 +335,97 @@ impl Agent {
             .and_then(|v| v.as_str())
             .unwrap_or("background");
 
-        if !std::path::Path::new(recipe_path).exists() {
-            return Err(ErrorData::new(
-                ErrorCode::INTERNAL_ERROR,
-                format!("Recipe file not found: {}", recipe_path),
-                None,
-            ));
+        let valid_from = parse_validity_bound(&arguments, "valid_from")?;
+        let valid_until = parse_validity_bound(&arguments, "valid_until")?;
+        if let (Some(from), Some(until)) = (valid_from, valid_until) {
+            if from >= until {
+                return Err(ErrorData::new(
+                    ErrorCode::INVALID_PARAMS,
+                    "'valid_from' must be before 'valid_until'".to_string(),
+                    None,
+                ));
+            }
         }
 
-        // Validate it's a valid recipe by trying to parse it
-        match std::fs::read_to_string(recipe_path) {
-            Ok(content) => {
-                if recipe_path.ends_with(".json") {
-                    serde_json::from_str::<Recipe>(&content).map_err(|e| {
-                        ErrorData::new(
-                            ErrorCode::INTERNAL_ERROR,
-                            format!("Invalid JSON recipe: {}", e),
-                            None,
-                        )
-                    })?;
-                } else {
-                    serde_yaml::from_str::<Recipe>(&content).map_err(|e| {
-                        ErrorData::new(
-                            ErrorCode::INTERNAL_ERROR,
-                            format!("Invalid YAML recipe: {}", e),
-                            None,
-                        )
-                    })?;
-                }
-            }
-            Err(e) => {
+        let (recipe, recipe_name) = match (recipe_content, recipe_path) {
+            (Some(content), _) => (parse_inline_recipe(content)?, "inline recipe"),
+            (None, Some(path)) => (load_recipe_file(path)?, path),
+            (None, None) => {
                 return Err(ErrorData::new(
-                    ErrorCode::INTERNAL_ERROR,
-                    format!("Cannot read recipe file: {}", e),
+                    ErrorCode::INVALID_PARAMS,
+                    "Missing 'recipe_path' or 'recipe_content' parameter".to_string(),
                     None,
                 ))
             }
+        };
+
+        let mut warning = None;
+        if recipe_uses_schedule_tool(&recipe, &mut HashSet::new()) {
+            let message = format!(
+                "Recipe '{}' uses the schedule management tool, so each run could create more scheduled jobs",
+                recipe_name
+            );
+            match SelfSchedulingPolicy::from_config() {
+                SelfSchedulingPolicy::Refuse => {
+                    return Err(ErrorData::new(
+                        ErrorCode::INVALID_PARAMS,
+                        format!("{}. Refusing to schedule it.", message),
+                        None,
+                    ));
+                }
+                SelfSchedulingPolicy::Warn => {
+                    tracing::warn!("{}", message);
+                    warning = Some(message);
+                }
+            }
         }
 
         // Generate unique job ID
         let job_id = format!("agent_created_{}", Utc::now().timestamp());
 
+        if dry_run {
+            let mut content = vec![Content::text(format!(
+                "Valid: would create scheduled job '{}' for recipe '{}' with cron expression '{}' in {} mode",
+                job_id, recipe_name, normalized_cron, execution_mode
+            ))];
+            if let Some(warning) = warning {
+                content.push(Content::text(format!("Warning: {}", warning)));
+            }
+            return Ok(content);
+        }
+
+        // Inline recipes are saved straight into the scheduled recipes directory, while
+        // recipe files are copied there by the scheduler
+        let (source, make_copy) = match (recipe_content, recipe_path) {
+            (None, Some(path)) => (path.to_string(), true),
+            _ => (save_inline_recipe(&job_id, &recipe)?, false),
+        };
+
         let job = crate::scheduler::ScheduledJob {
             id: job_id.clone(),
-            source: recipe_path.to_string(),
+            source,
             cron: cron_expression.to_string(),
             last_run: None,
             currently_running: false,
             paused: false,
             current_session_id: None,
             process_start_time: None,
+            user_id,
+            valid_from,
+            valid_until,
         };
 
-        match scheduler.add_scheduled_job(job, true).await {
-            Ok(()) => Ok(vec![Content::text(format!(
-                "Successfully created scheduled job '{}' for recipe '{}' with cron expression '{}' in {} mode",
-                job_id, recipe_path, cron_expression, execution_mode
-            ))]),
+        match scheduler.add_scheduled_job(job, make_copy).await {
+            Ok(()) => {
+                let mut content = vec![Content::text(format!(
+                    "Successfully created scheduled job '{}' for recipe '{}' with cron expression '{}' in {} mode",
+                    job_id, recipe_name, cron_expression, execution_mode
+                ))];
+                if let Some(warning) = warning {
+                    content.push(Content::text(format!("Warning: {}", warning)));
+                }
+                Ok(content)
+            }
             Err(e) => Err(ErrorData::new(
                 ErrorCode::INTERNAL_ERROR,
                 format!("Failed to create job: {}", e),
@@ -315,10 +572,21 @@ impl Agent {
             })?;
 
         match scheduler.kill_running_job(job_id).await {
-            Ok(()) => Ok(vec![Content::text(format!(
-                "Successfully killed running job '{}'",
-                job_id
-            ))]),
+            Ok(()) => {
+                let still_running =
+                    matches!(scheduler.get_running_job_info(job_id).await, Ok(Some(_)));
+                Ok(vec![Content::text(if still_running {
+                    format!(
+                        "Sent a stop signal to job '{}', but it is still reported as running",
+                        job_id
+                    )
+                } else {
+                    format!(
+                        "Successfully killed running job '{}'; it is no longer running and its schedule is kept",
+                        job_id
+                    )
+                })])
+            }
             Err(e) => Err(ErrorData::new(
                 ErrorCode::INTERNAL_ERROR,
                 format!("Failed to kill job: {}", e),
@@ -344,17 +612,28 @@ impl Agent {
                 )
             })?;
 
+        let schedule = scheduler
+            .list_scheduled_jobs()
+            .await
+            .into_iter()
+            .find(|job| job.id == job_id)
+            .map(|job| match describe_cron(&job.cron) {
+                Some(description) => format!("\n- Schedule: {} ({})", job.cron, description),
+                None => format!("\n- Schedule: {}", job.cron),
+            })
+            .unwrap_or_default();
+
         match scheduler.get_running_job_info(job_id).await {
             Ok(Some((session_id, start_time))) => {
                 let duration = Utc::now().signed_duration_since(start_time);
                 Ok(vec![Content::text(format!(
-                    "Job '{}' is currently running:\n- Session ID: {}\n- Started: {}\n- Duration: {} seconds",
-                    job_id, session_id, start_time.to_rfc3339(), duration.num_seconds()
+                    "Job '{}' is currently running:\n- Session ID: {}\n- Started: {}\n- Duration: {} seconds{}",
+                    job_id, session_id, start_time.to_rfc3339(), duration.num_seconds(), schedule
                 ))])
             }
             Ok(None) => Ok(vec![Content::text(format!(
-                "Job '{}' is not currently running",
-                job_id
+                "Job '{}' is not currently running{}",
+                job_id, schedule
             ))]),
             Err(e) => Err(ErrorData::new(
                 ErrorCode::INTERNAL_ERROR,
@@ -364,6 +643,93 @@ impl Agent {
         }
     }
 
+    /// List every job that is currently running along with its session and start time
+    async fn handle_list_running_jobs(
+        &self,
+        scheduler: Arc<dyn SchedulerTrait>,
+    ) -> ToolResult<Vec<Content>> {
+        let mut running = Vec::new();
+        for job in scheduler.list_scheduled_jobs().await {
+            match scheduler.get_running_job_info(&job.id).await {
+                Ok(Some((session_id, start_time))) => {
+                    let duration = Utc::now().signed_duration_since(start_time);
+                    running.push(format!(
+                        "- {}: Session ID: {}, Started: {}, Duration: {} seconds",
+                        job.id,
+                        session_id,
+                        start_time.to_rfc3339(),
+                        duration.num_seconds()
+                    ));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    return Err(ErrorData::new(
+                        ErrorCode::INTERNAL_ERROR,
+                        format!("Failed to inspect job '{}': {}", job.id, e),
+                        None,
+                    ))
+                }
+            }
+        }
+
+        if running.is_empty() {
+            Ok(vec![Content::text("No jobs are currently running")])
+        } else {
+            Ok(vec![Content::text(format!(
+                "Running Jobs:\n{}",
+                running.join("\n")
+            ))])
+        }
+    }
+
+    /// List the next fire times of a cron expression without creating a job
+    fn handle_preview_schedule(&self, arguments: serde_json::Value) -> ToolResult<Vec<Content>> {
+        let invalid = |message: String| ErrorData::new(ErrorCode::INVALID_PARAMS, message, None);
+
+        let cron_expression = arguments
+            .get("cron_expression")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| invalid("Missing 'cron_expression' parameter".to_string()))?;
+        let count = arguments
+            .get("count")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_PREVIEW_COUNT))
+            .unwrap_or(DEFAULT_PREVIEW_COUNT);
+
+        let (_, cron) = parse_cron(cron_expression)?;
+
+        let timezone = arguments.get("timezone").and_then(|v| v.as_str());
+        let times = match timezone {
+            None => next_fire_times(&cron, Local::now(), count),
+            Some(tz) if tz.eq_ignore_ascii_case("local") => {
+                next_fire_times(&cron, Local::now(), count)
+            }
+            Some(tz) if tz.eq_ignore_ascii_case("utc") => next_fire_times(&cron, Utc::now(), count),
+            Some(tz) => {
+                let offset: FixedOffset = tz.parse().map_err(|_| {
+                    invalid(format!(
+                        "Invalid timezone '{}': expected 'local', 'UTC' or an offset like '+02:00'",
+                        tz
+                    ))
+                })?;
+                next_fire_times(&cron, Utc::now().with_timezone(&offset), count)
+            }
+        };
+
+        if times.is_empty() {
+            return Ok(vec![Content::text(format!(
+                "Cron expression '{}' never fires",
+                cron_expression
+            ))]);
+        }
+        Ok(vec![Content::text(format!(
+            "Next {} fire times for '{}':\n{}",
+            times.len(),
+            cron_expression,
+            times.join("\n")
+        ))])
+    }
+
     /// List execution sessions for a job
     async fn handle_list_sessions(
         &self,
@@ -465,4 +831,220 @@ impl Agent {
             session_id, metadata_json
         ))])
     }
+
+    /// Stream the messages of a scheduled job's session as they are added, e.g. after
+    /// `run_now`. The stream ends once no scheduled job is running in that session.
+    pub fn stream_job_output(&self, session_id: &str) -> BoxStream<'_, Result<AgentEvent>> {
+        let session_id = session_id.to_string();
+        Box::pin(async_stream::try_stream! {
+            let mut seen = 0;
+            loop {
+                // Check before reading so messages written just before completion still go out
+                let running = self.is_job_session_running(&session_id).await?;
+                let session = crate::session::SessionManager::get_session(&session_id, true).await?;
+                let messages = session.conversation.unwrap_or_default().messages().clone();
+                for message in messages.into_iter().skip(seen) {
+                    seen += 1;
+                    yield AgentEvent::Message(message);
+                }
+                if !running {
+                    break;
+                }
+                tokio::time::sleep(JOB_OUTPUT_POLL_INTERVAL).await;
+            }
+        })
+    }
+
+    async fn is_job_session_running(&self, session_id: &str) -> Result<bool> {
+        let Some(scheduler) = self.scheduler_service.lock().await.clone() else {
+            return Ok(false);
+        };
+        for job in scheduler.list_scheduled_jobs().await {
+            if let Some((running_session, _)) = scheduler.get_running_job_info(&job.id).await? {
+                if running_session == session_id {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Check a cron expression the way the scheduler reads it, returning the 6-field form the
+/// scheduler registers along with the parsed schedule
+fn parse_cron(expression: &str) -> Result<(String, Cron), ErrorData> {
+    let invalid = |reason: String| {
+        ErrorData::new(
+            ErrorCode::INVALID_PARAMS,
+            format!("Invalid cron expression '{}': {}", expression, reason),
+            None,
+        )
+    };
+
+    // Same 5-to-6 field conversion the scheduler applies when registering a job
+    let fields = expression.split_whitespace().collect::<Vec<_>>();
+    let pattern = match fields.len() {
+        5 => format!("0 {}", fields.join(" ")),
+        6 => fields.join(" "),
+        count => {
+            return Err(invalid(format!(
+                "expected 5 fields (minute hour day-of-month month day-of-week) or 6 with a \
+                 leading seconds field, got {}",
+                count
+            )))
+        }
+    };
+    let cron = Cron::new(&pattern)
+        .with_seconds_required()
+        .with_dom_and_dow()
+        .parse()
+        .map_err(|e| invalid(e.to_string()))?;
+    Ok((pattern, cron))
+}
+
+fn next_fire_times<Tz: TimeZone>(cron: &Cron, from: DateTime<Tz>, count: usize) -> Vec<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    cron.iter_after(from)
+        .take(count)
+        .map(|time| time.to_rfc3339())
+        .collect()
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Read a 5- or 6-field cron expression as English, e.g. "0 0 5 * * *" as "At 05:00 every day"
+fn describe_cron(expression: &str) -> Option<String> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let (second, minute, hour, day, month, weekday) = match fields.as_slice() {
+        [minute, hour, day, month, weekday] => ("0", *minute, *hour, *day, *month, *weekday),
+        [second, minute, hour, day, month, weekday] => {
+            (*second, *minute, *hour, *day, *month, *weekday)
+        }
+        _ => return None,
+    };
+    let number = |field: &str| field.parse::<u32>().ok();
+
+    let clock_time = match (number(second), number(minute), number(hour)) {
+        (Some(0), Some(m), Some(h)) => Some(format!("At {:02}:{:02}", h, m)),
+        (Some(s), Some(m), Some(h)) => Some(format!("At {:02}:{:02}:{:02}", h, m, s)),
+        _ => None,
+    };
+    let is_clock_time = clock_time.is_some();
+    let time = match clock_time {
+        Some(time) => time,
+        None if second != "0" => format!(
+            "At {}, {} past {}",
+            describe_field(second, "second"),
+            describe_field(minute, "minute"),
+            describe_field(hour, "hour")
+        ),
+        None if number(minute).is_some() => {
+            format!("At minute {} past {}", minute, describe_field(hour, "hour"))
+        }
+        None if is_any(hour) => capitalize(&describe_field(minute, "minute")),
+        None => format!(
+            "{} during {}",
+            capitalize(&describe_field(minute, "minute")),
+            describe_field(hour, "hour")
+        ),
+    };
+
+    let mut parts = vec![time];
+    if !is_any(day) {
+        parts.push(format!("on {} of the month", describe_field(day, "day")));
+    }
+    if !is_any(weekday) {
+        parts.push(format!("on {}", join_values(weekday, weekday_name)));
+    }
+    if !is_any(month) {
+        parts.push(format!("in {}", join_values(month, month_name)));
+    }
+    if parts.len() == 1 && is_clock_time {
+        parts.push("every day".to_string());
+    }
+    Some(parts.join(" "))
+}
+
+fn is_any(field: &str) -> bool {
+    field == "*" || field == "?"
+}
+
+/// Describe a numeric cron field, e.g. "every 15 minutes" or "hours 9 through 17"
+fn describe_field(field: &str, unit: &str) -> String {
+    if is_any(field) {
+        return format!("every {}", unit);
+    }
+    if let Some(step) = field.strip_prefix("*/") {
+        return format!("every {} {}s", step, unit);
+    }
+    let plural = if field.contains([',', '-']) { "s" } else { "" };
+    format!("{}{} {}", unit, plural, join_values(field, str::to_string))
+}
+
+/// Join a field's list and range items, e.g. "1-5,7" as "1 through 5 and 7"
+fn join_values(field: &str, name: impl Fn(&str) -> String) -> String {
+    let items: Vec<String> = field
+        .split(',')
+        .map(|item| match item.split_once('-') {
+            Some((from, to)) => format!("{} through {}", name(from), name(to)),
+            None => name(item),
+        })
+        .collect();
+    match items.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => items.concat(),
+    }
+}
+
+fn weekday_name(value: &str) -> String {
+    let name = match value.parse::<usize>() {
+        Ok(7) => WEEKDAYS.first(),
+        Ok(n) => WEEKDAYS.get(n),
+        Err(_) => WEEKDAYS
+            .iter()
+            .find(|day| day[..3].eq_ignore_ascii_case(value)),
+    };
+    name.map_or_else(|| value.to_string(), |name| name.to_string())
+}
+
+fn month_name(value: &str) -> String {
+    let name = match value.parse::<usize>() {
+        Ok(n) => n.checked_sub(1).and_then(|index| MONTHS.get(index)),
+        Err(_) => MONTHS
+            .iter()
+            .find(|month| month[..3].eq_ignore_ascii_case(value)),
+    };
+    name.map_or_else(|| value.to_string(), |name| name.to_string())
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }
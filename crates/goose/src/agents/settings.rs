@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::agents::compact_serializer::CompactSerializer;
+use crate::agents::reference_validator::ReferenceValidator;
+use crate::agents::tool_executor::ToolExecutor;
+use crate::agents::types::{
+    ArgumentTransform, LargeResponseStrategy, PreSendHook, ResultTransform, RetryPolicy, ToolGate,
+    ToolRetryPolicy, ToolSandbox, TruncationPolicy,
+};
+use crate::agents::Agent;
+use crate::model::PromptCacheHints;
+
+/// Default cap on the combined length of a generated recipe's instructions and activities
+const DEFAULT_MAX_RECIPE_SIZE: usize = 64 * 1024;
+
+/// Tool-execution and reply settings configured on an [`Agent`], kept behind one lock
+pub(super) struct AgentSettings {
+    pub(super) result_transform: Option<ResultTransform>,
+    pub(super) compact_serializer: Option<Arc<CompactSerializer>>,
+    pub(super) pre_send_hook: Option<PreSendHook>,
+    pub(super) prompt_cache: Option<PromptCacheHints>,
+    pub(super) argument_transforms: HashMap<String, ArgumentTransform>,
+    pub(super) truncation_policies: HashMap<String, TruncationPolicy>,
+    pub(super) large_response_strategies: HashMap<String, LargeResponseStrategy>,
+    pub(super) sandbox: Option<ToolSandbox>,
+    pub(super) tool_retry_policies: HashMap<String, ToolRetryPolicy>,
+    /// Time limits for extension tool calls, keyed by tool name
+    pub(super) tool_timeouts: HashMap<String, Duration>,
+    /// Time limit for extension tools without an entry in `tool_timeouts`
+    pub(super) default_tool_timeout: Option<Duration>,
+    pub(super) tool_priorities: HashMap<String, i32>,
+    pub(super) reference_validator: Option<Arc<ReferenceValidator>>,
+    pub(super) tool_executor: Option<Arc<dyn ToolExecutor>>,
+    pub(super) dedup_tool_results: bool,
+    pub(super) tool_gates: Vec<ToolGate>,
+    pub(super) max_offered_tools: Option<usize>,
+    /// Tool confirmations shown to the user at once in approve mode; the rest wait their turn
+    pub(super) max_pending_confirmations: usize,
+    pub(super) resume_on_error: bool,
+    pub(super) provider_retry_policy: Option<RetryPolicy>,
+    pub(super) strict_recipe_json: bool,
+    pub(super) verbatim_recipe_fallback: bool,
+    pub(super) max_recipe_size: usize,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            result_transform: None,
+            compact_serializer: None,
+            pre_send_hook: None,
+            prompt_cache: None,
+            argument_transforms: HashMap::new(),
+            truncation_policies: HashMap::new(),
+            large_response_strategies: HashMap::new(),
+            sandbox: None,
+            tool_retry_policies: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            default_tool_timeout: None,
+            tool_priorities: HashMap::new(),
+            reference_validator: None,
+            tool_executor: None,
+            dedup_tool_results: false,
+            tool_gates: Vec::new(),
+            max_offered_tools: None,
+            max_pending_confirmations: 1,
+            resume_on_error: false,
+            provider_retry_policy: None,
+            strict_recipe_json: false,
+            verbatim_recipe_fallback: false,
+            max_recipe_size: DEFAULT_MAX_RECIPE_SIZE,
+        }
+    }
+}
+
+impl Agent {
+    /// Replace tool results identical to an earlier one in the conversation with a reference
+    pub async fn set_tool_result_dedup(&self, enabled: bool) {
+        self.settings.lock().await.dedup_tool_results = enabled;
+    }
+
+    /// Limit how many tool confirmations are awaiting the user at once; values below 1 mean 1
+    pub async fn set_max_pending_confirmations(&self, limit: usize) {
+        self.settings.lock().await.max_pending_confirmations = limit;
+    }
+
+    /// Hide `gate.tool` from the model until `gate.requires` has returned a successful result
+    pub async fn add_tool_gate(&self, gate: ToolGate) {
+        self.settings.lock().await.tool_gates.push(gate);
+    }
+
+    /// Offer at most `limit` tools per turn, dropping the rest in offering order
+    pub async fn set_max_offered_tools(&self, limit: Option<usize>) {
+        self.settings.lock().await.max_offered_tools = limit;
+    }
+
+    /// Register a transform applied to every tool result before it is added to the message,
+    /// e.g. for redaction or reformatting
+    pub async fn set_result_transform(&self, transform: ResultTransform) {
+        self.settings.lock().await.result_transform = Some(transform);
+    }
+
+    /// Rewrite JSON tool results compactly before they are added to the message
+    pub async fn set_compact_serializer(&self, serializer: Option<Arc<CompactSerializer>>) {
+        self.settings.lock().await.compact_serializer = serializer;
+    }
+
+    /// Register a hook that sees the outgoing messages just before each provider call
+    /// and returns the messages to send, e.g. to redact or inject content
+    pub async fn set_pre_send_hook(&self, hook: PreSendHook) {
+        self.settings.lock().await.pre_send_hook = Some(hook);
+    }
+
+    /// Ask providers that support prompt caching to cache the parts of the prompt in `hints`,
+    /// or stop hinting with None
+    pub async fn set_prompt_cache_hints(&self, hints: Option<PromptCacheHints>) {
+        self.settings.lock().await.prompt_cache = hints;
+    }
+
+    /// Register a transform applied to `tool_name`'s arguments before it is dispatched,
+    /// replacing any transform already registered for that tool
+    pub async fn set_argument_transform(&self, tool_name: &str, transform: ArgumentTransform) {
+        self.settings
+            .lock()
+            .await
+            .argument_transforms
+            .insert(tool_name.to_string(), transform);
+    }
+
+    /// Hand extension tool calls the executor accepts to it instead of running them inline
+    pub async fn set_tool_executor(&self, executor: Option<Arc<dyn ToolExecutor>>) {
+        self.settings.lock().await.tool_executor = executor;
+    }
+
+    /// Keep the part of `tool_name`'s oversized text results chosen by `policy` inline,
+    /// instead of replacing them with a reference to a file
+    pub async fn set_truncation_policy(&self, tool_name: &str, policy: TruncationPolicy) {
+        self.settings
+            .lock()
+            .await
+            .truncation_policies
+            .insert(tool_name.to_string(), policy);
+    }
+
+    /// Choose how `tool_name`'s oversized text results are handled. Tools without a strategy
+    /// are summarized into a file reference, or truncated if they have a truncation policy.
+    pub async fn configure_large_response(&self, tool_name: &str, strategy: LargeResponseStrategy) {
+        self.settings
+            .lock()
+            .await
+            .large_response_strategies
+            .insert(tool_name.to_string(), strategy);
+    }
+
+    /// Only run tools that `sandbox` allows and that are annotated read-only; `None` lifts
+    /// the restriction
+    pub async fn set_sandbox(&self, sandbox: Option<ToolSandbox>) {
+        self.settings.lock().await.sandbox = sandbox;
+    }
+
+    /// Stop extension tool calls that run longer than their entry in `per_tool`, or than
+    /// `default` for tools without one. Platform and frontend tools are not limited.
+    pub async fn configure_tool_timeouts(
+        &self,
+        per_tool: HashMap<String, Duration>,
+        default: Option<Duration>,
+    ) {
+        let mut settings = self.settings.lock().await;
+        settings.tool_timeouts = per_tool;
+        settings.default_tool_timeout = default;
+    }
+
+    /// Retry `tool_name` when it fails with an error `policy` considers transient
+    pub async fn set_tool_retry_policy(&self, tool_name: &str, policy: ToolRetryPolicy) {
+        self.settings
+            .lock()
+            .await
+            .tool_retry_policies
+            .insert(tool_name.to_string(), policy);
+    }
+
+    /// Check tool arguments against identifiers seen in earlier tool results, and note
+    /// unseen ones in the result so the model can correct itself
+    pub async fn set_reference_validator(&self, validator: Option<Arc<ReferenceValidator>>) {
+        self.settings.lock().await.reference_validator = validator;
+    }
+
+    /// Run `tool_name` ahead of lower-priority tools requested in the same turn.
+    /// Tools default to priority 0; calls with equal priority run concurrently.
+    pub async fn set_tool_priority(&self, tool_name: &str, priority: i32) {
+        self.settings
+            .lock()
+            .await
+            .tool_priorities
+            .insert(tool_name.to_string(), priority);
+    }
+
+    /// When enabled, a provider error no longer ends the reply. The error is emitted and the
+    /// loop waits for `handle_error_recovery` to decide whether to retry or abandon.
+    pub async fn set_resume_on_error(&self, enabled: bool) {
+        self.settings.lock().await.resume_on_error = enabled;
+    }
+
+    /// Retry provider requests that fail with a transient error before reporting the error.
+    /// Applies to replies started after the call. While a policy is set it replaces the
+    /// provider's own retries, and retries don't count toward `max_turns`.
+    pub async fn configure_retry_policy(&self, policy: Option<RetryPolicy>) {
+        self.settings.lock().await.provider_retry_policy = policy;
+    }
+
+    /// When a generated recipe can't be parsed, make one more provider call asking for strict
+    /// JSON before giving up
+    pub async fn set_strict_recipe_json(&self, enabled: bool) {
+        self.settings.lock().await.strict_recipe_json = enabled;
+    }
+
+    /// When a generated recipe can't be parsed, use the raw response as its instructions instead
+    /// of failing with [`RecipeParseError`](crate::agents::RecipeParseError)
+    pub async fn set_verbatim_recipe_fallback(&self, enabled: bool) {
+        self.settings.lock().await.verbatim_recipe_fallback = enabled;
+    }
+
+    /// Reject generated recipes whose instructions and activities together exceed `limit` characters
+    pub async fn set_max_recipe_size(&self, limit: usize) {
+        self.settings.lock().await.max_recipe_size = limit;
+    }
+}
@@ -133,7 +133,12 @@ fn get_agent_messages(
 
         let has_response_schema = recipe.response.is_some();
         agent
-            .apply_recipe_components(recipe.sub_recipes.clone(), recipe.response.clone(), true)
+            .apply_recipe_components(
+                recipe.sub_recipes.clone(),
+                recipe.response.clone(),
+                recipe.mode,
+                true,
+            )
             .await;
 
         let user_message = Message::user().with_text(text_instruction);
@@ -149,6 +154,9 @@ fn get_agent_messages(
             schedule_id: None,
             max_turns: task_config.max_turns.map(|v| v as u32),
             retry_config: recipe.retry,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
         };
 
         let mut stream = crate::session_context::with_session_id(Some(session_id.clone()), async {
@@ -159,7 +167,11 @@ fn get_agent_messages(
         while let Some(message_result) = stream.next().await {
             match message_result {
                 Ok(AgentEvent::Message(msg)) => conversation.push(msg),
-                Ok(AgentEvent::McpNotification(_)) | Ok(AgentEvent::ModelChange { .. }) => {}
+                Ok(AgentEvent::McpNotification(_))
+                | Ok(AgentEvent::ModelChange { .. })
+                | Ok(AgentEvent::ApprovalRequired { .. })
+                | Ok(AgentEvent::Reasoning(_))
+                | Ok(AgentEvent::Usage(_)) => {}
                 Ok(AgentEvent::HistoryReplaced(updated_conversation)) => {
                     conversation = updated_conversation;
                 }
@@ -0,0 +1,234 @@
+//! A scripted MCP extension for agent unit tests
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use rmcp::model::{
+    CallToolRequestParam, Content, ErrorCode, ErrorData, GetPromptResult, ServerNotification, Tool,
+};
+use serde_json::Value;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::{Agent, ExtensionConfig};
+use crate::conversation::message::Message;
+use crate::model::ModelConfig;
+use crate::providers::base::{Provider, ProviderUsage};
+use crate::providers::errors::ProviderError;
+
+/// Test extension with a single `long_task` tool that emits two log notifications
+struct NotifyingClient {
+    result: Vec<Content>,
+    received_arguments: Arc<std::sync::Mutex<Vec<rmcp::model::JsonObject>>>,
+    /// Error messages returned, in order, before calls start succeeding
+    failures: std::sync::Mutex<Vec<String>>,
+    /// How long each call takes before returning
+    delay: Duration,
+    /// Annotate `long_task` as read-only
+    read_only: bool,
+}
+
+#[async_trait::async_trait]
+impl crate::agents::mcp_client::McpClientTrait for NotifyingClient {
+    async fn list_resources(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<rmcp::model::ListResourcesResult, rmcp::ServiceError> {
+        Err(rmcp::ServiceError::TransportClosed)
+    }
+
+    async fn read_resource(
+        &self,
+        _uri: &str,
+        _cancel_token: CancellationToken,
+    ) -> Result<rmcp::model::ReadResourceResult, rmcp::ServiceError> {
+        Err(rmcp::ServiceError::TransportClosed)
+    }
+
+    async fn list_tools(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<rmcp::model::ListToolsResult, rmcp::ServiceError> {
+        Ok(rmcp::model::ListToolsResult {
+            tools: vec![Tool::new(
+                "long_task".to_string(),
+                "Emits progress while running".to_string(),
+                rmcp::object!({"type": "object", "properties": {}}),
+            )
+            .annotate(rmcp::model::ToolAnnotations {
+                read_only_hint: Some(self.read_only),
+                ..Default::default()
+            })],
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        _name: &str,
+        arguments: Option<rmcp::model::JsonObject>,
+        _cancel_token: CancellationToken,
+    ) -> Result<rmcp::model::CallToolResult, rmcp::ServiceError> {
+        self.received_arguments
+            .lock()
+            .unwrap()
+            .push(arguments.unwrap_or_default());
+        tokio::time::sleep(self.delay).await;
+        let mut failures = self.failures.lock().unwrap();
+        if !failures.is_empty() {
+            return Err(rmcp::ServiceError::McpError(ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                failures.remove(0),
+                None,
+            )));
+        }
+        Ok(rmcp::model::CallToolResult::success(self.result.clone()))
+    }
+
+    async fn list_prompts(
+        &self,
+        _next_cursor: Option<String>,
+        _cancel_token: CancellationToken,
+    ) -> Result<rmcp::model::ListPromptsResult, rmcp::ServiceError> {
+        Err(rmcp::ServiceError::TransportClosed)
+    }
+
+    async fn get_prompt(
+        &self,
+        _name: &str,
+        _arguments: Value,
+        _cancel_token: CancellationToken,
+    ) -> Result<GetPromptResult, rmcp::ServiceError> {
+        Err(rmcp::ServiceError::TransportClosed)
+    }
+
+    async fn subscribe(&self) -> mpsc::Receiver<ServerNotification> {
+        let (tx, rx) = mpsc::channel(4);
+        for step in ["step one", "step two"] {
+            let _ = tx.try_send(ServerNotification::LoggingMessageNotification(
+                rmcp::model::LoggingMessageNotification {
+                    method: rmcp::model::LoggingMessageNotificationMethod,
+                    params: rmcp::model::LoggingMessageNotificationParam {
+                        level: rmcp::model::LoggingLevel::Info,
+                        logger: None,
+                        data: serde_json::json!(step),
+                    },
+                    extensions: Default::default(),
+                },
+            ));
+        }
+        rx
+    }
+
+    fn get_info(&self) -> Option<&rmcp::model::InitializeResult> {
+        None
+    }
+}
+
+/// Returns the arguments of every call the extension receives
+pub(crate) async fn add_notifying_extension(
+    agent: &Agent,
+    result: Vec<Content>,
+) -> Arc<std::sync::Mutex<Vec<rmcp::model::JsonObject>>> {
+    add_failing_extension(agent, result, vec![]).await
+}
+
+/// Like `add_notifying_extension`, but the first calls fail with `failures`
+pub(crate) async fn add_failing_extension(
+    agent: &Agent,
+    result: Vec<Content>,
+    failures: Vec<&str>,
+) -> Arc<std::sync::Mutex<Vec<rmcp::model::JsonObject>>> {
+    add_test_extension(agent, result, failures, Duration::ZERO, false).await
+}
+
+/// Like `add_notifying_extension`, but every call takes `delay` to return
+pub(crate) async fn add_slow_extension(agent: &Agent, result: Vec<Content>, delay: Duration) {
+    add_test_extension(agent, result, vec![], delay, false).await;
+}
+
+pub(crate) async fn add_test_extension(
+    agent: &Agent,
+    result: Vec<Content>,
+    failures: Vec<&str>,
+    delay: Duration,
+    read_only: bool,
+) -> Arc<std::sync::Mutex<Vec<rmcp::model::JsonObject>>> {
+    let received_arguments = Arc::new(std::sync::Mutex::new(Vec::new()));
+    agent
+        .extension_manager
+        .add_client(
+            "notify".to_string(),
+            ExtensionConfig::Builtin {
+                name: "notify".to_string(),
+                display_name: None,
+                description: "notifying test extension".to_string(),
+                timeout: None,
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            },
+            Arc::new(Mutex::new(Box::new(NotifyingClient {
+                result,
+                received_arguments: received_arguments.clone(),
+                failures: std::sync::Mutex::new(failures.into_iter().map(str::to_string).collect()),
+                delay,
+                read_only,
+            }))),
+            None,
+            None,
+        )
+        .await;
+    received_arguments
+}
+
+/// Requests two `notify__long_task` calls, then finishes
+pub(crate) struct TwoLongTasksProvider;
+
+#[async_trait::async_trait]
+impl Provider for TwoLongTasksProvider {
+    fn metadata() -> crate::providers::base::ProviderMetadata {
+        crate::providers::base::ProviderMetadata::empty()
+    }
+
+    fn get_name(&self) -> &str {
+        "two-long-tasks"
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        ModelConfig::new("mock-model").unwrap()
+    }
+
+    async fn complete_with_model(
+        &self,
+        _model_config: &ModelConfig,
+        _system: &str,
+        messages: &[Message],
+        _tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let message = if messages.iter().any(|m| m.is_tool_call()) {
+            Message::assistant().with_text("done")
+        } else {
+            ["call_a", "call_b"]
+                .into_iter()
+                .fold(Message::assistant(), |message, id| {
+                    message.with_tool_request(
+                        id,
+                        Ok(CallToolRequestParam {
+                            name: "notify__long_task".into(),
+                            arguments: Some(rmcp::object!({})),
+                        }),
+                    )
+                })
+        };
+        Ok((
+            message,
+            ProviderUsage::new(
+                "mock-model".to_string(),
+                crate::providers::base::Usage::default(),
+            ),
+        ))
+    }
+}
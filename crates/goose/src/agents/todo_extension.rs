@@ -1,5 +1,6 @@
 use crate::agents::extension::PlatformExtensionContext;
 use crate::agents::mcp_client::{Error, McpClientTrait};
+use crate::agents::todo_store::TodoFileStore;
 use crate::session::extension_data::ExtensionState;
 use crate::session::{extension_data, SessionManager};
 use anyhow::Result;
@@ -27,6 +28,7 @@ pub struct TodoClient {
     info: InitializeResult,
     context: PlatformExtensionContext,
     fallback_content: tokio::sync::RwLock<String>,
+    file_store: Option<TodoFileStore>,
 }
 
 impl TodoClient {
@@ -81,6 +83,7 @@ impl TodoClient {
             info,
             context,
             fallback_content: tokio::sync::RwLock::new(String::new()),
+            file_store: TodoFileStore::from_config(),
         })
     }
 
@@ -88,13 +91,7 @@ impl TodoClient {
         &self,
         arguments: Option<JsonObject>,
     ) -> Result<Vec<Content>, String> {
-        let content = arguments
-            .as_ref()
-            .ok_or("Missing arguments")?
-            .get("content")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing required parameter: content")?
-            .to_string();
+        let content = content_argument(arguments.as_ref())?;
 
         let char_count = content.chars().count();
         let max_chars = std::env::var("GOOSE_TODO_MAX_CHARS")
@@ -109,6 +106,16 @@ impl TodoClient {
             ));
         }
 
+        if let Some(store) = &self.file_store {
+            if let Err(e) = store.write(&content) {
+                tracing::warn!(
+                    "Failed to write todo file {}: {}",
+                    store.path().display(),
+                    e
+                );
+            }
+        }
+
         if let Some(session_id) = &self.context.session_id {
             match SessionManager::get_session(session_id, false).await {
                 Ok(mut session) => {
@@ -174,6 +181,22 @@ impl TodoClient {
     }
 }
 
+/// The `content` argument of a todo write; anything else is rejected so nothing is overwritten
+fn content_argument(arguments: Option<&JsonObject>) -> Result<String, String> {
+    match arguments.and_then(|args| args.get("content")) {
+        Some(Value::String(content)) => Ok(content.clone()),
+        Some(other) => Err(format!(
+            "Parameter 'content' must be a string with the full TODO text, got {}",
+            other
+        )),
+        None => Err(
+            "Missing required parameter 'content': pass the full TODO text to store, \
+             including any items to keep"
+                .to_string(),
+        ),
+    }
+}
+
 #[async_trait]
 impl McpClientTrait for TodoClient {
     async fn list_resources(
@@ -260,3 +283,44 @@ impl McpClientTrait for TodoClient {
         Some(format!("Current tasks and notes:\n{}\n", state.content))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_without_content_is_rejected() {
+        let client = TodoClient::new(PlatformExtensionContext {
+            session_id: None,
+            extension_manager: None,
+            tool_route_manager: None,
+        })
+        .unwrap();
+        *client.fallback_content.write().await = "- [ ] keep me".to_string();
+
+        for arguments in [None, Some(JsonObject::new())] {
+            let result = client
+                .call_tool("todo_write", arguments, CancellationToken::new())
+                .await
+                .unwrap();
+            assert_eq!(result.is_error, Some(true));
+            let text = &result.content[0].as_text().unwrap().text;
+            assert!(
+                text.contains("Missing required parameter 'content'"),
+                "{text}"
+            );
+        }
+
+        let result = client
+            .call_tool(
+                "todo_write",
+                Some(rmcp::object!({"content": 3})),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let text = &result.content[0].as_text().unwrap().text;
+        assert!(text.contains("must be a string"), "{text}");
+        assert_eq!(*client.fallback_content.read().await, "- [ ] keep me");
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::session::extension_data::TodoState;
+
+const TODO_FILE_KEY: &str = "GOOSE_TODO_FILE";
+const TODO_FILE_FORMAT_KEY: &str = "GOOSE_TODO_FILE_FORMAT";
+
+/// On-disk representation of the todo content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoFileFormat {
+    /// `{"content": "..."}`, matching the session extension data
+    #[default]
+    Json,
+    /// The raw todo content, for users who edit the file by hand
+    Text,
+}
+
+/// Mirrors the todo content to a file in the configured format
+#[derive(Debug, Clone)]
+pub struct TodoFileStore {
+    path: PathBuf,
+    format: TodoFileFormat,
+}
+
+impl TodoFileStore {
+    pub fn new(path: impl Into<PathBuf>, format: TodoFileFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+        }
+    }
+
+    /// Build a store from `GOOSE_TODO_FILE` and `GOOSE_TODO_FILE_FORMAT`, if a file is configured
+    pub fn from_config() -> Option<Self> {
+        let config = Config::global();
+        let path = config.get_param::<String>(TODO_FILE_KEY).ok()?;
+        let format = config
+            .get_param::<TodoFileFormat>(TODO_FILE_FORMAT_KEY)
+            .unwrap_or_default();
+        Some(Self::new(path, format))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> TodoFileFormat {
+        self.format
+    }
+
+    pub fn write(&self, content: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = match self.format {
+            TodoFileFormat::Json => serde_json::to_string_pretty(&TodoState::new(content.into()))?,
+            TodoFileFormat::Text => content.to_string(),
+        };
+        fs::write(&self.path, data)
+    }
+
+    /// Read the stored content, or None if nothing has been written yet
+    pub fn read(&self) -> io::Result<Option<String>> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        match self.format {
+            TodoFileFormat::Json => {
+                let state: TodoState = serde_json::from_str(&data)?;
+                Ok(Some(state.content))
+            }
+            TodoFileFormat::Text => Ok(Some(data)),
+        }
+    }
+
+    /// Rewrite the stored content in `format`, returning a store that uses it
+    pub fn migrate(self, format: TodoFileFormat) -> io::Result<Self> {
+        if format == self.format {
+            return Ok(self);
+        }
+        let content = self.read()?;
+        let migrated = Self::new(self.path, format);
+        if let Some(content) = content {
+            migrated.write(&content)?;
+        }
+        Ok(migrated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const CONTENT: &str = "- [x] Write parser\n- [ ] Add \"quoted\" tests\n";
+
+    #[test]
+    fn test_round_trip_in_each_format() {
+        let dir = tempdir().unwrap();
+        for format in [TodoFileFormat::Json, TodoFileFormat::Text] {
+            let store = TodoFileStore::new(dir.path().join(format!("{:?}.todo", format)), format);
+            assert_eq!(store.read().unwrap(), None);
+
+            store.write(CONTENT).unwrap();
+            assert_eq!(store.read().unwrap().as_deref(), Some(CONTENT));
+        }
+
+        let text_on_disk = fs::read_to_string(dir.path().join("Text.todo")).unwrap();
+        assert_eq!(text_on_disk, CONTENT);
+        let json_on_disk: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("Json.todo")).unwrap())
+                .unwrap();
+        assert_eq!(json_on_disk["content"], CONTENT);
+    }
+
+    #[test]
+    fn test_migrate_between_formats() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("todo");
+        let store = TodoFileStore::new(&path, TodoFileFormat::Json);
+        store.write(CONTENT).unwrap();
+
+        let store = store.migrate(TodoFileFormat::Text).unwrap();
+        assert_eq!(store.format(), TodoFileFormat::Text);
+        assert_eq!(fs::read_to_string(&path).unwrap(), CONTENT);
+
+        let store = store.migrate(TodoFileFormat::Json).unwrap();
+        assert_eq!(store.read().unwrap().as_deref(), Some(CONTENT));
+        assert!(serde_json::from_str::<TodoState>(&fs::read_to_string(&path).unwrap()).is_ok());
+    }
+}
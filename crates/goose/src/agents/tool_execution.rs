@@ -1,17 +1,20 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_stream::try_stream;
 use futures::stream::{self, BoxStream};
-use futures::{Stream, StreamExt};
+use futures::{FutureExt, Stream, StreamExt};
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
+use tracing::warn;
 
 use crate::config::permission::PermissionLevel;
 use crate::mcp_utils::ToolResult;
 use crate::permission::Permission;
-use rmcp::model::{Content, ServerNotification};
+use rmcp::model::{CallToolRequestParam, Content, ErrorCode, ErrorData, ServerNotification};
 
 // ToolCallResult combines the result of a tool call with an optional notification stream that
 // can be used to receive notifications from the tool.
@@ -29,9 +32,11 @@ impl From<ToolResult<Vec<Content>>> for ToolCallResult {
     }
 }
 
-use super::agent::{tool_stream, ToolStream};
+use super::agent::{tool_stream, ToolStream, ToolStreamItem};
+use crate::agents::extension_manager::ExtensionManager;
+use crate::agents::types::ToolRetryPolicy;
 use crate::agents::Agent;
-use crate::conversation::message::{Message, ToolRequest};
+use crate::conversation::message::{Message, MessageContent, ToolRequest};
 use crate::session::Session;
 use crate::tool_inspection::get_security_finding_id_from_results;
 
@@ -48,6 +53,221 @@ pub const CHAT_MODE_TOOL_SKIPPED_RESPONSE: &str = "Let the user know the tool ca
                                         2. **Outline Steps** - Break down the steps.\n \
                                         If needed, adjust the explanation based on user preferences or questions.";
 
+pub const EMPTY_TOOL_RESULT_PLACEHOLDER: &str = "The tool completed but returned no content.";
+
+pub const CANCELLED_RESPONSE: &str = "Tool call was cancelled";
+
+/// Error code of a tool call stopped for exceeding its timeout, from the range JSON-RPC
+/// leaves to implementations
+pub const TOOL_TIMEOUT_ERROR_CODE: ErrorCode = ErrorCode(-32001);
+
+/// Replace each tool result in `message` that is identical to one already in `history`
+/// with a short reference to the earlier call, keeping repeated outputs out of the context.
+pub(crate) fn dedup_tool_response<'a>(
+    history: impl IntoIterator<Item = &'a Message>,
+    mut message: Message,
+) -> Message {
+    let history: Vec<&Message> = history.into_iter().collect();
+    for content in message.content.iter_mut() {
+        let MessageContent::ToolResponse(response) = content else {
+            continue;
+        };
+        let earlier_id = match &response.tool_result {
+            Ok(result) if !result.is_empty() => history
+                .iter()
+                .flat_map(|m| m.content.iter())
+                .find_map(|c| match c {
+                    MessageContent::ToolResponse(earlier)
+                        if earlier.id != response.id
+                            && matches!(&earlier.tool_result, Ok(prev) if prev == result) =>
+                    {
+                        Some(earlier.id.clone())
+                    }
+                    _ => None,
+                }),
+            _ => None,
+        };
+        if let Some(earlier_id) = earlier_id {
+            response.tool_result = Ok(vec![Content::text(format!(
+                "Output identical to tool call {}; see that result above.",
+                earlier_id
+            ))]);
+        }
+    }
+    message
+}
+
+/// Merge tool streams so each priority group runs to completion before the next lower one
+/// starts. Streams that share a priority run concurrently.
+pub(crate) fn prioritized_tool_streams<S>(
+    streams: Vec<(i32, S)>,
+) -> impl Stream<Item = S::Item> + Unpin
+where
+    S: Stream + Unpin,
+{
+    let mut groups: BTreeMap<Reverse<i32>, Vec<S>> = BTreeMap::new();
+    for (priority, stream) in streams {
+        groups.entry(Reverse(priority)).or_default().push(stream);
+    }
+    stream::iter(groups.into_values().map(stream::select_all)).flatten()
+}
+
+/// Re-dispatch an extension tool call while it fails with a retryable error, up to the
+/// policy's limit. Notifications are only forwarded from the first attempt.
+pub(crate) fn retry_tool_call(
+    first_attempt: ToolCallResult,
+    policy: ToolRetryPolicy,
+    extension_manager: Arc<ExtensionManager>,
+    tool_call: CallToolRequestParam,
+    cancellation_token: CancellationToken,
+) -> ToolCallResult {
+    let result = async move {
+        let mut output = first_attempt.result.await;
+        let mut retries = 0;
+        while let Err(error) = &output {
+            if retries >= policy.max_retries
+                || !policy.is_retryable(error)
+                || cancellation_token.is_cancelled()
+            {
+                break;
+            }
+            retries += 1;
+            warn!(
+                "Retrying {} after transient error ({}/{}): {}",
+                tool_call.name, retries, policy.max_retries, error.message
+            );
+            output = match extension_manager
+                .dispatch_tool_call(tool_call.clone(), cancellation_token.clone())
+                .await
+            {
+                Ok(result) => result.result.await,
+                Err(e) => Err(ErrorData::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    e.to_string(),
+                    None,
+                )),
+            };
+        }
+        output
+    };
+    ToolCallResult {
+        result: Box::new(result.boxed()),
+        notification_stream: first_attempt.notification_stream,
+    }
+}
+
+/// Fail a tool call that has not finished within `limit`, so a hung extension cannot stall
+/// the reply
+pub(crate) fn with_tool_timeout(
+    call: ToolCallResult,
+    limit: Duration,
+    tool_name: String,
+) -> ToolCallResult {
+    let result = call.result;
+    let result = async move {
+        tokio::time::timeout(limit, result)
+            .await
+            .unwrap_or_else(|_| {
+                warn!("Tool {} exceeded its {:?} timeout", tool_name, limit);
+                Err(ErrorData::new(
+                    TOOL_TIMEOUT_ERROR_CODE,
+                    format!("Tool '{}' exceeded {:?} and was stopped", tool_name, limit),
+                    None,
+                ))
+            })
+    };
+    ToolCallResult {
+        result: Box::new(result.boxed()),
+        notification_stream: call.notification_stream,
+    }
+}
+
+/// A token cancelled along with `parent`, or once `limit` has passed
+pub(crate) fn deadline_token(
+    parent: Option<CancellationToken>,
+    limit: Duration,
+) -> CancellationToken {
+    let token = parent.map(|t| t.child_token()).unwrap_or_default();
+    let timer = token.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::time::sleep(limit) => timer.cancel(),
+            _ = timer.cancelled() => {}
+        }
+    });
+    token
+}
+
+/// Tool calls currently running in a reply, keyed by request id
+#[derive(Default)]
+pub(crate) struct InFlightTools {
+    running: std::sync::Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl InFlightTools {
+    /// Register `stream` under `request_id` until it finishes. If the call is cancelled,
+    /// the stream ends early with an error result.
+    pub(crate) fn track(self: &Arc<Self>, request_id: String, stream: ToolStream) -> ToolStream {
+        let token = CancellationToken::new();
+        self.running
+            .lock()
+            .unwrap()
+            .insert(request_id.clone(), token.clone());
+        let guard = InFlightGuard {
+            registry: self.clone(),
+            request_id,
+        };
+
+        Box::pin(async_stream::stream! {
+            let _guard = guard;
+            let mut stream = stream;
+            loop {
+                tokio::select! {
+                    item = stream.next() => match item {
+                        Some(item) => yield item,
+                        None => break,
+                    },
+                    _ = token.cancelled() => {
+                        yield ToolStreamItem::Result(Err(ErrorData::new(
+                            ErrorCode::INTERNAL_ERROR,
+                            CANCELLED_RESPONSE.to_string(),
+                            None,
+                        )));
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    pub(crate) fn request_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.running.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    pub(crate) fn cancel_all(&self) -> usize {
+        let running = self.running.lock().unwrap();
+        for token in running.values() {
+            token.cancel();
+        }
+        running.len()
+    }
+}
+
+struct InFlightGuard {
+    registry: Arc<InFlightTools>,
+    request_id: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.registry.running.lock() {
+            running.remove(&self.request_id);
+        }
+    }
+}
+
 impl Agent {
     pub(crate) fn handle_approval_tool_requests<'a>(
         &'a self,
@@ -59,8 +279,19 @@ impl Agent {
         inspection_results: &'a [crate::tool_inspection::InspectionResult],
     ) -> BoxStream<'a, anyhow::Result<Message>> {
         try_stream! {
-        for request in tool_requests.iter() {
-            if let Ok(tool_call) = request.tool_call.clone() {
+        let limit = self.settings.lock().await.max_pending_confirmations.max(1);
+        let mut queued: VecDeque<(&ToolRequest, CallToolRequestParam)> = tool_requests
+            .iter()
+            .filter_map(|request| request.tool_call.clone().ok().map(|call| (request, call)))
+            .collect();
+        let mut pending: HashMap<String, (&ToolRequest, CallToolRequestParam)> = HashMap::new();
+
+        while !queued.is_empty() || !pending.is_empty() {
+            // Surface queued requests until `limit` confirmations are awaiting an answer
+            while pending.len() < limit {
+                let Some((request, tool_call)) = queued.pop_front() else {
+                    break;
+                };
                 // Find the corresponding inspection result for this tool request
                 let security_message = inspection_results.iter()
                     .find(|result| result.tool_request_id == request.id)
@@ -80,54 +311,69 @@ impl Agent {
                         security_message,
                     )
                     .user_only();
+                pending.insert(request.id.clone(), (request, tool_call));
                 yield confirmation;
+            }
 
-                let mut rx = self.confirmation_rx.lock().await;
-                while let Some((req_id, confirmation)) = rx.recv().await {
-                    if req_id == request.id {
-                        // Log user decision if this was a security alert
-                        if let Some(finding_id) = get_security_finding_id_from_results(&request.id, inspection_results) {
-                            tracing::info!(
-                                counter.goose.prompt_injection_user_decisions = 1,
-                                decision = ?confirmation.permission,
-                                finding_id = %finding_id,
-                                "User security decision"
-                            );
-                        }
+            // Stop waiting for the user once the reply is cancelled; unanswered requests are
+            // given a cancellation response by the reply loop
+            let received = tokio::select! {
+                received = async { self.confirmation_rx.lock().await.recv().await } => received,
+                _ = crate::utils::token_cancelled(cancellation_token.clone()) => None,
+            };
+            let Some((req_id, confirmation)) = received else {
+                break;
+            };
+            let Some((request, tool_call)) = pending.remove(&req_id) else {
+                continue;
+            };
 
-                        if confirmation.permission == Permission::AllowOnce || confirmation.permission == Permission::AlwaysAllow {
-                            let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone(), cancellation_token.clone(), session).await;
-                            let mut futures = tool_futures.lock().await;
-
-                            futures.push((req_id, match tool_result {
-                                Ok(result) => tool_stream(
-                                    result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
-                                    result.result,
-                                ),
-                                Err(e) => tool_stream(
-                                    Box::new(stream::empty()),
-                                    futures::future::ready(Err(e)),
-                                ),
-                            }));
-
-                            // Update the shared permission manager when user selects "Always Allow"
-                            if confirmation.permission == Permission::AlwaysAllow {
-                                self.tool_inspection_manager
-                                    .update_permission_manager(&tool_call.name, PermissionLevel::AlwaysAllow)
-                                    .await;
-                            }
-                        } else {
-                            // User declined - update the specific response message for this request
-                            if let Some(response_msg) = request_to_response_map.get(&request.id) {
-                                let mut response = response_msg.lock().await;
-                                *response = response.clone().with_tool_response(
-                                    request.id.clone(),
-                                    Ok(vec![Content::text(DECLINED_RESPONSE)]),
-                                );
-                            }
-                        }
-                        break; // Exit the loop once the matching `req_id` is found
-                    }
+            // Log user decision if this was a security alert
+            if let Some(finding_id) = get_security_finding_id_from_results(&request.id, inspection_results) {
+                tracing::info!(
+                    counter.goose.prompt_injection_user_decisions = 1,
+                    decision = ?confirmation.permission,
+                    finding_id = %finding_id,
+                    "User security decision"
+                );
+            }
+
+            if matches!(
+                confirmation.permission,
+                Permission::AllowOnce | Permission::AlwaysAllow | Permission::AlwaysAllowForSession
+            ) {
+                let (req_id, tool_result) = self.dispatch_tool_call(tool_call.clone(), request.id.clone(), cancellation_token.clone(), session).await;
+                let mut futures = tool_futures.lock().await;
+
+                futures.push((req_id, match tool_result {
+                    Ok(result) => tool_stream(
+                        result.notification_stream.unwrap_or_else(|| Box::new(stream::empty())),
+                        result.result,
+                    ),
+                    Err(e) => tool_stream(
+                        Box::new(stream::empty()),
+                        futures::future::ready(Err(e)),
+                    ),
+                }));
+
+                // Update the shared permission manager when user selects "Always Allow"
+                if confirmation.permission == Permission::AlwaysAllow {
+                    self.tool_inspection_manager
+                        .update_permission_manager(&tool_call.name, PermissionLevel::AlwaysAllow)
+                        .await;
+                } else if confirmation.permission == Permission::AlwaysAllowForSession {
+                    self.tool_inspection_manager
+                        .allow_tool_for_session(&session.id, &tool_call.name)
+                        .await;
+                }
+            } else {
+                // User declined - update the specific response message for this request
+                if let Some(response_msg) = request_to_response_map.get(&request.id) {
+                    let mut response = response_msg.lock().await;
+                    *response = response.clone().with_tool_response(
+                        request.id.clone(),
+                        Ok(vec![Content::text(DECLINED_RESPONSE)]),
+                    );
                 }
             }
         }
@@ -158,3 +404,1004 @@ impl Agent {
         .boxed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::extension_manager_extension::MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE;
+    use crate::agents::platform_tools::PLATFORM_MANAGE_SCHEDULE_TOOL_NAME;
+    use crate::agents::recipe_tools::dynamic_task_tools::DYNAMIC_TASK_TOOL_NAME_PREFIX;
+    use crate::agents::test_extension::{
+        add_failing_extension, add_notifying_extension, add_slow_extension, add_test_extension,
+        TwoLongTasksProvider,
+    };
+    use crate::agents::tool_history::{ToolHistoryFilter, ToolOutcome};
+    use crate::agents::types::{LargeResponseStrategy, ToolPolicy, ToolSandbox, TruncationPolicy};
+    use crate::agents::{
+        AgentEvent, CompactSerializer, ExtensionConfig, NotificationFileSink, SessionConfig,
+        ToolExecutor,
+    };
+    use crate::config::GooseMode;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{Provider, ProviderUsage};
+    use crate::providers::errors::ProviderError;
+    use crate::session::SessionManager;
+    use anyhow::{anyhow, Result};
+    use chrono::Utc;
+    use rmcp::model::Tool;
+    use serde_json::Value;
+
+    #[test]
+    fn test_dedup_tool_response_references_identical_output() {
+        let listing = vec![Content::text("Cargo.toml\nsrc/")];
+        let history = vec![
+            Message::assistant().with_text("Listing files"),
+            Message::user().with_tool_response("call_1", Ok(listing.clone())),
+        ];
+
+        let repeated = Message::user().with_tool_response("call_2", Ok(listing.clone()));
+        let deduped = dedup_tool_response(&history, repeated);
+        let MessageContent::ToolResponse(response) = &deduped.content[0] else {
+            panic!("Expected a tool response");
+        };
+        let content = response.tool_result.as_ref().unwrap();
+        assert_eq!(content.len(), 1);
+        let text = &content[0].as_text().unwrap().text;
+        assert!(text.contains("call_1"));
+        assert!(!text.contains("Cargo.toml"));
+
+        let different =
+            Message::user().with_tool_response("call_3", Ok(vec![Content::text("README.md")]));
+        assert_eq!(dedup_tool_response(&history, different.clone()), different);
+    }
+
+    fn confirmation_id(message: &Message) -> String {
+        match &message.content[0] {
+            MessageContent::ActionRequired(action) => match &action.data {
+                crate::conversation::message::ActionRequiredData::ToolConfirmation {
+                    id, ..
+                } => id.clone(),
+                other => panic!("Expected a tool confirmation, got {:?}", other),
+            },
+            other => panic!("Expected an action required message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_requests_are_presented_one_at_a_time() {
+        use crate::permission::permission_confirmation::PrincipalType;
+        use crate::permission::PermissionConfirmation;
+        use std::time::Duration;
+
+        let agent = Agent::new();
+        agent.set_max_pending_confirmations(1).await;
+
+        let requests: Vec<ToolRequest> = (1..=3)
+            .map(|i| ToolRequest {
+                id: format!("req_{}", i),
+                tool_call: Ok(CallToolRequestParam {
+                    name: "developer__shell".into(),
+                    arguments: None,
+                }),
+                thought_signature: None,
+            })
+            .collect();
+        let responses: HashMap<String, Arc<Mutex<Message>>> = requests
+            .iter()
+            .map(|r| (r.id.clone(), Arc::new(Mutex::new(Message::user()))))
+            .collect();
+        let session = Session::default();
+
+        let mut stream = agent.handle_approval_tool_requests(
+            &requests,
+            Arc::new(Mutex::new(Vec::new())),
+            &responses,
+            None,
+            &session,
+            &[],
+        );
+
+        for expected in ["req_1", "req_2", "req_3"] {
+            let message = stream.next().await.unwrap().unwrap();
+            assert_eq!(confirmation_id(&message), expected);
+            assert!(
+                tokio::time::timeout(Duration::from_millis(50), stream.next())
+                    .await
+                    .is_err(),
+                "next confirmation shown before {} was answered",
+                expected
+            );
+            agent
+                .handle_confirmation(
+                    expected.to_string(),
+                    PermissionConfirmation {
+                        principal_type: PrincipalType::Tool,
+                        permission: Permission::DenyOnce,
+                    },
+                )
+                .await;
+        }
+        assert!(stream.next().await.is_none());
+        drop(stream);
+
+        for response in responses.values() {
+            let response = response.lock().await;
+            let MessageContent::ToolResponse(declined) = &response.content[0] else {
+                panic!("Expected a tool response");
+            };
+            let content = declined.tool_result.as_ref().unwrap();
+            assert_eq!(content[0].as_text().unwrap().text, DECLINED_RESPONSE);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_result_transform_applied_to_tool_response() -> Result<()> {
+        let agent = Agent::new();
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "todo".to_string(),
+                description: "todo".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+
+        agent
+            .set_result_transform(Arc::new(|_tool_name, content| {
+                content
+                    .into_iter()
+                    .map(|c| match c.as_text() {
+                        Some(text) => Content::text(text.text.to_uppercase()),
+                        None => c,
+                    })
+                    .collect()
+            }))
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "result-transform-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let tool_call = CallToolRequestParam {
+            name: "todo__todo_write".into(),
+            arguments: Some(rmcp::object!({"content": "buy milk"})),
+        };
+        let (request_id, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let output = result.map_err(|e| anyhow!(e.message))?.result.await;
+
+        let message = Message::user().with_tool_response(request_id, output);
+        let MessageContent::ToolResponse(response) = &message.content[0] else {
+            panic!("Expected a tool response");
+        };
+        let content = response.tool_result.as_ref().expect("tool should succeed");
+        assert_eq!(
+            content[0].as_text().map(|t| t.text.as_str()),
+            Some("UPDATED (8 CHARS)")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_only_runs_read_only_allowed_tools() -> Result<()> {
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "sandbox-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+
+        for read_only in [false, true] {
+            let agent = Agent::new();
+            let received_arguments = add_test_extension(
+                &agent,
+                vec![Content::text("done")],
+                vec![],
+                Duration::ZERO,
+                read_only,
+            )
+            .await;
+            agent.set_sandbox(Some(ToolSandbox::new(["notify"]))).await;
+
+            let (_, result) = agent
+                .dispatch_tool_call(tool_call.clone(), "call_1".to_string(), None, &session)
+                .await;
+            match result {
+                Ok(result) => {
+                    assert!(read_only);
+                    result.result.await.map_err(|e| anyhow!(e.message))?;
+                    assert_eq!(received_arguments.lock().unwrap().len(), 1);
+                }
+                Err(e) => {
+                    assert!(!read_only);
+                    assert!(e.message.contains("not annotated read-only"));
+                    assert!(received_arguments.lock().unwrap().is_empty());
+                }
+            }
+        }
+
+        let agent = Agent::new();
+        add_test_extension(&agent, vec![], vec![], Duration::ZERO, true).await;
+        agent
+            .set_sandbox(Some(ToolSandbox::new(["developer"])))
+            .await;
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_2".to_string(), None, &session)
+            .await;
+        assert!(result.err().unwrap().message.contains("sandbox allowlist"));
+
+        // The owning extension is found by its prefix, even when aliased
+        let agent = Agent::new();
+        add_test_extension(
+            &agent,
+            vec![Content::text("done")],
+            vec![],
+            Duration::ZERO,
+            true,
+        )
+        .await;
+        agent
+            .extension_manager
+            .set_tool_prefix_alias("notify", Some("n"))
+            .await?;
+        agent.set_sandbox(Some(ToolSandbox::new(["notify"]))).await;
+        let aliased_call = CallToolRequestParam {
+            name: "n__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(aliased_call, "call_3".to_string(), None, &session)
+            .await;
+        result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_policy_hides_and_refuses_blocked_tools() -> Result<()> {
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-policy-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let agent = Agent::new();
+        let received_arguments = add_notifying_extension(&agent, vec![Content::text("done")]).await;
+        agent
+            .add_extension(ExtensionConfig::Platform {
+                name: "extensionmanager".to_string(),
+                description: "Extension Manager".to_string(),
+                bundled: None,
+                available_tools: vec![],
+                required: false,
+            })
+            .await?;
+
+        agent
+            .set_tool_policy(ToolPolicy::default().with_denied([
+                "notify__*",
+                PLATFORM_MANAGE_SCHEDULE_TOOL_NAME,
+                MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE,
+            ]))
+            .await;
+        let names: Vec<String> = agent
+            .list_tools(None)
+            .await
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert!(!names.iter().any(|name| name == "notify__long_task"));
+        assert!(!names
+            .iter()
+            .any(|name| name == PLATFORM_MANAGE_SCHEDULE_TOOL_NAME));
+        assert!(!names
+            .iter()
+            .any(|name| name == MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE));
+        assert!(names
+            .iter()
+            .any(|name| name == DYNAMIC_TASK_TOOL_NAME_PREFIX));
+
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call.clone(), "call_1".to_string(), None, &session)
+            .await;
+        assert!(result
+            .err()
+            .unwrap()
+            .message
+            .contains("blocked by the tool policy"));
+        assert!(received_arguments.lock().unwrap().is_empty());
+        let manage_extensions = CallToolRequestParam {
+            name: MANAGE_EXTENSIONS_TOOL_NAME_COMPLETE.into(),
+            arguments: Some(rmcp::object!({"action": "disable", "extension_name": "notify"})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(manage_extensions, "call_3".to_string(), None, &session)
+            .await;
+        assert!(result
+            .err()
+            .unwrap()
+            .message
+            .contains("blocked by the tool policy"));
+        assert!(agent
+            .list_extensions()
+            .await
+            .contains(&"notify".to_string()));
+
+        agent
+            .set_tool_policy(ToolPolicy::default().with_allowed(["notify__long_task"]))
+            .await;
+        let tools = agent.list_tools(None).await;
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "notify__long_task");
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_2".to_string(), None, &session)
+            .await;
+        result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+        assert_eq!(received_arguments.lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tail_truncation_policy_keeps_end_of_log_output() -> Result<()> {
+        let log: String = (0..20_000)
+            .map(|i| format!("line {i}: request served\n"))
+            .chain(std::iter::once("FATAL: disk full".to_string()))
+            .collect();
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![Content::text(log.clone())]).await;
+        agent
+            .set_truncation_policy("notify__long_task", TruncationPolicy::Tail)
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "truncation-policy-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let content = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        let text = &content[0].as_text().unwrap().text;
+        assert!(text.ends_with("FATAL: disk full"));
+        assert!(text.contains("characters omitted"));
+        assert!(!text.contains("line 0: request served"));
+        assert!(text.len() < log.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_strategy_keeps_large_result_intact() -> Result<()> {
+        let checksum = "f".repeat(1_048_576);
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![Content::text(checksum.clone())]).await;
+        agent
+            .configure_large_response("notify__long_task", LargeResponseStrategy::Passthrough)
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "large-response-strategy-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let content = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        assert_eq!(content[0].as_text().unwrap().text, checksum);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_retried_after_retryable_error() -> Result<()> {
+        let agent = Agent::new();
+        let received = add_failing_extension(
+            &agent,
+            vec![Content::text("deployed")],
+            vec!["connection reset by peer"],
+        )
+        .await;
+        agent
+            .set_tool_retry_policy(
+                "notify__long_task",
+                ToolRetryPolicy {
+                    retryable_errors: vec!["connection reset".to_string()],
+                    max_retries: 2,
+                },
+            )
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-retry-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let content = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        assert_eq!(content[0].as_text().unwrap().text, "deployed");
+        assert_eq!(received.lock().unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_history_filters_by_outcome() -> Result<()> {
+        let agent = Agent::new();
+        add_failing_extension(&agent, vec![Content::text("done")], vec!["boom", "bang"]).await;
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-history-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let started = Utc::now();
+        for i in 0..3 {
+            let tool_call = CallToolRequestParam {
+                name: "notify__long_task".into(),
+                arguments: Some(rmcp::object!({})),
+            };
+            let (_, result) = agent
+                .dispatch_tool_call(tool_call, format!("call_{}", i), None, &session)
+                .await;
+            let _ = result.map_err(|e| anyhow!(e.message))?.result.await;
+        }
+
+        let errors =
+            agent.tool_history(ToolHistoryFilter::default().with_outcome(ToolOutcome::Err));
+        let failed: Vec<(&str, Option<&str>)> = errors
+            .iter()
+            .map(|e| (e.request_id.as_str(), e.error.as_deref()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![("call_0", Some("boom")), ("call_1", Some("bang"))]
+        );
+
+        let succeeded = agent.tool_history(
+            ToolHistoryFilter::default()
+                .with_tool_name("notify__long_task")
+                .with_outcome(ToolOutcome::Ok),
+        );
+        assert_eq!(succeeded.len(), 1);
+        assert_eq!(succeeded[0].request_id, "call_2");
+
+        let later = started + chrono::Duration::hours(1);
+        assert!(agent
+            .tool_history(
+                ToolHistoryFilter::default().between(later, later + chrono::Duration::hours(1))
+            )
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hung_tool_fails_after_its_timeout() -> Result<()> {
+        let agent = Agent::new();
+        add_slow_extension(
+            &agent,
+            vec![Content::text("finally")],
+            Duration::from_secs(3600),
+        )
+        .await;
+        agent
+            .configure_tool_timeouts(
+                HashMap::from([("notify__long_task".to_string(), Duration::from_millis(50))]),
+                Some(Duration::from_secs(7200)),
+            )
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-timeout-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let error = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .expect_err("the call should time out");
+
+        assert_eq!(error.code, TOOL_TIMEOUT_ERROR_CODE);
+        assert_eq!(
+            error.message,
+            "Tool 'notify__long_task' exceeded 50ms and was stopped"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compact_serializer_shrinks_json_tool_results() -> Result<()> {
+        let agent = Agent::new();
+        let verbose = serde_json::to_string_pretty(&serde_json::json!({
+            "description": "nightly build",
+        }))?;
+        add_notifying_extension(&agent, vec![Content::text(verbose)]).await;
+        agent
+            .set_compact_serializer(Some(Arc::new(
+                CompactSerializer::new().with_abbreviation("description", "d"),
+            )))
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "compact-serializer-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let content = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        let texts: Vec<&str> = content
+            .iter()
+            .filter_map(|c| c.as_text().map(|t| t.text.as_str()))
+            .collect();
+        assert_eq!(texts[0], r#"{"d":"nightly build"}"#);
+        assert!(texts[1].contains("d=description"));
+        Ok(())
+    }
+
+    struct QueueExecutor {
+        dispatched: std::sync::Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ToolExecutor for QueueExecutor {
+        fn handles(&self, tool_call: &CallToolRequestParam) -> bool {
+            tool_call.name.starts_with("notify__")
+        }
+
+        async fn execute(
+            &self,
+            tool_call: CallToolRequestParam,
+            session_id: &str,
+        ) -> ToolResult<Vec<Content>> {
+            self.dispatched
+                .lock()
+                .unwrap()
+                .push((tool_call.name.to_string(), session_id.to_string()));
+            Ok(vec![Content::text("ran on worker")])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_executor_runs_handed_off_calls() -> Result<()> {
+        let agent = Agent::new();
+        let received = add_notifying_extension(&agent, vec![Content::text("ran inline")]).await;
+        let executor = Arc::new(QueueExecutor {
+            dispatched: std::sync::Mutex::new(Vec::new()),
+        });
+        agent.set_tool_executor(Some(executor.clone())).await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-executor-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let content = result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        assert_eq!(content[0].as_text().unwrap().text, "ran on worker");
+        assert_eq!(
+            *executor.dispatched.lock().unwrap(),
+            vec![("notify__long_task".to_string(), session.id.clone())]
+        );
+        assert!(received.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    /// Never finishes, so calls stay in flight until cancelled
+    struct StalledExecutor;
+
+    #[async_trait::async_trait]
+    impl ToolExecutor for StalledExecutor {
+        async fn execute(
+            &self,
+            _tool_call: CallToolRequestParam,
+            _session_id: &str,
+        ) -> ToolResult<Vec<Content>> {
+            std::future::pending().await
+        }
+    }
+
+    /// Records the order calls finish in; `ops__cancel` takes longer than the others
+    struct OrderRecordingExecutor {
+        finished: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ToolExecutor for OrderRecordingExecutor {
+        async fn execute(
+            &self,
+            tool_call: CallToolRequestParam,
+            _session_id: &str,
+        ) -> ToolResult<Vec<Content>> {
+            if tool_call.name == "ops__cancel" {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            self.finished
+                .lock()
+                .unwrap()
+                .push(tool_call.name.to_string());
+            Ok(vec![Content::text("ok")])
+        }
+    }
+
+    /// Requests `ops__migrate` then `ops__cancel` in one turn, then finishes
+    struct MigrateThenCancelProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for MigrateThenCancelProvider {
+        fn metadata() -> crate::providers::base::ProviderMetadata {
+            crate::providers::base::ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "migrate-then-cancel"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new("mock-model").unwrap()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            let message = if messages.iter().any(|m| m.is_tool_call()) {
+                Message::assistant().with_text("done")
+            } else {
+                ["ops__migrate", "ops__cancel"].into_iter().fold(
+                    Message::assistant(),
+                    |message, name| {
+                        message.with_tool_request(
+                            format!("call_{name}"),
+                            Ok(CallToolRequestParam {
+                                name: name.into(),
+                                arguments: Some(rmcp::object!({})),
+                            }),
+                        )
+                    },
+                )
+            };
+            Ok((
+                message,
+                ProviderUsage::new(
+                    "mock-model".to_string(),
+                    crate::providers::base::Usage::default(),
+                ),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_tool_runs_before_earlier_requests() -> Result<()> {
+        let agent = Agent::new();
+        let executor = Arc::new(OrderRecordingExecutor {
+            finished: std::sync::Mutex::new(Vec::new()),
+        });
+        agent.set_tool_executor(Some(executor.clone())).await;
+        agent.set_tool_priority("ops__cancel", 10).await;
+        agent.set_goose_mode_override(Some(GooseMode::Auto)).await;
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "tool-priority-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        agent
+            .update_provider(Arc::new(MigrateThenCancelProvider), &session.id)
+            .await?;
+
+        let session_config = SessionConfig {
+            id: session.id,
+            schedule_id: None,
+            max_turns: None,
+            retry_config: None,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
+        };
+        let mut stream = agent
+            .reply(Message::user().with_text("migrate"), session_config, None)
+            .await?;
+        let mut responses = Vec::new();
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::Message(message) = event? {
+                for content in message.content {
+                    if let MessageContent::ToolResponse(response) = content {
+                        responses.push(response.id);
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            *executor.finished.lock().unwrap(),
+            vec!["ops__cancel", "ops__migrate"]
+        );
+        // Results still land on the request that produced them
+        assert_eq!(responses, vec!["call_ops__migrate", "call_ops__cancel"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_tools_ends_in_flight_calls() -> Result<()> {
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![]).await;
+        agent
+            .set_tool_executor(Some(Arc::new(StalledExecutor)))
+            .await;
+        agent.set_goose_mode_override(Some(GooseMode::Auto)).await;
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "in-flight-tools-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+        agent
+            .update_provider(Arc::new(TwoLongTasksProvider), &session.id)
+            .await?;
+
+        let session_config = SessionConfig {
+            id: session.id,
+            schedule_id: None,
+            max_turns: None,
+            retry_config: None,
+            user_id: None,
+            thinking_budget: None,
+            reply_deadline: None,
+        };
+        let reply = async {
+            let mut stream = agent
+                .reply(Message::user().with_text("run both"), session_config, None)
+                .await?;
+            let mut errors = Vec::new();
+            while let Some(event) = stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    for content in message.content {
+                        if let MessageContent::ToolResponse(response) = content {
+                            if let Err(e) = response.tool_result {
+                                errors.push(e.message.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+            anyhow::Ok(errors)
+        };
+        let cancel = async {
+            loop {
+                let in_flight = agent.in_flight_tools();
+                if in_flight.len() == 2 {
+                    assert_eq!(in_flight, vec!["call_a", "call_b"]);
+                    assert_eq!(agent.cancel_all_tools(), 2);
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        let (errors, ()) = tokio::time::timeout(Duration::from_secs(10), async {
+            tokio::join!(reply, cancel)
+        })
+        .await?;
+        assert_eq!(errors?, vec!["Tool call was cancelled"; 2]);
+        assert!(agent.in_flight_tools().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_argument_transform_applied_before_dispatch() -> Result<()> {
+        let agent = Agent::new();
+        let received = add_notifying_extension(&agent, vec![Content::text("done")]).await;
+        agent
+            .set_argument_transform(
+                "notify__long_task",
+                Arc::new(|mut arguments, working_dir| {
+                    if let Some(Value::String(path)) = arguments.get("path") {
+                        let resolved = working_dir.join(path).to_string_lossy().to_string();
+                        arguments.insert("path".to_string(), Value::String(resolved));
+                    }
+                    arguments
+                }),
+            )
+            .await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::from("/work/project"),
+            "argument-transform-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: Some(rmcp::object!({"path": "notes/today.md"})),
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        result
+            .map_err(|e| anyhow!(e.message))?
+            .result
+            .await
+            .map_err(|e| anyhow!(e.message))?;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].get("path"),
+            Some(&Value::String(
+                std::path::Path::new("/work/project")
+                    .join("notes/today.md")
+                    .to_string_lossy()
+                    .to_string()
+            ))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notifications_written_to_sink() -> Result<()> {
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![Content::text("done")]).await;
+
+        let log_dir = tempfile::tempdir()?;
+        let sink = NotificationFileSink::new(log_dir.path());
+        agent.set_notification_sink(Some(sink.clone())).await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "notification-sink-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: None,
+        };
+        let (_, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let result = result.map_err(|e| anyhow!(e.message))?;
+        let notifications: Vec<ServerNotification> = result
+            .notification_stream
+            .expect("extension tools expose notifications")
+            .collect()
+            .await;
+        assert_eq!(notifications.len(), 2);
+
+        let logged = std::fs::read_to_string(sink.path_for(&session.id))?;
+        let lines: Vec<Value> = logged
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["request_id"], "call_1");
+        assert_eq!(lines[0]["notification"]["params"]["data"], "step one");
+        assert_eq!(lines[1]["notification"]["params"]["data"], "step two");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_tool_result_gets_placeholder() -> Result<()> {
+        let agent = Agent::new();
+        add_notifying_extension(&agent, vec![]).await;
+
+        let session = SessionManager::create_session(
+            std::path::PathBuf::default(),
+            "empty-result-test".to_string(),
+            crate::session::SessionType::Hidden,
+        )
+        .await?;
+
+        let tool_call = CallToolRequestParam {
+            name: "notify__long_task".into(),
+            arguments: None,
+        };
+        let (request_id, result) = agent
+            .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+            .await;
+        let output = result.map_err(|e| anyhow!(e.message))?.result.await;
+
+        let message = Message::user().with_tool_response(request_id, output);
+        let MessageContent::ToolResponse(response) = &message.content[0] else {
+            panic!("Expected a tool response");
+        };
+        let content = response.tool_result.as_ref().expect("tool should succeed");
+        assert_eq!(content.len(), 1);
+        assert_eq!(
+            content[0].as_text().map(|t| t.text.as_str()),
+            Some(EMPTY_TOOL_RESULT_PLACEHOLDER)
+        );
+
+        Ok(())
+    }
+}
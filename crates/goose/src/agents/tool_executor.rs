@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use rmcp::model::{CallToolRequestParam, Content};
+
+use crate::mcp_utils::ToolResult;
+
+/// Runs extension tool calls outside the reply loop, e.g. by pushing them onto a worker queue
+/// and awaiting the result. Without an executor, tools run inline through their extension.
+#[async_trait]
+pub trait ToolExecutor: Send + Sync {
+    /// Whether this executor takes `tool_call`; calls it declines run inline
+    fn handles(&self, _tool_call: &CallToolRequestParam) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        tool_call: CallToolRequestParam,
+        session_id: &str,
+    ) -> ToolResult<Vec<Content>>;
+}
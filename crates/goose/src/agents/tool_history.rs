@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Most recent tool calls kept in the history; older ones are dropped
+const MAX_TOOL_HISTORY_ENTRIES: usize = 1000;
+
+/// Whether a tool call returned a result or an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolOutcome {
+    Ok,
+    Err,
+}
+
+/// A finished tool call, as returned by [`crate::agents::Agent::tool_history`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolHistoryEntry {
+    pub request_id: String,
+    pub session_id: String,
+    pub tool_name: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// The error message, if the call failed
+    pub error: Option<String>,
+}
+
+impl ToolHistoryEntry {
+    pub fn outcome(&self) -> ToolOutcome {
+        match self.error {
+            Some(_) => ToolOutcome::Err,
+            None => ToolOutcome::Ok,
+        }
+    }
+}
+
+/// Selects tool history entries; unset fields match every entry
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolHistoryFilter {
+    pub tool_name: Option<String>,
+    pub outcome: Option<ToolOutcome>,
+    /// Only calls started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only calls started before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ToolHistoryFilter {
+    pub fn with_tool_name(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = Some(tool_name.into());
+        self
+    }
+
+    pub fn with_outcome(mut self, outcome: ToolOutcome) -> Self {
+        self.outcome = Some(outcome);
+        self
+    }
+
+    pub fn between(mut self, since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self.until = Some(until);
+        self
+    }
+
+    fn matches(&self, entry: &ToolHistoryEntry) -> bool {
+        self.tool_name
+            .as_ref()
+            .is_none_or(|name| *name == entry.tool_name)
+            && self
+                .outcome
+                .is_none_or(|outcome| outcome == entry.outcome())
+            && self.since.is_none_or(|since| entry.started_at >= since)
+            && self.until.is_none_or(|until| entry.started_at < until)
+    }
+}
+
+/// Ring buffer of the agent's most recent finished tool calls
+#[derive(Debug, Default)]
+pub(crate) struct ToolHistory {
+    entries: Mutex<VecDeque<ToolHistoryEntry>>,
+}
+
+impl ToolHistory {
+    pub(crate) fn record(&self, entry: ToolHistoryEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_TOOL_HISTORY_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Matching entries, oldest first
+    pub(crate) fn query(&self, filter: &ToolHistoryFilter) -> Vec<ToolHistoryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .cloned()
+            .collect()
+    }
+}
@@ -2,19 +2,27 @@ use crate::agents::extension_manager::ExtensionManager;
 use crate::agents::router_tool_selector::{create_tool_selector, RouterToolSelector};
 use crate::agents::router_tools::{self};
 use crate::agents::tool_execution::ToolCallResult;
-use crate::agents::tool_router_index_manager::ToolRouterIndexManager;
+use crate::agents::tool_router_index_manager::{ToolIndexStamps, ToolRouterIndexManager};
+use crate::agents::types::RouterStrategy;
 use crate::config::Config;
 use crate::conversation::message::ToolRequest;
 use crate::providers::base::Provider;
 use anyhow::{anyhow, Result};
 use rmcp::model::{ErrorCode, ErrorData, JsonObject, Tool};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
 use tracing::error;
 
+const ROUTER_INDEX_TTL_KEY: &str = "GOOSE_ROUTER_INDEX_TTL_SECS";
+
 pub struct ToolRouteManager {
     router_tool_selector: Mutex<Option<Arc<Box<dyn RouterToolSelector>>>>,
+    custom_router_tool_selector: Mutex<Option<Arc<Box<dyn RouterToolSelector>>>>,
     router_disabled_override: Mutex<bool>,
+    router_bypass: Mutex<bool>,
+    index_stamps: Mutex<ToolIndexStamps>,
 }
 
 impl Default for ToolRouteManager {
@@ -27,7 +35,10 @@ impl ToolRouteManager {
     pub fn new() -> Self {
         Self {
             router_tool_selector: Mutex::new(None),
+            custom_router_tool_selector: Mutex::new(None),
             router_disabled_override: Mutex::new(false),
+            router_bypass: Mutex::new(false),
+            index_stamps: Mutex::new(ToolIndexStamps::default()),
         }
     }
 
@@ -36,12 +47,56 @@ impl ToolRouteManager {
         *self.router_tool_selector.lock().await = None;
     }
 
-    pub async fn record_tool_requests(&self, requests: &[ToolRequest]) {
+    /// Temporarily offer the full tool set without tearing down the router index
+    pub async fn set_router_bypass(&self, bypass: bool) {
+        *self.router_bypass.lock().await = bypass;
+    }
+
+    pub async fn is_router_bypassed(&self) -> bool {
+        *self.router_bypass.lock().await
+    }
+
+    /// How tools are currently selected for the model
+    pub async fn router_strategy(&self) -> RouterStrategy {
+        if !self.is_router_enabled().await {
+            RouterStrategy::Disabled
+        } else if self.is_router_bypassed().await {
+            RouterStrategy::Bypassed
+        } else if self.custom_router_tool_selector.lock().await.is_some() {
+            RouterStrategy::Custom
+        } else {
+            RouterStrategy::LlmSearch
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) async fn set_router_tool_selector(
+        &self,
+        selector: Option<Arc<Box<dyn RouterToolSelector>>>,
+    ) {
+        *self.router_tool_selector.lock().await = selector;
+    }
+
+    /// Use `selector` instead of a built-in strategy, now and whenever the selector is rebuilt
+    pub async fn set_custom_router_tool_selector(
+        &self,
+        selector: Arc<Box<dyn RouterToolSelector>>,
+    ) {
+        *self.custom_router_tool_selector.lock().await = Some(selector.clone());
+        if !*self.router_disabled_override.lock().await {
+            *self.router_tool_selector.lock().await = Some(selector);
+        }
+    }
+
+    pub async fn record_tool_requests(&self, session_id: &str, requests: &[ToolRequest]) {
         let selector = self.router_tool_selector.lock().await.clone();
         for request in requests {
             if let Ok(tool_call) = &request.tool_call {
                 if let Some(ref selector) = selector {
-                    if let Err(e) = selector.record_tool_call(&tool_call.name).await {
+                    if let Err(e) = selector
+                        .record_session_tool_call(session_id, &tool_call.name)
+                        .await
+                    {
                         error!("Failed to record tool call: {}", e);
                     }
                 }
@@ -49,13 +104,24 @@ impl ToolRouteManager {
         }
     }
 
+    /// Search for tools, listing every extension tool instead if the search fails
     pub async fn dispatch_route_search_tool(
         &self,
         arguments: JsonObject,
+        extension_manager: &ExtensionManager,
     ) -> Result<ToolCallResult, ErrorData> {
         let selector = self.router_tool_selector.lock().await.clone();
         match selector.as_ref() {
-            Some(selector) => match selector.select_tools(arguments).await {
+            Some(selector) => match selector
+                .select_tools_or_fallback(
+                    arguments,
+                    &extension_manager
+                        .get_prefixed_tools(None)
+                        .await
+                        .unwrap_or_default(),
+                )
+                .await
+            {
                 Ok(tools) => Ok(ToolCallResult::from(Ok(tools))),
                 Err(e) => Err(ErrorData::new(
                     ErrorCode::INTERNAL_ERROR,
@@ -96,29 +162,33 @@ impl ToolRouteManager {
             return Ok(());
         }
 
-        let selector = create_tool_selector(provider.clone())
-            .await
-            .map_err(|e| anyhow!("Failed to create tool selector: {}", e))?;
+        let custom_selector = self.custom_router_tool_selector.lock().await.clone();
+        let selector_arc = match custom_selector {
+            Some(selector) => selector,
+            None => {
+                let selector = create_tool_selector(provider.clone())
+                    .await
+                    .map_err(|e| anyhow!("Failed to create tool selector: {}", e))?;
 
-        // Wrap selector in Arc for the index manager methods
-        let selector_arc = Arc::new(selector);
+                // Wrap selector in Arc for the index manager methods
+                Arc::new(selector)
+            }
+        };
 
         if reindex_all.unwrap_or(false) {
+            let mut stamps = self.index_stamps.lock().await;
+            stamps.clear();
             let enabled_extensions = extension_manager.list_extensions().await?;
-            for extension_name in enabled_extensions {
-                if let Err(e) = ToolRouterIndexManager::update_extension_tools(
-                    &selector_arc,
-                    extension_manager,
-                    &extension_name,
-                    "add",
-                )
-                .await
-                {
-                    error!(
-                        "Failed to index tools for extension {}: {}",
-                        extension_name, e
-                    );
-                }
+            if let Err(e) = ToolRouterIndexManager::index_extensions_batch(
+                &selector_arc,
+                extension_manager,
+                &enabled_extensions,
+                "add",
+                &mut stamps,
+            )
+            .await
+            {
+                error!("{}", e);
             }
         }
 
@@ -128,6 +198,103 @@ impl ToolRouteManager {
         Ok(())
     }
 
+    /// Maximum age of an indexed tool before it is re-indexed, from `GOOSE_ROUTER_INDEX_TTL_SECS`
+    fn index_ttl() -> Option<Duration> {
+        Config::global()
+            .get_param::<u64>(ROUTER_INDEX_TTL_KEY)
+            .ok()
+            .map(Duration::from_secs)
+    }
+
+    async fn refresh_extension_index(
+        selector: &Arc<Box<dyn RouterToolSelector>>,
+        extension_manager: &ExtensionManager,
+        extension_name: &str,
+        stamps: &mut ToolIndexStamps,
+        max_age: Option<Duration>,
+    ) -> Result<usize> {
+        let tools = extension_manager
+            .get_prefixed_tools(Some(extension_name.to_string()))
+            .await?;
+        ToolRouterIndexManager::refresh_stale_tools(
+            selector,
+            &tools,
+            extension_name,
+            stamps,
+            max_age,
+        )
+        .await
+    }
+
+    /// Update the index for `extension_name` after a reload changed its tools from `previous`
+    pub async fn reindex_reloaded_extension(
+        &self,
+        extension_manager: &ExtensionManager,
+        extension_name: &str,
+        previous: &[Tool],
+    ) -> Result<()> {
+        let Some(selector) = self.router_tool_selector.lock().await.clone() else {
+            return Ok(());
+        };
+        let current = extension_manager
+            .get_prefixed_tools(Some(extension_name.to_string()))
+            .await?;
+        let mut stamps = self.index_stamps.lock().await;
+        ToolRouterIndexManager::reindex_changed_tools(
+            &selector,
+            previous,
+            &current,
+            extension_name,
+            &mut stamps,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Re-index the tools of extensions whose index entries have outlived the TTL.
+    ///
+    /// Runs on every router turn, so it does nothing unless a TTL is configured and only
+    /// re-lists the extensions that actually have expired entries. Changed definitions are
+    /// picked up by the reload and list_changed paths instead.
+    pub async fn refresh_stale_index(&self, extension_manager: &ExtensionManager) {
+        let Some(max_age) = Self::index_ttl() else {
+            return;
+        };
+        let Some(selector) = self.router_tool_selector.lock().await.clone() else {
+            return;
+        };
+
+        let mut stamps = self.index_stamps.lock().await;
+        let expired = stamps.expired_extensions(max_age, Instant::now());
+        if expired.is_empty() {
+            return;
+        }
+        let extensions = match extension_manager.list_extensions().await {
+            Ok(extensions) => extensions,
+            Err(e) => {
+                error!("Failed to list extensions for router index refresh: {}", e);
+                return;
+            }
+        };
+
+        for extension_name in extensions.into_iter().filter(|name| expired.contains(name)) {
+            if let Err(e) = Self::refresh_extension_index(
+                &selector,
+                extension_manager,
+                &extension_name,
+                &mut stamps,
+                Some(max_age),
+            )
+            .await
+            {
+                error!(
+                    "Failed to refresh index for extension {}: {}",
+                    extension_name, e
+                );
+            }
+        }
+    }
+
     pub async fn get_router_tool_selector(&self) -> Option<Arc<Box<dyn RouterToolSelector>>> {
         self.router_tool_selector.lock().await.clone()
     }
@@ -142,7 +309,12 @@ impl ToolRouteManager {
         self.router_tool_selector.lock().await.is_some()
     }
 
-    pub async fn list_tools_for_router(&self, extension_manager: &ExtensionManager) -> Vec<Tool> {
+    /// The search tool plus the tools most recently called in `session_id`
+    pub async fn list_tools_for_router(
+        &self,
+        extension_manager: &ExtensionManager,
+        session_id: &str,
+    ) -> Vec<Tool> {
         // If router is disabled or overridden, return empty
         if *self.router_disabled_override.lock().await {
             return vec![];
@@ -155,21 +327,19 @@ impl ToolRouteManager {
             return prefixed_tools;
         }
         prefixed_tools.push(router_tools::llm_search_tool());
+        self.refresh_stale_index(extension_manager).await;
 
-        // Get recent tool calls from router tool selector
+        // Get this session's recent tool calls from router tool selector
         let selector = self.router_tool_selector.lock().await.clone();
         if let Some(selector) = selector {
-            if let Ok(recent_calls) = selector.get_recent_tool_calls(20).await {
+            if let Ok(recent_calls) = selector.get_session_recent_tool_calls(session_id, 20).await {
                 // Add recent tool calls to the list, avoiding duplicates
                 for tool_name in recent_calls {
-                    // Find the tool in the extension manager's tools
-                    if let Ok(extension_tools) = extension_manager.get_prefixed_tools(None).await {
-                        if let Some(tool) = extension_tools.iter().find(|t| t.name == tool_name) {
-                            // Only add if not already in prefixed_tools
-                            if !prefixed_tools.iter().any(|t| t.name == tool.name) {
-                                prefixed_tools.push(tool.clone());
-                            }
-                        }
+                    if prefixed_tools.iter().any(|t| t.name == tool_name) {
+                        continue;
+                    }
+                    if let Some(tool) = extension_manager.get_tool(&tool_name).await {
+                        prefixed_tools.push(tool);
                     }
                 }
             }
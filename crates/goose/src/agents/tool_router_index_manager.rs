@@ -1,10 +1,76 @@
 use anyhow::{anyhow, Result};
+use rmcp::model::Tool;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
 use tracing;
 
 use crate::agents::extension_manager::ExtensionManager;
 use crate::agents::router_tool_selector::RouterToolSelector;
 
+/// When a tool was last indexed, the extension it belongs to, and a fingerprint of the
+/// definition that was indexed
+#[derive(Debug, Clone)]
+struct IndexStamp {
+    extension: String,
+    indexed_at: Instant,
+    version: u64,
+}
+
+/// Tracks indexed tools so entries can be refreshed once they age out or their definition changes
+#[derive(Debug, Default)]
+pub struct ToolIndexStamps {
+    stamps: HashMap<String, IndexStamp>,
+}
+
+impl ToolIndexStamps {
+    pub fn clear(&mut self) {
+        self.stamps.clear();
+    }
+
+    fn record(&mut self, extension: &str, tool: &Tool, now: Instant) {
+        self.stamps.insert(
+            tool.name.to_string(),
+            IndexStamp {
+                extension: extension.to_string(),
+                indexed_at: now,
+                version: tool_version(tool),
+            },
+        );
+    }
+
+    /// Extensions with at least one tool indexed `max_age` or longer ago
+    pub fn expired_extensions(&self, max_age: Duration, now: Instant) -> BTreeSet<String> {
+        self.stamps
+            .values()
+            .filter(|stamp| now.duration_since(stamp.indexed_at) >= max_age)
+            .map(|stamp| stamp.extension.clone())
+            .collect()
+    }
+
+    fn is_stale(&self, tool: &Tool, max_age: Option<Duration>, now: Instant) -> bool {
+        match self.stamps.get(tool.name.as_ref()) {
+            None => true,
+            Some(stamp) => {
+                stamp.version != tool_version(tool)
+                    || max_age.is_some_and(|age| now.duration_since(stamp.indexed_at) >= age)
+            }
+        }
+    }
+}
+
+/// Fingerprint of everything the selector indexes about a tool
+fn tool_version(tool: &Tool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(tool)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Manages tool indexing operations for the router when LLM routing is enabled
 pub struct ToolRouterIndexManager;
 
@@ -73,4 +139,497 @@ impl ToolRouterIndexManager {
 
         Ok(())
     }
+
+    /// Like `update_extension_tools` for every extension in `names`, but tools are added with a
+    /// single `index_tools_batch` call. An extension that fails doesn't stop the others; the
+    /// error names each one that failed.
+    pub async fn index_extensions_batch(
+        selector: &Arc<Box<dyn RouterToolSelector>>,
+        extension_manager: &ExtensionManager,
+        names: &[String],
+        action: &str,
+        stamps: &mut ToolIndexStamps,
+    ) -> Result<()> {
+        if action != "add" && action != "remove" {
+            return Err(anyhow!("Invalid action: {}", action));
+        }
+
+        let mut failures = Vec::new();
+        let mut batch = Vec::new();
+        for name in names {
+            match extension_manager
+                .get_prefixed_tools(Some(name.clone()))
+                .await
+            {
+                Ok(tools) if !tools.is_empty() => batch.push((name.clone(), tools)),
+                Ok(_) => {}
+                Err(e) => failures.push((name.clone(), e.to_string())),
+            }
+        }
+
+        if action == "add" {
+            let failed = selector.index_tools_batch(&batch).await;
+            let now = Instant::now();
+            for (name, tools) in &batch {
+                if !failed.iter().any(|(failed_name, _)| failed_name == name) {
+                    for tool in tools {
+                        stamps.record(name, tool, now);
+                    }
+                }
+            }
+            tracing::info!(
+                "Indexed tools for {} of {} extensions",
+                batch.len().saturating_sub(failed.len()),
+                batch.len()
+            );
+            failures.extend(failed.into_iter().map(|(name, e)| (name, e.to_string())));
+        } else {
+            for (name, tools) in &batch {
+                for tool in tools {
+                    stamps.stamps.remove(tool.name.as_ref());
+                    if let Err(e) = selector.remove_tool(&tool.name).await {
+                        failures.push((name.clone(), format!("tool {}: {}", tool.name, e)));
+                        break;
+                    }
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = failures
+            .iter()
+            .map(|(name, e)| format!("{} ({})", name, e))
+            .collect();
+        Err(anyhow!(
+            "Failed to {} tools for {} of {} extensions: {}",
+            if action == "add" { "index" } else { "remove" },
+            failures.len(),
+            names.len(),
+            details.join("; ")
+        ))
+    }
+
+    /// Re-index `extension_name` if any of its tools were never indexed, are older than
+    /// `max_age`, or changed since they were indexed. The whole extension is re-indexed.
+    /// Returns how many tools were stale.
+    pub async fn refresh_stale_tools(
+        selector: &Arc<Box<dyn RouterToolSelector>>,
+        tools: &[Tool],
+        extension_name: &str,
+        stamps: &mut ToolIndexStamps,
+        max_age: Option<Duration>,
+    ) -> Result<usize> {
+        let now = Instant::now();
+        let stale = tools
+            .iter()
+            .filter(|tool| stamps.is_stale(tool, max_age, now))
+            .count();
+        if stale == 0 {
+            return Ok(0);
+        }
+
+        for tool in tools {
+            if stamps.stamps.remove(tool.name.as_ref()).is_some() {
+                selector
+                    .remove_tool(&tool.name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to remove stale tool {}: {}", tool.name, e))?;
+            }
+        }
+        selector
+            .index_tools(tools, extension_name)
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to index tools for extension {}: {}",
+                    extension_name,
+                    e
+                )
+            })?;
+
+        for tool in tools {
+            stamps.record(extension_name, tool, now);
+        }
+
+        tracing::info!(
+            "Re-indexed extension {} with {} stale tools",
+            extension_name,
+            stale
+        );
+        Ok(stale)
+    }
+
+    /// Bring the index in line with an extension whose tools went from `previous` to
+    /// `current`, e.g. after a reload. Only tools that were removed, added or whose definition
+    /// changed are touched. Returns whether anything was re-indexed.
+    pub async fn reindex_changed_tools(
+        selector: &Arc<Box<dyn RouterToolSelector>>,
+        previous: &[Tool],
+        current: &[Tool],
+        extension_name: &str,
+        stamps: &mut ToolIndexStamps,
+    ) -> Result<bool> {
+        let versions = |tools: &[Tool]| -> HashMap<String, u64> {
+            tools
+                .iter()
+                .map(|tool| (tool.name.to_string(), tool_version(tool)))
+                .collect()
+        };
+        let (before, after) = (versions(previous), versions(current));
+        let outdated: Vec<&Tool> = previous
+            .iter()
+            .filter(|tool| after.get(tool.name.as_ref()) != before.get(tool.name.as_ref()))
+            .collect();
+        let changed: Vec<Tool> = current
+            .iter()
+            .filter(|tool| before.get(tool.name.as_ref()) != after.get(tool.name.as_ref()))
+            .cloned()
+            .collect();
+        if outdated.is_empty() && changed.is_empty() {
+            return Ok(false);
+        }
+
+        for tool in outdated {
+            stamps.stamps.remove(tool.name.as_ref());
+            selector
+                .remove_tool(&tool.name)
+                .await
+                .map_err(|e| anyhow!("Failed to remove tool {}: {}", tool.name, e))?;
+        }
+        if !changed.is_empty() {
+            selector
+                .index_tools(&changed, extension_name)
+                .await
+                .map_err(|e| {
+                    anyhow!(
+                        "Failed to index tools for extension {}: {}",
+                        extension_name,
+                        e
+                    )
+                })?;
+            let now = Instant::now();
+            for tool in &changed {
+                stamps.record(extension_name, tool, now);
+            }
+        }
+        tracing::info!(
+            "Re-indexed {} changed tools for extension {}",
+            changed.len(),
+            extension_name
+        );
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use rmcp::model::{Content, ErrorData, JsonObject};
+    use rmcp::object;
+
+    type IndexLog = Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>;
+
+    struct RecordingSelector {
+        indexed: IndexLog,
+        removed: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl RouterToolSelector for RecordingSelector {
+        async fn select_tools(&self, _params: JsonObject) -> Result<Vec<Content>, ErrorData> {
+            Ok(vec![])
+        }
+
+        async fn index_tools(
+            &self,
+            tools: &[Tool],
+            _extension_name: &str,
+        ) -> Result<(), ErrorData> {
+            let mut indexed = self.indexed.lock().unwrap();
+            for tool in tools {
+                indexed.push((
+                    tool.name.to_string(),
+                    tool.description.as_ref().map(|d| d.to_string()),
+                ));
+            }
+            Ok(())
+        }
+
+        async fn remove_tool(&self, tool_name: &str) -> Result<(), ErrorData> {
+            self.removed.lock().unwrap().push(tool_name.to_string());
+            Ok(())
+        }
+
+        async fn record_tool_call(&self, _tool_name: &str) -> Result<(), ErrorData> {
+            Ok(())
+        }
+
+        async fn get_recent_tool_calls(&self, _limit: usize) -> Result<Vec<String>, ErrorData> {
+            Ok(vec![])
+        }
+    }
+
+    /// Indexes every batch in one call, failing the extensions in `failing`
+    struct BatchSelector {
+        batches: Arc<std::sync::Mutex<Vec<Vec<String>>>>,
+        failing: Vec<String>,
+    }
+
+    #[async_trait]
+    impl RouterToolSelector for BatchSelector {
+        async fn select_tools(&self, _params: JsonObject) -> Result<Vec<Content>, ErrorData> {
+            Ok(vec![])
+        }
+
+        async fn index_tools(
+            &self,
+            _tools: &[Tool],
+            _extension_name: &str,
+        ) -> Result<(), ErrorData> {
+            panic!("extensions should be indexed in a batch");
+        }
+
+        async fn index_tools_batch(
+            &self,
+            batch: &[(String, Vec<Tool>)],
+        ) -> Vec<(String, ErrorData)> {
+            self.batches
+                .lock()
+                .unwrap()
+                .push(batch.iter().map(|(name, _)| name.clone()).collect());
+            self.failing
+                .iter()
+                .map(|name| {
+                    (
+                        name.clone(),
+                        ErrorData::internal_error("embedding service unavailable", None),
+                    )
+                })
+                .collect()
+        }
+
+        async fn remove_tool(&self, _tool_name: &str) -> Result<(), ErrorData> {
+            Ok(())
+        }
+
+        async fn record_tool_call(&self, _tool_name: &str) -> Result<(), ErrorData> {
+            Ok(())
+        }
+
+        async fn get_recent_tool_calls(&self, _limit: usize) -> Result<Vec<String>, ErrorData> {
+            Ok(vec![])
+        }
+    }
+
+    fn tool(name: &str, description: &str) -> Tool {
+        Tool::new(
+            name.to_string(),
+            description.to_string(),
+            object!({"type": "object"}),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stale_entries_are_refreshed() {
+        let indexed = IndexLog::default();
+        let selector: Arc<Box<dyn RouterToolSelector>> = Arc::new(Box::new(RecordingSelector {
+            indexed: indexed.clone(),
+            removed: Default::default(),
+        }));
+        let mut stamps = ToolIndexStamps::default();
+        let max_age = Some(Duration::from_secs(60));
+        let tools = vec![
+            tool("dev__shell", "Run a command"),
+            tool("dev__edit", "Edit"),
+        ];
+
+        let count = ToolRouterIndexManager::refresh_stale_tools(
+            &selector,
+            &tools,
+            "dev",
+            &mut stamps,
+            max_age,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+
+        // Fresh, unchanged entries are left alone
+        let count = ToolRouterIndexManager::refresh_stale_tools(
+            &selector,
+            &tools,
+            "dev",
+            &mut stamps,
+            max_age,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 0);
+
+        // A changed description is re-indexed right away
+        let updated = vec![tool("dev__shell", "Run a shell command"), tools[1].clone()];
+        let count = ToolRouterIndexManager::refresh_stale_tools(
+            &selector,
+            &updated,
+            "dev",
+            &mut stamps,
+            max_age,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            indexed.lock().unwrap()[2],
+            (
+                "dev__shell".to_string(),
+                Some("Run a shell command".to_string())
+            )
+        );
+
+        // Only extensions indexed longer ago than the TTL are due for a refresh
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let web = vec![tool("web__fetch", "Fetch a page")];
+        ToolRouterIndexManager::refresh_stale_tools(&selector, &web, "web", &mut stamps, max_age)
+            .await
+            .unwrap();
+        let ttl = Duration::from_secs(60);
+        assert!(stamps.expired_extensions(ttl, Instant::now()).is_empty());
+        tokio::time::advance(Duration::from_secs(31)).await;
+        assert_eq!(
+            stamps.expired_extensions(ttl, Instant::now()),
+            BTreeSet::from(["dev".to_string()])
+        );
+
+        // Entries older than the TTL are re-indexed on the next pass
+        let count = ToolRouterIndexManager::refresh_stale_tools(
+            &selector,
+            &updated,
+            "dev",
+            &mut stamps,
+            max_age,
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(indexed.lock().unwrap().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_reload_reindexes_only_changed_tools() {
+        let indexed = IndexLog::default();
+        let removed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let selector: Arc<Box<dyn RouterToolSelector>> = Arc::new(Box::new(RecordingSelector {
+            indexed: indexed.clone(),
+            removed: removed.clone(),
+        }));
+        let mut stamps = ToolIndexStamps::default();
+        let tools = vec![
+            tool("dev__shell", "Run a command"),
+            tool("dev__tree", "List files"),
+        ];
+
+        let reindexed = ToolRouterIndexManager::reindex_changed_tools(
+            &selector,
+            &tools,
+            &tools,
+            "dev",
+            &mut stamps,
+        )
+        .await
+        .unwrap();
+        assert!(!reindexed);
+        assert!(indexed.lock().unwrap().is_empty());
+
+        // dev__shell is unchanged, dev__tree changed, dev__edit is new
+        let reloaded = vec![
+            tool("dev__shell", "Run a command"),
+            tool("dev__tree", "List a directory tree"),
+            tool("dev__edit", "Edit"),
+        ];
+        let reindexed = ToolRouterIndexManager::reindex_changed_tools(
+            &selector,
+            &tools,
+            &reloaded,
+            "dev",
+            &mut stamps,
+        )
+        .await
+        .unwrap();
+        assert!(reindexed);
+        let names: Vec<String> = indexed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        assert_eq!(names, vec!["dev__tree", "dev__edit"]);
+        assert_eq!(*removed.lock().unwrap(), vec!["dev__tree"]);
+
+        // A tool dropped by the reload is removed without touching the rest
+        removed.lock().unwrap().clear();
+        let reindexed = ToolRouterIndexManager::reindex_changed_tools(
+            &selector,
+            &reloaded,
+            &reloaded[..2],
+            "dev",
+            &mut stamps,
+        )
+        .await
+        .unwrap();
+        assert!(reindexed);
+        assert_eq!(*removed.lock().unwrap(), vec!["dev__edit"]);
+        assert_eq!(indexed.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_indexing_reports_failed_extensions() {
+        let extension_manager = ExtensionManager::default();
+        for name in ["todo", "chatrecall"] {
+            extension_manager
+                .add_extension(crate::agents::ExtensionConfig::Platform {
+                    name: name.to_string(),
+                    description: name.to_string(),
+                    bundled: None,
+                    available_tools: vec![],
+                    required: false,
+                })
+                .await
+                .unwrap();
+        }
+        let batches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let selector: Arc<Box<dyn RouterToolSelector>> = Arc::new(Box::new(BatchSelector {
+            batches: batches.clone(),
+            failing: vec!["chatrecall".to_string()],
+        }));
+        let mut stamps = ToolIndexStamps::default();
+
+        let error = ToolRouterIndexManager::index_extensions_batch(
+            &selector,
+            &extension_manager,
+            &["chatrecall".to_string(), "todo".to_string()],
+            "add",
+            &mut stamps,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert_eq!(*batches.lock().unwrap(), vec![vec!["chatrecall", "todo"]]);
+        assert!(error.contains("1 of 2 extensions"), "{error}");
+        assert!(error.contains("chatrecall (embedding service unavailable)"));
+        assert!(!error.contains("todo"));
+
+        // Only the extension that was indexed is considered fresh
+        let now = Instant::now();
+        for (name, fresh) in [("todo", true), ("chatrecall", false)] {
+            let tools = extension_manager
+                .get_prefixed_tools(Some(name.to_string()))
+                .await
+                .unwrap();
+            assert!(tools.iter().all(|t| stamps.is_stale(t, None, now) != fresh));
+        }
+    }
 }
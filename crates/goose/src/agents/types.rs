@@ -1,8 +1,15 @@
-use crate::mcp_utils::ToolResult;
+use crate::config::permission::PermissionLevel;
+use crate::config::GooseMode;
+use crate::conversation::message::Message;
+use crate::mcp_utils::{ErrorData, ToolResult};
 use crate::providers::base::Provider;
-use rmcp::model::{Content, Tool};
+use crate::providers::errors::ProviderError;
+use rmcp::model::{Content, JsonObject, Tool};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
 use utoipa::ToSchema;
 
@@ -12,6 +19,303 @@ pub type ToolResultReceiver = Arc<Mutex<mpsc::Receiver<(String, ToolResult<Vec<C
 // We use double Arc here to allow easy provider swaps while sharing concurrent access
 pub type SharedProvider = Arc<Mutex<Option<Arc<dyn Provider>>>>;
 
+/// Transform applied to a tool's result content before it is added to the conversation.
+/// Receives the tool name and the content returned by the tool.
+pub type ResultTransform = Arc<dyn Fn(&str, Vec<Content>) -> Vec<Content> + Send + Sync>;
+
+/// Reshapes a tool's arguments before it is dispatched, e.g. to resolve relative paths.
+/// Receives the arguments and the session's working directory.
+pub type ArgumentTransform = Arc<dyn Fn(JsonObject, &Path) -> JsonObject + Send + Sync>;
+
+/// Inspects or rewrites the messages about to be sent to the provider, e.g. for redaction.
+/// Receives the outgoing messages and returns the ones to send instead.
+pub type PreSendHook = Arc<dyn Fn(Vec<Message>) -> Vec<Message> + Send + Sync>;
+
+/// Receives each piece of recipe text as the provider generates it
+pub type RecipeProgressCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// How the agent should proceed after a provider error when resume-on-error is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorRecoveryAction {
+    /// Re-send the request that failed
+    Retry,
+    /// Stop the reply, as if resume-on-error were disabled
+    Abandon,
+}
+
+/// Provider dialect used when exporting tool definitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSchemaFormat {
+    /// `{"type": "function", "function": {...}}` entries, as sent by the OpenAI format
+    OpenAi,
+    /// `{"name", "description", "input_schema"}` entries, as sent to Anthropic
+    Anthropic,
+}
+
+/// Which part of an oversized tool result to keep in the conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TruncationPolicy {
+    /// Keep the beginning, e.g. for listings
+    Head,
+    /// Keep the end, e.g. for logs
+    Tail,
+    /// Keep both ends and drop the middle
+    Middle,
+}
+
+/// How an oversized text tool result is handled before it goes into the conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LargeResponseStrategy {
+    /// Keep the part chosen by the tool's `TruncationPolicy` inline, the beginning if unset
+    Truncate,
+    /// Replace the text with a note on its size and the file it was saved to
+    #[default]
+    Summarize,
+    /// Keep the text as returned, e.g. for checksums that must not be cut
+    Passthrough,
+}
+
+/// Retry a tool whose call fails with a transient error, recognized by its message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRetryPolicy {
+    /// Substrings such as "connection reset" that mark an error as retryable
+    pub retryable_errors: Vec<String>,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+}
+
+impl ToolRetryPolicy {
+    pub fn is_retryable(&self, error: &ErrorData) -> bool {
+        self.retryable_errors
+            .iter()
+            .any(|substring| error.message.contains(substring.as_str()))
+    }
+}
+
+/// Retry provider requests in the reply loop that fail with a transient error, such as a rate
+/// limit or a 5xx response, backing off exponentially with jitter
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+    /// Upper bound on a single delay, before jitter
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn is_transient(error: &ProviderError) -> bool {
+        matches!(
+            error,
+            ProviderError::RateLimitExceeded { .. } | ProviderError::ServerError(_)
+        )
+    }
+
+    /// How long to wait before retry number `attempt`, counting from 1, or None when `error`
+    /// should not be retried. A delay requested by a rate-limited provider takes precedence,
+    /// up to `max_delay`.
+    pub fn delay_before_retry(&self, error: &ProviderError, attempt: u32) -> Option<Duration> {
+        if !Self::is_transient(error) || attempt == 0 || attempt > self.max_attempts {
+            return None;
+        }
+        if let ProviderError::RateLimitExceeded {
+            retry_delay: Some(delay),
+            ..
+        } = error
+        {
+            return Some((*delay).min(self.max_delay));
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        Some(backoff.mul_f64(0.8 + rand::random::<f64>() * 0.4))
+    }
+}
+
+/// Usage summary for a session in a stable JSON shape, intended for dashboards
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMetricsJson {
+    pub session_id: String,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub total_tokens: Option<i32>,
+    /// Estimated cost in USD, when pricing for the session's model is known
+    pub estimated_cost: Option<f64>,
+    pub message_count: usize,
+    /// Number of tool requests made in the session, keyed by tool name
+    pub tool_call_counts: BTreeMap<String, usize>,
+}
+
+/// Keeps `tool` out of the offered tool set until `requires` has succeeded in the conversation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolGate {
+    pub tool: String,
+    pub requires: String,
+}
+
+/// Limits tool calls to read-only tools on an allowlist
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolSandbox {
+    /// Extension names such as `developer`, or full tool names such as `developer__shell`
+    pub allowed: Vec<String>,
+}
+
+impl ToolSandbox {
+    pub fn new<I, S>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: allowed.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Whether `tool_name` or `extension`, the extension that owns it, is on the allowlist
+    pub fn allows(&self, tool_name: &str, extension: Option<&str>) -> bool {
+        self.allowed
+            .iter()
+            .any(|entry| entry == tool_name || Some(entry.as_str()) == extension)
+    }
+}
+
+/// Tools to hide from the model and refuse to run, whatever extensions are loaded. Entries are
+/// full tool names such as `developer__shell` or glob patterns such as `developer__*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// When non-empty, only matching tools are permitted
+    pub allow: Vec<String>,
+    /// Matching tools are never permitted, even if also allowed
+    pub deny: Vec<String>,
+}
+
+impl ToolPolicy {
+    pub fn with_allowed<I, S>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allow.extend(entries.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_denied<I, S>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.deny.extend(entries.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether `tool_name` may be offered to the model and run
+    pub fn permits(&self, tool_name: &str) -> bool {
+        let matches = |entry: &String| {
+            entry == tool_name
+                || glob::Pattern::new(entry).is_ok_and(|pattern| pattern.matches(tool_name))
+        };
+        !self.deny.iter().any(matches) && (self.allow.is_empty() || self.allow.iter().any(matches))
+    }
+}
+
+/// Tool names that appeared or disappeared between two tool refreshes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolSetDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ToolSetDiff {
+    pub fn between(previous: &BTreeSet<String>, current: &BTreeSet<String>) -> Self {
+        Self {
+            added: current.difference(previous).cloned().collect(),
+            removed: previous.difference(current).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Whether a tool is offered to the model this turn, and if not, what kept it out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum ToolOfferingDecision {
+    Offered,
+    /// The router did not select the tool for this session
+    FilteredByRouter,
+    /// The tool fell past the limit set with `set_max_offered_tools`
+    OverCap {
+        cap: usize,
+    },
+    /// No enabled extension provides the tool
+    Disabled,
+    /// A tool gate holds the tool back until `requires` succeeds
+    Gated {
+        requires: String,
+    },
+}
+
+impl ToolOfferingDecision {
+    pub fn is_offered(&self) -> bool {
+        matches!(self, Self::Offered)
+    }
+
+    /// Why the tool is not offered, or None when it is
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            Self::Offered => None,
+            Self::FilteredByRouter => Some("not selected by the tool router".to_string()),
+            Self::OverCap { cap } => Some(format!("over the cap of {} offered tools", cap)),
+            Self::Disabled => Some("not provided by any enabled extension".to_string()),
+            Self::Gated { requires } => Some(format!(
+                "gated until '{}' returns a successful result",
+                requires
+            )),
+        }
+    }
+}
+
+/// Which strategy picks the tools offered to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouterStrategy {
+    /// The router is off and every tool is offered
+    Disabled,
+    /// The router is on but skipped with `set_router_bypass`
+    Bypassed,
+    /// A selector installed with `set_router_selector`
+    Custom,
+    /// The built-in LLM search selector
+    LlmSearch,
+}
+
+/// Serializable view of how an agent is configured, for debugging and bug reports.
+/// Holds no credentials or extension environment values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    /// Provider name, or None when no provider is set
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub mode: GooseMode,
+    /// Names of the loaded extensions, sorted
+    pub extensions: Vec<String>,
+    pub router_strategy: RouterStrategy,
+    /// Inspectors run on each batch of tool calls, in order
+    pub tool_inspectors: Vec<String>,
+    /// Repeated identical calls the tool monitor allows, or None for no limit
+    pub max_tool_repetitions: Option<u32>,
+    /// Permission level the user set for each tool
+    pub permission_rules: BTreeMap<String, PermissionLevel>,
+}
+
 /// Default timeout for retry operations (5 minutes)
 pub const DEFAULT_RETRY_TIMEOUT_SECONDS: u64 = 300;
 
@@ -92,4 +396,25 @@ pub struct SessionConfig {
     /// Retry configuration for automated validation and recovery
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry_config: Option<RetryConfig>,
+    /// User on whose behalf the session runs, for attribution in multi-user deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Thinking token budget forwarded to providers that support one; ignored by the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    /// Wall-clock limit for a whole reply, after which it stops and in-flight tools are cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_deadline: Option<Duration>,
+}
+
+impl SessionConfig {
+    pub fn with_thinking_budget(mut self, budget: Option<u32>) -> Self {
+        self.thinking_budget = budget;
+        self
+    }
+
+    pub fn with_reply_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.reply_deadline = deadline;
+        self
+    }
 }
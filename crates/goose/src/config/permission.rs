@@ -1,6 +1,6 @@
 use crate::config::paths::Paths;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use utoipa::ToSchema;
@@ -27,6 +27,7 @@ pub struct PermissionConfig {
 pub struct PermissionManager {
     config_path: PathBuf, // Path to the permission configuration file
     permission_map: HashMap<String, PermissionConfig>, // Mapping of permission names to configurations
+    session_allowed: HashMap<String, HashSet<String>>, // Tools allowed for the rest of a session, not persisted
 }
 
 // Constants representing specific permission categories
@@ -51,6 +52,7 @@ impl Default for PermissionManager {
         PermissionManager {
             config_path,
             permission_map,
+            session_allowed: HashMap::new(),
         }
     }
 }
@@ -73,6 +75,7 @@ impl PermissionManager {
         PermissionManager {
             config_path,
             permission_map,
+            session_allowed: HashMap::new(),
         }
     }
 
@@ -91,6 +94,22 @@ impl PermissionManager {
         self.get_permission(SMART_APPROVE_PERMISSION, principal_name)
     }
 
+    /// Returns every tool the user has set a permission level for.
+    pub fn get_user_permissions(&self) -> BTreeMap<String, PermissionLevel> {
+        let Some(config) = self.permission_map.get(USER_PERMISSION) else {
+            return BTreeMap::new();
+        };
+        let levels = [
+            (&config.always_allow, PermissionLevel::AlwaysAllow),
+            (&config.ask_before, PermissionLevel::AskBefore),
+            (&config.never_allow, PermissionLevel::NeverAllow),
+        ];
+        levels
+            .into_iter()
+            .flat_map(|(tools, level)| tools.iter().map(move |tool| (tool.clone(), level.clone())))
+            .collect()
+    }
+
     /// Retrieves the config file path.
     pub fn get_config_path(&self) -> &Path {
         self.config_path.as_path()
@@ -168,6 +187,21 @@ impl PermissionManager {
         fs::write(&self.config_path, yaml_content).expect("Failed to write to permission.yaml");
     }
 
+    /// Allows a tool without prompting for the rest of a session, without persisting the decision.
+    pub fn allow_for_session(&mut self, session_id: &str, principal_name: &str) {
+        self.session_allowed
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(principal_name.to_string());
+    }
+
+    /// Checks whether a tool was allowed for the rest of a session.
+    pub fn is_allowed_for_session(&self, session_id: &str, principal_name: &str) -> bool {
+        self.session_allowed
+            .get(session_id)
+            .is_some_and(|tools| tools.contains(principal_name))
+    }
+
     /// Removes all entries where the principal name starts with the given extension name.
     pub fn remove_extension(&mut self, extension_name: &str) {
         for permission_config in self.permission_map.values_mut() {
@@ -444,6 +444,9 @@ mod tests {
                     toolshim: false,
                     toolshim_model: None,
                     fast_model: None,
+                    thinking_budget: None,
+                    response_format: None,
+                    prompt_cache: None,
                 },
                 max_tool_responses: None,
             }
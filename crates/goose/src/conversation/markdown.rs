@@ -0,0 +1,143 @@
+use rmcp::model::{RawContent, ResourceContents, Role};
+
+use crate::conversation::message::{Message, MessageContent};
+
+/// Render the user-visible messages as Markdown for sharing. Each turn gets a heading, tool
+/// calls are shown as JSON code blocks and tool results are folded into `<details>` blocks.
+pub fn to_markdown(messages: &[Message]) -> String {
+    let mut sections = Vec::new();
+    let mut current_role = None;
+
+    for message in messages.iter().filter(|m| m.is_user_visible()) {
+        // Tool results arrive as user messages but belong to the assistant's turn
+        let only_tool_results = !message.content.is_empty()
+            && message
+                .content
+                .iter()
+                .all(|c| matches!(c, MessageContent::ToolResponse(_)));
+        if !only_tool_results && current_role.as_ref() != Some(&message.role) {
+            sections.push(match message.role {
+                Role::User => "## User".to_string(),
+                Role::Assistant => "## Assistant".to_string(),
+            });
+            current_role = Some(message.role.clone());
+        }
+        sections.extend(message.content.iter().filter_map(content_to_markdown));
+    }
+
+    let mut markdown = sections.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+fn content_to_markdown(content: &MessageContent) -> Option<String> {
+    let markdown = match content {
+        MessageContent::Text(text) if text.text.trim().is_empty() => return None,
+        MessageContent::Text(text) => text.text.trim().to_string(),
+        MessageContent::Image(image) => format!("*[image: {}]*", image.mime_type),
+        MessageContent::ToolRequest(request) => match &request.tool_call {
+            Ok(call) => {
+                let arguments = serde_json::to_string_pretty(&call.arguments)
+                    .unwrap_or_else(|_| "{}".to_string());
+                format!(
+                    "**Tool call:** `{}`\n\n```json\n{}\n```",
+                    call.name, arguments
+                )
+            }
+            Err(e) => format!("**Invalid tool call:** {}", e.message),
+        },
+        MessageContent::FrontendToolRequest(request) => match &request.tool_call {
+            Ok(call) => format!("**Frontend tool call:** `{}`", call.name),
+            Err(e) => format!("**Invalid tool call:** {}", e.message),
+        },
+        MessageContent::ToolResponse(response) => match &response.tool_result {
+            Ok(contents) => {
+                let body = contents
+                    .iter()
+                    .map(|content| match &content.raw {
+                        RawContent::Text(text) => text.text.clone(),
+                        RawContent::Image(image) => format!("[image: {}]", image.mime_type),
+                        RawContent::Audio(audio) => format!("[audio: {}]", audio.mime_type),
+                        RawContent::Resource(resource) => match &resource.resource {
+                            ResourceContents::TextResourceContents { uri, .. }
+                            | ResourceContents::BlobResourceContents { uri, .. } => {
+                                format!("[resource: {}]", uri)
+                            }
+                        },
+                        RawContent::ResourceLink(link) => format!("[resource: {}]", link.uri),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                folded("Tool result", &body)
+            }
+            Err(e) => folded("Tool error", &e.message),
+        },
+        MessageContent::Thinking(_) | MessageContent::RedactedThinking(_) => return None,
+        MessageContent::ToolConfirmationRequest(request) => {
+            format!("*[confirmation requested for `{}`]*", request.tool_name)
+        }
+        MessageContent::ActionRequired(_) => "*[action required]*".to_string(),
+        MessageContent::SystemNotification(notification) => format!("*{}*", notification.msg),
+    };
+    Some(markdown)
+}
+
+/// A collapsed block whose body is shown as preformatted text
+fn folded(summary: &str, body: &str) -> String {
+    // A fence longer than any backtick run in the body keeps it from closing early
+    let longest_run = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!(
+        "<details>\n<summary>{}</summary>\n\n{}\n{}\n{}\n\n</details>",
+        summary, fence, body, fence
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{CallToolRequestParam, Content};
+    use rmcp::object;
+
+    #[test]
+    fn test_to_markdown_sections_tool_calls_and_results() {
+        let messages = vec![
+            Message::user().with_text("List the files"),
+            Message::assistant()
+                .with_text("Let me look.")
+                .with_tool_request(
+                    "call_1",
+                    Ok(CallToolRequestParam {
+                        name: "developer__shell".into(),
+                        arguments: Some(object!({"command": "ls"})),
+                    }),
+                ),
+            Message::user().with_tool_response(
+                "call_1",
+                Ok(vec![
+                    Content::text("Cargo.toml\nsrc"),
+                    Content::image("aGVsbG8=", "image/png"),
+                ]),
+            ),
+            Message::assistant().with_text("There are two entries."),
+        ];
+
+        let markdown = to_markdown(&messages);
+
+        assert_eq!(
+            markdown,
+            concat!(
+                "## User\n\n",
+                "List the files\n\n",
+                "## Assistant\n\n",
+                "Let me look.\n\n",
+                "**Tool call:** `developer__shell`\n\n",
+                "```json\n{\n  \"command\": \"ls\"\n}\n```\n\n",
+                "<details>\n<summary>Tool result</summary>\n\n",
+                "```\nCargo.toml\nsrc\n[image: image/png]\n```\n\n",
+                "</details>\n\n",
+                "There are two entries.\n",
+            )
+        );
+    }
+}
@@ -5,6 +5,7 @@ use std::collections::HashSet;
 use thiserror::Error;
 use utoipa::ToSchema;
 
+pub mod markdown;
 pub mod message;
 mod tool_result_serde;
 
@@ -417,6 +418,69 @@ pub fn merge_consecutive_messages(messages: Vec<Message>) -> (Vec<Message>, Vec<
     (merged_messages, issues)
 }
 
+/// Drop tool responses with no earlier tool request, e.g. after the history was trimmed,
+/// along with any message left empty by the removal
+pub fn remove_orphaned_tool_responses(messages: Vec<Message>) -> (Vec<Message>, Vec<String>) {
+    let mut issues = Vec::new();
+    let mut requested: HashSet<String> = HashSet::new();
+    let mut kept = Vec::with_capacity(messages.len());
+
+    for mut message in messages {
+        let had_content = !message.content.is_empty();
+        message.content.retain(|content| match content {
+            MessageContent::ToolRequest(req) => {
+                requested.insert(req.id.clone());
+                true
+            }
+            MessageContent::FrontendToolRequest(req) => {
+                requested.insert(req.id.clone());
+                true
+            }
+            MessageContent::ToolResponse(resp) if !requested.contains(&resp.id) => {
+                issues.push(format!("Removed orphaned tool response '{}'", resp.id));
+                false
+            }
+            _ => true,
+        });
+        if had_content && message.content.is_empty() {
+            continue;
+        }
+        kept.push(message);
+    }
+
+    (kept, issues)
+}
+
+/// Combine two histories into one valid conversation: `primary` first, then `secondary`.
+/// Messages of `secondary` already in `primary` are skipped, and the result is run through
+/// [`fix_conversation`] to repair role ordering.
+pub fn merge_conversations(
+    primary: &Conversation,
+    secondary: &Conversation,
+) -> (Conversation, Vec<String>) {
+    let mut issues = Vec::new();
+    let mut merged = primary.messages().clone();
+
+    for message in secondary.iter() {
+        if primary.iter().any(|m| is_same_message(m, message)) {
+            issues.push("Skipped message present in both conversations".to_string());
+            continue;
+        }
+        merged.push(message.clone());
+    }
+
+    let (fixed, fix_issues) = fix_conversation(Conversation::new_unvalidated(merged));
+    issues.extend(fix_issues);
+    (fixed, issues)
+}
+
+/// Stored messages get session-specific ids, so copies across sessions are matched on
+/// role, timestamp and content instead
+fn is_same_message(a: &Message, b: &Message) -> bool {
+    (a.id.is_some() && a.id == b.id)
+        || (a.role == b.role && a.created == b.created && a.content == b.content)
+}
+
 fn has_tool_response(message: &Message) -> bool {
     message
         .content
@@ -77,6 +77,33 @@ pub struct ModelConfig {
     pub toolshim: bool,
     pub toolshim_model: Option<String>,
     pub fast_model: Option<String>,
+    /// Tokens the model may spend thinking before it answers, for providers that support it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    /// Output format the provider should constrain responses to, for providers that support it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Stable prompt prefix the provider should cache between requests, for providers that
+    /// support it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_cache: Option<PromptCacheHints>,
+}
+
+/// Parts of the prompt that stay the same across requests and are worth caching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PromptCacheHints {
+    /// Cache the system prompt
+    pub system_prompt: bool,
+    /// Cache the conversation up to and including this many leading messages
+    pub leading_messages: usize,
+}
+
+/// Structured output modes a provider can be asked to enforce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// A single JSON object
+    Json,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,6 +134,9 @@ impl ModelConfig {
             toolshim,
             toolshim_model,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         })
     }
 
@@ -256,6 +286,21 @@ impl ModelConfig {
         self
     }
 
+    pub fn with_thinking_budget(mut self, budget: Option<u32>) -> Self {
+        self.thinking_budget = budget;
+        self
+    }
+
+    pub fn with_response_format(mut self, format: Option<ResponseFormat>) -> Self {
+        self.response_format = format;
+        self
+    }
+
+    pub fn with_prompt_cache(mut self, hints: Option<PromptCacheHints>) -> Self {
+        self.prompt_cache = hints;
+        self
+    }
+
     pub fn with_fast(mut self, fast_model: String) -> Self {
         self.fast_model = Some(fast_model);
         self
@@ -4,6 +4,8 @@ use utoipa::ToSchema;
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum Permission {
     AlwaysAllow,
+    /// Allow this tool without asking again for the rest of the session
+    AlwaysAllowForSession,
     AllowOnce,
     Cancel,
     DenyOnce,
@@ -9,7 +9,9 @@ use tokio::pin;
 use tokio_util::io::StreamReader;
 
 use super::api_client::{ApiClient, ApiResponse, AuthMethod};
-use super::base::{ConfigKey, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage};
+use super::base::{
+    ConfigKey, FinishReason, MessageStream, ModelInfo, Provider, ProviderMetadata, ProviderUsage,
+};
 use super::errors::ProviderError;
 use super::formats::anthropic::{
     create_request, get_usage, response_to_message, response_to_streaming_message,
@@ -152,10 +154,10 @@ impl AnthropicProvider {
                         }
                     }
                 }
-                Err(map_http_error_to_provider_error(
-                    response.status,
-                    response.payload,
-                ))
+                Err(
+                    map_http_error_to_provider_error(response.status, response.payload)
+                        .with_retry_delay(response.retry_after),
+                )
             }
         }
     }
@@ -223,7 +225,12 @@ impl Provider for AnthropicProvider {
         let response_model = get_model(&json_response);
         let mut log = RequestLog::start(&self.model, &payload)?;
         log.write(&json_response, Some(&usage))?;
-        let provider_usage = ProviderUsage::new(response_model, usage);
+        let finish_reason = json_response
+            .get("stop_reason")
+            .and_then(|v| v.as_str())
+            .map(FinishReason::from_provider);
+        let provider_usage =
+            ProviderUsage::new(response_model, usage).with_finish_reason(finish_reason);
         tracing::debug!(
             "🔍 Anthropic non-streaming returning ProviderUsage: {:?}",
             provider_usage
@@ -261,7 +268,18 @@ impl Provider for AnthropicProvider {
         messages: &[Message],
         tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
-        let mut payload = create_request(&self.model, system, messages, tools)?;
+        self.stream_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream_with_model(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        let mut payload = create_request(model_config, system, messages, tools)?;
         payload
             .as_object_mut()
             .unwrap()
@@ -300,4 +318,12 @@ impl Provider for AnthropicProvider {
     fn supports_streaming(&self) -> bool {
         self.supports_streaming
     }
+
+    fn supports_thinking_budget(&self) -> bool {
+        true
+    }
+
+    fn supports_prompt_cache_hints(&self) -> bool {
+        true
+    }
 }
@@ -1,3 +1,4 @@
+use super::utils::parse_retry_after;
 use crate::session_context::SESSION_ID_HEADER;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -167,6 +168,8 @@ pub trait AuthProvider: Send + Sync {
 pub struct ApiResponse {
     pub status: StatusCode,
     pub payload: Option<Value>,
+    /// Delay requested by the server's `Retry-After` headers, if any
+    pub retry_after: Option<Duration>,
 }
 
 impl fmt::Debug for AuthMethod {
@@ -187,8 +190,13 @@ impl fmt::Debug for AuthMethod {
 impl ApiResponse {
     pub async fn from_response(response: Response) -> Result<Self> {
         let status = response.status();
+        let retry_after = parse_retry_after(response.headers());
         let payload = response.json().await.ok();
-        Ok(Self { status, payload })
+        Ok(Self {
+            status,
+            payload,
+            retry_after,
+        })
     }
 }
 
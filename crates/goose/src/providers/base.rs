@@ -226,15 +226,74 @@ impl ConfigKey {
     }
 }
 
+/// Where a provider expects the system prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SystemPromptPlacement {
+    /// Passed separately from the conversation, as the `system` argument
+    #[default]
+    Separate,
+    /// Merged into the first user message, for APIs without a system field. The built-in
+    /// providers all take the system prompt separately.
+    FirstMessage,
+}
+
+/// Why the provider stopped generating, normalized across provider APIs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Stop,
+    Length,
+    ToolCalls,
+    ContentFilter,
+    Other(String),
+}
+
+impl FinishReason {
+    /// Map a provider-specific reason (OpenAI `finish_reason`, Anthropic `stop_reason`, ...)
+    pub fn from_provider(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" => FinishReason::Length,
+            "tool_calls" | "tool_use" | "function_call" => FinishReason::ToolCalls,
+            "content_filter" | "safety" | "refusal" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+
+    /// Explanation for reasons that mean the output was cut short, if any
+    pub fn explanation(&self) -> Option<&'static str> {
+        match self {
+            FinishReason::Length => {
+                Some("The response was cut off because it reached the output token limit (finish reason: length).")
+            }
+            FinishReason::ContentFilter => {
+                Some("The response was stopped by the provider's content filter (finish reason: content_filter).")
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderUsage {
     pub model: String,
     pub usage: Usage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
 }
 
 impl ProviderUsage {
     pub fn new(model: String, usage: Usage) -> Self {
-        Self { model, usage }
+        Self {
+            model,
+            usage,
+            finish_reason: None,
+        }
+    }
+
+    pub fn with_finish_reason(mut self, finish_reason: Option<FinishReason>) -> Self {
+        self.finish_reason = finish_reason;
+        self
     }
 
     /// Ensures this ProviderUsage has token counts, estimating them if necessary
@@ -262,6 +321,10 @@ impl ProviderUsage {
         ProviderUsage {
             model: self.model.clone(),
             usage: self.usage + other.usage,
+            finish_reason: other
+                .finish_reason
+                .clone()
+                .or_else(|| self.finish_reason.clone()),
         }
     }
 }
@@ -447,10 +510,44 @@ pub trait Provider: Send + Sync {
         ))
     }
 
+    /// Stream using `model_config` instead of the provider's own, e.g. to apply cache hints.
+    /// Providers that take no per-request settings stream as usual.
+    async fn stream_with_model(
+        &self,
+        _model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream(system, messages, tools).await
+    }
+
     fn supports_streaming(&self) -> bool {
         false
     }
 
+    /// Whether `ModelConfig::thinking_budget` is honored by `complete_with_model` and
+    /// `stream_with_model`
+    fn supports_thinking_budget(&self) -> bool {
+        false
+    }
+
+    /// Whether `ModelConfig::response_format` is honored by `complete_with_model` and
+    /// `stream_with_model`
+    fn supports_response_format(&self) -> bool {
+        false
+    }
+
+    /// Whether `ModelConfig::prompt_cache` is honored by `complete_with_model` and
+    /// `stream_with_model`
+    fn supports_prompt_cache_hints(&self) -> bool {
+        false
+    }
+
+    fn system_prompt_placement(&self) -> SystemPromptPlacement {
+        SystemPromptPlacement::Separate
+    }
+
     /// Get the currently active model name
     /// For regular providers, this returns the configured model
     /// For LeadWorkerProvider, this returns the currently active model (lead or worker)
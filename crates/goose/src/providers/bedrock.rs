@@ -120,12 +120,12 @@ impl BedrockProvider {
             .get_param::<u64>("BEDROCK_MAX_RETRY_INTERVAL_MS")
             .unwrap_or(BEDROCK_DEFAULT_MAX_RETRY_INTERVAL_MS);
 
-        RetryConfig {
+        RetryConfig::new(
             max_retries,
             initial_interval_ms,
             backoff_multiplier,
             max_interval_ms,
-        }
+        )
     }
 
     async fn converse(
@@ -45,6 +45,20 @@ impl ProviderError {
             ProviderError::NotImplemented(_) => "not_implemented",
         }
     }
+
+    /// Attach a server-suggested retry delay to a rate limit error that lacks one
+    pub fn with_retry_delay(self, delay: Option<Duration>) -> Self {
+        match self {
+            ProviderError::RateLimitExceeded {
+                details,
+                retry_delay: None,
+            } => ProviderError::RateLimitExceeded {
+                details,
+                retry_delay: delay,
+            },
+            other => other,
+        }
+    }
 }
 
 impl From<anyhow::Error> for ProviderError {
@@ -1,5 +1,5 @@
 use crate::conversation::message::{Message, MessageContent};
-use crate::model::ModelConfig;
+use crate::model::{ModelConfig, PromptCacheHints};
 use crate::providers::base::Usage;
 use crate::providers::errors::ProviderError;
 use crate::providers::utils::{convert_image, ImageFormat};
@@ -218,6 +218,53 @@ pub fn format_system(system: &str) -> Value {
     }])
 }
 
+/// Move the cache markers to where `hints` asks for them. A leading-messages marker takes the
+/// place of the older of the two trailing user message markers, since Anthropic allows at most
+/// four cache breakpoints per request.
+fn apply_prompt_cache_hints(
+    hints: &PromptCacheHints,
+    system_spec: &mut Value,
+    messages: &mut [Value],
+) {
+    if !hints.system_prompt {
+        if let Some(block) = system_spec.get_mut(0).and_then(Value::as_object_mut) {
+            block.remove(CACHE_CONTROL_FIELD);
+        }
+    }
+    if hints.leading_messages == 0 || messages.is_empty() {
+        return;
+    }
+
+    let mut newest_kept = false;
+    for message in messages.iter_mut().rev() {
+        let Some(block) = last_content_block(message) else {
+            continue;
+        };
+        if block.contains_key(CACHE_CONTROL_FIELD) {
+            if newest_kept {
+                block.remove(CACHE_CONTROL_FIELD);
+            }
+            newest_kept = true;
+        }
+    }
+
+    let index = hints.leading_messages.min(messages.len()) - 1;
+    if let Some(block) = last_content_block(&mut messages[index]) {
+        block.insert(
+            CACHE_CONTROL_FIELD.to_string(),
+            json!({ TYPE_FIELD: "ephemeral" }),
+        );
+    }
+}
+
+fn last_content_block(message: &mut Value) -> Option<&mut serde_json::Map<String, Value>> {
+    message
+        .get_mut(CONTENT_FIELD)?
+        .as_array_mut()?
+        .last_mut()?
+        .as_object_mut()
+}
+
 /// Convert Anthropic's API response to internal Message format
 pub fn response_to_message(response: &Value) -> Result<Message> {
     let content_blocks = response
@@ -384,9 +431,12 @@ pub fn create_request(
     messages: &[Message],
     tools: &[Tool],
 ) -> Result<Value> {
-    let anthropic_messages = format_messages(messages);
+    let mut anthropic_messages = format_messages(messages);
     let tool_specs = format_tools(tools);
-    let system_spec = format_system(system);
+    let mut system_spec = format_system(system);
+    if let Some(hints) = &model_config.prompt_cache {
+        apply_prompt_cache_hints(hints, &mut system_spec, &mut anthropic_messages);
+    }
 
     // Check if we have any messages to send
     if anthropic_messages.is_empty() {
@@ -421,7 +471,9 @@ pub fn create_request(
     // Add temperature if specified and not using extended thinking model
     if let Some(temp) = model_config.temperature {
         // Claude 3.7 models with thinking enabled don't support temperature
-        if !model_config.model_name.starts_with("claude-3-7-sonnet-") {
+        if !model_config.model_name.starts_with("claude-3-7-sonnet-")
+            && model_config.thinking_budget.is_none()
+        {
             payload
                 .as_object_mut()
                 .unwrap()
@@ -429,15 +481,24 @@ pub fn create_request(
         }
     }
 
-    // Add thinking parameters for claude-3-7-sonnet model
+    // Add thinking parameters for claude-3-7-sonnet model, or any model given a budget
     let is_thinking_enabled = std::env::var("CLAUDE_THINKING_ENABLED").is_ok();
-    if model_config.model_name.starts_with("claude-3-7-sonnet-") && is_thinking_enabled {
-        // Minimum budget_tokens is 1024
-        let budget_tokens = std::env::var("CLAUDE_THINKING_BUDGET")
-            .unwrap_or_else(|_| "16000".to_string())
-            .parse()
-            .unwrap_or(16000);
-
+    let budget_tokens = match model_config.thinking_budget {
+        Some(budget) => Some(budget as i32),
+        None if model_config.model_name.starts_with("claude-3-7-sonnet-")
+            && is_thinking_enabled =>
+        {
+            // Minimum budget_tokens is 1024
+            Some(
+                std::env::var("CLAUDE_THINKING_BUDGET")
+                    .unwrap_or_else(|_| "16000".to_string())
+                    .parse()
+                    .unwrap_or(16000),
+            )
+        }
+        None => None,
+    };
+    if let Some(budget_tokens) = budget_tokens {
         payload
             .as_object_mut()
             .unwrap()
@@ -484,6 +545,7 @@ where
         let mut accumulated_tool_calls: std::collections::HashMap<String, (String, String)> = std::collections::HashMap::new();
         let mut current_tool_id: Option<String> = None;
         let mut final_usage: Option<crate::providers::base::ProviderUsage> = None;
+        let mut finish_reason: Option<crate::providers::base::FinishReason> = None;
         let mut message_id: Option<String> = None;
 
         while let Some(line_result) = stream.next().await {
@@ -622,6 +684,9 @@ where
                 "message_delta" => {
                     // Message metadata delta (like stop_reason) and cumulative usage
                     tracing::debug!("🔍 Anthropic message_delta event data: {}", serde_json::to_string_pretty(&event.data).unwrap_or_else(|_| format!("{:?}", event.data)));
+                    if let Some(stop_reason) = event.data.get("delta").and_then(|d| d.get("stop_reason")).and_then(|v| v.as_str()) {
+                        finish_reason = Some(crate::providers::base::FinishReason::from_provider(stop_reason));
+                    }
                     if let Some(usage_data) = event.data.get("usage") {
                         tracing::debug!("🔍 Anthropic message_delta usage data (cumulative): {}", serde_json::to_string_pretty(usage_data).unwrap_or_else(|_| format!("{:?}", usage_data)));
                         let delta_usage = get_usage(usage_data).unwrap_or_default();
@@ -684,9 +749,14 @@ where
             }
         }
 
+        // A stop reason without usage still needs to reach the caller
+        if final_usage.is_none() && finish_reason.is_some() {
+            final_usage = Some(crate::providers::base::ProviderUsage::new("unknown".to_string(), Usage::default()));
+        }
+
         // Yield final usage information if available
         if let Some(usage) = final_usage {
-            yield (None, Some(usage));
+            yield (None, Some(usage.with_finish_reason(finish_reason)));
         } else {
             tracing::debug!("🔍 Anthropic no final usage to yield");
         }
@@ -930,6 +1000,53 @@ mod tests {
         assert!(spec_array[0].get("cache_control").is_some());
     }
 
+    #[test]
+    fn test_create_request_with_model_thinking_budget() -> Result<()> {
+        let model_config = ModelConfig::new_or_fail("claude-sonnet-4-20250514")
+            .with_temperature(Some(0.2))
+            .with_thinking_budget(Some(2048));
+        let messages = vec![Message::user().with_text("Hello")];
+
+        let payload = create_request(&model_config, "", &messages, &[])?;
+
+        assert_eq!(payload["thinking"]["type"], "enabled");
+        assert_eq!(payload["thinking"]["budget_tokens"], 2048);
+        assert_eq!(payload["max_tokens"], 8192 + 2048);
+        assert!(payload.get("temperature").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_request_with_prompt_cache_hints() -> Result<()> {
+        let model_config = ModelConfig::new_or_fail("claude-sonnet-4-20250514").with_prompt_cache(
+            Some(PromptCacheHints {
+                system_prompt: false,
+                leading_messages: 1,
+            }),
+        );
+        let messages = vec![
+            Message::user().with_text("Here is the project background"),
+            Message::assistant().with_text("Got it"),
+            Message::user().with_text("First question"),
+            Message::assistant().with_text("First answer"),
+            Message::user().with_text("Second question"),
+        ];
+
+        let payload = create_request(&model_config, "You are helpful", &messages, &[])?;
+
+        assert!(payload["system"][0].get(CACHE_CONTROL_FIELD).is_none());
+        let marked: Vec<usize> = payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m["content"][0].get(CACHE_CONTROL_FIELD).is_some())
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(marked, vec![0, 4]);
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_with_thinking() -> Result<()> {
         let original_value = std::env::var("CLAUDE_THINKING_ENABLED").ok();
@@ -1043,4 +1160,30 @@ mod tests {
         );
         assert_eq!(spec[1]["content"][0]["is_error"], true);
     }
+
+    #[tokio::test]
+    async fn test_streaming_reports_stop_reason() -> Result<()> {
+        use futures::StreamExt;
+
+        let lines = [
+            r#"data: {"type": "message_start", "message": {"id": "msg_1", "model": "claude-test", "usage": {"input_tokens": 12, "output_tokens": 1}}}"#,
+            r#"data: {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "The answer is"}}"#,
+            r#"data: {"type": "message_delta", "delta": {"stop_reason": "max_tokens", "stop_sequence": null}, "usage": {"output_tokens": 4096}}"#,
+            r#"data: {"type": "message_stop"}"#,
+        ];
+        let stream = futures::stream::iter(lines.map(|line| Ok(line.to_string())));
+        let items: Vec<_> = response_to_streaming_message(stream).collect().await;
+
+        let usage = items
+            .into_iter()
+            .rev()
+            .find_map(|item| item.ok().and_then(|(_, usage)| usage))
+            .expect("the stream should end with usage");
+        assert_eq!(
+            usage.finish_reason,
+            Some(crate::providers::base::FinishReason::Length)
+        );
+        assert_eq!(usage.usage.output_tokens, Some(4096));
+        Ok(())
+    }
 }
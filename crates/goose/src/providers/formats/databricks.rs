@@ -985,6 +985,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1017,6 +1020,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1050,6 +1056,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
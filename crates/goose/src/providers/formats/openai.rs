@@ -1,6 +1,6 @@
 use crate::conversation::message::{Message, MessageContent};
-use crate::model::ModelConfig;
-use crate::providers::base::{ProviderUsage, Usage};
+use crate::model::{ModelConfig, ResponseFormat};
+use crate::providers::base::{FinishReason, ProviderUsage, Usage};
 use crate::providers::utils::{
     convert_image, detect_image_path, is_valid_function_name, load_image_file, safely_parse_json,
     sanitize_function_name, ImageFormat,
@@ -465,10 +465,13 @@ where
 
             let usage = chunk.usage.as_ref().and_then(|u| {
                 chunk.model.as_ref().map(|model| {
-                    ProviderUsage {
-                        usage: get_usage(u),
-                        model: model.clone(),
-                    }
+                    ProviderUsage::new(model.clone(), get_usage(u)).with_finish_reason(
+                        chunk
+                            .choices
+                            .first()
+                            .and_then(|choice| choice.finish_reason.as_deref())
+                            .map(FinishReason::from_provider),
+                    )
                 })
             });
 
@@ -703,6 +706,13 @@ pub fn create_request(
             .unwrap()
             .insert(key.to_string(), json!(tokens));
     }
+
+    if model_config.response_format == Some(ResponseFormat::Json) {
+        payload.as_object_mut().unwrap().insert(
+            "response_format".to_string(),
+            json!({"type": "json_object"}),
+        );
+    }
     Ok(payload)
 }
 
@@ -1260,6 +1270,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1281,6 +1294,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_create_request_json_response_format() -> anyhow::Result<()> {
+        let model_config =
+            ModelConfig::new_or_fail("gpt-4o").with_response_format(Some(ResponseFormat::Json));
+        let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
+        assert_eq!(request["response_format"], json!({"type": "json_object"}));
+
+        let plain = create_request(
+            &ModelConfig::new_or_fail("gpt-4o"),
+            "system",
+            &[],
+            &[],
+            &ImageFormat::OpenAi,
+        )?;
+        assert!(plain.get("response_format").is_none());
+        Ok(())
+    }
+
     #[test]
     fn test_create_request_o1_default() -> anyhow::Result<()> {
         // Test default medium reasoning effort for O1 model
@@ -1292,6 +1323,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
@@ -1325,6 +1359,9 @@ mod tests {
             toolshim: false,
             toolshim_model: None,
             fast_model: None,
+            thinking_budget: None,
+            response_format: None,
+            prompt_cache: None,
         };
         let request = create_request(&model_config, "system", &[], &[], &ImageFormat::OpenAi)?;
         let obj = request.as_object().unwrap();
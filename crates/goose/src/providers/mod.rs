@@ -26,8 +26,10 @@ pub mod openrouter;
 pub mod pricing;
 pub mod provider_registry;
 pub mod provider_test;
-mod retry;
+pub(crate) mod retry;
 pub mod sagemaker_tgi;
+#[cfg(test)]
+pub mod scriptedprovider;
 pub mod snowflake;
 pub mod testprovider;
 pub mod tetrate;
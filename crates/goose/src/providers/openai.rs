@@ -12,7 +12,9 @@ use tokio_util::codec::{FramedRead, LinesCodec};
 use tokio_util::io::StreamReader;
 
 use super::api_client::{ApiClient, AuthMethod};
-use super::base::{ConfigKey, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage};
+use super::base::{
+    ConfigKey, FinishReason, ModelInfo, Provider, ProviderMetadata, ProviderUsage, Usage,
+};
 use super::embedding::{EmbeddingCapable, EmbeddingRequest, EmbeddingResponse};
 use super::errors::ProviderError;
 use super::formats::openai::{create_request, get_usage, response_to_message};
@@ -261,8 +263,15 @@ impl Provider for OpenAiProvider {
             });
 
         let model = get_model(&json_response);
+        let finish_reason = json_response
+            .pointer("/choices/0/finish_reason")
+            .and_then(|v| v.as_str())
+            .map(FinishReason::from_provider);
         log.write(&json_response, Some(&usage))?;
-        Ok((message, ProviderUsage::new(model, usage)))
+        Ok((
+            message,
+            ProviderUsage::new(model, usage).with_finish_reason(finish_reason),
+        ))
     }
 
     async fn fetch_supported_models(&self) -> Result<Option<Vec<String>>, ProviderError> {
@@ -313,19 +322,34 @@ impl Provider for OpenAiProvider {
         self.supports_streaming
     }
 
+    fn supports_response_format(&self) -> bool {
+        true
+    }
+
     async fn stream(
         &self,
         system: &str,
         messages: &[Message],
         tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_model(&self.model, system, messages, tools)
+            .await
+    }
+
+    async fn stream_with_model(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
     ) -> Result<MessageStream, ProviderError> {
         let mut payload =
-            create_request(&self.model, system, messages, tools, &ImageFormat::OpenAi)?;
+            create_request(model_config, system, messages, tools, &ImageFormat::OpenAi)?;
         payload["stream"] = serde_json::Value::Bool(true);
         payload["stream_options"] = json!({
             "include_usage": true,
         });
-        let mut log = RequestLog::start(&self.model, &payload)?;
+        let mut log = RequestLog::start(model_config, &payload)?;
 
         let response = self
             .with_retry(|| async {
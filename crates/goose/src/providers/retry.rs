@@ -9,6 +9,7 @@ pub const DEFAULT_MAX_RETRIES: usize = 3;
 pub const DEFAULT_INITIAL_RETRY_INTERVAL_MS: u64 = 1000;
 pub const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
 pub const DEFAULT_MAX_RETRY_INTERVAL_MS: u64 = 30_000;
+const HONOR_RETRY_AFTER_KEY: &str = "GOOSE_PROVIDER_HONOR_RETRY_AFTER";
 
 #[derive(Debug, Clone)]
 pub struct RetryConfig {
@@ -20,6 +21,15 @@ pub struct RetryConfig {
     pub(crate) backoff_multiplier: f64,
     /// Maximum interval between retries in milliseconds
     pub(crate) max_interval_ms: u64,
+    /// Wait for the delay a rate-limited provider asks for instead of backing off
+    pub(crate) honor_retry_after: bool,
+}
+
+/// Read `GOOSE_PROVIDER_HONOR_RETRY_AFTER`, which defaults to true
+fn honor_retry_after_from_config() -> bool {
+    crate::config::Config::global()
+        .get_param::<bool>(HONOR_RETRY_AFTER_KEY)
+        .unwrap_or(true)
 }
 
 impl Default for RetryConfig {
@@ -29,6 +39,7 @@ impl Default for RetryConfig {
             initial_interval_ms: DEFAULT_INITIAL_RETRY_INTERVAL_MS,
             backoff_multiplier: DEFAULT_BACKOFF_MULTIPLIER,
             max_interval_ms: DEFAULT_MAX_RETRY_INTERVAL_MS,
+            honor_retry_after: honor_retry_after_from_config(),
         }
     }
 }
@@ -45,9 +56,15 @@ impl RetryConfig {
             initial_interval_ms,
             backoff_multiplier,
             max_interval_ms,
+            honor_retry_after: honor_retry_after_from_config(),
         }
     }
 
+    pub fn with_honor_retry_after(mut self, honor: bool) -> Self {
+        self.honor_retry_after = honor;
+        self
+    }
+
     pub fn delay_for_attempt(&self, attempt: usize) -> Duration {
         if attempt == 0 {
             return Duration::from_millis(0);
@@ -65,6 +82,31 @@ impl RetryConfig {
 
         Duration::from_millis(jitter_delay_ms)
     }
+
+    /// The delay before retrying after `error`, preferring a provider-suggested delay when enabled.
+    /// Suggestions longer than `max_interval_ms` are capped at it.
+    pub fn delay_for_error(&self, error: &ProviderError, attempt: usize) -> Duration {
+        match error {
+            ProviderError::RateLimitExceeded {
+                retry_delay: Some(provider_delay),
+                ..
+            } if self.honor_retry_after => {
+                (*provider_delay).min(Duration::from_millis(self.max_interval_ms))
+            }
+            _ => self.delay_for_attempt(attempt),
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Set while the caller retries failed requests itself
+    static CALLER_RETRIES: ();
+}
+
+/// Run `request` with `with_retry` making a single attempt, for callers with their own retry
+/// policy so the two don't multiply
+pub(crate) async fn without_provider_retry<F: Future>(request: F) -> F::Output {
+    CALLER_RETRIES.scope((), request).await
 }
 
 /// Trait for retry functionality to keep Provider dyn-compatible
@@ -81,7 +123,10 @@ pub trait ProviderRetry {
         T: Send,
     {
         let mut attempts = 0;
-        let config = self.retry_config();
+        let mut config = self.retry_config();
+        if CALLER_RETRIES.try_with(|_| ()).is_ok() {
+            config.max_retries = 0;
+        }
 
         loop {
             return match operation().await {
@@ -101,13 +146,7 @@ pub trait ProviderRetry {
                             error
                         );
 
-                        let delay = match &error {
-                            ProviderError::RateLimitExceeded {
-                                retry_delay: Some(provider_delay),
-                                ..
-                            } => *provider_delay,
-                            _ => config.delay_for_attempt(attempts),
-                        };
+                        let delay = config.delay_for_error(&error, attempts);
 
                         let skip_backoff = std::env::var("GOOSE_PROVIDER_SKIP_BACKOFF")
                             .unwrap_or_default()
@@ -136,3 +175,108 @@ impl<P: Provider> ProviderRetry for P {
         Provider::retry_config(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::message::Message;
+    use crate::model::ModelConfig;
+    use crate::providers::base::{ProviderMetadata, ProviderUsage, Usage};
+    use rmcp::model::Tool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::time::Instant;
+
+    const SUGGESTED_DELAY: Duration = Duration::from_secs(7);
+
+    struct RateLimitedProvider {
+        calls: AtomicUsize,
+        retry_config: RetryConfig,
+    }
+
+    #[async_trait]
+    impl Provider for RateLimitedProvider {
+        fn metadata() -> ProviderMetadata {
+            ProviderMetadata::empty()
+        }
+
+        fn get_name(&self) -> &str {
+            "rate-limited"
+        }
+
+        fn get_model_config(&self) -> ModelConfig {
+            ModelConfig::new_or_fail("test-model")
+        }
+
+        fn retry_config(&self) -> RetryConfig {
+            self.retry_config.clone()
+        }
+
+        async fn complete_with_model(
+            &self,
+            _model_config: &ModelConfig,
+            _system: &str,
+            _messages: &[Message],
+            _tools: &[Tool],
+        ) -> Result<(Message, ProviderUsage), ProviderError> {
+            self.with_retry(|| async {
+                if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(ProviderError::RateLimitExceeded {
+                        details: "slow down".to_string(),
+                        retry_delay: Some(SUGGESTED_DELAY),
+                    });
+                }
+                Ok((
+                    Message::assistant().with_text("ok"),
+                    ProviderUsage::new("mock".to_string(), Usage::default()),
+                ))
+            })
+            .await
+        }
+    }
+
+    async fn time_until_success(max_interval_ms: u64, honor_retry_after: bool) -> Duration {
+        let provider = RateLimitedProvider {
+            calls: AtomicUsize::new(0),
+            retry_config: RetryConfig::new(1, 100, 2.0, max_interval_ms)
+                .with_honor_retry_after(honor_retry_after),
+        };
+        let start = Instant::now();
+        provider.complete("", &[], &[]).await.unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_retry_waits_for_suggested_delay() {
+        let elapsed = time_until_success(10_000, true).await;
+        assert!(elapsed >= SUGGESTED_DELAY);
+        assert!(elapsed < SUGGESTED_DELAY + Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_retry_uses_backoff_when_retry_after_is_ignored() {
+        let elapsed = time_until_success(10_000, false).await;
+        assert!(elapsed < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_without_provider_retry_makes_one_attempt() {
+        let provider = RateLimitedProvider {
+            calls: AtomicUsize::new(0),
+            retry_config: RetryConfig::new(3, 100, 2.0, 1000),
+        };
+        let result = without_provider_retry(provider.complete("", &[], &[])).await;
+        assert!(matches!(
+            result,
+            Err(ProviderError::RateLimitExceeded { .. })
+        ));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_retry_caps_suggested_delay_at_max() {
+        let elapsed = time_until_success(1000, true).await;
+        assert!(elapsed >= Duration::from_millis(1000));
+        assert!(elapsed < SUGGESTED_DELAY);
+    }
+}
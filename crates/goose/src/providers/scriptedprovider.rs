@@ -0,0 +1,264 @@
+//! A provider that answers from a script, for exercising the agent loop in unit tests
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rmcp::model::{CallToolRequestParam, Tool};
+
+use super::base::{
+    FinishReason, MessageStream, Provider, ProviderMetadata, ProviderUsage, SystemPromptPlacement,
+    Usage,
+};
+use super::errors::ProviderError;
+use crate::conversation::message::Message;
+use crate::conversation::Conversation;
+use crate::model::ModelConfig;
+
+/// A request received by a [`ScriptedProvider`]
+#[derive(Debug, Clone)]
+pub struct ScriptedRequest {
+    /// Position of this request among all requests the provider received
+    pub index: usize,
+    pub model_config: ModelConfig,
+    pub system: String,
+    pub messages: Vec<Message>,
+    pub tools: Vec<Tool>,
+    /// Whether the request came in through `stream_with_model`
+    pub streamed: bool,
+}
+
+type ScriptedResponder =
+    Arc<dyn Fn(&ScriptedRequest) -> Result<Message, ProviderError> + Send + Sync>;
+
+/// Provider that answers from a script instead of a model, for exercising the agent loop.
+///
+/// Every request is recorded and can be inspected with [`ScriptedProvider::requests`].
+/// Session naming answers with a fixed name and is neither scripted nor recorded.
+pub struct ScriptedProvider {
+    name: String,
+    responder: ScriptedResponder,
+    usage: Usage,
+    finish_reason: Option<FinishReason>,
+    delay: Option<Duration>,
+    streaming: bool,
+    thinking_budget: bool,
+    response_format: bool,
+    prompt_cache_hints: bool,
+    placement: SystemPromptPlacement,
+    requests: Mutex<Vec<ScriptedRequest>>,
+}
+
+impl ScriptedProvider {
+    pub fn new(
+        responder: impl Fn(&ScriptedRequest) -> Result<Message, ProviderError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: "scripted".to_string(),
+            responder: Arc::new(responder),
+            usage: Usage::default(),
+            finish_reason: None,
+            delay: None,
+            streaming: false,
+            thinking_budget: false,
+            response_format: false,
+            prompt_cache_hints: false,
+            placement: SystemPromptPlacement::Separate,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Always answers with `text`
+    pub fn replying(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self::new(move |_| Ok(Message::assistant().with_text(&text)))
+    }
+
+    /// Requests `calls` in a single turn, then answers "done" once they show up in the history
+    pub fn calling_tools(calls: Vec<CallToolRequestParam>) -> Self {
+        Self::new(move |request| {
+            if request.messages.iter().any(|m| m.is_tool_call()) {
+                return Ok(Message::assistant().with_text("done"));
+            }
+            Ok(calls
+                .iter()
+                .enumerate()
+                .fold(Message::assistant(), |message, (i, call)| {
+                    message.with_tool_request(format!("call_{i}"), Ok(call.clone()))
+                }))
+        })
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    pub fn with_finish_reason(mut self, finish_reason: FinishReason) -> Self {
+        self.finish_reason = Some(finish_reason);
+        self
+    }
+
+    /// Waits `delay` before answering each request
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Supports streaming, yielding each content item of a scripted reply as its own chunk
+    pub fn streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
+
+    pub fn with_thinking_budget_support(mut self) -> Self {
+        self.thinking_budget = true;
+        self
+    }
+
+    pub fn with_response_format_support(mut self) -> Self {
+        self.response_format = true;
+        self
+    }
+
+    pub fn with_prompt_cache_hint_support(mut self) -> Self {
+        self.prompt_cache_hints = true;
+        self
+    }
+
+    pub fn with_system_prompt_placement(mut self, placement: SystemPromptPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<ScriptedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    async fn respond(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+        streamed: bool,
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        let request = {
+            let mut requests = self.requests.lock().unwrap();
+            let request = ScriptedRequest {
+                index: requests.len(),
+                model_config: model_config.clone(),
+                system: system.to_string(),
+                messages: messages.to_vec(),
+                tools: tools.to_vec(),
+                streamed,
+            };
+            requests.push(request.clone());
+            request
+        };
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        let message = (self.responder)(&request)?;
+        let usage = ProviderUsage::new(model_config.model_name.clone(), self.usage)
+            .with_finish_reason(self.finish_reason.clone());
+        Ok((message, usage))
+    }
+}
+
+#[async_trait]
+impl Provider for ScriptedProvider {
+    fn metadata() -> ProviderMetadata {
+        ProviderMetadata::empty()
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_model_config(&self) -> ModelConfig {
+        ModelConfig::new_or_fail("mock-model")
+    }
+
+    async fn complete_with_model(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<(Message, ProviderUsage), ProviderError> {
+        self.respond(model_config, system, messages, tools, false)
+            .await
+    }
+
+    async fn stream(
+        &self,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        self.stream_with_model(&self.get_model_config(), system, messages, tools)
+            .await
+    }
+
+    async fn stream_with_model(
+        &self,
+        model_config: &ModelConfig,
+        system: &str,
+        messages: &[Message],
+        tools: &[Tool],
+    ) -> Result<MessageStream, ProviderError> {
+        if !self.streaming {
+            return Err(ProviderError::NotImplemented(
+                "streaming not implemented".to_string(),
+            ));
+        }
+        let (message, usage) = self
+            .respond(model_config, system, messages, tools, true)
+            .await?;
+        let mut chunks: Vec<Result<_, ProviderError>> = message
+            .content
+            .iter()
+            .map(|content| {
+                let mut chunk = message.clone();
+                chunk.content = vec![content.clone()];
+                Ok((Some(chunk), None))
+            })
+            .collect();
+        chunks.push(Ok((None, Some(usage))));
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    fn supports_thinking_budget(&self) -> bool {
+        self.thinking_budget
+    }
+
+    fn supports_response_format(&self) -> bool {
+        self.response_format
+    }
+
+    fn supports_prompt_cache_hints(&self) -> bool {
+        self.prompt_cache_hints
+    }
+
+    fn system_prompt_placement(&self) -> SystemPromptPlacement {
+        self.placement
+    }
+
+    async fn generate_session_name(
+        &self,
+        _messages: &Conversation,
+    ) -> Result<String, ProviderError> {
+        Ok("Scripted session".to_string())
+    }
+}
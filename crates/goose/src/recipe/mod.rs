@@ -6,6 +6,7 @@ use std::path::Path;
 
 use crate::agents::extension::ExtensionConfig;
 use crate::agents::types::RetryConfig;
+use crate::config::GooseMode;
 use crate::recipe::read_recipe_file_content::read_recipe_file;
 use crate::recipe::yaml_format_utils::reformat_fields_with_multiline_values;
 use crate::utils::contains_unicode_tags;
@@ -73,6 +74,9 @@ pub struct Recipe {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry: Option<RetryConfig>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<GooseMode>, // overrides the configured goose mode for sessions running this recipe
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -210,6 +214,7 @@ pub struct RecipeBuilder {
     response: Option<Response>,
     sub_recipes: Option<Vec<SubRecipe>>,
     retry: Option<RetryConfig>,
+    mode: Option<GooseMode>,
 }
 
 impl Recipe {
@@ -255,6 +260,7 @@ impl Recipe {
             response: None,
             sub_recipes: None,
             retry: None,
+            mode: None,
         }
     }
 
@@ -357,6 +363,11 @@ impl RecipeBuilder {
         self
     }
 
+    pub fn mode(mut self, mode: GooseMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
     pub fn build(self) -> Result<Recipe, &'static str> {
         let title = self.title.ok_or("Title is required")?;
         let description = self.description.ok_or("Description is required")?;
@@ -379,6 +390,7 @@ impl RecipeBuilder {
             response: self.response,
             sub_recipes: self.sub_recipes,
             retry: self.retry,
+            mode: self.mode,
         })
     }
 }
@@ -717,6 +729,7 @@ isGlobal: true"#;
             response: None,
             sub_recipes: None,
             retry: None,
+            mode: None,
         };
 
         assert!(!recipe.check_for_security_warnings());
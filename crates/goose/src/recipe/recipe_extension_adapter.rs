@@ -22,6 +22,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "stdio")]
     Stdio {
@@ -39,6 +41,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "builtin")]
     Builtin {
@@ -51,6 +55,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "platform")]
     Platform {
@@ -61,6 +67,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "streamable_http")]
     StreamableHttp {
@@ -79,6 +87,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "frontend")]
     Frontend {
@@ -91,6 +101,8 @@ enum RecipeExtensionConfigInternal {
         bundled: Option<bool>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
     #[serde(rename = "inline_python")]
     InlinePython {
@@ -103,6 +115,8 @@ enum RecipeExtensionConfigInternal {
         dependencies: Option<Vec<String>>,
         #[serde(default)]
         available_tools: Vec<String>,
+        #[serde(default)]
+        required: bool,
     },
 }
 
@@ -134,7 +148,8 @@ impl From<RecipeExtensionConfigInternal> for ExtensionConfig {
                 env_keys,
                 timeout,
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             Stdio {
                 cmd,
@@ -143,17 +158,20 @@ impl From<RecipeExtensionConfigInternal> for ExtensionConfig {
                 env_keys,
                 timeout,
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             Builtin {
                 display_name,
                 timeout,
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             Platform {
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             StreamableHttp {
                 uri,
@@ -162,19 +180,22 @@ impl From<RecipeExtensionConfigInternal> for ExtensionConfig {
                 headers,
                 timeout,
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             Frontend {
                 tools,
                 instructions,
                 bundled,
-                available_tools
+                available_tools,
+                required
             },
             InlinePython {
                 code,
                 timeout,
                 dependencies,
-                available_tools
+                available_tools,
+                required
             }
         )
     }
@@ -232,6 +253,7 @@ mod tests {
                 timeout,
                 bundled,
                 available_tools,
+                required,
             } => {
                 assert_eq!(name, "test-builtin");
                 assert_eq!(description, "");
@@ -242,6 +264,7 @@ mod tests {
                     available_tools,
                     &vec!["tool_a".to_string(), "tool_b".to_string()]
                 );
+                assert!(!required);
             }
             other => panic!("unexpected extension variant: {:?}", other),
         }
@@ -269,6 +292,7 @@ mod tests {
                 timeout,
                 bundled,
                 available_tools,
+                required,
             } => {
                 assert_eq!(name, "null-description-builtin");
                 assert_eq!(description, "");
@@ -276,6 +300,7 @@ mod tests {
                 assert!(timeout.is_none());
                 assert!(bundled.is_none());
                 assert!(available_tools.is_empty());
+                assert!(!required);
             }
             other => panic!("unexpected extension variant: {:?}", other),
         }
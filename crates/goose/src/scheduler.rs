@@ -113,6 +113,25 @@ pub struct ScheduledJob {
     pub current_session_id: Option<String>,
     #[serde(default)]
     pub process_start_time: Option<DateTime<Utc>>,
+    /// User who created the job, when known
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Triggers before this time are skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<DateTime<Utc>>,
+    /// Triggers after this time are skipped and the job is deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl ScheduledJob {
+    pub fn is_within_window(&self, now: DateTime<Utc>) -> bool {
+        self.valid_from.is_none_or(|from| now >= from) && !self.is_expired(now)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.valid_until.is_some_and(|until| now > until)
+    }
 }
 
 async fn persist_jobs(
@@ -190,7 +209,7 @@ impl Scheduler {
 
         let local_tz = Local::now().timezone();
 
-        Job::new_async_tz(&cron, local_tz, move |_uuid, _l| {
+        Job::new_async_tz(&cron, local_tz, move |uuid, l| {
             tracing::info!("Cron task triggered for job '{}'", job_for_task.id);
             let task_job_id = job_for_task.id.clone();
             let current_jobs_arc = jobs_arc.clone();
@@ -199,14 +218,34 @@ impl Scheduler {
             let running_tasks = running_tasks_arc.clone();
 
             Box::pin(async move {
-                let should_execute = {
+                let now = Utc::now();
+                let (should_execute, expired) = {
                     let jobs_guard = current_jobs_arc.lock().await;
                     jobs_guard
                         .get(&task_job_id)
-                        .map(|(_, j)| !j.paused)
-                        .unwrap_or(false)
+                        .map(|(_, j)| (!j.paused && j.is_within_window(now), j.is_expired(now)))
+                        .unwrap_or((false, false))
                 };
 
+                if expired {
+                    tracing::info!(
+                        "Job '{}' is past its valid_until time, removing it",
+                        task_job_id
+                    );
+                    current_jobs_arc.lock().await.remove(&task_job_id);
+                    if let Err(e) = l.remove(&uuid).await {
+                        tracing::error!(
+                            "Failed to unschedule expired job '{}': {}",
+                            task_job_id,
+                            e
+                        );
+                    }
+                    if let Err(e) = persist_jobs(&local_storage_path, &current_jobs_arc).await {
+                        tracing::error!("Failed to persist job removal: {}", e);
+                    }
+                    return;
+                }
+
                 if !should_execute {
                     return;
                 }
@@ -350,6 +389,9 @@ impl Scheduler {
                         paused: false,
                         current_session_id: None,
                         process_start_time: None,
+                        user_id: None,
+                        valid_from: None,
+                        valid_until: None,
                     };
                     self.add_scheduled_job(job, false).await
                 }
@@ -666,14 +708,22 @@ impl Scheduler {
             }
         }
 
+        if let Some(token) = self.running_tasks.lock().await.remove(sched_id) {
+            token.cancel();
+        }
+
+        // Clear the running state now rather than when the cancelled task unwinds, so the
+        // job does not keep reporting a session that is being torn down
         {
-            let tasks = self.running_tasks.lock().await;
-            if let Some(token) = tasks.get(sched_id) {
-                token.cancel();
+            let mut jobs_guard = self.jobs.lock().await;
+            if let Some((_, job)) = jobs_guard.get_mut(sched_id) {
+                job.currently_running = false;
+                job.current_session_id = None;
+                job.process_start_time = None;
             }
         }
 
-        Ok(())
+        persist_jobs(&self.storage_path, &self.jobs).await
     }
 
     pub async fn get_running_job_info(
@@ -763,6 +813,9 @@ async fn execute_job(
         schedule_id: Some(job.id.clone()),
         max_turns: None,
         retry_config: None,
+        user_id: None,
+        thinking_budget: None,
+        reply_deadline: None,
     };
 
     let session_id = session_config.id.clone();
@@ -900,6 +953,9 @@ mod tests {
             paused: false,
             current_session_id: None,
             process_start_time: None,
+            user_id: None,
+            valid_from: None,
+            valid_until: None,
         };
 
         scheduler.add_scheduled_job(job, true).await.unwrap();
@@ -925,6 +981,9 @@ mod tests {
             paused: false,
             current_session_id: None,
             process_start_time: None,
+            user_id: None,
+            valid_from: None,
+            valid_until: None,
         };
 
         scheduler.add_scheduled_job(job, true).await.unwrap();
@@ -934,4 +993,135 @@ mod tests {
         let jobs = scheduler.list_scheduled_jobs().await;
         assert!(jobs[0].last_run.is_none(), "Paused job should not run");
     }
+
+    fn windowed_job(
+        id: &str,
+        recipe_path: &Path,
+        valid_from: Option<DateTime<Utc>>,
+        valid_until: Option<DateTime<Utc>>,
+    ) -> ScheduledJob {
+        ScheduledJob {
+            id: id.to_string(),
+            source: recipe_path.to_string_lossy().to_string(),
+            cron: "* * * * * *".to_string(),
+            last_run: None,
+            currently_running: false,
+            paused: false,
+            current_session_id: None,
+            process_start_time: None,
+            user_id: None,
+            valid_from,
+            valid_until,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_inside_validity_window() {
+        let temp_dir = tempdir().unwrap();
+        let storage_path = temp_dir.path().join("schedules.json");
+        let recipe_path = create_test_recipe(temp_dir.path(), "windowed_job");
+        let scheduler = Scheduler::new(storage_path).await.unwrap();
+
+        let now = Utc::now();
+        let job = windowed_job(
+            "windowed_job",
+            &recipe_path,
+            Some(now - chrono::Duration::hours(1)),
+            Some(now + chrono::Duration::hours(1)),
+        );
+
+        scheduler.add_scheduled_job(job, true).await.unwrap();
+        sleep(Duration::from_millis(1500)).await;
+
+        let jobs = scheduler.list_scheduled_jobs().await;
+        assert!(
+            jobs[0].last_run.is_some(),
+            "Job inside its window should run"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_job_outside_validity_window_does_not_run() {
+        let temp_dir = tempdir().unwrap();
+        let storage_path = temp_dir.path().join("schedules.json");
+        let early_recipe = create_test_recipe(temp_dir.path(), "early_job");
+        let expired_recipe = create_test_recipe(temp_dir.path(), "expired_job");
+        let scheduler = Scheduler::new(storage_path.clone()).await.unwrap();
+
+        let now = Utc::now();
+        let early = windowed_job(
+            "early_job",
+            &early_recipe,
+            Some(now + chrono::Duration::hours(1)),
+            None,
+        );
+        let expired = windowed_job(
+            "expired_job",
+            &expired_recipe,
+            None,
+            Some(now - chrono::Duration::hours(1)),
+        );
+
+        scheduler.add_scheduled_job(early, true).await.unwrap();
+        scheduler.add_scheduled_job(expired, true).await.unwrap();
+        sleep(Duration::from_millis(1500)).await;
+
+        let jobs = scheduler.list_scheduled_jobs().await;
+        assert_eq!(jobs.len(), 1, "Expired job should be deleted");
+        assert_eq!(jobs[0].id, "early_job");
+        assert!(
+            jobs[0].last_run.is_none(),
+            "Job before its window should not run"
+        );
+
+        let persisted: Vec<ScheduledJob> =
+            serde_json::from_str(&fs::read_to_string(&storage_path).unwrap()).unwrap();
+        assert!(persisted.iter().all(|job| job.id != "expired_job"));
+    }
+
+    #[tokio::test]
+    async fn test_kill_clears_running_state() {
+        let temp_dir = tempdir().unwrap();
+        let storage_path = temp_dir.path().join("schedules.json");
+        let recipe_path = create_test_recipe(temp_dir.path(), "killed_job");
+        let scheduler = Scheduler::new(storage_path.clone()).await.unwrap();
+
+        // Keep the trigger from firing on its own while the job is marked as running
+        let job = windowed_job(
+            "killed_job",
+            &recipe_path,
+            Some(Utc::now() + chrono::Duration::hours(1)),
+            None,
+        );
+        scheduler.add_scheduled_job(job, true).await.unwrap();
+
+        let token = CancellationToken::new();
+        {
+            let mut jobs_guard = scheduler.jobs.lock().await;
+            let (_, job) = jobs_guard.get_mut("killed_job").unwrap();
+            job.currently_running = true;
+            job.current_session_id = Some("session_1".to_string());
+            job.process_start_time = Some(Utc::now());
+        }
+        scheduler
+            .running_tasks
+            .lock()
+            .await
+            .insert("killed_job".to_string(), token.clone());
+
+        scheduler.kill_running_job("killed_job").await.unwrap();
+
+        assert!(token.is_cancelled());
+        assert_eq!(
+            scheduler.get_running_job_info("killed_job").await.unwrap(),
+            None
+        );
+        let persisted: Vec<ScheduledJob> =
+            serde_json::from_str(&fs::read_to_string(&storage_path).unwrap()).unwrap();
+        for job in [&scheduler.list_scheduled_jobs().await[0], &persisted[0]] {
+            assert!(!job.currently_running);
+            assert_eq!(job.current_session_id, None);
+            assert_eq!(job.process_start_time, None);
+        }
+    }
 }
@@ -19,7 +19,18 @@ use tokio::sync::OnceCell;
 use tracing::{info, warn};
 use utoipa::ToSchema;
 
-const CURRENT_SCHEMA_VERSION: i32 = 6;
+const CURRENT_SCHEMA_VERSION: i32 = 7;
+/// Usage records a session collects before they are folded into its accumulated counts
+const USAGE_COMPACTION_THRESHOLD: i64 = 32;
+/// Per-session sums of the usage records not yet folded into the session row
+const PENDING_USAGE: &str = "SELECT session_id, SUM(total_tokens) AS total_tokens, \
+     SUM(input_tokens) AS input_tokens, SUM(output_tokens) AS output_tokens \
+     FROM usage_records GROUP BY session_id";
+/// The session's accumulated counts with its pending usage (`u`) folded in
+const ACCUMULATED_WITH_PENDING: &str = "\
+     COALESCE(s.accumulated_total_tokens + u.total_tokens, u.total_tokens, s.accumulated_total_tokens) AS accumulated_total_tokens, \
+     COALESCE(s.accumulated_input_tokens + u.input_tokens, u.input_tokens, s.accumulated_input_tokens) AS accumulated_input_tokens, \
+     COALESCE(s.accumulated_output_tokens + u.output_tokens, u.output_tokens, s.accumulated_output_tokens) AS accumulated_output_tokens";
 pub const SESSIONS_FOLDER: &str = "sessions";
 pub const DB_NAME: &str = "sessions.db";
 
@@ -115,6 +126,8 @@ pub struct SessionUpdateBuilder {
     user_recipe_values: Option<Option<HashMap<String, String>>>,
     provider_name: Option<Option<String>>,
     model_config: Option<Option<ModelConfig>>,
+    /// Total, input and output tokens of one turn, appended as a usage record
+    usage: Option<[Option<i32>; 3]>,
 }
 
 #[derive(Serialize, ToSchema, Debug)]
@@ -144,6 +157,7 @@ impl SessionUpdateBuilder {
             user_recipe_values: None,
             provider_name: None,
             model_config: None,
+            usage: None,
         }
     }
 
@@ -210,6 +224,20 @@ impl SessionUpdateBuilder {
         self
     }
 
+    /// Record the token usage of one turn. Each turn appends a small usage record that is
+    /// periodically folded into the accumulated counts, so concurrent turns aren't lost and a
+    /// write never rewrites the session's totals. A `None` count leaves the accumulated one
+    /// unchanged.
+    pub fn record_usage(
+        mut self,
+        total: Option<i32>,
+        input: Option<i32>,
+        output: Option<i32>,
+    ) -> Self {
+        self.usage = Some([total, input, output]);
+        self
+    }
+
     pub fn schedule_id(mut self, schedule_id: Option<String>) -> Self {
         self.schedule_id = Some(schedule_id);
         self
@@ -592,9 +620,27 @@ impl SessionStorage {
         .execute(&pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE usage_records (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL REFERENCES sessions(id),
+                total_tokens INTEGER,
+                input_tokens INTEGER,
+                output_tokens INTEGER,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            )
+        "#,
+        )
+        .execute(&pool)
+        .await?;
+
         sqlx::query("CREATE INDEX idx_messages_session ON messages(session_id)")
             .execute(&pool)
             .await?;
+        sqlx::query("CREATE INDEX idx_usage_records_session ON usage_records(session_id)")
+            .execute(&pool)
+            .await?;
         sqlx::query("CREATE INDEX idx_messages_timestamp ON messages(timestamp)")
             .execute(&pool)
             .await?;
@@ -843,6 +889,28 @@ impl SessionStorage {
                 .execute(&self.pool)
                 .await?;
             }
+            7 => {
+                sqlx::query(
+                    r#"
+                    CREATE TABLE IF NOT EXISTS usage_records (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        session_id TEXT NOT NULL REFERENCES sessions(id),
+                        total_tokens INTEGER,
+                        input_tokens INTEGER,
+                        output_tokens INTEGER,
+                        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                    )
+                "#,
+                )
+                .execute(&self.pool)
+                .await?;
+
+                sqlx::query(
+                    "CREATE INDEX IF NOT EXISTS idx_usage_records_session ON usage_records(session_id)",
+                )
+                .execute(&self.pool)
+                .await?;
+            }
             _ => {
                 anyhow::bail!("Unknown migration version: {}", version);
             }
@@ -892,17 +960,20 @@ impl SessionStorage {
     }
 
     async fn get_session(&self, id: &str, include_messages: bool) -> Result<Session> {
-        let mut session = sqlx::query_as::<_, Session>(
+        let query = format!(
             r#"
-        SELECT id, working_dir, name, description, user_set_name, session_type, created_at, updated_at, extension_data,
-               total_tokens, input_tokens, output_tokens,
-               accumulated_total_tokens, accumulated_input_tokens, accumulated_output_tokens,
-               schedule_id, recipe_json, user_recipe_values_json,
-               provider_name, model_config_json
-        FROM sessions
-        WHERE id = ?
+        SELECT s.id, s.working_dir, s.name, s.description, s.user_set_name, s.session_type, s.created_at, s.updated_at, s.extension_data,
+               s.total_tokens, s.input_tokens, s.output_tokens,
+               {},
+               s.schedule_id, s.recipe_json, s.user_recipe_values_json,
+               s.provider_name, s.model_config_json
+        FROM sessions s
+        LEFT JOIN ({}) u ON u.session_id = s.id
+        WHERE s.id = ?
     "#,
-        )
+            ACCUMULATED_WITH_PENDING, PENDING_USAGE
+        );
+        let mut session = sqlx::query_as::<_, Session>(&query)
             .bind(id)
             .fetch_optional(&self.pool)
             .await?
@@ -962,7 +1033,7 @@ impl SessionStorage {
         add_update!(builder.provider_name, "provider_name");
         add_update!(builder.model_config, "model_config_json");
 
-        if updates.is_empty() {
+        if updates.is_empty() && builder.usage.is_none() {
             return Ok(());
         }
 
@@ -1028,13 +1099,65 @@ impl SessionStorage {
         }
 
         let mut tx = self.pool.begin().await?;
-        q = q.bind(&builder.session_id);
-        q.execute(&mut *tx).await?;
+        if !updates.is_empty() {
+            q = q.bind(&builder.session_id);
+            q.execute(&mut *tx).await?;
+        }
+
+        if let Some([total, input, output]) = builder.usage {
+            sqlx::query(
+                "INSERT INTO usage_records (session_id, total_tokens, input_tokens, output_tokens) VALUES (?, ?, ?, ?)",
+            )
+            .bind(&builder.session_id)
+            .bind(total)
+            .bind(input)
+            .bind(output)
+            .execute(&mut *tx)
+            .await?;
+
+            let pending = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM usage_records WHERE session_id = ?",
+            )
+            .bind(&builder.session_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if pending >= USAGE_COMPACTION_THRESHOLD {
+                Self::compact_usage(&mut tx, &builder.session_id).await?;
+            }
+        }
 
         tx.commit().await?;
         Ok(())
     }
 
+    /// Fold a session's usage records into its accumulated counts and drop them.
+    async fn compact_usage(tx: &mut sqlx::Transaction<'_, Sqlite>, session_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE sessions SET
+                accumulated_total_tokens = COALESCE(accumulated_total_tokens + u.total_tokens, u.total_tokens, accumulated_total_tokens),
+                accumulated_input_tokens = COALESCE(accumulated_input_tokens + u.input_tokens, u.input_tokens, accumulated_input_tokens),
+                accumulated_output_tokens = COALESCE(accumulated_output_tokens + u.output_tokens, u.output_tokens, accumulated_output_tokens)
+            FROM (
+                SELECT SUM(total_tokens) AS total_tokens, SUM(input_tokens) AS input_tokens,
+                       SUM(output_tokens) AS output_tokens
+                FROM usage_records WHERE session_id = ?
+            ) u
+            WHERE id = ?
+            "#,
+        )
+        .bind(session_id)
+        .bind(session_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("DELETE FROM usage_records WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
     async fn get_conversation(&self, session_id: &str) -> Result<Conversation> {
         let rows = sqlx::query_as::<_, (String, String, i64, Option<String>)>(
             "SELECT role, content_json, created_timestamp, metadata_json FROM messages WHERE session_id = ? ORDER BY timestamp",
@@ -1139,17 +1262,18 @@ impl SessionStorage {
             r#"
             SELECT s.id, s.working_dir, s.name, s.description, s.user_set_name, s.session_type, s.created_at, s.updated_at, s.extension_data,
                    s.total_tokens, s.input_tokens, s.output_tokens,
-                   s.accumulated_total_tokens, s.accumulated_input_tokens, s.accumulated_output_tokens,
+                   {},
                    s.schedule_id, s.recipe_json, s.user_recipe_values_json,
                    s.provider_name, s.model_config_json,
                    COUNT(m.id) as message_count
             FROM sessions s
             INNER JOIN messages m ON s.id = m.session_id
+            LEFT JOIN ({}) u ON u.session_id = s.id
             WHERE s.session_type IN ({})
             GROUP BY s.id
             ORDER BY s.updated_at DESC
             "#,
-            placeholders
+            ACCUMULATED_WITH_PENDING, PENDING_USAGE, placeholders
         );
 
         let mut q = sqlx::query_as::<_, Session>(&query);
@@ -1183,6 +1307,11 @@ impl SessionStorage {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("DELETE FROM usage_records WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+
         sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(session_id)
             .execute(&mut *tx)
@@ -1193,13 +1322,16 @@ impl SessionStorage {
     }
 
     async fn get_insights(&self) -> Result<SessionInsights> {
-        let row = sqlx::query_as::<_, (i64, Option<i64>)>(
+        let query = format!(
             r#"
             SELECT COUNT(*) as total_sessions,
-                   COALESCE(SUM(COALESCE(accumulated_total_tokens, total_tokens, 0)), 0) as total_tokens
-            FROM sessions
+                   COALESCE(SUM(COALESCE(s.accumulated_total_tokens + u.total_tokens, u.total_tokens, s.accumulated_total_tokens, s.total_tokens, 0)), 0) as total_tokens
+            FROM sessions s
+            LEFT JOIN ({}) u ON u.session_id = s.id
             "#,
-        )
+            PENDING_USAGE
+        );
+        let row = sqlx::query_as::<_, (i64, Option<i64>)>(&query)
             .fetch_one(&self.pool)
             .await?;
 
@@ -1412,6 +1544,130 @@ mod tests {
         assert_eq!(insights.total_tokens, expected_tokens as i64);
     }
 
+    #[tokio::test]
+    async fn test_concurrent_token_accumulation() {
+        const TURNS: i32 = 50;
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(
+            SessionStorage::create(&temp_dir.path().join("test_sessions.db"))
+                .await
+                .unwrap(),
+        );
+        let session = storage
+            .create_session(
+                PathBuf::from("/tmp"),
+                "metrics".to_string(),
+                SessionType::User,
+            )
+            .await
+            .unwrap();
+        storage
+            .add_message(&session.id, &Message::user().with_text("hello"))
+            .await
+            .unwrap();
+
+        let handles: Vec<_> = (0..TURNS)
+            .map(|turn| {
+                let storage = Arc::clone(&storage);
+                let id = session.id.clone();
+                tokio::spawn(async move {
+                    // Some providers don't report output tokens
+                    let output = (turn % 5 != 0).then_some(3);
+                    storage
+                        .apply_update(
+                            SessionUpdateBuilder::new(id)
+                                .total_tokens(Some(10))
+                                .record_usage(Some(10), Some(7), output),
+                        )
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let updated = storage.get_session(&session.id, true).await.unwrap();
+        assert_eq!(updated.accumulated_total_tokens, Some(10 * TURNS));
+        assert_eq!(updated.accumulated_input_tokens, Some(7 * TURNS));
+        assert_eq!(
+            updated.accumulated_output_tokens,
+            Some(3 * (TURNS - TURNS / 5))
+        );
+        assert_eq!(updated.total_tokens, Some(10));
+        assert_eq!(updated.message_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_usage_records_are_compacted() {
+        const TURNS: i64 = 100;
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SessionStorage::create(&temp_dir.path().join("test_sessions.db"))
+            .await
+            .unwrap();
+        let session = storage
+            .create_session(
+                PathBuf::from("/tmp"),
+                "usage".to_string(),
+                SessionType::User,
+            )
+            .await
+            .unwrap();
+        storage
+            .add_message(&session.id, &Message::user().with_text("hello"))
+            .await
+            .unwrap();
+
+        for turn in 1..=TURNS {
+            storage
+                .apply_update(
+                    SessionUpdateBuilder::new(session.id.clone())
+                        .total_tokens(Some(10))
+                        .record_usage(Some(10), Some(7), None),
+                )
+                .await
+                .unwrap();
+
+            // A turn only appends a record; the session row's counts move at compaction
+            let pending = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM usage_records WHERE session_id = ?",
+            )
+            .bind(&session.id)
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+            assert_eq!(pending, turn % USAGE_COMPACTION_THRESHOLD);
+            let stored = sqlx::query_scalar::<_, Option<i32>>(
+                "SELECT accumulated_total_tokens FROM sessions WHERE id = ?",
+            )
+            .bind(&session.id)
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+            let compacted = (turn - pending) as i32;
+            assert_eq!(stored, (compacted > 0).then_some(10 * compacted));
+
+            let current = storage.get_session(&session.id, false).await.unwrap();
+            assert_eq!(current.accumulated_total_tokens, Some(10 * turn as i32));
+            assert_eq!(current.accumulated_input_tokens, Some(7 * turn as i32));
+            assert_eq!(current.accumulated_output_tokens, None);
+        }
+
+        let listed = storage.list_sessions().await.unwrap();
+        assert_eq!(listed[0].accumulated_total_tokens, Some(10 * TURNS as i32));
+        assert_eq!(listed[0].message_count, 1);
+        let insights = storage.get_insights().await.unwrap();
+        assert_eq!(insights.total_tokens, 10 * TURNS);
+
+        storage.delete_session(&session.id).await.unwrap();
+        let leftover = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM usage_records")
+            .fetch_one(&storage.pool)
+            .await
+            .unwrap();
+        assert_eq!(leftover, 0);
+    }
+
     #[tokio::test]
     async fn test_export_import_roundtrip() {
         const DESCRIPTION: &str = "Original session";
@@ -1,11 +1,13 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use crate::config::permission::PermissionLevel;
 use crate::config::GooseMode;
 use crate::conversation::message::{Message, ToolRequest};
 use crate::permission::permission_inspector::PermissionInspector;
 use crate::permission::permission_judge::PermissionCheckResult;
+use crate::tool_monitor::RepetitionInspector;
 
 /// Result of inspecting a tool call
 #[derive(Debug, Clone)]
@@ -136,7 +138,7 @@ impl ToolInspectionManager {
     pub async fn update_permission_manager(
         &self,
         tool_name: &str,
-        permission_level: crate::config::permission::PermissionLevel,
+        permission_level: PermissionLevel,
     ) {
         for inspector in &self.inspectors {
             if inspector.name() == "permission" {
@@ -154,6 +156,81 @@ impl ToolInspectionManager {
         tracing::warn!("Permission inspector not found for permission manager update");
     }
 
+    /// Allow `tool_name` without prompting for the rest of `session_id`
+    pub async fn allow_tool_for_session(&self, session_id: &str, tool_name: &str) {
+        match self.permission_inspector() {
+            Some(permission_inspector) => permission_inspector
+                .permission_manager
+                .lock()
+                .await
+                .allow_for_session(session_id, tool_name),
+            None => tracing::warn!("Permission inspector not found for session approval"),
+        }
+    }
+
+    /// Approve requests awaiting confirmation whose tool was allowed for the rest of
+    /// `session_id`, unless another inspector asked for approval of that request
+    pub async fn apply_session_approvals(
+        &self,
+        session_id: &str,
+        permission_check_result: &mut PermissionCheckResult,
+        inspection_results: &[InspectionResult],
+    ) {
+        let Some(permission_inspector) = self.permission_inspector() else {
+            return;
+        };
+        let permission_manager = permission_inspector.permission_manager.lock().await;
+        let flagged = |request: &ToolRequest| {
+            inspection_results.iter().any(|result| {
+                result.tool_request_id == request.id
+                    && result.inspector_name != "permission"
+                    && matches!(result.action, InspectionAction::RequireApproval(_))
+            })
+        };
+
+        let (allowed, still_pending): (Vec<ToolRequest>, Vec<ToolRequest>) =
+            std::mem::take(&mut permission_check_result.needs_approval)
+                .into_iter()
+                .partition(|request| {
+                    request.tool_call.as_ref().is_ok_and(|tool_call| {
+                        permission_manager.is_allowed_for_session(session_id, &tool_call.name)
+                    }) && !flagged(request)
+                });
+        permission_check_result.needs_approval = still_pending;
+        permission_check_result.approved.extend(allowed);
+    }
+
+    /// Permission levels the user has set for individual tools
+    pub async fn user_permissions(&self) -> BTreeMap<String, PermissionLevel> {
+        match self.permission_inspector() {
+            Some(permission_inspector) => permission_inspector
+                .permission_manager
+                .lock()
+                .await
+                .get_user_permissions(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    /// The repetition inspector's limit on identical consecutive calls, if any
+    pub fn max_tool_repetitions(&self) -> Option<u32> {
+        self.repetition_inspector()
+            .and_then(RepetitionInspector::max_repetitions)
+    }
+
+    pub(crate) fn repetition_inspector(&self) -> Option<&RepetitionInspector> {
+        self.inspectors
+            .iter()
+            .find_map(|inspector| inspector.as_any().downcast_ref::<RepetitionInspector>())
+    }
+
+    pub(crate) fn permission_inspector(&self) -> Option<&PermissionInspector> {
+        self.inspectors
+            .iter()
+            .find(|inspector| inspector.name() == "permission")
+            .and_then(|inspector| inspector.as_any().downcast_ref::<PermissionInspector>())
+    }
+
     /// Process inspection results using the permission inspector
     /// This delegates to the permission inspector's process_inspection_results method
     pub fn process_inspection_results_with_permission_inspector(
@@ -3,11 +3,35 @@ use crate::tool_inspection::{InspectionAction, InspectionResult, ToolInspector};
 use anyhow::Result;
 use async_trait::async_trait;
 use rmcp::model::CallToolRequestParam;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Canonicalizes tool arguments before calls are compared, so calls that differ only
+/// trivially count as repeats
+pub type ArgumentNormalizer = Arc<dyn Fn(&Value) -> Value + Send + Sync>;
+
+/// The default normalizer: sorts object keys recursively
+pub fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sort_keys).collect()),
+        other => other.clone(),
+    }
+}
 
 // Helper struct for internal tracking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct InternalToolCall {
     name: String,
     parameters: Value,
@@ -18,70 +42,155 @@ impl InternalToolCall {
         self.name == other.name && self.parameters == other.parameters
     }
 
-    fn from_tool_call(tool_call: &CallToolRequestParam) -> Self {
+    fn from_tool_call(tool_call: &CallToolRequestParam, normalizer: &ArgumentNormalizer) -> Self {
         let name = tool_call.name.to_string();
         let parameters = tool_call
             .arguments
             .as_ref()
-            .map(|obj| Value::Object(obj.clone()))
+            .map(|obj| normalizer(&Value::Object(obj.clone())))
             .unwrap_or(Value::Null);
         Self { name, parameters }
     }
+
+    /// Key for the call counts: the tool name followed by its normalized arguments
+    fn key(&self) -> String {
+        format!("{} {}", self.name, self.parameters)
+    }
 }
 
-#[derive(Debug)]
-pub struct RepetitionInspector {
-    max_repetitions: Option<u32>,
+/// The calls a [`RepetitionInspector`] has seen, serializable so counts survive a restart
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RepetitionState {
     last_call: Option<InternalToolCall>,
     repeat_count: u32,
     call_counts: HashMap<String, u32>,
 }
 
-impl RepetitionInspector {
-    pub fn new(max_repetitions: Option<u32>) -> Self {
-        Self {
-            max_repetitions,
-            last_call: None,
-            repeat_count: 0,
-            call_counts: HashMap::new(),
-        }
-    }
+impl RepetitionState {
+    fn check(&mut self, call: InternalToolCall, max_repetitions: Option<u32>) -> bool {
+        *self.call_counts.entry(call.key()).or_insert(0) += 1;
 
-    pub fn check_tool_call(&mut self, tool_call: CallToolRequestParam) -> bool {
-        let internal_call = InternalToolCall::from_tool_call(&tool_call);
-        let total_calls = self
-            .call_counts
-            .entry(internal_call.name.clone())
-            .or_insert(0);
-        *total_calls += 1;
-
-        if self.max_repetitions.is_none() {
-            self.last_call = Some(internal_call);
+        let Some(max_repetitions) = max_repetitions else {
+            self.last_call = Some(call);
             self.repeat_count = 1;
             return true;
-        }
+        };
 
-        if let Some(last) = &self.last_call {
-            if last.matches(&internal_call) {
-                self.repeat_count += 1;
-                if self.repeat_count > self.max_repetitions.unwrap() {
-                    return false;
-                }
-            } else {
-                self.repeat_count = 1;
+        if self
+            .last_call
+            .as_ref()
+            .is_some_and(|last| last.matches(&call))
+        {
+            self.repeat_count += 1;
+            if self.repeat_count > max_repetitions {
+                return false;
             }
         } else {
             self.repeat_count = 1;
         }
 
-        self.last_call = Some(internal_call);
+        self.last_call = Some(call);
         true
     }
 
+    /// Add `other`'s counts to these. The current run of repeats continues `other`'s when both
+    /// end with the same call, and is taken from `other` when nothing has been called yet.
+    fn merge(&mut self, other: RepetitionState) {
+        for (key, count) in other.call_counts {
+            *self.call_counts.entry(key).or_insert(0) += count;
+        }
+        match (&self.last_call, other.last_call) {
+            (None, last_call) => {
+                self.last_call = last_call;
+                self.repeat_count = other.repeat_count;
+            }
+            (Some(current), Some(last_call)) if current.matches(&last_call) => {
+                self.repeat_count += other.repeat_count;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct RepetitionInspector {
+    max_repetitions: Mutex<Option<u32>>,
+    normalizer: ArgumentNormalizer,
+    state: Mutex<RepetitionState>,
+}
+
+impl std::fmt::Debug for RepetitionInspector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepetitionInspector")
+            .field("max_repetitions", &self.max_repetitions)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RepetitionInspector {
+    pub fn new(max_repetitions: Option<u32>) -> Self {
+        Self::with_normalizer(max_repetitions, sort_keys)
+    }
+
+    /// Compare calls by their arguments after passing them through `normalizer`, e.g. to
+    /// ignore whitespace in a shell command
+    pub fn with_normalizer<F>(max_repetitions: Option<u32>, normalizer: F) -> Self
+    where
+        F: Fn(&Value) -> Value + Send + Sync + 'static,
+    {
+        Self {
+            max_repetitions: Mutex::new(max_repetitions),
+            normalizer: Arc::new(normalizer),
+            state: Mutex::new(RepetitionState::default()),
+        }
+    }
+
+    /// Identical consecutive calls allowed before further ones are denied, if limited
+    pub fn max_repetitions(&self) -> Option<u32> {
+        *self.max_repetitions.lock().unwrap()
+    }
+
+    pub fn set_max_repetitions(&self, max_repetitions: Option<u32>) {
+        *self.max_repetitions.lock().unwrap() = max_repetitions;
+    }
+
+    /// How often each call was checked, keyed by tool name and normalized arguments
+    pub fn get_stats(&self) -> HashMap<String, u32> {
+        self.state.lock().unwrap().call_counts.clone()
+    }
+
+    pub fn check_tool_call(&mut self, tool_call: CallToolRequestParam) -> bool {
+        let internal_call = InternalToolCall::from_tool_call(&tool_call, &self.normalizer);
+        let max_repetitions = *self.max_repetitions.get_mut().unwrap();
+        self.state
+            .get_mut()
+            .unwrap()
+            .check(internal_call, max_repetitions)
+    }
+
+    /// Count a call that passed inspection and is being run, so later ones are checked against it
+    pub fn record_tool_call(&self, tool_call: &CallToolRequestParam) {
+        let internal_call = InternalToolCall::from_tool_call(tool_call, &self.normalizer);
+        let max_repetitions = self.max_repetitions();
+        self.state
+            .lock()
+            .unwrap()
+            .check(internal_call, max_repetitions);
+    }
+
     pub fn reset(&mut self) {
-        self.last_call = None;
-        self.repeat_count = 0;
-        self.call_counts.clear();
+        *self.state.get_mut().unwrap() = RepetitionState::default();
+    }
+
+    /// A copy of the counts seen so far, e.g. to persist with the session
+    pub fn export_state(&self) -> RepetitionState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Add counts from `export_state` to this inspector's own, so a limit keeps applying to
+    /// calls made before a restart
+    pub fn import_state(&self, state: RepetitionState) {
+        self.state.lock().unwrap().merge(state);
     }
 }
 
@@ -101,17 +210,16 @@ impl ToolInspector for RepetitionInspector {
         _messages: &[Message],
     ) -> Result<Vec<InspectionResult>> {
         let mut results = Vec::new();
+        let max_repetitions = self.max_repetitions();
 
         // Check repetition limits for each tool request
         for tool_request in tool_requests {
             if let Ok(tool_call) = &tool_request.tool_call {
-                // Create a temporary clone to check without modifying state
-                let mut temp_inspector = RepetitionInspector::new(self.max_repetitions);
-                temp_inspector.last_call = self.last_call.clone();
-                temp_inspector.repeat_count = self.repeat_count;
-                temp_inspector.call_counts = self.call_counts.clone();
+                // Check against a copy of the state without modifying it
+                let mut temp_state = self.state.lock().unwrap().clone();
+                let internal_call = InternalToolCall::from_tool_call(tool_call, &self.normalizer);
 
-                if !temp_inspector.check_tool_call(tool_call.clone()) {
+                if !temp_state.check(internal_call, max_repetitions) {
                     results.push(InspectionResult {
                         tool_request_id: tool_request.id.clone(),
                         action: InspectionAction::Deny,
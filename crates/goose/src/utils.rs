@@ -47,6 +47,14 @@ pub fn is_token_cancelled(cancellation_token: &Option<CancellationToken>) -> boo
         .is_some_and(|t| t.is_cancelled())
 }
 
+/// Resolves once `cancellation_token` is cancelled; never resolves without a token
+pub async fn token_cancelled(cancellation_token: Option<CancellationToken>) {
+    match cancellation_token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -18,17 +18,20 @@ mod tests {
         use goose::scheduler::{ScheduledJob, SchedulerError};
         use goose::scheduler_trait::SchedulerTrait;
         use goose::session::Session;
+        use std::collections::HashMap;
         use std::path::PathBuf;
         use std::sync::Arc;
 
         struct MockScheduler {
             jobs: tokio::sync::Mutex<Vec<ScheduledJob>>,
+            running: tokio::sync::Mutex<HashMap<String, (String, DateTime<Utc>)>>,
         }
 
         impl MockScheduler {
             fn new() -> Self {
                 Self {
                     jobs: tokio::sync::Mutex::new(Vec::new()),
+                    running: tokio::sync::Mutex::new(HashMap::new()),
                 }
             }
         }
@@ -106,9 +109,25 @@ mod tests {
 
             async fn get_running_job_info(
                 &self,
-                _sched_id: &str,
+                sched_id: &str,
             ) -> Result<Option<(String, DateTime<Utc>)>, SchedulerError> {
-                Ok(None)
+                Ok(self.running.lock().await.get(sched_id).cloned())
+            }
+        }
+
+        fn test_job(id: &str) -> ScheduledJob {
+            ScheduledJob {
+                id: id.to_string(),
+                source: format!("/tmp/{}.yaml", id),
+                cron: "0 0 * * * *".to_string(),
+                last_run: None,
+                currently_running: false,
+                paused: false,
+                current_session_id: None,
+                process_start_time: None,
+                user_id: None,
+                valid_from: None,
+                valid_until: None,
             }
         }
 
@@ -133,6 +152,555 @@ mod tests {
                 .contains("Manage scheduled recipe execution"));
         }
 
+        #[tokio::test]
+        async fn test_schedule_management_running_action() {
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            mock_scheduler
+                .jobs
+                .lock()
+                .await
+                .extend([test_job("nightly_report"), test_job("idle_job")]);
+            let started = Utc::now();
+            mock_scheduler.running.lock().await.insert(
+                "nightly_report".to_string(),
+                ("session_42".to_string(), started),
+            );
+            agent.set_scheduler(mock_scheduler.clone()).await;
+
+            let result = agent
+                .handle_schedule_management(
+                    serde_json::json!({"action": "running"}),
+                    "req_1".to_string(),
+                    None,
+                )
+                .await
+                .expect("running action should succeed");
+
+            let text = result[0].as_text().unwrap().text.clone();
+            assert!(text.contains("nightly_report"));
+            assert!(text.contains("session_42"));
+            assert!(text.contains(&started.to_rfc3339()));
+            assert!(!text.contains("idle_job"));
+        }
+
+        #[tokio::test]
+        async fn test_list_and_inspect_describe_cron_schedule() {
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            let mut job = test_job("morning_digest");
+            job.cron = "0 0 5 * * *".to_string();
+            mock_scheduler.jobs.lock().await.push(job);
+            agent.set_scheduler(mock_scheduler.clone()).await;
+
+            for arguments in [
+                serde_json::json!({"action": "list"}),
+                serde_json::json!({"action": "inspect", "job_id": "morning_digest"}),
+            ] {
+                let result = agent
+                    .handle_schedule_management(arguments, "req_1".to_string(), None)
+                    .await
+                    .expect("action should succeed");
+                let text = result[0].as_text().unwrap().text.clone();
+                assert!(text.contains("At 05:00 every day"), "{}", text);
+            }
+        }
+
+        #[tokio::test]
+        async fn test_run_now_with_unknown_job_id_is_flagged() -> anyhow::Result<()> {
+            use goose::agents::reference_validator::ReferenceValidator;
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+            use rmcp::model::CallToolRequestParam;
+
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            mock_scheduler
+                .jobs
+                .lock()
+                .await
+                .push(test_job("daily_report"));
+            agent.set_scheduler(mock_scheduler).await;
+            agent
+                .set_reference_validator(Some(Arc::new(ReferenceValidator::default())))
+                .await;
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "reference-validator-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+
+            let mut texts = Vec::new();
+            for arguments in [
+                rmcp::object!({"action": "list"}),
+                rmcp::object!({"action": "run_now", "job_id": "daily_report"}),
+                rmcp::object!({"action": "run_now", "job_id": "weekly_digest"}),
+            ] {
+                let tool_call = CallToolRequestParam {
+                    name: PLATFORM_MANAGE_SCHEDULE_TOOL_NAME.into(),
+                    arguments: Some(arguments),
+                };
+                let (_, result) = agent
+                    .dispatch_tool_call(tool_call, "req_1".to_string(), None, &session)
+                    .await;
+                let content = result
+                    .map_err(|e| anyhow::anyhow!(e.message))?
+                    .result
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))?;
+                texts.push(
+                    content
+                        .iter()
+                        .filter_map(|c| c.as_text().map(|t| t.text.clone()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+
+            assert!(!texts[1].contains("may not exist"));
+            assert!(texts[2].contains("job_id 'weekly_digest' did not appear"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_stream_job_output_follows_running_job() -> anyhow::Result<()> {
+            use goose::conversation::message::Message;
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "job-output-test".to_string(),
+                SessionType::Scheduled,
+            )
+            .await?;
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            mock_scheduler
+                .jobs
+                .lock()
+                .await
+                .push(test_job("nightly_report"));
+            mock_scheduler.running.lock().await.insert(
+                "nightly_report".to_string(),
+                (session.id.clone(), Utc::now()),
+            );
+            let agent = Agent::new();
+            agent.set_scheduler(mock_scheduler.clone()).await;
+
+            SessionManager::add_message(&session.id, &Message::user().with_text("Run the report"))
+                .await?;
+            SessionManager::add_message(
+                &session.id,
+                &Message::assistant().with_text("Collecting numbers"),
+            )
+            .await?;
+
+            let mut stream = agent.stream_job_output(&session.id);
+            let mut texts = Vec::new();
+            while let Some(event) =
+                tokio::time::timeout(std::time::Duration::from_secs(10), stream.next()).await?
+            {
+                if let AgentEvent::Message(message) = event? {
+                    texts.push(message.as_concat_text());
+                }
+                if texts.len() == 2 {
+                    // The job writes its last message and finishes
+                    SessionManager::add_message(
+                        &session.id,
+                        &Message::assistant().with_text("Report sent"),
+                    )
+                    .await?;
+                    mock_scheduler.running.lock().await.clear();
+                }
+            }
+
+            assert_eq!(
+                texts,
+                vec!["Run the report", "Collecting numbers", "Report sent"]
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_schedule_management_unknown_action_suggests_closest() {
+            let agent = Agent::new();
+            agent.set_scheduler(Arc::new(MockScheduler::new())).await;
+
+            let err = agent
+                .handle_schedule_management(
+                    serde_json::json!({"action": "creat"}),
+                    "req_1".to_string(),
+                    None,
+                )
+                .await
+                .expect_err("unknown action should fail");
+            assert!(err.message.contains("Did you mean 'create'?"));
+            assert!(err.message.contains("session_content"));
+
+            let err = agent
+                .handle_schedule_management(
+                    serde_json::json!({"action": "teleport"}),
+                    "req_2".to_string(),
+                    None,
+                )
+                .await
+                .expect_err("unknown action should fail");
+            assert!(!err.message.contains("Did you mean"));
+            assert!(err.message.contains("Valid actions: list, create"));
+        }
+
+        #[tokio::test]
+        async fn test_schedule_management_preview_lists_hourly_fire_times() {
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+
+            let result = agent
+                .handle_schedule_management(
+                    serde_json::json!({
+                        "action": "preview",
+                        "cron_expression": "0 0 * * * *",
+                        "timezone": "UTC",
+                        "count": 4,
+                    }),
+                    "req_1".to_string(),
+                    None,
+                )
+                .await
+                .expect("preview action should succeed");
+
+            let text = result[0].as_text().unwrap().text.clone();
+            let times: Vec<DateTime<Utc>> = text
+                .lines()
+                .skip(1)
+                .map(|line| {
+                    DateTime::parse_from_rfc3339(line)
+                        .unwrap()
+                        .with_timezone(&Utc)
+                })
+                .collect();
+            assert_eq!(times.len(), 4);
+            assert!(times[0] > Utc::now());
+            for pair in times.windows(2) {
+                assert_eq!((pair[1] - pair[0]).num_seconds(), 3600);
+            }
+            assert!(times
+                .iter()
+                .all(|t| t.format("%M:%S").to_string() == "00:00"));
+            assert!(mock_scheduler.jobs.lock().await.is_empty());
+
+            let err = agent
+                .handle_schedule_management(
+                    serde_json::json!({"action": "preview", "cron_expression": "0 0 * *"}),
+                    "req_2".to_string(),
+                    None,
+                )
+                .await
+                .expect_err("invalid cron should be rejected");
+            assert_eq!(err.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        }
+
+        #[tokio::test]
+        async fn test_create_job_from_inline_recipe() {
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+
+            let invalid = agent
+                .handle_schedule_management(
+                    serde_json::json!({
+                        "action": "create",
+                        "recipe_content": "title: [unterminated",
+                        "cron_expression": "0 0 9 * * *",
+                    }),
+                    "req_1".to_string(),
+                    None,
+                )
+                .await
+                .expect_err("malformed recipe content should be rejected");
+            assert!(invalid.message.contains("Invalid inline recipe"));
+            assert!(mock_scheduler.jobs.lock().await.is_empty());
+
+            agent
+                .handle_schedule_management(
+                    serde_json::json!({
+                        "action": "create",
+                        "recipe_content": r#"{"title": "Standup", "description": "Daily standup notes", "instructions": "Summarize yesterday's commits"}"#,
+                        "cron_expression": "0 0 9 * * *",
+                    }),
+                    "req_2".to_string(),
+                    None,
+                )
+                .await
+                .expect("inline recipe should be scheduled");
+
+            let jobs = mock_scheduler.jobs.lock().await;
+            assert_eq!(jobs.len(), 1);
+            let stored =
+                goose::recipe::Recipe::from_file_path(std::path::Path::new(&jobs[0].source))
+                    .expect("inline recipe should be saved as a recipe file");
+            assert_eq!(stored.title, "Standup");
+            std::fs::remove_file(&jobs[0].source).unwrap();
+        }
+
+        #[tokio::test]
+        async fn test_validate_reports_job_without_creating_it() {
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+            let recipe = r#"{"title": "Standup", "description": "Daily standup notes", "instructions": "Summarize yesterday's commits"}"#;
+
+            let result = agent
+                .handle_schedule_management(
+                    serde_json::json!({
+                        "action": "validate",
+                        "recipe_content": recipe,
+                        "cron_expression": "30 9 * * 1-5",
+                    }),
+                    "req_1".to_string(),
+                    None,
+                )
+                .await
+                .expect("valid arguments should pass validation");
+            let text = &result[0].as_text().unwrap().text;
+            assert!(text.contains("agent_created_"));
+            assert!(text.contains("'0 30 9 * * 1-5'"));
+            assert!(mock_scheduler.jobs.lock().await.is_empty());
+
+            let err = agent
+                .handle_schedule_management(
+                    serde_json::json!({
+                        "action": "validate",
+                        "recipe_content": recipe,
+                        "cron_expression": "30 9 *",
+                    }),
+                    "req_2".to_string(),
+                    None,
+                )
+                .await
+                .expect_err("a 3-field cron expression should be rejected");
+            assert!(err.message.contains("expected 5 fields"));
+            assert!(err.message.contains("got 3"));
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_job_guards_self_scheduling_recipe() {
+            let dir = tempfile::tempdir().unwrap();
+            let recipe_path = dir.path().join("self_scheduling.yaml");
+            std::fs::write(
+                &recipe_path,
+                "title: Spawner\ndescription: Schedules itself\ninstructions: Use platform__manage_schedule to create a job that runs this recipe again.\n",
+            )
+            .unwrap();
+            let arguments = serde_json::json!({
+                "action": "create",
+                "recipe_path": recipe_path.to_str().unwrap(),
+                "cron_expression": "0 0 * * * *",
+            });
+            let original = std::env::var("GOOSE_SELF_SCHEDULING_POLICY").ok();
+
+            std::env::set_var("GOOSE_SELF_SCHEDULING_POLICY", "warn");
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+            let warned = agent
+                .handle_schedule_management(arguments.clone(), "req_1".to_string(), None)
+                .await;
+
+            std::env::set_var("GOOSE_SELF_SCHEDULING_POLICY", "refuse");
+            let refusing_agent = Agent::new();
+            let refusing_scheduler = Arc::new(MockScheduler::new());
+            refusing_agent
+                .set_scheduler(refusing_scheduler.clone())
+                .await;
+            let refused = refusing_agent
+                .handle_schedule_management(arguments, "req_2".to_string(), None)
+                .await;
+
+            match original {
+                Some(val) => std::env::set_var("GOOSE_SELF_SCHEDULING_POLICY", val),
+                None => std::env::remove_var("GOOSE_SELF_SCHEDULING_POLICY"),
+            }
+
+            let content = warned.expect("warn policy should still create the job");
+            assert!(content
+                .iter()
+                .any(|c| c.as_text().is_some_and(|t| t.text.starts_with("Warning:"))));
+            assert_eq!(mock_scheduler.jobs.lock().await.len(), 1);
+
+            let err = refused.expect_err("refuse policy should reject the recipe");
+            assert!(err.message.contains("schedule management tool"));
+            assert!(refusing_scheduler.jobs.lock().await.is_empty());
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_job_refuses_recipe_with_self_scheduling_sub_recipe() {
+            let dir = tempfile::tempdir().unwrap();
+            let spawner_path = dir.path().join("spawner.yaml");
+            std::fs::write(
+                &spawner_path,
+                "title: Spawner\ndescription: Schedules itself\ninstructions: Call manage_schedule to run this again tomorrow.\n",
+            )
+            .unwrap();
+            let parent = format!(
+                "title: Nightly\ndescription: Runs the nightly steps\ninstructions: Run the nightly steps\nsub_recipes:\n- name: spawner\n  path: {}\n",
+                spawner_path.display()
+            );
+            // Mentions a similarly named report, not the schedule tool
+            let unrelated = "title: Report\ndescription: Summarizes jobs\ninstructions: Write the manage_schedule_report.md summary.\n";
+            let original = std::env::var("GOOSE_SELF_SCHEDULING_POLICY").ok();
+            std::env::set_var("GOOSE_SELF_SCHEDULING_POLICY", "refuse");
+
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+            let mut results = Vec::new();
+            for recipe in [parent.as_str(), unrelated] {
+                results.push(
+                    agent
+                        .handle_schedule_management(
+                            serde_json::json!({
+                                "action": "create",
+                                "recipe_content": recipe,
+                                "cron_expression": "0 0 * * * *",
+                            }),
+                            "req_1".to_string(),
+                            None,
+                        )
+                        .await,
+                );
+            }
+
+            match original {
+                Some(val) => std::env::set_var("GOOSE_SELF_SCHEDULING_POLICY", val),
+                None => std::env::remove_var("GOOSE_SELF_SCHEDULING_POLICY"),
+            }
+
+            let err = results[0]
+                .as_ref()
+                .expect_err("a sub-recipe that schedules jobs should be refused");
+            assert!(err.message.contains("schedule management tool"));
+            assert!(results[1].is_ok());
+            assert_eq!(mock_scheduler.jobs.lock().await.len(), 1);
+        }
+
+        struct SchedulingProvider {
+            recipe_path: String,
+        }
+
+        #[async_trait]
+        impl goose::providers::base::Provider for SchedulingProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &goose::model::ModelConfig,
+                _system_prompt: &str,
+                messages: &[goose::conversation::message::Message],
+                tools: &[rmcp::model::Tool],
+            ) -> Result<
+                (
+                    goose::conversation::message::Message,
+                    goose::providers::base::ProviderUsage,
+                ),
+                goose::providers::errors::ProviderError,
+            > {
+                use goose::conversation::message::Message;
+                let usage = goose::providers::base::ProviderUsage::new(
+                    "mock-model".to_string(),
+                    goose::providers::base::Usage::default(),
+                );
+                let answered = messages.last().is_some_and(|m| m.is_tool_response());
+                if tools.is_empty() || answered {
+                    return Ok((Message::assistant().with_text("Scheduled"), usage));
+                }
+                let tool_call = rmcp::model::CallToolRequestParam {
+                    name: PLATFORM_MANAGE_SCHEDULE_TOOL_NAME.into(),
+                    arguments: Some(rmcp::object!({
+                        "action": "create",
+                        "recipe_path": self.recipe_path.clone(),
+                        "cron_expression": "0 0 9 * * *",
+                    })),
+                };
+                Ok((
+                    Message::assistant().with_tool_request("schedule_1", Ok(tool_call)),
+                    usage,
+                ))
+            }
+
+            fn get_model_config(&self) -> goose::model::ModelConfig {
+                goose::model::ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> goose::providers::base::ProviderMetadata {
+                goose::providers::base::ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-scheduling"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_user_id_recorded_on_agent_created_job() -> Result<()> {
+            use goose::agents::SessionConfig;
+            use goose::conversation::message::Message;
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+
+            let dir = tempfile::tempdir()?;
+            let recipe_path = dir.path().join("daily.yaml");
+            std::fs::write(
+                &recipe_path,
+                "title: Daily\ndescription: Daily digest\ninstructions: Summarize yesterday.\n",
+            )?;
+
+            let agent = Agent::new();
+            let mock_scheduler = Arc::new(MockScheduler::new());
+            agent.set_scheduler(mock_scheduler.clone()).await;
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "user-attribution-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(
+                    Arc::new(SchedulingProvider {
+                        recipe_path: recipe_path.to_string_lossy().to_string(),
+                    }),
+                    &session.id,
+                )
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: Some("alice".to_string()),
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(
+                    Message::user().with_text("Schedule the daily digest"),
+                    session_config,
+                    None,
+                )
+                .await?;
+            tokio::pin!(reply_stream);
+            while let Some(event) = reply_stream.next().await {
+                event?;
+            }
+
+            let jobs = mock_scheduler.jobs.lock().await;
+            assert_eq!(jobs.len(), 1);
+            assert_eq!(jobs[0].user_id.as_deref(), Some("alice"));
+            Ok(())
+        }
+
         #[tokio::test]
         async fn test_schedule_management_tool_no_scheduler() {
             let agent = Agent::new();
@@ -389,6 +957,9 @@ mod tests {
                 schedule_id: None,
                 max_turns: None,
                 retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
             };
 
             let reply_stream = agent.reply(user_message, session_config, None).await?;
@@ -413,6 +984,9 @@ mod tests {
                     }
                     Ok(AgentEvent::McpNotification(_)) => {}
                     Ok(AgentEvent::ModelChange { .. }) => {}
+                    Ok(AgentEvent::ApprovalRequired { .. }) => {}
+                    Ok(AgentEvent::Reasoning(_)) => {}
+                    Ok(AgentEvent::Usage(_)) => {}
                     Ok(AgentEvent::HistoryReplaced(_updated_conversation)) => {
                         // We should update the conversation here, but we're not reading it
                     }
@@ -443,58 +1017,2530 @@ mod tests {
     }
 
     #[cfg(test)]
-    mod extension_manager_tests {
+    mod error_recovery_tests {
         use super::*;
-        use goose::agents::extension::{ExtensionConfig, PlatformExtensionContext};
-        use goose::agents::extension_manager_extension::{
-            MANAGE_EXTENSIONS_TOOL_NAME, SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
-        };
+        use async_trait::async_trait;
+        use goose::agents::{ErrorRecoveryAction, RetryPolicy, SessionConfig};
+        use goose::conversation::message::{Message, MessageContent};
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
 
-        async fn setup_agent_with_extension_manager() -> Agent {
-            // Add the TODO extension to the config so it can be discovered by search_available_extensions
-            // Set it as disabled initially so tests can enable it
-            let todo_extension_entry = ExtensionEntry {
-                enabled: false,
-                config: ExtensionConfig::Platform {
-                    name: "todo".to_string(),
-                    description:
-                        "Enable a todo list for Goose so it can keep track of what it is doing"
-                            .to_string(),
-                    bundled: Some(true),
-                    available_tools: vec![],
-                },
-            };
-            set_extension(todo_extension_entry);
+        /// Fails the first agent request, then answers with plain text
+        struct FlakyProvider {
+            calls: AtomicUsize,
+        }
 
-            let agent = Agent::new();
+        #[async_trait]
+        impl Provider for FlakyProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let usage = ProviderUsage::new(
+                    "mock-model".to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                );
+                // Session naming calls the provider without tools; keep it out of the count
+                if tools.is_empty() {
+                    return Ok((Message::assistant().with_text("session name"), usage));
+                }
+                if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(ProviderError::ServerError("temporarily unavailable".into()));
+                }
+                Ok((Message::assistant().with_text("recovered"), usage))
+            }
 
-            agent
-                .extension_manager
-                .set_context(PlatformExtensionContext {
-                    session_id: Some("test_session".to_string()),
-                    extension_manager: Some(Arc::downgrade(&agent.extension_manager)),
-                    tool_route_manager: Some(Arc::downgrade(&agent.tool_route_manager)),
-                })
-                .await;
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
 
-            // Now add the extension manager platform extension
-            let ext_config = ExtensionConfig::Platform {
-                name: "extensionmanager".to_string(),
-                description: "Extension Manager".to_string(),
-                bundled: Some(true),
-                available_tools: vec![],
-            };
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
 
-            agent
-                .add_extension(ext_config)
-                .await
-                .expect("Failed to add extension manager");
-            agent
+            fn get_name(&self) -> &str {
+                "mock-flaky"
+            }
         }
 
-        #[tokio::test]
-        async fn test_extension_manager_tools_available() {
-            let agent = setup_agent_with_extension_manager().await;
+        /// Fails every agent request
+        struct DownProvider {
+            calls: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Provider for DownProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                if tools.is_empty() {
+                    return Ok((
+                        Message::assistant().with_text("session name"),
+                        ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                    ));
+                }
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                Err(ProviderError::ServerError("still down".into()))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-down"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_resume_after_error_retries_request() -> Result<()> {
+            let agent = Agent::new();
+            let provider = Arc::new(FlakyProvider {
+                calls: AtomicUsize::new(0),
+            });
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "error-recovery-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent.update_provider(provider.clone(), &session.id).await?;
+            agent.set_resume_on_error(true).await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    if let Some(MessageContent::Text(text)) = message.content.first() {
+                        if text.text.starts_with("Ran into this error") {
+                            agent
+                                .handle_error_recovery(ErrorRecoveryAction::Retry)
+                                .await;
+                        }
+                        texts.push(text.text.clone());
+                    }
+                }
+            }
+
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+            assert!(texts[0].contains("temporarily unavailable"));
+            assert_eq!(texts.last().map(String::as_str), Some("recovered"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_resume_after_error_ignores_stale_recovery_action() -> Result<()> {
+            let agent = Agent::new();
+            let provider = Arc::new(FlakyProvider {
+                calls: AtomicUsize::new(0),
+            });
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "stale-recovery-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent.update_provider(provider.clone(), &session.id).await?;
+            agent.set_resume_on_error(true).await;
+            // Sent while no error is pending, so it must not answer the error below
+            agent
+                .handle_error_recovery(ErrorRecoveryAction::Abandon)
+                .await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    if let Some(MessageContent::Text(text)) = message.content.first() {
+                        if text.text.starts_with("Ran into this error") {
+                            agent
+                                .handle_error_recovery(ErrorRecoveryAction::Retry)
+                                .await;
+                        }
+                        texts.push(text.text.clone());
+                    }
+                }
+            }
+
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+            assert_eq!(texts.last().map(String::as_str), Some("recovered"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_retry_policy_retries_transient_errors() -> Result<()> {
+            let agent = Agent::new();
+            let provider = Arc::new(FlakyProvider {
+                calls: AtomicUsize::new(0),
+            });
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "retry-policy-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent.update_provider(provider.clone(), &session.id).await?;
+            let policy = RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+            };
+            agent.configure_retry_policy(Some(policy.clone())).await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    if let Some(MessageContent::Text(text)) = message.content.first() {
+                        texts.push(text.text.clone());
+                    }
+                }
+            }
+
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+            assert_eq!(texts, vec!["recovered".to_string()]);
+            assert_eq!(
+                policy.delay_before_retry(
+                    &ProviderError::ContextLengthExceeded("too long".into()),
+                    1
+                ),
+                None
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_retry_policy_retries_do_not_count_as_turns() -> Result<()> {
+            let agent = Agent::new();
+            let provider = Arc::new(FlakyProvider {
+                calls: AtomicUsize::new(0),
+            });
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "retry-turns-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent.update_provider(provider.clone(), &session.id).await?;
+            agent
+                .configure_retry_policy(Some(RetryPolicy {
+                    max_attempts: 2,
+                    base_delay: Duration::from_millis(1),
+                    max_delay: Duration::from_millis(10),
+                }))
+                .await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: Some(1),
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    texts.push(message.as_concat_text());
+                }
+            }
+
+            // The retry repeats the only turn instead of using up the limit
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+            assert_eq!(texts, vec!["recovered".to_string()]);
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_retry_policy_gives_up_after_max_attempts() -> Result<()> {
+            let agent = Agent::new();
+            let provider = Arc::new(DownProvider {
+                calls: AtomicUsize::new(0),
+            });
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "retry-give-up-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent.update_provider(provider.clone(), &session.id).await?;
+            let policy = RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+            };
+            agent.configure_retry_policy(Some(policy.clone())).await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    texts.push(message.as_concat_text());
+                }
+            }
+
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 3);
+            assert!(texts.last().is_some_and(|t| t.contains("still down")));
+
+            let rate_limited = ProviderError::RateLimitExceeded {
+                details: "slow down".to_string(),
+                retry_delay: Some(Duration::from_secs(3600)),
+            };
+            assert_eq!(
+                policy.delay_before_retry(&rate_limited, 1),
+                Some(policy.max_delay)
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod finish_reason_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::{Message, MessageContent};
+        use goose::model::ModelConfig;
+        use goose::providers::base::{
+            FinishReason, Provider, ProviderMetadata, ProviderUsage, Usage,
+        };
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+
+        struct TruncatingProvider;
+
+        #[async_trait]
+        impl Provider for TruncatingProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let usage = ProviderUsage::new(
+                    "mock-model".to_string(),
+                    Usage::new(Some(10), Some(4096), Some(4106)),
+                )
+                .with_finish_reason(Some(FinishReason::from_provider("length")));
+                Ok((Message::assistant().with_text("The answer is"), usage))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-truncating"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_length_finish_reason_is_surfaced() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "finish-reason-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(TruncatingProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut notices = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    for content in &message.content {
+                        if let MessageContent::SystemNotification(notification) = content {
+                            notices.push(notification.msg.clone());
+                        }
+                    }
+                }
+            }
+
+            assert!(
+                notices.iter().any(|n| n.contains("finish reason: length")),
+                "expected a length notice, got {:?}",
+                notices
+            );
+            Ok(())
+        }
+    }
+
+    mod reasoning_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::{Message, MessageContent};
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+
+        /// Answers with a reasoning block followed by the answer
+        struct ReasoningProvider;
+
+        #[async_trait]
+        impl Provider for ReasoningProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let usage = ProviderUsage::new(
+                    "mock-model".to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                );
+                let message = Message::assistant()
+                    .with_thinking("Both options work; A is simpler.", "signature")
+                    .with_text("Go with option A.");
+                Ok((message, usage))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-reasoning"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_usage_event_precedes_response() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "usage-event-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(ReasoningProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id.clone(),
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(Message::user().with_text("A or B?"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut events = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                events.push(event?);
+            }
+
+            let usages: Vec<&ProviderUsage> = events
+                .iter()
+                .filter_map(|e| match e {
+                    AgentEvent::Usage(usage) => Some(usage),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(usages.len(), 1);
+            assert_eq!(usages[0].model, "mock-model");
+            assert_eq!(usages[0].usage.input_tokens, Some(10));
+            assert_eq!(usages[0].usage.output_tokens, Some(5));
+
+            let usage_at = events
+                .iter()
+                .position(|e| matches!(e, AgentEvent::Usage(_)));
+            let message_at = events
+                .iter()
+                .position(|e| matches!(e, AgentEvent::Message(_)));
+            assert!(usage_at < message_at);
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_reasoning_is_a_separate_event() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "reasoning-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(ReasoningProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id.clone(),
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(Message::user().with_text("A or B?"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut reasoning = Vec::new();
+            let mut messages = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                match event? {
+                    AgentEvent::Reasoning(text) => reasoning.push(text),
+                    AgentEvent::Message(message) => messages.push(message),
+                    _ => {}
+                }
+            }
+
+            assert_eq!(reasoning, vec!["Both options work; A is simpler."]);
+            assert!(messages
+                .iter()
+                .any(|m| m.as_concat_text() == "Go with option A."));
+            assert!(!messages
+                .iter()
+                .flat_map(|m| m.content.iter())
+                .any(|c| matches!(c, MessageContent::Thinking(_))));
+
+            let stored = SessionManager::get_session(&session.id, true).await?;
+            let kept_reasoning = stored
+                .conversation
+                .expect("conversation is loaded")
+                .messages()
+                .iter()
+                .flat_map(|m| m.content.iter())
+                .any(|c| matches!(c, MessageContent::Thinking(_)));
+            assert!(kept_reasoning);
+            Ok(())
+        }
+    }
+
+    mod reply_deadline_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::Message;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::time::Duration;
+
+        /// Takes an hour to answer agent requests
+        struct SlowProvider;
+
+        #[async_trait]
+        impl Provider for SlowProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let usage = ProviderUsage::new(
+                    "mock-model".to_string(),
+                    Usage::new(Some(10), Some(5), Some(15)),
+                );
+                // Session naming calls the provider without tools; answer that right away
+                if !tools.is_empty() {
+                    tokio::time::sleep(Duration::from_secs(3600)).await;
+                }
+                Ok((Message::assistant().with_text("finally done"), usage))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-slow"
+            }
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn test_reply_stops_at_deadline() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "reply-deadline-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(SlowProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            }
+            .with_reply_deadline(Some(Duration::from_secs(30)));
+            let started = tokio::time::Instant::now();
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut texts = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    texts.push(message.as_concat_text());
+                }
+            }
+
+            assert!(started.elapsed() < Duration::from_secs(3600));
+            assert!(!texts.iter().any(|t| t == "finally done"));
+            assert_eq!(
+                texts.last().map(String::as_str),
+                Some("The reply was stopped after reaching its deadline of 30s.")
+            );
+            Ok(())
+        }
+    }
+
+    mod pre_send_hook_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::Message;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+
+        #[derive(Default)]
+        struct CapturingProvider {
+            received: std::sync::Mutex<Vec<String>>,
+        }
+
+        #[async_trait]
+        impl Provider for CapturingProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                *self.received.lock().unwrap() =
+                    messages.iter().map(|m| m.as_concat_text()).collect();
+                Ok((
+                    Message::assistant().with_text("ok"),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-capturing"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_pre_send_hook_modifies_outgoing_messages() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "pre-send-hook-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let provider = Arc::new(CapturingProvider::default());
+            agent.update_provider(provider.clone(), &session.id).await?;
+            agent
+                .set_pre_send_hook(Arc::new(|mut messages: Vec<Message>| {
+                    messages.push(Message::user().with_text("[system note] be brief"));
+                    messages
+                }))
+                .await;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(Message::user().with_text("Hello"), session_config, None)
+                .await?;
+            tokio::pin!(reply_stream);
+            while let Some(event) = reply_stream.next().await {
+                event?;
+            }
+
+            let received = provider.received.lock().unwrap().clone();
+            assert_eq!(
+                received.last().map(String::as_str),
+                Some("[system note] be brief")
+            );
+            assert!(received.iter().any(|text| text == "Hello"));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod session_metrics_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::Message;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::{CallToolRequestParam, Tool};
+        use std::path::PathBuf;
+
+        struct CountingUsageProvider;
+
+        #[async_trait]
+        impl Provider for CountingUsageProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                Ok((
+                    Message::assistant().with_text("Done"),
+                    ProviderUsage::new(
+                        "mock-model".to_string(),
+                        Usage::new(Some(100), Some(20), Some(120)),
+                    ),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-usage"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_export_session_metrics_after_turns() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "metrics-export-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(CountingUsageProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id.clone(),
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+
+            for prompt in ["First question", "Second question"] {
+                let reply_stream = agent
+                    .reply(
+                        Message::user().with_text(prompt),
+                        session_config.clone(),
+                        None,
+                    )
+                    .await?;
+                tokio::pin!(reply_stream);
+                while let Some(event) = reply_stream.next().await {
+                    event?;
+                }
+            }
+
+            let tool_call = CallToolRequestParam {
+                name: "todo__todo_write".into(),
+                arguments: None,
+            };
+            SessionManager::add_message(
+                &session.id,
+                &Message::assistant()
+                    .with_tool_request("call_1", Ok(tool_call.clone()))
+                    .with_tool_request("call_2", Ok(tool_call)),
+            )
+            .await?;
+
+            let metrics = agent.export_session_metrics(&session_config).await?;
+
+            assert_eq!(metrics.session_id, session.id);
+            assert_eq!(metrics.input_tokens, Some(200));
+            assert_eq!(metrics.output_tokens, Some(40));
+            assert_eq!(metrics.total_tokens, Some(240));
+            assert_eq!(metrics.message_count, 5);
+            assert_eq!(metrics.tool_call_counts.get("todo__todo_write"), Some(&2));
+            assert_eq!(metrics.tool_call_counts.len(), 1);
+
+            let json = serde_json::to_value(&metrics)?;
+            assert_eq!(json["tool_call_counts"]["todo__todo_write"], 2);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_cancellation_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::RecipeCreationCancelled;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+        use tokio_util::sync::CancellationToken;
+
+        struct SlowProvider;
+
+        #[async_trait]
+        impl Provider for SlowProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok((
+                    Message::assistant().with_text("{}"),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-slow"
+            }
+        }
+
+        /// Answers the first request with prose at once, then stalls on the corrective call
+        struct SlowCorrectionProvider {
+            calls: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl Provider for SlowCorrectionProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                if self.calls.fetch_add(1, Ordering::SeqCst) > 0 {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+                Ok((
+                    Message::assistant().with_text("Sure! Here is how to triage bugs."),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-slow-correction"
+            }
+        }
+
+        #[tokio::test]
+        async fn test_create_recipe_can_be_cancelled() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-cancel-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(SlowProvider), &session.id)
+                .await?;
+
+            let cancel_token = CancellationToken::new();
+            let canceller = cancel_token.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                canceller.cancel();
+            });
+
+            let conversation =
+                Conversation::new_unvalidated(vec![Message::user().with_text("Make a recipe")]);
+            let result = tokio::time::timeout(
+                Duration::from_secs(10),
+                agent.create_recipe(conversation, Some(cancel_token)),
+            )
+            .await
+            .expect("create_recipe should return promptly once cancelled");
+
+            let err = result.expect_err("cancelled recipe creation should fail");
+            assert!(err.downcast_ref::<RecipeCreationCancelled>().is_some());
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_corrective_recipe_call_can_be_cancelled() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-correction-cancel-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let provider = Arc::new(SlowCorrectionProvider {
+                calls: AtomicUsize::new(0),
+            });
+            agent.update_provider(provider.clone(), &session.id).await?;
+            agent.set_strict_recipe_json(true).await;
+
+            let cancel_token = CancellationToken::new();
+            let canceller = cancel_token.clone();
+            let watched = provider.clone();
+            tokio::spawn(async move {
+                while watched.calls.load(Ordering::SeqCst) < 2 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                canceller.cancel();
+            });
+
+            let conversation =
+                Conversation::new_unvalidated(vec![Message::user().with_text("Make a recipe")]);
+            let result = tokio::time::timeout(
+                Duration::from_secs(10),
+                agent.create_recipe(conversation, Some(cancel_token)),
+            )
+            .await
+            .expect("create_recipe should return promptly once cancelled");
+
+            let err = result.expect_err("cancelled recipe creation should fail");
+            assert!(err.downcast_ref::<RecipeCreationCancelled>().is_some());
+            assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod rerun_last_turn_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::conversation::message::Message;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+
+        struct FixedReplyProvider {
+            name: &'static str,
+            reply: &'static str,
+        }
+
+        #[async_trait]
+        impl Provider for FixedReplyProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                Ok((
+                    Message::assistant().with_text(self.reply),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                self.name
+            }
+        }
+
+        #[tokio::test]
+        async fn test_rerun_last_turn_with_alternative_provider() -> Result<()> {
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "rerun-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(
+                    Arc::new(FixedReplyProvider {
+                        name: "primary",
+                        reply: "Paris",
+                    }),
+                    &session.id,
+                )
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id.clone(),
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(
+                    Message::user().with_text("What is the capital of France?"),
+                    session_config.clone(),
+                    None,
+                )
+                .await?;
+            tokio::pin!(reply_stream);
+            let mut original = String::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    original.push_str(&message.as_concat_text());
+                }
+            }
+
+            let alternative = agent
+                .rerun_last_turn(
+                    &session_config,
+                    Arc::new(FixedReplyProvider {
+                        name: "alternative",
+                        reply: "The capital of France is Paris.",
+                    }),
+                )
+                .await?;
+
+            assert_eq!(original, "Paris");
+            assert_eq!(
+                alternative.as_concat_text(),
+                "The capital of France is Paris."
+            );
+            assert_ne!(alternative.as_concat_text(), original);
+
+            assert_eq!(agent.provider().await?.get_name(), "primary");
+            let stored = SessionManager::get_session(&session.id, true).await?;
+            assert_eq!(stored.message_count, 2);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_progress_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{
+            MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+        };
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::sync::Mutex;
+
+        const RECIPE_CHUNKS: &[&str] = &[
+            r#"{"instructions": "Summarize "#,
+            r#"the release notes", "#,
+            r#""activities": ["Draft summary"]}"#,
+        ];
+
+        struct StreamingRecipeProvider;
+
+        #[async_trait]
+        impl Provider for StreamingRecipeProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                Err(ProviderError::ExecutionError(
+                    "recipe generation should stream".to_string(),
+                ))
+            }
+
+            async fn stream(
+                &self,
+                _system: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<MessageStream, ProviderError> {
+                let mut items: Vec<Result<_, ProviderError>> = RECIPE_CHUNKS
+                    .iter()
+                    .map(|chunk| Ok((Some(Message::assistant().with_text(*chunk)), None)))
+                    .collect();
+                items.push(Ok((
+                    None,
+                    Some(ProviderUsage::new(
+                        "mock-model".to_string(),
+                        Usage::default(),
+                    )),
+                )));
+                Ok(Box::pin(futures::stream::iter(items)))
+            }
+
+            fn supports_streaming(&self) -> bool {
+                true
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-streaming-recipe"
+            }
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_recipe_reports_streamed_progress() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-streaming-recipe");
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-progress-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(StreamingRecipeProvider), &session.id)
+                .await?;
+
+            let progress = Arc::new(Mutex::new(Vec::<String>::new()));
+            let recorder = progress.clone();
+            let conversation = Conversation::new_unvalidated(vec![
+                Message::user().with_text("Summarize the release notes")
+            ]);
+            let result = agent
+                .create_recipe_with_progress(
+                    conversation,
+                    None,
+                    Arc::new(move |chunk| recorder.lock().unwrap().push(chunk.to_string())),
+                )
+                .await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            let recipe = result?;
+            let progress = progress.lock().unwrap();
+            assert_eq!(*progress, RECIPE_CHUNKS);
+            assert_eq!(
+                recipe.instructions.as_deref(),
+                Some("Summarize the release notes")
+            );
+            assert_eq!(recipe.activities, Some(vec!["Draft summary".to_string()]));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_correction_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::RecipeParseError;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::sync::Mutex;
+
+        /// Answers with prose first, then with JSON once asked again
+        struct ProseThenJsonProvider {
+            requests: Arc<Mutex<Vec<Vec<Message>>>>,
+        }
+
+        #[async_trait]
+        impl Provider for ProseThenJsonProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let mut requests = self.requests.lock().unwrap();
+                requests.push(messages.to_vec());
+                let text = if requests.len() == 1 {
+                    "Sure! This recipe walks through triaging the open bug reports."
+                } else {
+                    r#"{"title": "Bug triage", "description": "Triage open bugs", "instructions": "Triage the open bug reports", "activities": ["Label new bugs"]}"#
+                };
+                Ok((
+                    Message::assistant().with_text(text),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-prose-then-json"
+            }
+        }
+
+        /// Always answers with the same prose
+        struct ProseProvider {
+            text: &'static str,
+        }
+
+        #[async_trait]
+        impl Provider for ProseProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                Ok((
+                    Message::assistant().with_text(self.text),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-prose"
+            }
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_recipe_makes_corrective_call_for_prose() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-prose-then-json");
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-correction-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            agent
+                .update_provider(
+                    Arc::new(ProseThenJsonProvider {
+                        requests: requests.clone(),
+                    }),
+                    &session.id,
+                )
+                .await?;
+            agent.set_strict_recipe_json(true).await;
+
+            let conversation = Conversation::new_unvalidated(vec![
+                Message::user().with_text("Help me triage bugs")
+            ]);
+            let result = agent.create_recipe(conversation, None).await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            let recipe = result?;
+            assert_eq!(recipe.title, "Bug triage");
+            assert_eq!(
+                recipe.instructions.as_deref(),
+                Some("Triage the open bug reports")
+            );
+            assert_eq!(recipe.activities, Some(vec!["Label new bugs".to_string()]));
+
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            let corrective = requests[1].last().unwrap().as_concat_text();
+            assert!(corrective.contains("JSON"));
+            Ok(())
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_recipe_rejects_prose_unless_verbatim_fallback() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-prose");
+
+            let prose = "Sure! This recipe walks through triaging the open bug reports.";
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-verbatim-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(ProseProvider { text: prose }), &session.id)
+                .await?;
+
+            let conversation = Conversation::new_unvalidated(vec![
+                Message::user().with_text("Help me triage bugs")
+            ]);
+            let rejected = agent.create_recipe(conversation.clone(), None).await;
+            agent.set_verbatim_recipe_fallback(true).await;
+            let accepted = agent.create_recipe(conversation, None).await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            let err = rejected.expect_err("prose should not be accepted as a recipe");
+            let parse_error = err
+                .downcast_ref::<RecipeParseError>()
+                .expect("expected RecipeParseError");
+            assert_eq!(parse_error.raw, prose);
+            assert_eq!(accepted?.instructions.as_deref(), Some(prose));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_response_format_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::model::{ModelConfig, ResponseFormat};
+        use goose::providers::base::{
+            MessageStream, Provider, ProviderMetadata, ProviderUsage, Usage,
+        };
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+        use std::sync::Mutex;
+
+        /// Records the response format of every request
+        struct JsonModeProvider {
+            formats: Arc<Mutex<Vec<Option<ResponseFormat>>>>,
+        }
+
+        #[async_trait]
+        impl Provider for JsonModeProvider {
+            async fn complete_with_model(
+                &self,
+                model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                self.formats
+                    .lock()
+                    .unwrap()
+                    .push(model_config.response_format);
+                Ok((
+                    Message::assistant().with_text(
+                        r#"{"title": "Release", "description": "Cut a release", "instructions": "Tag and publish"}"#,
+                    ),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn supports_response_format(&self) -> bool {
+                true
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-json-mode"
+            }
+        }
+
+        /// Like `JsonModeProvider`, but only answers streamed requests
+        struct StreamingJsonModeProvider {
+            formats: Arc<Mutex<Vec<Option<ResponseFormat>>>>,
+        }
+
+        #[async_trait]
+        impl Provider for StreamingJsonModeProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                Err(ProviderError::ExecutionError(
+                    "recipe generation should stream".to_string(),
+                ))
+            }
+
+            async fn stream_with_model(
+                &self,
+                model_config: &ModelConfig,
+                _system: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<MessageStream, ProviderError> {
+                self.formats
+                    .lock()
+                    .unwrap()
+                    .push(model_config.response_format);
+                let items: Vec<Result<_, ProviderError>> = vec![
+                    Ok((
+                        Some(Message::assistant().with_text(
+                            r#"{"title": "Release", "description": "Cut a release", "instructions": "Tag and publish"}"#,
+                        )),
+                        None,
+                    )),
+                    Ok((
+                        None,
+                        Some(ProviderUsage::new(
+                            "mock-model".to_string(),
+                            Usage::default(),
+                        )),
+                    )),
+                ];
+                Ok(Box::pin(futures::stream::iter(items)))
+            }
+
+            fn supports_streaming(&self) -> bool {
+                true
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn supports_response_format(&self) -> bool {
+                true
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-json-mode"
+            }
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_recipe_requests_json_response_format() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-json-mode");
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-response-format-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let formats = Arc::new(Mutex::new(Vec::new()));
+            agent
+                .update_provider(
+                    Arc::new(JsonModeProvider {
+                        formats: formats.clone(),
+                    }),
+                    &session.id,
+                )
+                .await?;
+
+            let conversation = Conversation::new_unvalidated(vec![
+                Message::user().with_text("Help me cut a release")
+            ]);
+            let result = agent.create_recipe(conversation, None).await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            assert_eq!(result?.title, "Release");
+            assert_eq!(*formats.lock().unwrap(), vec![Some(ResponseFormat::Json)]);
+            Ok(())
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_streamed_recipe_requests_json_response_format() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-json-mode");
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-streamed-response-format-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let formats = Arc::new(Mutex::new(Vec::new()));
+            agent
+                .update_provider(
+                    Arc::new(StreamingJsonModeProvider {
+                        formats: formats.clone(),
+                    }),
+                    &session.id,
+                )
+                .await?;
+
+            let conversation = Conversation::new_unvalidated(vec![
+                Message::user().with_text("Help me cut a release")
+            ]);
+            let result = agent
+                .create_recipe_with_progress(conversation, None, Arc::new(|_| {}))
+                .await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            assert_eq!(result?.title, "Release");
+            assert_eq!(*formats.lock().unwrap(), vec![Some(ResponseFormat::Json)]);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_size_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::RecipeTooLarge;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Tool;
+        use std::path::PathBuf;
+
+        /// Answers with a recipe whose instructions run on and on
+        struct RunawayRecipeProvider;
+
+        #[async_trait]
+        impl Provider for RunawayRecipeProvider {
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system_prompt: &str,
+                _messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let recipe = serde_json::json!({
+                    "title": "Runaway",
+                    "description": "Too long",
+                    "instructions": "Repeat this step. ".repeat(1000),
+                    "activities": ["Start"],
+                });
+                Ok((
+                    Message::assistant().with_text(recipe.to_string()),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "mock-runaway-recipe"
+            }
+        }
+
+        #[tokio::test]
+        #[serial_test::serial]
+        async fn test_create_recipe_rejects_oversized_output() -> Result<()> {
+            let original_provider = std::env::var("GOOSE_PROVIDER").ok();
+            std::env::set_var("GOOSE_PROVIDER", "mock-runaway-recipe");
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-size-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(RunawayRecipeProvider), &session.id)
+                .await?;
+            agent.set_max_recipe_size(4096).await;
+
+            let conversation =
+                Conversation::new_unvalidated(vec![Message::user().with_text("Write me a recipe")]);
+            let result = agent.create_recipe(conversation, None).await;
+
+            match original_provider {
+                Some(val) => std::env::set_var("GOOSE_PROVIDER", val),
+                None => std::env::remove_var("GOOSE_PROVIDER"),
+            }
+
+            let err = result.expect_err("oversized recipe should be rejected");
+            let too_large = err
+                .downcast_ref::<RecipeTooLarge>()
+                .expect("expected RecipeTooLarge");
+            assert_eq!(too_large.limit, 4096);
+            assert_eq!(
+                too_large.size,
+                "Repeat this step. ".len() * 1000 + "Start".len()
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod recipe_mode_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::config::GooseMode;
+        use goose::conversation::message::{Message, MessageContent};
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::recipe::Recipe;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::{CallToolRequestParam, Tool};
+        use rmcp::object;
+        use std::path::PathBuf;
+
+        /// Asks for a shell command until it sees a tool response, then answers in text
+        struct ShellRequestingProvider;
+
+        #[async_trait]
+        impl Provider for ShellRequestingProvider {
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "shell-requesting"
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system: &str,
+                messages: &[Message],
+                tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let answered = messages.iter().any(|m| {
+                    m.content
+                        .iter()
+                        .any(|c| matches!(c, MessageContent::ToolResponse(_)))
+                });
+                let message = if tools.is_empty() || answered {
+                    Message::assistant().with_text("done")
+                } else {
+                    Message::assistant().with_tool_request(
+                        "call_1",
+                        Ok(CallToolRequestParam {
+                            name: "developer__shell".into(),
+                            arguments: Some(object!({"command": "rm -rf build"})),
+                        }),
+                    )
+                };
+                Ok((
+                    message,
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_recipe_chat_mode_skips_tool_calls() -> Result<()> {
+            let recipe = Recipe::from_content(
+                "title: Chat only\ndescription: Talks without tools\nmode: chat\nprompt: Clean the build directory\n",
+            )?;
+            assert_eq!(recipe.mode, Some(GooseMode::Chat));
+
+            let agent = Agent::new();
+            agent
+                .apply_recipe_components(recipe.sub_recipes, recipe.response, recipe.mode, false)
+                .await;
+            assert_eq!(agent.goose_mode().await, GooseMode::Chat);
+
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "recipe-mode-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(ShellRequestingProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(
+                    Message::user().with_text(recipe.prompt.unwrap()),
+                    session_config,
+                    None,
+                )
+                .await?;
+            tokio::pin!(reply_stream);
+
+            let mut tool_responses = Vec::new();
+            while let Some(event) = reply_stream.next().await {
+                if let AgentEvent::Message(message) = event? {
+                    for content in message.content {
+                        match content {
+                            MessageContent::ToolResponse(response) => tool_responses.push(response),
+                            MessageContent::ToolConfirmationRequest(_) => {
+                                panic!("chat mode should not ask to run tools")
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            assert_eq!(tool_responses.len(), 1);
+            let content = tool_responses[0].tool_result.as_ref().unwrap();
+            assert!(content[0]
+                .as_text()
+                .unwrap()
+                .text
+                .contains("tool call was skipped in goose chat mode"));
+            Ok(())
+        }
+    }
+
+    mod run_recipe_once_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::final_output_tool::FINAL_OUTPUT_TOOL_NAME;
+        use goose::config::GooseMode;
+        use goose::conversation::message::Message;
+        use goose::model::ModelConfig;
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::recipe::{Recipe, Response};
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::{Role, Tool};
+        use serde_json::json;
+        use std::path::PathBuf;
+
+        /// Echoes the last user message, noting whether the system prompt carried the recipe
+        struct EchoProvider;
+
+        #[async_trait]
+        impl Provider for EchoProvider {
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "echo"
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                system: &str,
+                messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let input = messages
+                    .iter()
+                    .rev()
+                    .find(|m| m.role == Role::User)
+                    .map(|m| m.as_concat_text())
+                    .unwrap_or_default();
+                let briefed = system.contains("Always answer in haiku");
+                Ok((
+                    Message::assistant().with_text(format!("echo({briefed}): {input}")),
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_run_recipe_once_returns_reply_messages() -> Result<()> {
+            let recipe = Recipe::from_content(
+                "title: Haiku\ndescription: Answers in haiku\ninstructions: Always answer in haiku\n",
+            )?;
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "run-recipe-once-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(EchoProvider), &session.id)
+                .await?;
+
+            let messages = agent.run_recipe_once(&recipe, "describe autumn").await?;
+
+            let texts: Vec<String> = messages.iter().map(|m| m.as_concat_text()).collect();
+            assert_eq!(texts, vec!["echo(true): describe autumn".to_string()]);
+            assert_eq!(messages[0].role, Role::Assistant);
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_run_recipe_once_restores_agent_state() -> Result<()> {
+            let recipe = Recipe::from_content(
+                r#"
+title: Charts
+description: Draws charts
+instructions: Always answer in haiku
+mode: chat
+extensions:
+  - type: frontend
+    name: ui
+    description: Chart rendering
+    tools:
+      - name: show_chart
+        description: Show a chart
+        inputSchema:
+          type: object
+          properties: {}
+"#,
+            )?;
+
+            let agent = Agent::new();
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "run-recipe-once-restore-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(EchoProvider), &session.id)
+                .await?;
+            agent
+                .set_goose_mode_override(Some(GooseMode::Approve))
+                .await;
+            agent
+                .add_final_output_tool(Response {
+                    json_schema: Some(json!({"type": "object"})),
+                })
+                .await;
+
+            agent.run_recipe_once(&recipe, "describe autumn").await?;
+
+            assert_eq!(agent.goose_mode().await, GooseMode::Approve);
+            assert!(!agent.is_frontend_tool("show_chart").await);
+            assert_eq!(
+                agent.extension_manager.enabled_extensions_limit().await,
+                None
+            );
+            let tools = agent.list_tools(None).await;
+            assert!(tools.iter().any(|t| t.name == FINAL_OUTPUT_TOOL_NAME));
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod session_approval_tests {
+        use super::*;
+        use async_trait::async_trait;
+        use goose::agents::SessionConfig;
+        use goose::config::GooseMode;
+        use goose::conversation::message::{ActionRequiredData, Message, MessageContent};
+        use goose::model::ModelConfig;
+        use goose::permission::permission_confirmation::PrincipalType;
+        use goose::permission::{Permission, PermissionConfirmation};
+        use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+        use goose::providers::errors::ProviderError;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::{CallToolRequestParam, Tool};
+        use rmcp::object;
+        use std::path::PathBuf;
+        use tokio_util::sync::CancellationToken;
+
+        /// Calls `deploy__run` twice, then finishes
+        struct TwoCallProvider;
+
+        #[async_trait]
+        impl Provider for TwoCallProvider {
+            fn metadata() -> ProviderMetadata {
+                ProviderMetadata::empty()
+            }
+
+            fn get_name(&self) -> &str {
+                "two-call"
+            }
+
+            fn get_model_config(&self) -> ModelConfig {
+                ModelConfig::new("mock-model").unwrap()
+            }
+
+            async fn complete_with_model(
+                &self,
+                _model_config: &ModelConfig,
+                _system: &str,
+                messages: &[Message],
+                _tools: &[Tool],
+            ) -> Result<(Message, ProviderUsage), ProviderError> {
+                let calls = messages.iter().filter(|m| m.is_tool_call()).count();
+                let message = if calls < 2 {
+                    Message::assistant().with_tool_request(
+                        format!("call_{calls}"),
+                        Ok(CallToolRequestParam {
+                            name: "deploy__run".into(),
+                            arguments: Some(object!({"attempt": calls})),
+                        }),
+                    )
+                } else {
+                    Message::assistant().with_text("done")
+                };
+                Ok((
+                    message,
+                    ProviderUsage::new("mock-model".to_string(), Usage::default()),
+                ))
+            }
+        }
+
+        #[tokio::test]
+        async fn test_allow_for_session_skips_later_prompts() -> Result<()> {
+            let agent = Agent::new();
+            agent
+                .set_goose_mode_override(Some(GooseMode::Approve))
+                .await;
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "session-approval-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(TwoCallProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let mut stream = agent
+                .reply(
+                    Message::user().with_text("Deploy twice"),
+                    session_config,
+                    None,
+                )
+                .await?;
+
+            let mut prompts = 0;
+            let mut tool_responses = 0;
+            while let Some(event) = stream.next().await {
+                let AgentEvent::Message(message) = event? else {
+                    continue;
+                };
+                for content in &message.content {
+                    match content {
+                        MessageContent::ActionRequired(action) => {
+                            let ActionRequiredData::ToolConfirmation { id, .. } = &action.data
+                            else {
+                                continue;
+                            };
+                            prompts += 1;
+                            agent
+                                .handle_confirmation(
+                                    id.clone(),
+                                    PermissionConfirmation {
+                                        principal_type: PrincipalType::Tool,
+                                        permission: Permission::AlwaysAllowForSession,
+                                    },
+                                )
+                                .await;
+                        }
+                        MessageContent::ToolResponse(_) => tool_responses += 1,
+                        _ => {}
+                    }
+                }
+            }
+
+            assert_eq!(prompts, 1, "Only the first call should ask for approval");
+            assert_eq!(tool_responses, 2, "Both calls should run");
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_approval_required_event_describes_tool_call() -> Result<()> {
+            let agent = Agent::new();
+            agent
+                .set_goose_mode_override(Some(GooseMode::Approve))
+                .await;
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "approval-event-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(TwoCallProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let mut stream = agent
+                .reply(
+                    Message::user().with_text("Deploy twice"),
+                    session_config,
+                    None,
+                )
+                .await?;
+
+            let mut approvals = Vec::new();
+            while let Some(event) = stream.next().await {
+                let AgentEvent::ApprovalRequired {
+                    request_id,
+                    tool_name,
+                    arguments,
+                } = event?
+                else {
+                    continue;
+                };
+                agent
+                    .handle_confirmation(
+                        request_id.clone(),
+                        PermissionConfirmation {
+                            principal_type: PrincipalType::Tool,
+                            permission: Permission::AllowOnce,
+                        },
+                    )
+                    .await;
+                approvals.push((request_id, tool_name, arguments));
+            }
+
+            assert_eq!(
+                approvals,
+                vec![
+                    (
+                        "call_0".to_string(),
+                        "deploy__run".to_string(),
+                        object!({"attempt": 0})
+                    ),
+                    (
+                        "call_1".to_string(),
+                        "deploy__run".to_string(),
+                        object!({"attempt": 1})
+                    ),
+                ]
+            );
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_cancel_while_awaiting_approval_ends_reply() -> Result<()> {
+            let agent = Agent::new();
+            agent
+                .set_goose_mode_override(Some(GooseMode::Approve))
+                .await;
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "cancel-approval-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(TwoCallProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id,
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let cancel_token = CancellationToken::new();
+            let mut stream = agent
+                .reply(
+                    Message::user().with_text("Deploy twice"),
+                    session_config,
+                    Some(cancel_token.clone()),
+                )
+                .await?;
+
+            let mut approvals = 0;
+            let mut cancelled_responses = 0;
+            let mut last_text = String::new();
+            let drained = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+                while let Some(event) = stream.next().await {
+                    match event? {
+                        AgentEvent::ApprovalRequired { .. } => {
+                            approvals += 1;
+                            cancel_token.cancel();
+                        }
+                        AgentEvent::Message(message) => {
+                            for content in &message.content {
+                                match content {
+                                    MessageContent::ToolResponse(response)
+                                        if response.tool_result.is_err() =>
+                                    {
+                                        cancelled_responses += 1
+                                    }
+                                    MessageContent::Text(text) => last_text = text.text.clone(),
+                                    _ => {}
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Ok::<_, anyhow::Error>(())
+            })
+            .await;
+
+            assert!(drained.is_ok(), "Reply should stop once cancelled");
+            drained??;
+            assert_eq!(approvals, 1, "No further calls after cancelling");
+            assert_eq!(cancelled_responses, 1, "The pending call gets a response");
+            assert!(last_text.contains("cancelled"));
+            Ok(())
+        }
+    }
+
+    mod merge_sessions_tests {
+        use super::*;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use rmcp::model::Role;
+        use std::path::PathBuf;
+
+        fn text(role: Role, created: i64, text: &str) -> Message {
+            Message::new(role, created, vec![]).with_text(text)
+        }
+
+        async fn session_with(name: &str, messages: &[Message]) -> Result<String> {
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                name.to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            for message in messages {
+                SessionManager::add_message(&session.id, message).await?;
+            }
+            Ok(session.id)
+        }
+
+        #[tokio::test]
+        async fn test_merge_sessions_produces_valid_history() -> Result<()> {
+            let question = text(Role::User, 100, "Plan a trip to Lisbon");
+            let answer = text(Role::Assistant, 101, "Three days is enough");
+
+            let primary = session_with(
+                "merge-primary",
+                &[
+                    question.clone(),
+                    answer.clone(),
+                    text(Role::User, 102, "Thanks"),
+                ],
+            )
+            .await?;
+            // Forked from the same start, then continued separately
+            let secondary = session_with(
+                "merge-secondary",
+                &[
+                    question,
+                    answer,
+                    text(Role::User, 200, "What about hotels?"),
+                    text(Role::Assistant, 201, "Stay in Alfama"),
+                    text(Role::User, 202, "Book it"),
+                ],
+            )
+            .await?;
+
+            let agent = Agent::new();
+            let merged = agent.merge_sessions(&primary, &secondary).await?;
+
+            let texts: Vec<String> = merged.iter().map(|m| m.as_concat_text()).collect();
+            assert_eq!(
+                texts,
+                vec![
+                    "Plan a trip to Lisbon",
+                    "Three days is enough",
+                    "Thanks\nWhat about hotels?",
+                    "Stay in Alfama",
+                    "Book it",
+                ]
+            );
+            assert!(Conversation::new(merged).is_ok());
+            Ok(())
+        }
+    }
+
+    mod amend_message_tests {
+        use super::*;
+        use goose::conversation::message::Message;
+        use goose::conversation::Conversation;
+        use goose::session::session_manager::SessionType;
+        use goose::session::SessionManager;
+        use std::path::PathBuf;
+
+        #[tokio::test]
+        async fn test_amend_assistant_message() -> Result<()> {
+            let session = SessionManager::create_session(
+                PathBuf::default(),
+                "amend-message-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            for message in [
+                Message::user().with_text("What is the capital of Australia?"),
+                Message::assistant().with_text("Sydney"),
+                Message::user().with_text("Are you sure?"),
+            ] {
+                SessionManager::add_message(&session.id, &message).await?;
+            }
+
+            let agent = Agent::new();
+            agent
+                .amend_message(&session.id, 1, Message::assistant().with_text("Canberra"))
+                .await?;
+
+            let history = SessionManager::get_session(&session.id, true)
+                .await?
+                .conversation
+                .unwrap_or_default();
+            let texts: Vec<String> = history.iter().map(|m| m.as_concat_text()).collect();
+            assert_eq!(
+                texts,
+                vec![
+                    "What is the capital of Australia?",
+                    "Canberra",
+                    "Are you sure?"
+                ]
+            );
+            assert!(Conversation::new(history.messages().clone()).is_ok());
+
+            // A replacement that breaks the user/assistant alternation is rejected
+            let result = agent
+                .amend_message(&session.id, 1, Message::user().with_text("Canberra"))
+                .await;
+            assert!(result.is_err());
+            assert!(agent
+                .amend_message(&session.id, 3, Message::assistant().with_text("Yes"))
+                .await
+                .is_err());
+            let unchanged = SessionManager::get_session(&session.id, true)
+                .await?
+                .conversation
+                .unwrap_or_default();
+            assert_eq!(unchanged.messages()[1].as_concat_text(), "Canberra");
+            assert_eq!(unchanged.messages()[1].role, rmcp::model::Role::Assistant);
+            Ok(())
+        }
+    }
+
+    mod extension_manager_tests {
+        use super::*;
+        use goose::agents::extension::{ExtensionConfig, PlatformExtensionContext};
+        use goose::agents::extension_manager_extension::{
+            MANAGE_EXTENSIONS_TOOL_NAME, SEARCH_AVAILABLE_EXTENSIONS_TOOL_NAME,
+        };
+
+        async fn setup_agent_with_extension_manager() -> Agent {
+            // Add the TODO extension to the config so it can be discovered by search_available_extensions
+            // Set it as disabled initially so tests can enable it
+            let todo_extension_entry = ExtensionEntry {
+                enabled: false,
+                config: ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description:
+                        "Enable a todo list for Goose so it can keep track of what it is doing"
+                            .to_string(),
+                    bundled: Some(true),
+                    available_tools: vec![],
+                    required: false,
+                },
+            };
+            set_extension(todo_extension_entry);
+
+            let agent = Agent::new();
+
+            agent
+                .extension_manager
+                .set_context(PlatformExtensionContext {
+                    session_id: Some("test_session".to_string()),
+                    extension_manager: Some(Arc::downgrade(&agent.extension_manager)),
+                    tool_route_manager: Some(Arc::downgrade(&agent.tool_route_manager)),
+                })
+                .await;
+
+            // Now add the extension manager platform extension
+            let ext_config = ExtensionConfig::Platform {
+                name: "extensionmanager".to_string(),
+                description: "Extension Manager".to_string(),
+                bundled: Some(true),
+                available_tools: vec![],
+                required: false,
+            };
+
+            agent
+                .add_extension(ext_config)
+                .await
+                .expect("Failed to add extension manager");
+            agent
+        }
+
+        #[tokio::test]
+        async fn test_extension_manager_tools_available() {
+            let agent = setup_agent_with_extension_manager().await;
             let tools = agent.list_tools(None).await;
 
             // Note: Tool names are prefixed with the normalized extension name "extensionmanager"
@@ -515,5 +3561,240 @@ mod tests {
                 "manage_extensions tool should be available"
             );
         }
+
+        #[tokio::test]
+        async fn test_manage_extensions_without_name_is_rejected() -> Result<()> {
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+            use rmcp::model::CallToolRequestParam;
+
+            let agent = setup_agent_with_extension_manager().await;
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "missing-extension-name-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+
+            for arguments in [
+                rmcp::object!({"action": "enable"}),
+                rmcp::object!({"action": "disable", "extension_name": ""}),
+            ] {
+                let tool_call = CallToolRequestParam {
+                    name: format!("extensionmanager__{MANAGE_EXTENSIONS_TOOL_NAME}").into(),
+                    arguments: Some(arguments),
+                };
+                let (_, result) = agent
+                    .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+                    .await;
+                let content = result
+                    .map_err(|e| anyhow::anyhow!(e.message))?
+                    .result
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))?;
+
+                let text = content[0]
+                    .as_text()
+                    .map(|t| t.text.clone())
+                    .unwrap_or_default();
+                assert_eq!(text, "Missing required parameter: extension_name");
+            }
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_pinned_extension_cannot_be_disabled() -> Result<()> {
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+            use rmcp::model::CallToolRequestParam;
+
+            let agent = setup_agent_with_extension_manager().await;
+            agent
+                .add_extension(ExtensionConfig::Platform {
+                    name: "todo".to_string(),
+                    description: "todo".to_string(),
+                    bundled: Some(true),
+                    available_tools: vec![],
+                    required: false,
+                })
+                .await?;
+            agent.pin_extension("todo").await;
+
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "pinned-extension-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let tool_call = CallToolRequestParam {
+                name: format!("extensionmanager__{MANAGE_EXTENSIONS_TOOL_NAME}").into(),
+                arguments: Some(rmcp::object!({"action": "disable", "extension_name": "todo"})),
+            };
+            let (_, result) = agent
+                .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+                .await;
+            let content = result
+                .map_err(|e| anyhow::anyhow!(e.message))?
+                .result
+                .await
+                .map_err(|e| anyhow::anyhow!(e.message))?;
+
+            let text = content[0]
+                .as_text()
+                .map(|t| t.text.clone())
+                .unwrap_or_default();
+            assert!(text.contains("pinned"), "unexpected response: {}", text);
+            assert!(agent.list_extensions().await.contains(&"todo".to_string()));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_recipe_refuses_undeclared_extensions() -> Result<()> {
+            use goose::recipe::Recipe;
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+            use rmcp::model::CallToolRequestParam;
+
+            let recipe = Recipe::from_content(
+                "title: Notes\n\
+                 description: Takes notes\n\
+                 instructions: Keep notes\n\
+                 extensions:\n\
+                 - type: platform\n  name: extensionmanager\n  description: Extension Manager\n",
+            )?;
+            let agent = setup_agent_with_extension_manager().await;
+            agent
+                .limit_enabled_extensions(
+                    recipe
+                        .extensions
+                        .as_ref()
+                        .map(|extensions| extensions.iter().map(|e| e.name()).collect()),
+                )
+                .await;
+
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "recipe-extensions-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            let tool_call = CallToolRequestParam {
+                name: format!("extensionmanager__{MANAGE_EXTENSIONS_TOOL_NAME}").into(),
+                arguments: Some(rmcp::object!({"action": "enable", "extension_name": "todo"})),
+            };
+            let (_, result) = agent
+                .dispatch_tool_call(tool_call, "call_1".to_string(), None, &session)
+                .await;
+            let content = result
+                .map_err(|e| anyhow::anyhow!(e.message))?
+                .result
+                .await
+                .map_err(|e| anyhow::anyhow!(e.message))?;
+
+            let text = content[0]
+                .as_text()
+                .map(|t| t.text.clone())
+                .unwrap_or_default();
+            assert!(
+                text.contains("not declared by the current recipe"),
+                "unexpected response: {}",
+                text
+            );
+            assert!(!agent.list_extensions().await.contains(&"todo".to_string()));
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn test_enabling_extension_mid_reply_reports_added_tools() -> Result<()> {
+            use goose::agents::SessionConfig;
+            use goose::config::GooseMode;
+            use goose::conversation::message::Message;
+            use goose::model::ModelConfig;
+            use goose::providers::base::{Provider, ProviderMetadata, ProviderUsage, Usage};
+            use goose::providers::errors::ProviderError;
+            use goose::session::session_manager::SessionType;
+            use goose::session::SessionManager;
+            use rmcp::model::{CallToolRequestParam, Tool};
+
+            /// Enables the todo extension, then answers once the call shows up in the history
+            struct EnablingProvider;
+
+            #[async_trait::async_trait]
+            impl Provider for EnablingProvider {
+                async fn complete_with_model(
+                    &self,
+                    _model_config: &ModelConfig,
+                    _system_prompt: &str,
+                    messages: &[Message],
+                    _tools: &[Tool],
+                ) -> Result<(Message, ProviderUsage), ProviderError> {
+                    let usage = ProviderUsage::new("mock-model".to_string(), Usage::default());
+                    if messages.iter().any(|m| m.is_tool_call()) {
+                        return Ok((Message::assistant().with_text("done"), usage));
+                    }
+                    let call = CallToolRequestParam {
+                        name: format!("extensionmanager__{MANAGE_EXTENSIONS_TOOL_NAME}").into(),
+                        arguments: Some(
+                            rmcp::object!({"action": "enable", "extension_name": "todo"}),
+                        ),
+                    };
+                    Ok((
+                        Message::assistant().with_tool_request("enable_todo", Ok(call)),
+                        usage,
+                    ))
+                }
+
+                fn get_model_config(&self) -> ModelConfig {
+                    ModelConfig::new("mock-model").unwrap()
+                }
+
+                fn metadata() -> ProviderMetadata {
+                    ProviderMetadata::empty()
+                }
+
+                fn get_name(&self) -> &str {
+                    "mock-enabling"
+                }
+            }
+
+            let agent = setup_agent_with_extension_manager().await;
+            agent.set_goose_mode_override(Some(GooseMode::Auto)).await;
+            let session = SessionManager::create_session(
+                std::path::PathBuf::default(),
+                "tool-set-diff-test".to_string(),
+                SessionType::Hidden,
+            )
+            .await?;
+            agent
+                .update_provider(Arc::new(EnablingProvider), &session.id)
+                .await?;
+
+            let session_config = SessionConfig {
+                id: session.id.clone(),
+                schedule_id: None,
+                max_turns: None,
+                retry_config: None,
+                user_id: None,
+                thinking_budget: None,
+                reply_deadline: None,
+            };
+            let reply_stream = agent
+                .reply(
+                    Message::user().with_text("Keep a todo list"),
+                    session_config,
+                    None,
+                )
+                .await?;
+            tokio::pin!(reply_stream);
+            while let Some(event) = reply_stream.next().await {
+                event?;
+            }
+
+            let diff = agent.tool_set_diff(&session.id).await;
+            assert_eq!(diff.added, vec!["todo__todo_write".to_string()]);
+            assert!(diff.removed.is_empty());
+            assert!(agent.tool_set_diff("other-session").await.is_empty());
+            Ok(())
+        }
     }
 }
@@ -1,4 +1,4 @@
-use goose::tool_monitor::RepetitionInspector;
+use goose::tool_monitor::{RepetitionInspector, RepetitionState};
 use rmcp::model::CallToolRequestParam;
 use rmcp::object;
 
@@ -39,3 +39,70 @@ fn test_repetition_inspector_denies_after_exceeding_and_resets_on_param_change()
     // One more identical call with new params → denied again
     assert!(!inspector.check_tool_call(call_v2));
 }
+
+// Reordered keys are treated as the same call by default, and a custom normalizer can
+// also ignore whitespace. Stats are keyed by the normalized arguments.
+#[test]
+fn test_repetition_inspector_normalizes_arguments() {
+    let mut inspector = RepetitionInspector::new(Some(1));
+    let call_ab = CallToolRequestParam {
+        name: "shell".into(),
+        arguments: Some(object!({"command": "ls", "cwd": "/tmp"})),
+    };
+    let call_ba = CallToolRequestParam {
+        name: "shell".into(),
+        arguments: Some(object!({"cwd": "/tmp", "command": "ls"})),
+    };
+    assert!(inspector.check_tool_call(call_ab));
+    assert!(!inspector.check_tool_call(call_ba));
+    assert_eq!(
+        inspector
+            .get_stats()
+            .get(r#"shell {"command":"ls","cwd":"/tmp"}"#),
+        Some(&2)
+    );
+
+    let mut inspector = RepetitionInspector::with_normalizer(Some(1), |value| {
+        let command = value["command"].as_str().unwrap_or_default();
+        serde_json::json!({
+            "command": command.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+    });
+    let spaced = |command: &str| CallToolRequestParam {
+        name: "shell".into(),
+        arguments: Some(object!({"command": command})),
+    };
+    assert!(inspector.check_tool_call(spaced("ls  -la")));
+    assert!(!inspector.check_tool_call(spaced(" ls -la ")));
+    assert_eq!(
+        inspector.get_stats().get(r#"shell {"command":"ls -la"}"#),
+        Some(&2)
+    );
+}
+
+// Exported counts survive a serde round trip and, once imported into a fresh inspector,
+// still count towards the limit; importing adds to counts already recorded
+#[test]
+fn test_repetition_inspector_state_survives_export_and_import() {
+    let call = CallToolRequestParam {
+        name: "fetch_user".into(),
+        arguments: Some(object!({"id": 123})),
+    };
+    let mut inspector = RepetitionInspector::new(Some(3));
+    assert!(inspector.check_tool_call(call.clone()));
+    assert!(inspector.check_tool_call(call.clone()));
+
+    let saved = serde_json::to_string(&inspector.export_state()).unwrap();
+    let state: RepetitionState = serde_json::from_str(&saved).unwrap();
+
+    let mut restored = RepetitionInspector::new(Some(3));
+    restored.import_state(state.clone());
+    assert!(restored.check_tool_call(call.clone()));
+    assert!(!restored.check_tool_call(call.clone()));
+
+    let mut merged = RepetitionInspector::new(Some(3));
+    assert!(merged.check_tool_call(call.clone()));
+    merged.import_state(state);
+    assert_eq!(merged.get_stats().get(r#"fetch_user {"id":123}"#), Some(&3));
+    assert!(!merged.check_tool_call(call));
+}